@@ -0,0 +1,120 @@
+//! Frame-time measurement for the `benchmark` command.
+
+use anyhow::{Context, Result};
+use onedrop_engine::{
+    EngineConfig, FRAME_TIME_BUCKET_EDGES_MS, FrameTimeStats, MilkEngine, RenderConfig,
+    SilentAudioSource,
+};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Mean frame time above which a preset can't sustain 60 fps, in ms.
+pub const TARGET_FRAME_MS: f32 = 16.6;
+
+/// Outcome of a benchmark run.
+pub struct BenchmarkResult {
+    /// Distribution of `update` durations
+    pub stats: FrameTimeStats,
+
+    /// Wall-clock time of the whole run
+    pub elapsed: Duration,
+}
+
+impl BenchmarkResult {
+    /// Frames per second achieved over the whole run.
+    pub fn fps(&self) -> f32 {
+        self.stats.frames as f32 / self.elapsed.as_secs_f32().max(f32::EPSILON)
+    }
+}
+
+/// Render `frames` frames offscreen with silent audio and time each update.
+///
+/// Nothing is read back from the GPU, so this measures evaluation and GPU
+/// submission rather than image encoding.
+pub fn run_benchmark(
+    preset_path: Option<&Path>,
+    frames: u32,
+    width: u32,
+    height: u32,
+) -> Result<BenchmarkResult> {
+    let config = EngineConfig {
+        render_config: RenderConfig {
+            width,
+            height,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let sample_rate = config.sample_rate as u32;
+    let mut engine =
+        pollster::block_on(MilkEngine::new(config)).context("Failed to create engine")?;
+
+    match preset_path {
+        Some(path) => engine.load_preset(path).context("Failed to load preset")?,
+        None => engine
+            .load_default_preset()
+            .context("Failed to load default preset")?,
+    }
+
+    engine.set_audio_source(Box::new(SilentAudioSource::new(sample_rate)));
+    let start = Instant::now();
+    for _ in 0..frames {
+        engine
+            .update_from_source(1.0 / crate::RENDER_FPS)
+            .context("Failed to update engine")?;
+    }
+
+    Ok(BenchmarkResult {
+        stats: engine.frame_time_histogram(),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Print frame-time statistics and a histogram of frame-time buckets.
+pub fn print_report(result: &BenchmarkResult) {
+    let stats = &result.stats;
+    println!("\n=== Frame Times ===\n");
+    println!(
+        "Frames: {} in {:.2}s ({:.1} fps)",
+        stats.frames,
+        result.elapsed.as_secs_f32(),
+        result.fps()
+    );
+    println!(
+        "min: {:.2} ms  mean: {:.2} ms  median: {:.2} ms  p95: {:.2} ms  p99: {:.2} ms  max: {:.2} ms\n",
+        stats.min_ms, stats.mean_ms, stats.p50_ms, stats.p95_ms, stats.p99_ms, stats.max_ms
+    );
+
+    let most = stats.buckets.iter().copied().max().unwrap_or(0).max(1);
+    let mut lower = 0.0;
+    for (i, count) in stats.buckets.iter().enumerate() {
+        let label = match FRAME_TIME_BUCKET_EDGES_MS.get(i) {
+            Some(upper) => format!("{:>6.1} - {:>6.1} ms", lower, upper),
+            None => format!("{:>6.1} ms and up  ", lower),
+        };
+        let bar = "#".repeat((*count * 40 / most) as usize);
+        println!("  {} {:>7} {}", label, count, bar);
+        lower = FRAME_TIME_BUCKET_EDGES_MS.get(i).copied().unwrap_or(lower);
+    }
+
+    if stats.mean_ms > TARGET_FRAME_MS {
+        println!(
+            "\n⚠ Mean frame time {:.2} ms exceeds {:.1} ms: this preset can't sustain 60 fps at this size",
+            stats.mean_ms, TARGET_FRAME_MS
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_smoke() {
+        let result = run_benchmark(None, 5, 64, 64).unwrap();
+        assert_eq!(result.stats.frames, 5);
+        assert!(result.stats.min_ms <= result.stats.mean_ms);
+        assert!(result.stats.mean_ms <= result.stats.max_ms);
+        assert!(result.fps() > 0.0);
+    }
+}
@@ -0,0 +1,128 @@
+//! Animated GIF output for the `render` command.
+//!
+//! Each frame is quantized to its own 256-color palette, which keeps short
+//! previews small enough to share. Frames are encoded as they arrive rather
+//! than held in memory.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+
+/// Most frames written to one GIF; longer renders are cut short.
+pub const MAX_FRAMES: u32 = 600;
+
+/// Quantization speed passed to the encoder: 1 is best and slowest, 30 fastest.
+const QUANTIZE_SPEED: i32 = 10;
+
+/// GIF frame delay for `fps`, in hundredths of a second.
+///
+/// Most viewers treat delays under 2 as 10, so faster rates are slowed to
+/// 50 fps rather than played back at a crawl.
+pub fn frame_delay(fps: f32) -> u16 {
+    (100.0 / fps.max(1.0)).round().clamp(2.0, u16::MAX as f32) as u16
+}
+
+/// Looping GIF encoder fed with RGBA8 frames.
+pub struct GifWriter<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: u16,
+    height: u16,
+    delay: u16,
+    frames: u32,
+}
+
+impl<W: Write> GifWriter<W> {
+    /// Start a `width`×`height` GIF played back at `fps`.
+    pub fn new(writer: W, width: u32, height: u32, fps: f32) -> Result<Self> {
+        let width = u16::try_from(width).context("GIF width is limited to 65535 pixels")?;
+        let height = u16::try_from(height).context("GIF height is limited to 65535 pixels")?;
+        let mut encoder =
+            gif::Encoder::new(writer, width, height, &[]).context("Failed to start GIF")?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .context("Failed to start GIF")?;
+        Ok(Self {
+            encoder,
+            width,
+            height,
+            delay: frame_delay(fps),
+            frames: 0,
+        })
+    }
+
+    /// Quantize and append one frame of tightly packed RGBA8 pixels.
+    pub fn push_frame(&mut self, mut rgba: Vec<u8>) -> Result<()> {
+        let expected = self.width as usize * self.height as usize * 4;
+        anyhow::ensure!(
+            rgba.len() == expected,
+            "Frame has {} bytes, expected {}",
+            rgba.len(),
+            expected
+        );
+
+        let mut frame =
+            gif::Frame::from_rgba_speed(self.width, self.height, &mut rgba, QUANTIZE_SPEED);
+        frame.delay = self.delay;
+        self.encoder
+            .write_frame(&frame)
+            .context("Failed to write GIF frame")?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Frames written so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Write the trailer and return the underlying writer.
+    pub fn finish(self) -> Result<W> {
+        self.encoder.into_inner().context("Failed to finish GIF")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use onedrop_engine::{EngineConfig, MilkEngine, RenderConfig};
+
+    #[test]
+    fn test_render_frames_to_gif() {
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 32,
+                height: 24,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+
+        let mut writer = GifWriter::new(Vec::new(), 32, 24, 30.0).unwrap();
+        for _ in 0..5 {
+            let (_, _, pixels) = engine.render_to_buffer(&[0.0; 1024], 1.0 / 30.0).unwrap();
+            writer.push_frame(pixels).unwrap();
+        }
+        assert_eq!(writer.frames(), 5);
+        let bytes = writer.finish().unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (32, 24));
+        let mut decoded = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 3);
+            decoded += 1;
+        }
+        assert_eq!(decoded, 5);
+    }
+
+    #[test]
+    fn test_frame_delay() {
+        assert_eq!(frame_delay(30.0), 3);
+        assert_eq!(frame_delay(10.0), 10);
+        assert_eq!(frame_delay(60.0), 2);
+        assert_eq!(frame_delay(0.0), 100);
+    }
+}
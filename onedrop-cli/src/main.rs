@@ -1,9 +1,23 @@
 //! OneDrop CLI - Command-line interface for Milkdrop visualizations
 
+mod benchmark;
+mod gif_export;
+mod montage;
+mod preset_list;
+mod transpile;
+mod validate;
+
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use onedrop_engine::{EngineConfig, MilkEngine, RenderConfig};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use onedrop_engine::{
+    AudioSource, EngineConfig, FileAudioSource, MilkEngine, RenderConfig, SOURCE_FRAME_SAMPLES,
+    SineAudioSource,
+};
 use std::path::PathBuf;
+use transpile::Stage;
+
+/// Frame rate of rendered output; each frame consumes `sample_rate / fps` samples.
+const RENDER_FPS: f32 = 60.0;
 
 #[derive(Parser)]
 #[command(name = "onedrop")]
@@ -27,25 +41,37 @@ enum Commands {
         preset: PathBuf,
     },
 
-    /// Validate a preset file
+    /// Validate a preset file, or every preset in a directory
     Validate {
-        /// Path to the .milk preset file
+        /// Path to the .milk preset file, or a directory with --all
         preset: PathBuf,
+
+        /// Validate every .milk preset under the directory, recursively
+        #[arg(long)]
+        all: bool,
+
+        /// Print the directory summary as JSON
+        #[arg(long, requires = "all")]
+        json: bool,
+
+        /// Exit with an error if any preset in the directory fails
+        #[arg(long, requires = "all")]
+        strict: bool,
     },
 
     /// Render a preset to images
-    Render {
-        /// Path to the .milk preset file
-        preset: PathBuf,
+    Render(RenderArgs),
+
+    /// Render offscreen as fast as possible and report frame times
+    #[command(alias = "bench")]
+    Benchmark {
+        /// Path to the .milk preset file (defaults to the built-in preset)
+        preset: Option<PathBuf>,
 
         /// Number of frames to render
-        #[arg(short, long, default_value = "60")]
+        #[arg(short, long, default_value = "600")]
         frames: u32,
 
-        /// Output directory for frames
-        #[arg(short, long, default_value = "output")]
-        output: PathBuf,
-
         /// Width of output
         #[arg(short, long, default_value = "1280")]
         width: u32,
@@ -55,13 +81,112 @@ enum Commands {
         height: u32,
     },
 
+    /// Emit the WGSL generated for one stage of a preset
+    Transpile {
+        /// Path to the .milk preset file
+        preset: PathBuf,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Stage to emit
+        #[arg(short, long, value_enum, default_value = "per-pixel")]
+        stage: Stage,
+    },
+
+    /// Tile a warmed-up still of every preset in a directory into one image
+    Montage {
+        /// Directory containing .milk files
+        directory: PathBuf,
+
+        /// Tiles per row
+        #[arg(short, long, default_value = "5")]
+        cols: u32,
+
+        /// Output image
+        #[arg(short, long, default_value = "montage.png")]
+        output: PathBuf,
+
+        /// Width of each tile
+        #[arg(short, long, default_value = "320")]
+        width: u32,
+
+        /// Height of each tile, without its caption
+        #[arg(short = 'H', long, default_value = "180")]
+        height: u32,
+
+        /// Frames to run before capturing each still
+        #[arg(long, default_value = "60")]
+        warmup: u32,
+    },
+
     /// List all presets in a directory
     List {
         /// Directory containing .milk files
         directory: PathBuf,
+
+        /// Include presets in subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Parse each preset and tabulate its version, equations and shaders
+        #[arg(short, long)]
+        details: bool,
+
+        /// Sort order
+        #[arg(short, long, value_enum, default_value = "name")]
+        sort: preset_list::SortOrder,
     },
 }
 
+#[derive(Args)]
+struct RenderArgs {
+    /// Path to the .milk preset file
+    preset: PathBuf,
+
+    /// Number of frames to render
+    #[arg(short, long, default_value = "60")]
+    frames: u32,
+
+    /// Output directory for frames
+    #[arg(short, long, default_value = "output")]
+    output: PathBuf,
+
+    /// Width of output
+    #[arg(short, long, default_value = "1280")]
+    width: u32,
+
+    /// Height of output
+    #[arg(short = 'H', long, default_value = "720")]
+    height: u32,
+
+    /// WAV or MP3 file driving the visualization (defaults to a sine wave)
+    #[arg(short, long)]
+    audio: Option<PathBuf>,
+
+    /// Frames to run before the first output frame, so feedback trails settle
+    #[arg(long, default_value = "0")]
+    warmup: u32,
+
+    /// Output format: numbered PNG frames, or one animated GIF
+    #[arg(long, value_enum, default_value = "png")]
+    format: OutputFormat,
+
+    /// Frames per second of simulated time, and GIF playback rate
+    #[arg(long, default_value_t = RENDER_FPS)]
+    fps: f32,
+}
+
+/// File format written by the `render` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// One `frame_NNNN.png` per frame
+    Png,
+    /// A single looping `<preset>.gif`
+    Gif,
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -78,15 +203,39 @@ fn main() -> Result<()> {
 
     match cli.command {
         Commands::Info { preset } => cmd_info(preset),
-        Commands::Validate { preset } => cmd_validate(preset),
-        Commands::Render {
+        Commands::Validate {
+            preset,
+            all: true,
+            json,
+            strict,
+        } => cmd_validate_all(preset, json, strict),
+        Commands::Validate { preset, .. } => cmd_validate(preset),
+        Commands::Render(args) => cmd_render(args),
+        Commands::Benchmark {
             preset,
             frames,
+            width,
+            height,
+        } => cmd_benchmark(preset, frames, width, height),
+        Commands::Transpile {
+            preset,
+            output,
+            stage,
+        } => cmd_transpile(preset, output, stage),
+        Commands::Montage {
+            directory,
+            cols,
             output,
             width,
             height,
-        } => cmd_render(preset, frames, output, width, height),
-        Commands::List { directory } => cmd_list(directory),
+            warmup,
+        } => cmd_montage(directory, cols, output, width, height, warmup),
+        Commands::List {
+            directory,
+            recursive,
+            details,
+            sort,
+        } => cmd_list(directory, recursive, details, sort),
     }
 }
 
@@ -155,35 +304,81 @@ fn cmd_validate(preset_path: PathBuf) -> Result<()> {
 
     let content = std::fs::read_to_string(&preset_path).context("Failed to read preset file")?;
 
-    match onedrop_parser::parse_preset(&content) {
-        Ok(preset) => {
-            println!("✓ Preset is valid!");
-            println!("  Version: {}", preset.version);
-            println!(
-                "  Per-frame equations: {}",
-                preset.per_frame_equations.len()
-            );
-            println!(
-                "  Per-pixel equations: {}",
-                preset.per_pixel_equations.len()
-            );
-            Ok(())
-        }
+    let preset = match onedrop_parser::parse_preset(&content) {
+        Ok(preset) => preset,
         Err(e) => {
             println!("✗ Preset is invalid!");
             println!("  Error: {}", e);
-            Err(e.into())
+            return Err(e.into());
+        }
+    };
+
+    let errors = onedrop_eval::equation_errors(&preset);
+    if !errors.is_empty() {
+        println!("✗ Preset has {} failing equation(s):", errors.len());
+        for e in &errors {
+            println!("  {}", e);
         }
+        anyhow::bail!("{} equation(s) failed to evaluate", errors.len());
     }
+
+    println!("✓ Preset is valid!");
+    println!("  Version: {}", preset.version);
+    println!(
+        "  Per-frame equations: {}",
+        preset.per_frame_equations.len()
+    );
+    println!(
+        "  Per-pixel equations: {}",
+        preset.per_pixel_equations.len()
+    );
+    Ok(())
 }
 
-fn cmd_render(
-    preset_path: PathBuf,
-    frames: u32,
-    output_dir: PathBuf,
-    width: u32,
-    height: u32,
-) -> Result<()> {
+fn cmd_validate_all(directory: PathBuf, json: bool, strict: bool) -> Result<()> {
+    log::info!("Validating presets in: {}", directory.display());
+
+    let report = validate::ValidationReport::from_directory(&directory)?;
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report.to_table());
+    }
+
+    if strict && !report.failures.is_empty() {
+        anyhow::bail!(
+            "{} of {} preset(s) failed validation",
+            report.failures.len(),
+            report.total
+        );
+    }
+    Ok(())
+}
+
+fn cmd_render(args: RenderArgs) -> Result<()> {
+    let RenderArgs {
+        preset: preset_path,
+        mut frames,
+        output: output_dir,
+        width,
+        height,
+        audio: audio_path,
+        warmup,
+        format,
+        fps,
+    } = args;
+    anyhow::ensure!(fps > 0.0, "--fps must be positive");
+
+    if format == OutputFormat::Gif && frames > gif_export::MAX_FRAMES {
+        log::warn!(
+            "GIF output is capped at {} frames; rendering {} instead of {}",
+            gif_export::MAX_FRAMES,
+            gif_export::MAX_FRAMES,
+            frames
+        );
+        frames = gif_export::MAX_FRAMES;
+    }
+
     log::info!("Rendering preset: {}", preset_path.display());
     log::info!("Output: {} frames to {}", frames, output_dir.display());
     log::info!("Resolution: {}x{}", width, height);
@@ -198,9 +393,11 @@ fn cmd_render(
             height,
             ..Default::default()
         },
+        warmup_frames: warmup,
         ..Default::default()
     };
 
+    let sample_rate = config.sample_rate as u32;
     let mut engine =
         pollster::block_on(MilkEngine::new(config)).context("Failed to create engine")?;
 
@@ -209,22 +406,81 @@ fn cmd_render(
         .load_preset(&preset_path)
         .context("Failed to load preset")?;
 
+    // Decode the soundtrack, if any, at the engine's sample rate
+    let mut audio = audio_path
+        .map(|path| FileAudioSource::open(&path).map(|source| source.resampled(sample_rate)))
+        .transpose()
+        .context("Failed to load audio")?;
+    let samples_per_frame = ((sample_rate as f32 / fps).round() as usize).max(1);
+    if let Some(source) = &audio {
+        let covered = source.len().div_ceil(samples_per_frame);
+        if covered < frames as usize {
+            log::info!(
+                "Audio covers {} of {} frames; the rest render with silence",
+                covered,
+                frames
+            );
+        }
+    }
+
+    if warmup > 0 {
+        println!("Warming up for {} frames...", warmup);
+        engine
+            .warm_up(&[0.0; 1024], 1.0 / fps)
+            .context("Failed to warm up engine")?;
+    }
+
+    // GIF frames are appended to one file as they are rendered
+    let gif_path = output_dir.join(format!(
+        "{}.gif",
+        preset_path
+            .file_stem()
+            .map_or("render".into(), |stem| stem.to_string_lossy())
+    ));
+    let mut gif = match format {
+        OutputFormat::Gif => {
+            let file = std::fs::File::create(&gif_path).context("Failed to create GIF file")?;
+            let (width, height) = engine.renderer().gpu().physical_size();
+            Some(gif_export::GifWriter::new(
+                std::io::BufWriter::new(file),
+                width,
+                height,
+                fps,
+            )?)
+        }
+        OutputFormat::Png => None,
+    };
+
     println!("Rendering {} frames...", frames);
 
     // Render frames
+    let mut demo = SineAudioSource::demo();
     for frame in 0..frames {
-        // Generate some audio (sine wave for demo)
-        let audio_samples: Vec<f32> = (0..1024)
-            .map(|i| {
-                let t = (frame * 1024 + i) as f32 * 0.001;
-                (t * 2.0 * std::f32::consts::PI * 60.0).sin() * 0.5
-            })
-            .collect();
-
-        // Update engine
-        engine
-            .update(&audio_samples, 0.016)
-            .context("Failed to update engine")?;
+        let audio_samples: Vec<f32> = match &mut audio {
+            // This frame's slice of the soundtrack (silence once it ends,
+            // rather than looping)
+            Some(source) => {
+                let start = frame as usize * samples_per_frame;
+                let remaining = source.len().saturating_sub(start);
+                source.next_frame(samples_per_frame.min(remaining))
+            }
+            None => demo.next_frame(SOURCE_FRAME_SAMPLES),
+        };
+
+        // Render and read back the frame
+        let (frame_width, frame_height, pixels) = engine
+            .render_to_buffer(&audio_samples, 1.0 / fps)
+            .context("Failed to render frame")?;
+        match &mut gif {
+            Some(gif) => gif.push_frame(pixels)?,
+            None => {
+                let path = output_dir.join(format!("frame_{:04}.png", frame));
+                image::RgbaImage::from_raw(frame_width, frame_height, pixels)
+                    .context("Rendered frame has the wrong size")?
+                    .save(&path)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+        }
 
         // Progress indicator
         if frame % 10 == 0 || frame == frames - 1 {
@@ -238,43 +494,105 @@ fn cmd_render(
     }
 
     println!("\n✓ Rendering complete!");
-    println!("  Output: {}", output_dir.display());
+    match gif {
+        Some(gif) => {
+            let frames = gif.frames();
+            gif.finish()?;
+            println!("  Output: {} ({} frames)", gif_path.display(), frames);
+        }
+        None => println!("  Output: {}", output_dir.display()),
+    }
 
     Ok(())
 }
 
-fn cmd_list(directory: PathBuf) -> Result<()> {
-    log::info!("Listing presets in: {}", directory.display());
+fn cmd_benchmark(preset_path: Option<PathBuf>, frames: u32, width: u32, height: u32) -> Result<()> {
+    println!("Benchmarking {} frames at {}x{}...", frames, width, height);
+    let result = benchmark::run_benchmark(preset_path.as_deref(), frames, width, height)?;
+    benchmark::print_report(&result);
+    Ok(())
+}
 
-    let entries = std::fs::read_dir(&directory).context("Failed to read directory")?;
+fn cmd_transpile(preset_path: PathBuf, output: Option<PathBuf>, stage: Stage) -> Result<()> {
+    log::info!("Transpiling preset: {}", preset_path.display());
 
-    let mut presets = Vec::new();
+    let content = std::fs::read_to_string(&preset_path).context("Failed to read preset file")?;
+    let preset = onedrop_parser::parse_preset(&content).context("Failed to parse preset")?;
 
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+    let wgsl = transpile::transpile_stage(&preset, stage)?;
 
-        if path.extension().and_then(|s| s.to_str()) == Some("milk") {
-            presets.push(path);
+    match output {
+        Some(path) => {
+            std::fs::write(&path, wgsl).context("Failed to write WGSL file")?;
+            println!("✓ Wrote {}", path.display());
         }
+        None => print!("{}", wgsl),
     }
 
+    Ok(())
+}
+
+fn cmd_montage(
+    directory: PathBuf,
+    cols: u32,
+    output: PathBuf,
+    width: u32,
+    height: u32,
+    warmup: u32,
+) -> Result<()> {
+    let mut presets: Vec<PathBuf> = std::fs::read_dir(&directory)
+        .context("Failed to read directory")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("milk"))
+        .collect();
+    presets.sort();
+
+    if presets.is_empty() {
+        anyhow::bail!("No .milk presets found in {}", directory.display());
+    }
+
+    println!("Rendering {} presets...", presets.len());
+    let options = montage::MontageOptions {
+        columns: cols,
+        tile_width: width,
+        tile_height: height,
+        warmup_frames: warmup,
+    };
+    let sheet = montage::render_montage(&presets, options)?;
+    sheet.save(&output).context("Failed to write montage")?;
+
+    println!(
+        "✓ Wrote {} ({}x{})",
+        output.display(),
+        sheet.width(),
+        sheet.height()
+    );
+    Ok(())
+}
+
+fn cmd_list(
+    directory: PathBuf,
+    recursive: bool,
+    details: bool,
+    sort: preset_list::SortOrder,
+) -> Result<()> {
+    log::info!("Listing presets in: {}", directory.display());
+
+    let presets = preset_list::list_presets(&directory, recursive, details, sort)?;
     if presets.is_empty() {
         println!("No .milk presets found in {}", directory.display());
         return Ok(());
     }
 
-    presets.sort();
-
     println!("\n=== Presets in {} ===\n", directory.display());
     println!("Found {} preset(s):\n", presets.len());
 
-    for (i, preset) in presets.iter().enumerate() {
-        println!(
-            "  {}. {}",
-            i + 1,
-            preset.file_name().unwrap().to_string_lossy()
-        );
+    if details {
+        print!("{}", preset_list::details_table(&presets));
+    } else {
+        for (i, preset) in presets.iter().enumerate() {
+            println!("  {}. {}", i + 1, preset.path.display());
+        }
     }
 
     Ok(())
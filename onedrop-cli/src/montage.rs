@@ -0,0 +1,198 @@
+//! Contact sheets for the `montage` command.
+//!
+//! Each preset is warmed up and rendered to a still, and the stills are tiled
+//! into one image with the preset's file name under each tile.
+
+use anyhow::{Context, Result};
+use image::{Rgba, RgbaImage};
+use onedrop_engine::{
+    AudioSource, EngineConfig, MilkEngine, RenderConfig, SOURCE_FRAME_SAMPLES, SineAudioSource,
+};
+use onedrop_renderer::bitmap_font::{GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, glyph};
+use std::path::{Path, PathBuf};
+
+/// Height of the caption strip under each tile, in pixels.
+pub const CAPTION_HEIGHT: u32 = 11;
+
+/// Caption strip background.
+const CAPTION_BACKGROUND: Rgba<u8> = Rgba([16, 16, 16, 255]);
+
+/// Caption text color.
+const CAPTION_COLOR: Rgba<u8> = Rgba([230, 230, 230, 255]);
+
+/// Background of a tile whose preset failed to load or render.
+const PLACEHOLDER_BACKGROUND: Rgba<u8> = Rgba([48, 48, 48, 255]);
+
+/// Cross drawn over a placeholder tile.
+const PLACEHOLDER_CROSS: Rgba<u8> = Rgba([200, 40, 40, 255]);
+
+/// Size and warm-up of each tile.
+#[derive(Debug, Clone, Copy)]
+pub struct MontageOptions {
+    /// Tiles per row
+    pub columns: u32,
+
+    /// Tile width in pixels
+    pub tile_width: u32,
+
+    /// Tile height in pixels, without the caption
+    pub tile_height: u32,
+
+    /// Frames rendered before each still is captured
+    pub warmup_frames: u32,
+}
+
+/// Render a still of each preset and tile them into a contact sheet.
+///
+/// Presets that fail to load or render get a placeholder tile, so the sheet
+/// always has one tile per path, in order.
+pub fn render_montage(presets: &[PathBuf], options: MontageOptions) -> Result<RgbaImage> {
+    let config = EngineConfig {
+        render_config: RenderConfig {
+            width: options.tile_width,
+            height: options.tile_height,
+            ..Default::default()
+        },
+        warmup_frames: options.warmup_frames,
+        ..Default::default()
+    };
+    let mut engine =
+        pollster::block_on(MilkEngine::new(config)).context("Failed to create engine")?;
+
+    let (columns, rows) = grid_size(presets.len(), options.columns);
+    let cell_height = options.tile_height + CAPTION_HEIGHT;
+    let mut sheet = RgbaImage::from_pixel(
+        columns * options.tile_width,
+        rows * cell_height,
+        CAPTION_BACKGROUND,
+    );
+
+    for (i, path) in presets.iter().enumerate() {
+        let tile = match render_still(&mut engine, path) {
+            Ok(tile) => tile,
+            Err(e) => {
+                log::warn!("Using a placeholder for {}: {:#}", path.display(), e);
+                placeholder_tile(options.tile_width, options.tile_height)
+            }
+        };
+
+        let x = (i as u32 % columns) * options.tile_width;
+        let y = (i as u32 / columns) * cell_height;
+        image::imageops::replace(&mut sheet, &tile, x as i64, y as i64);
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        draw_caption(
+            &mut sheet,
+            &name,
+            x,
+            y + options.tile_height,
+            options.tile_width,
+        );
+    }
+
+    Ok(sheet)
+}
+
+/// Columns and rows needed for `count` tiles with at most `columns` per row.
+fn grid_size(count: usize, columns: u32) -> (u32, u32) {
+    let count = count.max(1) as u32;
+    let columns = columns.clamp(1, count);
+    (columns, count.div_ceil(columns))
+}
+
+/// Load a preset from scratch and capture one warmed-up frame.
+fn render_still(engine: &mut MilkEngine, path: &Path) -> Result<RgbaImage> {
+    engine.reset();
+    engine.load_preset(path).context("Failed to load preset")?;
+
+    let audio = SineAudioSource::demo().next_frame(SOURCE_FRAME_SAMPLES);
+    let (width, height, pixels) = engine
+        .render_to_buffer(&audio, 1.0 / crate::RENDER_FPS)
+        .context("Failed to render preset")?;
+    RgbaImage::from_raw(width, height, pixels).context("Rendered frame has the wrong size")
+}
+
+/// Tile shown for a preset that couldn't be rendered: a crossed-out box.
+fn placeholder_tile(width: u32, height: u32) -> RgbaImage {
+    let mut tile = RgbaImage::from_pixel(width, height, PLACEHOLDER_BACKGROUND);
+    let steps = width.max(height);
+    for step in 0..steps {
+        let x = step * width / steps;
+        let y = step * height / steps;
+        tile.put_pixel(x, y, PLACEHOLDER_CROSS);
+        tile.put_pixel(width - 1 - x, y, PLACEHOLDER_CROSS);
+    }
+    tile
+}
+
+/// Draw `text` into the caption strip at (`x`, `y`), truncated to `width`.
+fn draw_caption(sheet: &mut RgbaImage, text: &str, x: u32, y: u32, width: u32) {
+    let max_chars = (width.saturating_sub(2) / GLYPH_ADVANCE) as usize;
+    let top = y + (CAPTION_HEIGHT - GLYPH_HEIGHT) / 2;
+    for (i, c) in text.chars().take(max_chars).enumerate() {
+        let left = x + 2 + i as u32 * GLYPH_ADVANCE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> col) != 0 {
+                    sheet.put_pixel(left + col, top + row as u32, CAPTION_COLOR);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_montage_dimensions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let preset = onedrop_engine::default_preset().to_milk_string();
+        let presets: Vec<PathBuf> = ["a.milk", "b.milk"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                std::fs::write(&path, &preset).unwrap();
+                path
+            })
+            .collect();
+
+        let options = MontageOptions {
+            columns: 5,
+            tile_width: 64,
+            tile_height: 48,
+            warmup_frames: 2,
+        };
+        let sheet = render_montage(&presets, options).unwrap();
+
+        // Two tiles fill a single, two-column row
+        assert_eq!(sheet.dimensions(), (2 * 64, 48 + CAPTION_HEIGHT));
+    }
+
+    #[test]
+    fn test_missing_preset_gets_placeholder() {
+        let options = MontageOptions {
+            columns: 2,
+            tile_width: 32,
+            tile_height: 32,
+            warmup_frames: 0,
+        };
+        let missing = PathBuf::from("/nonexistent/missing.milk");
+        let sheet = render_montage(&[missing], options).unwrap();
+        assert_eq!(sheet.dimensions(), (32, 32 + CAPTION_HEIGHT));
+        assert_eq!(*sheet.get_pixel(0, 0), PLACEHOLDER_CROSS);
+        assert_eq!(*sheet.get_pixel(1, 0), PLACEHOLDER_BACKGROUND);
+    }
+
+    #[test]
+    fn test_grid_size() {
+        assert_eq!(grid_size(2, 5), (2, 1));
+        assert_eq!(grid_size(7, 3), (3, 3));
+        assert_eq!(grid_size(0, 4), (1, 1));
+    }
+}
@@ -0,0 +1,220 @@
+//! Preset listings for the `list` command.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use onedrop_parser::MilkPreset;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// Order of listed presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    /// Alphabetical by path
+    Name,
+    /// Highest `fRating` first, unparseable presets last
+    Rating,
+}
+
+/// The `info` summary of a parsed preset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetDetails {
+    pub version: u32,
+    pub rating: f32,
+    pub per_frame_equations: usize,
+    pub per_pixel_equations: usize,
+    pub warp_shader: bool,
+    pub comp_shader: bool,
+}
+
+impl PresetDetails {
+    fn new(preset: &MilkPreset) -> Self {
+        Self {
+            version: preset.version,
+            rating: preset.parameters.f_rating,
+            per_frame_equations: preset.per_frame_equations.len(),
+            per_pixel_equations: preset.per_pixel_equations.len(),
+            warp_shader: preset.warp_shader.is_some(),
+            comp_shader: preset.comp_shader.is_some(),
+        }
+    }
+
+    /// Read and parse the preset at `path`, or `None` if either fails.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match onedrop_parser::parse_preset(&content) {
+            Ok(preset) => Some(Self::new(&preset)),
+            Err(e) => {
+                log::debug!("Cannot parse {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// One listed preset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetEntry {
+    /// Path relative to the listed directory
+    pub path: PathBuf,
+
+    /// Parsed details, or `None` when they weren't requested or the preset
+    /// is unparseable
+    pub details: Option<PresetDetails>,
+}
+
+/// List the `.milk` presets in `directory`, descending into subdirectories
+/// with `recursive`. Presets are parsed when `details` is set or sorting by
+/// rating needs them.
+pub fn list_presets(
+    directory: &Path,
+    recursive: bool,
+    details: bool,
+    sort: SortOrder,
+) -> Result<Vec<PresetEntry>> {
+    let paths = if recursive {
+        crate::validate::find_presets(directory)?
+    } else {
+        std::fs::read_dir(directory)
+            .context("Failed to read directory")?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("milk"))
+            .collect()
+    };
+
+    let parse = details || sort == SortOrder::Rating;
+    let mut entries: Vec<PresetEntry> = paths
+        .iter()
+        .map(|path| PresetEntry {
+            path: path.strip_prefix(directory).unwrap_or(path).to_path_buf(),
+            details: if parse {
+                PresetDetails::load(path)
+            } else {
+                None
+            },
+        })
+        .collect();
+
+    entries.sort_by(|a, b| match sort {
+        SortOrder::Name => a.path.cmp(&b.path),
+        SortOrder::Rating => {
+            let rating = |entry: &PresetEntry| entry.details.as_ref().map(|d| d.rating);
+            match (rating(a), rating(b)) {
+                (Some(a), Some(b)) => b.total_cmp(&a),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(|| a.path.cmp(&b.path))
+        }
+    });
+    Ok(entries)
+}
+
+/// Tabulate `entries` with their details, one row per preset.
+pub fn details_table(entries: &[PresetEntry]) -> String {
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    let header = [
+        "Preset",
+        "Version",
+        "Rating",
+        "Per-frame",
+        "Per-pixel",
+        "Warp",
+        "Comp",
+    ]
+    .map(String::from);
+    let rows: Vec<[String; 7]> = entries
+        .iter()
+        .map(|entry| {
+            let name = entry.path.display().to_string();
+            match &entry.details {
+                Some(d) => [
+                    name,
+                    d.version.to_string(),
+                    format!("{:.1}", d.rating),
+                    d.per_frame_equations.to_string(),
+                    d.per_pixel_equations.to_string(),
+                    yes_no(d.warp_shader).to_string(),
+                    yes_no(d.comp_shader).to_string(),
+                ],
+                None => [
+                    name,
+                    "unparseable".to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                ],
+            }
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|cell| cell.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        table.push_str("  ");
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursive_details() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        let mut preset = onedrop_engine::default_preset();
+        std::fs::write(dir.join("b.milk"), preset.to_milk_string()).unwrap();
+        preset.parameters.f_rating = 5.0;
+        std::fs::write(dir.join("nested/a.milk"), preset.to_milk_string()).unwrap();
+        std::fs::write(dir.join("broken.milk"), "MILKDROP_PRESET_VERSION=abc\n").unwrap();
+
+        let flat = list_presets(dir, false, false, SortOrder::Name).unwrap();
+        let names: Vec<_> = flat.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            names,
+            [PathBuf::from("b.milk"), PathBuf::from("broken.milk")]
+        );
+        assert!(flat.iter().all(|e| e.details.is_none()));
+
+        let entries = list_presets(dir, true, true, SortOrder::Rating).unwrap();
+
+        // Highest rating first, unparseable last
+        let names: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("nested/a.milk"),
+                PathBuf::from("b.milk"),
+                PathBuf::from("broken.milk"),
+            ]
+        );
+        let details = entries[0].details.as_ref().unwrap();
+        assert_eq!(details.rating, 5.0);
+        assert!(details.version > 0);
+        assert!(details.per_frame_equations > 0);
+        assert!(entries[2].details.is_none());
+
+        let table = details_table(&entries);
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().last().unwrap().contains("unparseable"));
+    }
+}
@@ -0,0 +1,89 @@
+//! WGSL output for the `transpile` command.
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use onedrop_codegen::{ExpressionTranspiler, ShaderGenerator};
+use onedrop_parser::MilkPreset;
+
+/// Part of a preset to emit as WGSL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Stage {
+    /// Embedded warp shader, translated from HLSL
+    Warp,
+    /// Embedded composite shader, translated from HLSL
+    Comp,
+    /// Per-frame equations, one WGSL statement each after local declarations
+    PerFrame,
+    /// Complete per-pixel shader generated from the per-pixel equations
+    PerPixel,
+}
+
+/// Generate WGSL for one stage of a preset.
+pub fn transpile_stage(preset: &MilkPreset, stage: Stage) -> Result<String> {
+    match stage {
+        Stage::Warp => translate_hlsl(preset.warp_shader.as_deref(), "warp shader"),
+        Stage::Comp => translate_hlsl(preset.comp_shader.as_deref(), "composite shader"),
+        Stage::PerFrame => {
+            if preset.per_frame_equations.is_empty() {
+                bail!("Preset has no per-frame equations");
+            }
+
+            let statements = ExpressionTranspiler::new()
+                .transpile_all(&preset.per_frame_equations)
+                .context("Failed to transpile per-frame equations")?;
+
+            let mut wgsl = String::from("// Per-frame equations\n");
+            for statement in statements {
+                wgsl.push_str(&statement);
+                wgsl.push('\n');
+            }
+            Ok(wgsl)
+        }
+        Stage::PerPixel => {
+            if preset.per_pixel_equations.is_empty() {
+                bail!("Preset has no per-pixel equations");
+            }
+
+            ShaderGenerator::new()
+                .generate_per_pixel_shader(preset)
+                .context("Failed to generate per-pixel shader")
+        }
+    }
+}
+
+fn translate_hlsl(hlsl: Option<&str>, name: &str) -> Result<String> {
+    let Some(hlsl) = hlsl else {
+        bail!("Preset has no {}", name);
+    };
+    onedrop_hlsl::translate_shader(hlsl).with_context(|| format!("Failed to translate {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESET: &str = "[preset00]
+zoom=1.0
+per_frame_1=q1 = 0.01*bass;
+per_pixel_1=x = x + q1*rad;
+per_pixel_2=y = y + 0.1*sin(ang);
+";
+
+    #[test]
+    fn test_per_pixel_stage() {
+        let preset = onedrop_parser::parse_preset(PRESET).unwrap();
+        let wgsl = transpile_stage(&preset, Stage::PerPixel).unwrap();
+        assert!(wgsl.contains("@fragment"));
+        assert!(wgsl.contains("sin"));
+
+        let per_frame = transpile_stage(&preset, Stage::PerFrame).unwrap();
+        assert!(per_frame.contains("bass"));
+    }
+
+    #[test]
+    fn test_missing_stage() {
+        let preset = onedrop_parser::parse_preset(PRESET).unwrap();
+        let err = transpile_stage(&preset, Stage::Warp).unwrap_err();
+        assert!(err.to_string().contains("no warp shader"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Preset validation, for a single file or a whole directory tree.
+//!
+//! Presets are sorted into the same outcomes as the compatibility report in
+//! `tools/`: unreadable, unparsable, or failing to evaluate an equation, as
+//! checked by [`onedrop_eval::equation_errors`].
+
+use anyhow::{Context, Result};
+use onedrop_eval::equation_errors;
+use std::path::{Path, PathBuf};
+
+/// Check one preset, returning the first line of its first error.
+pub fn first_error(path: &Path) -> Option<String> {
+    let first_line = |message: String| message.lines().next().unwrap_or_default().to_string();
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return Some(first_line(format!("Read error: {}", e))),
+    };
+    let preset = match onedrop_parser::parse_preset(&content) {
+        Ok(preset) => preset,
+        Err(e) => return Some(first_line(format!("Parse error: {}", e))),
+    };
+    equation_errors(&preset)
+        .into_iter()
+        .next()
+        .map(|e| first_line(format!("Eval error: {}", e)))
+}
+
+/// All `.milk` files under `directory`, recursively, sorted by path.
+pub fn find_presets(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut presets = Vec::new();
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            // Not following directory symlinks, so a link loop can't
+            // recurse forever
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|s| s.to_str()) == Some("milk") {
+                presets.push(path);
+            }
+        }
+    }
+    presets.sort();
+    Ok(presets)
+}
+
+/// Outcome of validating every preset in a directory.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Number of presets checked
+    pub total: usize,
+
+    /// Failing presets, relative to the validated directory, with the first
+    /// line of their first error
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+impl ValidationReport {
+    /// Validate every `.milk` preset under `directory`.
+    pub fn from_directory(directory: &Path) -> Result<Self> {
+        let presets = find_presets(directory)?;
+        let failures = presets
+            .iter()
+            .filter_map(|path| {
+                let error = first_error(path)?;
+                let relative = path.strip_prefix(directory).unwrap_or(path);
+                Some((relative.to_path_buf(), error))
+            })
+            .collect();
+        Ok(Self {
+            total: presets.len(),
+            failures,
+        })
+    }
+
+    /// Number of presets that passed.
+    pub fn passed(&self) -> usize {
+        self.total - self.failures.len()
+    }
+
+    /// Human-readable summary: counts, then one line per failure.
+    pub fn to_table(&self) -> String {
+        let mut table = String::from("=== Validation Summary ===\n\n");
+        table.push_str(&format!("  Total:  {}\n", self.total));
+        table.push_str(&format!("  Passed: {}\n", self.passed()));
+        table.push_str(&format!("  Failed: {}\n", self.failures.len()));
+
+        if !self.failures.is_empty() {
+            table.push_str("\n=== Failures ===\n\n");
+            let width = self
+                .failures
+                .iter()
+                .map(|(path, _)| path.display().to_string().chars().count())
+                .max()
+                .unwrap_or(0);
+            for (path, error) in &self.failures {
+                let name = path.display().to_string();
+                table.push_str(&format!("  ✗ {:<width$}  {}\n", name, error));
+            }
+        }
+        table
+    }
+
+    /// Machine-readable summary for CI pipelines.
+    pub fn to_json(&self) -> String {
+        let failures: Vec<String> = self
+            .failures
+            .iter()
+            .map(|(path, error)| {
+                format!(
+                    "{{\"preset\":{},\"error\":{}}}",
+                    json_string(&path.display().to_string()),
+                    json_string(error)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"total\":{},\"passed\":{},\"failed\":{},\"failures\":[{}]}}",
+            self.total,
+            self.passed(),
+            self.failures.len(),
+            failures.join(",")
+        )
+    }
+}
+
+/// Quote and escape `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_report() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        let valid = onedrop_engine::default_preset().to_milk_string();
+        std::fs::write(dir.join("valid.milk"), valid).unwrap();
+        std::fs::write(
+            dir.join("nested/invalid.milk"),
+            "[preset00]\nper_frame_1=zoom = (1 +;\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a preset").unwrap();
+        // A directory symlink back up the tree isn't followed
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir, dir.join("nested/loop")).unwrap();
+
+        let report = ValidationReport::from_directory(dir).unwrap();
+
+        assert_eq!((report.total, report.passed()), (2, 1));
+        let (path, error) = &report.failures[0];
+        assert_eq!(path, Path::new("nested/invalid.milk"));
+        assert!(!error.contains('\n'));
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"total\":2,\"passed\":1,\"failed\":1,"));
+        assert!(json.contains("\"preset\":\"nested/invalid.milk\""));
+        assert!(report.to_table().contains("Failed: 1"));
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+}
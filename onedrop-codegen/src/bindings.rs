@@ -0,0 +1,36 @@
+//! Standard bind group layout for generated composite shaders.
+//!
+//! Mirrors `onedrop_renderer::bindings` and the renderer's `composite.wgsl`,
+//! so generated shaders can be used with the renderer's composite pipeline.
+
+/// Composite uniforms
+pub const UNIFORMS: u32 = 0;
+
+/// Main (previous frame) texture
+pub const MAIN_TEXTURE: u32 = 1;
+
+/// Sampler shared by the main and blur textures
+pub const SAMPLER: u32 = 2;
+
+/// Blur textures (blur1..blur3)
+pub const BLUR_TEXTURES: [u32; 3] = [3, 4, 5];
+
+/// WGSL declarations for the standard composite layout.
+///
+/// `uniform_type` is the name of the uniform struct bound as `uniforms`.
+pub fn composite_binding_declarations(uniform_type: &str) -> String {
+    let mut wgsl = format!(
+        "@group(0) @binding({})\nvar<uniform> uniforms: {};\n\n\
+         @group(0) @binding({})\nvar main_texture: texture_2d<f32>;\n\n\
+         @group(0) @binding({})\nvar texture_sampler: sampler;\n\n",
+        UNIFORMS, uniform_type, MAIN_TEXTURE, SAMPLER
+    );
+    for (i, binding) in BLUR_TEXTURES.iter().enumerate() {
+        wgsl.push_str(&format!(
+            "@group(0) @binding({})\nvar blur{}_texture: texture_2d<f32>;\n\n",
+            binding,
+            i + 1
+        ));
+    }
+    wgsl
+}
@@ -2,6 +2,7 @@
 
 use crate::error::{CodegenError, Result};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 /// Compiled shader with validated module
@@ -18,8 +19,11 @@ pub struct CompiledShader {
 }
 
 /// Shader compiler with caching
+///
+/// Clones share the cache, so a clone can warm it from another thread.
 pub struct ShaderCompiler {
     cache: Arc<Mutex<HashMap<String, CompiledShader>>>,
+    compiles: Arc<AtomicUsize>,
     validator: naga::valid::Validator,
 }
 
@@ -27,13 +31,18 @@ impl ShaderCompiler {
     pub fn new() -> Self {
         Self {
             cache: Arc::new(Mutex::new(HashMap::new())),
-            validator: naga::valid::Validator::new(
-                naga::valid::ValidationFlags::all(),
-                naga::valid::Capabilities::all(),
-            ),
+            compiles: Arc::new(AtomicUsize::new(0)),
+            validator: Self::create_validator(),
         }
     }
 
+    fn create_validator() -> naga::valid::Validator {
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+    }
+
     /// Compile and validate a WGSL shader
     pub fn compile(&mut self, source: &str) -> Result<CompiledShader> {
         // Check cache first
@@ -46,6 +55,7 @@ impl ShaderCompiler {
         }
 
         log::debug!("Compiling shader ({} bytes)", source.len());
+        self.compiles.fetch_add(1, Ordering::Relaxed);
 
         // Parse WGSL
         let module = naga::front::wgsl::parse_str(source)
@@ -80,6 +90,7 @@ impl ShaderCompiler {
         CacheStats {
             size: cache.len(),
             total_source_bytes: cache.values().map(|s| s.source.len()).sum(),
+            compiles: self.compiles.load(Ordering::Relaxed),
         }
     }
 
@@ -91,6 +102,16 @@ impl ShaderCompiler {
     }
 }
 
+impl Clone for ShaderCompiler {
+    fn clone(&self) -> Self {
+        Self {
+            cache: Arc::clone(&self.cache),
+            compiles: Arc::clone(&self.compiles),
+            validator: Self::create_validator(),
+        }
+    }
+}
+
 impl Default for ShaderCompiler {
     fn default() -> Self {
         Self::new()
@@ -102,6 +123,8 @@ impl Default for ShaderCompiler {
 pub struct CacheStats {
     pub size: usize,
     pub total_source_bytes: usize,
+    /// Cache misses compiled so far (including failed compiles)
+    pub compiles: usize,
 }
 
 #[cfg(test)]
@@ -149,6 +172,12 @@ fn fs_main() -> @location(0) vec4<f32> {
 
         let stats = compiler.cache_stats();
         assert_eq!(stats.size, 1);
+        assert_eq!(stats.compiles, 1);
+
+        // Clones share the cache
+        let mut clone = compiler.clone();
+        clone.compile(source).unwrap();
+        assert_eq!(compiler.cache_stats().compiles, 1);
     }
 
     #[test]
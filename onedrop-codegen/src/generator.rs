@@ -2,8 +2,9 @@
 //!
 //! Generates complete WGSL shaders from Milkdrop presets.
 
+use crate::bindings;
 use crate::error::Result;
-use crate::transpiler::ExpressionTranspiler;
+use crate::transpiler::{ExpressionTranspiler, VariableMapper};
 use onedrop_parser::MilkPreset;
 
 pub struct ShaderGenerator {
@@ -23,6 +24,7 @@ impl ShaderGenerator {
 
         // Add shader header
         shader.push_str(&self.generate_header());
+        shader.push_str(&self.generate_q_mapping(preset));
 
         // Add variable struct
         shader.push_str(&self.generate_variable_struct());
@@ -39,10 +41,370 @@ impl ShaderGenerator {
         Ok(shader)
     }
 
+    /// Generate a warp shader for `PerPixelPipeline` from per-pixel equations.
+    ///
+    /// `fs_main` runs the equations for each pixel on a copy of the
+    /// `PixelVars` uniform, then samples the input texture at the UV warped
+    /// by the resulting `zoom`, `rot`, `cx`/`cy`, `sx`/`sy` and `dx`/`dy`. `vs_main`
+    /// draws a full-screen quad as 6 vertices without vertex buffers.
+    pub fn generate_warp_shader(&self, per_pixel: &[String]) -> Result<String> {
+        let mut shader = String::new();
+
+        shader.push_str(&self.generate_header());
+        shader.push_str(&self.generate_variable_struct());
+        shader.push_str(&self.generate_uniforms());
+        shader.push_str(
+            r#"struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Two triangles covering the screen
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(corner.x * 2.0 - 1.0, 1.0 - corner.y * 2.0, 0.0, 1.0);
+    output.uv = corner;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    var vars = vars; // Local copy
+    vars.x = input.uv.x;
+    vars.y = input.uv.y;
+    vars.rad = length(input.uv - vec2<f32>(0.5, 0.5));
+    vars.ang = atan2(input.uv.y - 0.5, input.uv.x - 0.5);
+
+"#,
+        );
+
+        if !per_pixel.is_empty() {
+            shader.push_str("    // Per-pixel equations\n");
+            shader.push_str(&self.transpile_body(per_pixel)?);
+        }
+
+        shader.push_str(
+            r#"    // Warp: center, rotate, zoom, stretch, translate
+    let center = vec2<f32>(vars.cx, vars.cy);
+    var uv = input.uv - center;
+    let cos_r = cos(vars.rot);
+    let sin_r = sin(vars.rot);
+    uv = vec2<f32>(uv.x * cos_r - uv.y * sin_r, uv.x * sin_r + uv.y * cos_r);
+    uv = uv / vars.zoom;
+    uv = vec2<f32>(uv.x / vars.sx, uv.y / vars.sy);
+    uv = uv + center + vec2<f32>(vars.dx, vars.dy);
+
+    return textureSample(input_texture, texture_sampler, uv);
+}
+"#,
+        );
+
+        Ok(shader)
+    }
+
+    /// Generate a single module containing both the warp vertex stage and the
+    /// composite fragment stage.
+    ///
+    /// `vs_main` draws a full-screen quad (4 vertices, triangle strip) and runs
+    /// the per-pixel equations at each vertex to compute warped texture
+    /// coordinates; `fs_main` samples the previous frame at those coordinates
+    /// and applies decay. Both stages share one uniform struct, so simple
+    /// presets can be rendered with a single pipeline.
+    pub fn generate_combined(&self, preset: &MilkPreset) -> Result<String> {
+        let mut shader = String::new();
+
+        shader.push_str(&self.generate_header());
+        shader.push_str(&self.generate_q_mapping(preset));
+        shader.push_str(&self.generate_combined_struct());
+        shader.push_str(&bindings::composite_binding_declarations("CombinedVars"));
+        shader.push_str(
+            r#"struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+"#,
+        );
+        shader.push_str(&self.generate_combined_vertex_shader(preset)?);
+        shader.push_str(
+            r#"@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Sample previous frame (feedback)
+    var color = textureSample(main_texture, texture_sampler, input.uv);
+
+    // Apply decay
+    color = vec4<f32>(color.rgb * uniforms.decay, color.a);
+
+    return color;
+}
+"#,
+        );
+
+        Ok(shader)
+    }
+
+    /// Generate a composite shader using the standard binding layout.
+    ///
+    /// The uniform struct matches the renderer's `composite.wgsl`, and
+    /// `GetMain`/`GetBlur1`..`GetBlur3` helpers read the main and blur
+    /// textures so preset composite code can sample feedback.
+    pub fn generate_composite_shader(&self, preset: &MilkPreset) -> Result<String> {
+        let mut shader = String::new();
+
+        shader.push_str(&self.generate_header());
+        shader.push_str(
+            r#"struct Uniforms {
+    resolution: vec2<f32>,
+    time: f32,
+    decay: f32,
+    zoom: f32,
+    rot: f32,
+    cx: f32,
+    cy: f32,
+    dx: f32,
+    dy: f32,
+    sx: f32,
+    sy: f32,
+    warp: f32,
+    zoomexp: f32,
+    warp_scale: f32,
+    warp_time: f32,
+}
+
+"#,
+        );
+        shader.push_str(&bindings::composite_binding_declarations("Uniforms"));
+        shader.push_str(
+            r#"fn GetMain(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(main_texture, texture_sampler, uv).rgb;
+}
+
+fn GetBlur1(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(blur1_texture, texture_sampler, uv).rgb;
+}
+
+fn GetBlur2(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(blur2_texture, texture_sampler, uv).rgb;
+}
+
+fn GetBlur3(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(blur3_texture, texture_sampler, uv).rgb;
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Full-screen triangle
+    let x = f32((vertex_index & 1u) << 2u);
+    let y = f32((vertex_index & 2u) << 1u);
+
+    output.position = vec4<f32>(x - 1.0, 1.0 - y, 0.0, 1.0);
+    output.uv = vec2<f32>(x * 0.5, y * 0.5);
+
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Warp: center, rotate, zoom, stretch, translate
+    let center = vec2<f32>(uniforms.cx, uniforms.cy);
+    var uv = input.uv - center;
+    let cos_r = cos(uniforms.rot);
+    let sin_r = sin(uniforms.rot);
+    uv = vec2<f32>(uv.x * cos_r - uv.y * sin_r, uv.x * sin_r + uv.y * cos_r);
+    uv = uv / uniforms.zoom;
+    uv = vec2<f32>(uv.x / uniforms.sx, uv.y / uniforms.sy);
+    uv = uv + center + vec2<f32>(uniforms.dx, uniforms.dy);
+
+    // Sample feedback; outside the frame is black
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    var ret = select(vec3<f32>(0.0), GetMain(uv), inside);
+
+    // Decay and gamma
+"#,
+        );
+        shader.push_str(&format!(
+            "    ret = ret * uniforms.decay * {:?};
+
+",
+            preset.parameters.gamma()
+        ));
+        shader.push_str(
+            "    return vec4<f32>(ret, 1.0);
+}
+",
+        );
+
+        Ok(shader)
+    }
+
+    fn generate_combined_struct(&self) -> String {
+        r#"struct CombinedVars {
+    // Coordinates
+    x: f32,
+    y: f32,
+    rad: f32,
+    ang: f32,
+
+    // Audio
+    bass: f32,
+    mid: f32,
+    treb: f32,
+    bass_att: f32,
+    mid_att: f32,
+    treb_att: f32,
+
+    // Time
+    time: f32,
+    frame: f32,
+    fps: f32,
+
+    // Motion
+    decay: f32,
+    zoom: f32,
+    rot: f32,
+    cx: f32,
+    cy: f32,
+    dx: f32,
+    dy: f32,
+    sx: f32,
+    sy: f32,
+    warp: f32,
+    zoomexp: f32,
+
+    // Custom variables (vec4 for proper alignment)
+    q: array<vec4<f32>, 16>,  // 64 floats as 16 vec4s
+}
+
+"#
+        .to_string()
+    }
+
+    fn generate_combined_vertex_shader(&self, preset: &MilkPreset) -> Result<String> {
+        let mut shader = String::new();
+
+        shader.push_str(
+            r#"@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Full-screen quad corner
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32((vertex_index >> 1u) & 1u));
+
+    // Local copy
+    var vars = uniforms;
+    vars.x = corner.x;
+    vars.y = corner.y;
+    vars.rad = length(corner - vec2<f32>(0.5, 0.5));
+    vars.ang = atan2(corner.y - 0.5, corner.x - 0.5);
+
+"#,
+        );
+
+        if !preset.per_pixel_equations.is_empty() {
+            shader.push_str("    // Per-pixel equations (evaluated per vertex)\n");
+            shader.push_str(&self.transpile_body(&preset.per_pixel_equations)?);
+        }
+
+        shader.push_str(
+            r#"    // Warp: center, rotate, zoom, stretch, translate
+    let center = vec2<f32>(vars.cx, vars.cy);
+    var uv = corner - center;
+    let cos_r = cos(vars.rot);
+    let sin_r = sin(vars.rot);
+    uv = vec2<f32>(uv.x * cos_r - uv.y * sin_r, uv.x * sin_r + uv.y * cos_r);
+    uv = uv / vars.zoom;
+    uv = vec2<f32>(uv.x / vars.sx, uv.y / vars.sy);
+    uv = uv + center + vec2<f32>(vars.dx, vars.dy);
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(corner.x * 2.0 - 1.0, 1.0 - corner.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+"#,
+        );
+
+        Ok(shader)
+    }
+
+    /// Indented WGSL for a list of equations, with local variable
+    /// declarations first and a trailing blank line.
+    fn transpile_body(&self, equations: &[String]) -> Result<String> {
+        let mut body = String::new();
+        for line in self.transpiler.transpile_all(equations)? {
+            body.push_str("    ");
+            body.push_str(&line);
+            body.push('\n');
+        }
+        body.push('\n');
+        Ok(body)
+    }
+
     fn generate_header(&self) -> String {
         "// Auto-generated WGSL shader from Milkdrop preset\n\n".to_string()
     }
 
+    /// Comment block mapping each q variable the preset uses to its slot in
+    /// `vars.q`, with the per-frame equation that last assigns it.
+    fn generate_q_mapping(&self, preset: &MilkPreset) -> String {
+        let equations = preset
+            .per_frame_init_equations
+            .iter()
+            .chain(&preset.per_frame_equations)
+            .chain(&preset.per_pixel_equations);
+        let mut used = [false; 64];
+        for equation in equations {
+            for word in equation.split(|c: char| !c.is_ascii_alphanumeric() && c != '_') {
+                if let Some(n) = q_number(word) {
+                    used[n - 1] = true;
+                }
+            }
+        }
+        if !used.contains(&true) {
+            return String::new();
+        }
+
+        let mut last_writer: [Option<&str>; 64] = [None; 64];
+        let per_frame = preset
+            .per_frame_init_equations
+            .iter()
+            .chain(&preset.per_frame_equations);
+        for statement in per_frame.flat_map(|eq| eq.split(';')) {
+            if let Some(n) = assigned_q(statement) {
+                last_writer[n - 1] = Some(statement.trim());
+            }
+        }
+
+        let mut block = String::from("// q variables (vars.q is array<vec4<f32>, 16>):\n");
+        for n in (1..=64).filter(|n| used[n - 1]) {
+            let (vec, component) = VariableMapper::q_slot(n).expect("q1-q64 are in range");
+            block.push_str(&format!("//   q{} -> q[{}][{}]", n, vec, component));
+            if let Some(statement) = last_writer[n - 1] {
+                block.push_str(&format!("  (per-frame: {})", statement));
+            }
+            block.push('\n');
+        }
+        block.push('\n');
+        block
+    }
+
     fn generate_variable_struct(&self) -> String {
         r#"struct PixelVars {
     // Coordinates
@@ -64,8 +426,18 @@ impl ShaderGenerator {
     frame: f32,
     fps: f32,
     
-    // Padding for alignment
-    _padding: f32,
+    // Motion
+    decay: f32,
+    zoom: f32,
+    rot: f32,
+    cx: f32,
+    cy: f32,
+    dx: f32,
+    dy: f32,
+    sx: f32,
+    sy: f32,
+    warp: f32,
+    zoomexp: f32,
     
     // Custom variables (vec4 for proper alignment)
     q: array<vec4<f32>, 16>,  // 64 floats as 16 vec4s
@@ -131,14 +503,7 @@ fn vs_main(input: VertexInput) -> VertexOutput {
             shader.push_str("    vars.rad = length(input.uv - vec2<f32>(0.5, 0.5));\n");
             shader.push_str("    vars.ang = atan2(input.uv.y - 0.5, input.uv.x - 0.5);\n\n");
 
-            for equation in &preset.per_pixel_equations {
-                let wgsl = self.transpiler.transpile(equation)?;
-                shader.push_str("    ");
-                shader.push_str(&wgsl);
-                shader.push('\n');
-            }
-
-            shader.push('\n');
+            shader.push_str(&self.transpile_body(&preset.per_pixel_equations)?);
         }
 
         shader.push_str("    return color;\n");
@@ -148,6 +513,22 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     }
 }
 
+/// Parse `q<n>` (1-64) into `n`.
+fn q_number(word: &str) -> Option<usize> {
+    let n: usize = word.strip_prefix('q')?.parse().ok()?;
+    (1..=64).contains(&n).then_some(n)
+}
+
+/// The q variable assigned by a statement such as `q3 = bass` or `q3 += 1`.
+fn assigned_q(statement: &str) -> Option<usize> {
+    let eq = statement.find('=')?;
+    if statement[eq + 1..].starts_with('=') {
+        return None;
+    }
+    let lhs = statement[..eq].trim_end_matches(['+', '-', '*', '/', '%']);
+    q_number(lhs.trim())
+}
+
 impl Default for ShaderGenerator {
     fn default() -> Self {
         Self::new()
@@ -158,6 +539,19 @@ impl Default for ShaderGenerator {
 mod tests {
     use super::*;
 
+    /// Parse and validate generated WGSL, panicking with naga's error.
+    fn assert_valid_wgsl(src: &str) -> naga::Module {
+        let module = naga::front::wgsl::parse_str(src)
+            .unwrap_or_else(|e| panic!("WGSL should parse: {}", e.emit_to_string(src)));
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("WGSL should validate: {:?}", e));
+        module
+    }
+
     #[test]
     fn test_generate_empty_shader() {
         let generator = ShaderGenerator::new();
@@ -180,4 +574,114 @@ mod tests {
         assert!(shader.contains("vars.x"));
         assert!(shader.contains("0.01"));
     }
+
+    #[test]
+    fn test_generate_combined_shader() {
+        let generator = ShaderGenerator::new();
+        let mut preset = MilkPreset::default();
+        preset
+            .per_pixel_equations
+            .push("x = x + 0.01*sin(time)".to_string());
+        preset
+            .per_pixel_equations
+            .push("y = y + 0.01*bass".to_string());
+        preset.per_pixel_equations.push("sx=1.1".to_string());
+
+        let shader = generator.generate_combined(&preset).unwrap();
+        assert!(shader.contains("vars.sx = 1.1;"));
+
+        assert!(shader.contains("fn vs_main"));
+        assert!(shader.contains("fn fs_main"));
+        assert_eq!(shader.matches("var<uniform>").count(), 1);
+
+        let module = assert_valid_wgsl(&shader);
+        assert_eq!(module.entry_points.len(), 2);
+    }
+
+    #[test]
+    fn test_generate_warp_shader() {
+        let generator = ShaderGenerator::new();
+        let per_pixel = vec![
+            "zoom = zoom + 0.05*rad".to_string(),
+            "rot = rot + 0.1*sin(ang + time)".to_string(),
+            "dx = dx + 0.01*x".to_string(),
+            "dy = 0.01*y".to_string(),
+            "cy = cx + q1".to_string(),
+            "sy = sy * warp + zoomexp - 1".to_string(),
+            "my_warp = bass_att * 0.1".to_string(),
+            "zoom = zoom + my_warp".to_string(),
+            "rot = rot + if(bass>0.5, if(treb>0.5,1,0.5), 0) * above(mid, 0.2)".to_string(),
+        ];
+
+        let shader = generator.generate_warp_shader(&per_pixel).unwrap();
+        assert!(shader.contains("vars.zoom"));
+        assert!(shader.contains("var local_my_warp: f32 = 0.0;"));
+        assert!(shader.contains("textureSample(input_texture, texture_sampler, uv)"));
+
+        let module = assert_valid_wgsl(&shader);
+        assert_eq!(module.entry_points.len(), 2);
+
+        // Uniform layout must match the renderer's PixelVarsUniform
+        let vars = module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some("PixelVars"))
+            .map(|(_, ty)| ty)
+            .unwrap();
+        if let naga::TypeInner::Struct { members, span } = &vars.inner {
+            let q = members.iter().find(|m| m.name.as_deref() == Some("q"));
+            assert_eq!(q.unwrap().offset, 96);
+            assert_eq!(*span, 352);
+        } else {
+            panic!("PixelVars should be a struct");
+        }
+    }
+
+    #[test]
+    fn test_generate_composite_shader() {
+        let generator = ShaderGenerator::new();
+        let preset = MilkPreset::default();
+        let shader = generator.generate_composite_shader(&preset).unwrap();
+
+        assert!(shader.contains("@group(0) @binding(1)\nvar main_texture: texture_2d<f32>;"));
+        assert!(shader.contains("@group(0) @binding(2)\nvar texture_sampler: sampler;"));
+        assert!(shader.contains("@group(0) @binding(5)\nvar blur3_texture: texture_2d<f32>;"));
+        assert!(shader.contains("fn GetMain"));
+
+        assert_valid_wgsl(&shader);
+
+        // Same uniform layout as the renderer's composite pass
+        let uniforms = |wgsl: &str| {
+            let start = wgsl.find("struct Uniforms {").unwrap();
+            let end = start + wgsl[start..].find('}').unwrap();
+            wgsl[start..end].to_string()
+        };
+        let renderer = include_str!("../../onedrop-renderer/shaders/composite.wgsl");
+        assert_eq!(uniforms(&shader), uniforms(renderer));
+    }
+
+    #[test]
+    fn test_q_mapping_comment() {
+        let generator = ShaderGenerator::new();
+        let preset = MilkPreset {
+            per_frame_equations: vec!["q9 = bass * 0.5".to_string(), "q9 += 1".to_string()],
+            per_pixel_equations: vec!["x = x + q9 * 0.01".to_string()],
+            ..Default::default()
+        };
+
+        let shader = generator.generate_per_pixel_shader(&preset).unwrap();
+        assert!(shader.contains("//   q9 -> q[2][0]  (per-frame: q9 += 1)"));
+        assert!(shader.contains("vars.q[2][0]"));
+        assert!(!shader.contains("q1 ->"));
+
+        let combined = generator.generate_combined(&preset).unwrap();
+        assert!(combined.contains("q9 -> q[2][0]"));
+        assert_valid_wgsl(&combined);
+
+        // Presets without q variables get no mapping block
+        let plain = generator
+            .generate_per_pixel_shader(&MilkPreset::default())
+            .unwrap();
+        assert!(!plain.contains("q variables"));
+    }
 }
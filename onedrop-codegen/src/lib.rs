@@ -2,6 +2,7 @@
 //!
 //! Generate WGSL shaders from Milkdrop presets.
 
+pub mod bindings;
 pub mod compiler;
 pub mod error;
 pub mod generator;
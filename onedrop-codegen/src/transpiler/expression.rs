@@ -1,7 +1,10 @@
 //! Expression transpiler implementation
 
 use super::VariableMapper;
-use crate::error::Result;
+use crate::error::{CodegenError, Result};
+
+/// Milkdrop conditional functions rewritten into WGSL expressions.
+const CONDITIONALS: [&str; 4] = ["if", "above", "below", "equal"];
 
 pub struct ExpressionTranspiler {
     variable_mapper: VariableMapper,
@@ -15,7 +18,32 @@ impl ExpressionTranspiler {
     }
 
     /// Transpile a Milkdrop equation to WGSL
+    ///
+    /// Identifiers that aren't shader uniforms become function-local
+    /// variables (see [`VariableMapper::local_name`]); use
+    /// [`transpile_all`](Self::transpile_all) to get their declarations.
     pub fn transpile(&self, equation: &str) -> Result<String> {
+        self.transpile_with_locals(equation, &mut Vec::new())
+    }
+
+    /// Transpile several equations into WGSL statements, preceded by a
+    /// `var <name>: f32 = 0.0;` declaration for each local variable they use.
+    pub fn transpile_all(&self, equations: &[String]) -> Result<Vec<String>> {
+        let mut locals = Vec::new();
+        let mut statements = Vec::new();
+        for equation in equations {
+            statements.push(self.transpile_with_locals(equation, &mut locals)?);
+        }
+
+        Ok(locals
+            .iter()
+            .map(|local| format!("var {}: f32 = 0.0;", local))
+            .chain(statements)
+            .collect())
+    }
+
+    /// Transpile one equation, recording the local variables it uses.
+    fn transpile_with_locals(&self, equation: &str, locals: &mut Vec<String>) -> Result<String> {
         // Remove whitespace
         let equation = equation.trim();
 
@@ -25,32 +53,35 @@ impl ExpressionTranspiler {
 
         // Parse assignment (e.g., "x = expression")
         if let Some((lhs, rhs)) = equation.split_once('=') {
-            let lhs = self.transpile_variable(lhs.trim())?;
-            let rhs = self.transpile_expression(rhs.trim())?;
+            let lhs = self.transpile_variable(lhs.trim(), locals)?;
+            let rhs = self.transpile_expression(rhs.trim(), locals)?;
             Ok(format!("{} = {};", lhs, rhs))
         } else {
             // Just an expression
-            let expr = self.transpile_expression(equation)?;
+            let expr = self.transpile_expression(equation, locals)?;
             Ok(format!("{};", expr))
         }
     }
 
     /// Transpile a variable name
-    fn transpile_variable(&self, var: &str) -> Result<String> {
-        self.variable_mapper.map_variable(var)
+    fn transpile_variable(&self, var: &str, locals: &mut Vec<String>) -> Result<String> {
+        let is_identifier = var.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(CodegenError::InvalidVariable(var.to_string()));
+        }
+        Ok(self.map_identifier(var, locals))
     }
 
     /// Transpile an expression
-    fn transpile_expression(&self, expr: &str) -> Result<String> {
+    fn transpile_expression(&self, expr: &str, locals: &mut Vec<String>) -> Result<String> {
         let mut result = expr.to_string();
 
         // Replace Milkdrop functions with WGSL equivalents
         result = self.replace_functions(&result);
 
         // Replace variables
-        result = self.replace_variables(&result)?;
-
-        Ok(result)
+        self.replace_variables(&result, locals)
     }
 
     /// Replace function names
@@ -60,86 +91,152 @@ impl ExpressionTranspiler {
         expr.to_string()
     }
 
-    /// Replace variable names
-    fn replace_variables(&self, expr: &str) -> Result<String> {
-        let mut result = expr.to_string();
+    /// Replace variable names, leaving function names and numbers alone.
+    ///
+    /// Calls to Milkdrop's conditional functions are rewritten as a whole
+    /// (see [`rewrite_conditional`](Self::rewrite_conditional)).
+    fn replace_variables(&self, expr: &str, locals: &mut Vec<String>) -> Result<String> {
+        let mut result = String::new();
+        let mut chars = expr.char_indices().peekable();
 
-        // Common variables
-        let vars = [
-            ("time", "vars.time"),
-            ("frame", "vars.frame"),
-            ("bass", "vars.bass"),
-            ("mid", "vars.mid"),
-            ("treb", "vars.treb"),
-            ("x", "vars.x"),
-            ("y", "vars.y"),
-            ("rad", "vars.rad"),
-            ("ang", "vars.ang"),
-        ];
-
-        for (from, to) in &vars {
-            // Only replace whole words
-            result = Self::replace_word(&result, from, to);
-        }
+        while let Some((start, c)) = chars.next() {
+            if c.is_ascii_digit() || c == '.' {
+                // Number literal, including exponents such as 1e-3
+                result.push(c);
+                let mut prev = c;
+                while let Some(&(_, next)) = chars.peek() {
+                    let exponent_sign = (next == '-' || next == '+') && matches!(prev, 'e' | 'E');
+                    if !(next.is_ascii_alphanumeric() || next == '.' || exponent_sign) {
+                        break;
+                    }
+                    result.push(next);
+                    prev = next;
+                    chars.next();
+                }
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, next)) = chars.peek() {
+                    if !(next.is_ascii_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    end = i + next.len_utf8();
+                    chars.next();
+                }
+                let word = &expr[start..end];
+
+                let is_call = expr[end..].trim_start().starts_with('(');
+                if is_call && CONDITIONALS.contains(&word.to_ascii_lowercase().as_str()) {
+                    let open = expr[end..].find('(').expect("call has an opening paren") + end;
+                    let close = matching_paren(expr, open).ok_or_else(|| {
+                        CodegenError::UnsupportedExpression(format!("unbalanced call: {}", expr))
+                    })?;
+                    let args = split_arguments(&expr[open + 1..close]);
+                    result.push_str(&self.rewrite_conditional(word, &args, locals)?);
 
-        // Replace q variables (q1-q64)
-        for i in 1..=64 {
-            let from = format!("q{}", i);
-            let to = format!("vars.q[{}]", i - 1);
-            result = Self::replace_word(&result, &from, &to);
+                    // Skip past the call's closing paren
+                    while chars.next_if(|(i, _)| *i <= close).is_some() {}
+                } else if is_call {
+                    result.push_str(word);
+                } else {
+                    result.push_str(&self.map_identifier(word, locals));
+                }
+            } else {
+                result.push(c);
+            }
         }
 
         Ok(result)
     }
 
-    /// Replace a whole word (not part of another word)
-    fn replace_word(text: &str, from: &str, to: &str) -> String {
-        let mut result = String::new();
-        let mut chars = text.chars().peekable();
-        let from_chars: Vec<char> = from.chars().collect();
-
-        while chars.peek().is_some() {
-            // Try to match the word
-            let mut matched = true;
-            let mut temp_chars = chars.clone();
-
-            // Check if we're at the start of a word
-            if result.is_empty() || !result.chars().last().unwrap().is_alphanumeric() {
-                for &fc in &from_chars {
-                    if let Some(&tc) = temp_chars.peek() {
-                        if tc == fc {
-                            temp_chars.next();
-                        } else {
-                            matched = false;
-                            break;
-                        }
-                    } else {
-                        matched = false;
-                        break;
-                    }
-                }
+    /// Rewrite `if(c, a, b)`, `above(a, b)`, `below(a, b)` or `equal(a, b)`.
+    ///
+    /// Milkdrop treats any non-zero value as true and comparisons yield 0 or
+    /// 1, so `if` becomes `select(b, a, c != 0.0)` (WGSL takes the false
+    /// value first) and the comparisons become `f32(a > b)` and so on. Values
+    /// are wrapped in `f32()` so integer literals and WGSL bools type-check.
+    /// Arguments are transpiled recursively, so nested calls work.
+    fn rewrite_conditional(
+        &self,
+        name: &str,
+        args: &[&str],
+        locals: &mut Vec<String>,
+    ) -> Result<String> {
+        let name = name.to_ascii_lowercase();
+        let expected = if name == "if" { 3 } else { 2 };
+        if args.len() != expected {
+            return Err(CodegenError::UnsupportedExpression(format!(
+                "{}() takes {} arguments, got {}",
+                name,
+                expected,
+                args.len()
+            )));
+        }
 
-                // Check if we're at the end of a word
-                if matched {
-                    if let Some(&next_ch) = temp_chars.peek() {
-                        if next_ch.is_alphanumeric() || next_ch == '_' {
-                            matched = false;
-                        }
-                    }
-                }
+        let args = args
+            .iter()
+            .map(|arg| self.transpile_expression(arg.trim(), locals))
+            .collect::<Result<Vec<_>>>()?;
 
-                if matched {
-                    result.push_str(to);
-                    chars = temp_chars;
-                    continue;
-                }
+        Ok(match name.as_str() {
+            "if" => format!(
+                "select(f32({}), f32({}), f32({}) != 0.0)",
+                args[2], args[1], args[0]
+            ),
+            "above" => format!("f32(({}) > ({}))", args[0], args[1]),
+            "below" => format!("f32(({}) < ({}))", args[0], args[1]),
+            _ => format!("f32(({}) == ({}))", args[0], args[1]),
+        })
+    }
+
+    /// Map a variable to its uniform field, or to a local variable.
+    fn map_identifier(&self, var: &str, locals: &mut Vec<String>) -> String {
+        let var = var.to_ascii_lowercase();
+        self.variable_mapper.map_variable(&var).unwrap_or_else(|_| {
+            let local = VariableMapper::local_name(&var);
+            if !locals.contains(&local) {
+                locals.push(local.clone());
             }
+            local
+        })
+    }
+}
 
-            result.push(chars.next().unwrap());
+/// Byte index of the paren closing the one at `open`.
+fn matching_paren(expr: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in expr[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
         }
+    }
+    None
+}
 
-        result
+/// Split call arguments on top-level commas.
+fn split_arguments(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
     }
+    parts.push(&args[start..]);
+    parts
 }
 
 impl Default for ExpressionTranspiler {
@@ -167,17 +264,92 @@ mod tests {
         assert!(result.contains("sin(vars.time)"));
     }
 
+    #[test]
+    fn test_stretch_writes_uniform_field() {
+        let transpiler = ExpressionTranspiler::new();
+        assert_eq!(transpiler.transpile("sx=1.1").unwrap(), "vars.sx = 1.1;");
+        assert_eq!(
+            transpiler.transpile("sy = sy * warp").unwrap(),
+            "vars.sy = vars.sy * vars.warp;"
+        );
+    }
+
     #[test]
     fn test_q_variable() {
         let transpiler = ExpressionTranspiler::new();
         let result = transpiler.transpile("x = q1 + q2").unwrap();
-        assert!(result.contains("vars.q[0]"));
-        assert!(result.contains("vars.q[1]"));
+        assert!(result.contains("vars.q[0][0]"));
+        assert!(result.contains("vars.q[0][1]"));
     }
 
     #[test]
-    fn test_replace_word() {
-        let result = ExpressionTranspiler::replace_word("x + x2 + x", "x", "vars.x");
-        assert_eq!(result, "vars.x + x2 + vars.x");
+    fn test_replace_whole_words() {
+        let transpiler = ExpressionTranspiler::new();
+        let result = transpiler
+            .replace_variables("x + x2 + x", &mut Vec::new())
+            .unwrap();
+        assert_eq!(result, "vars.x + local_x2 + vars.x");
+    }
+
+    #[test]
+    fn test_attenuated_audio_and_literals() {
+        let transpiler = ExpressionTranspiler::new();
+        let result = transpiler
+            .transpile("x = bass_att*1.5e-2 + sin(treb_att)")
+            .unwrap();
+        assert_eq!(
+            result,
+            "vars.x = vars.bass_att*1.5e-2 + sin(vars.treb_att);"
+        );
+    }
+
+    #[test]
+    fn test_unknown_identifiers_become_locals() {
+        let transpiler = ExpressionTranspiler::new();
+        let lines = transpiler
+            .transpile_all(&[
+                "my_var = bass * 2".to_string(),
+                "x = x + My_Var + q37".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(
+            lines,
+            [
+                "var local_my_var: f32 = 0.0;",
+                "local_my_var = vars.bass * 2;",
+                "vars.x = vars.x + local_my_var + vars.q[9][0];",
+            ]
+        );
+
+        assert!(transpiler.transpile("x + = 1").is_err());
+    }
+
+    #[test]
+    fn test_nested_if() {
+        let transpiler = ExpressionTranspiler::new();
+        let result = transpiler
+            .transpile("x = if(bass>0.5, if(treb>0.5,1,0.5), 0)")
+            .unwrap();
+        assert_eq!(
+            result,
+            "vars.x = select(f32(0), f32(select(f32(0.5), f32(1), f32(vars.treb>0.5) != 0.0)), \
+             f32(vars.bass>0.5) != 0.0);"
+        );
+    }
+
+    #[test]
+    fn test_comparison_functions() {
+        let transpiler = ExpressionTranspiler::new();
+        assert_eq!(
+            transpiler.transpile("x = above(bass, 0.5)").unwrap(),
+            "vars.x = f32((vars.bass) > (0.5));"
+        );
+        assert_eq!(
+            transpiler
+                .transpile("y = below(treb, mid) + equal(q1, 2)")
+                .unwrap(),
+            "vars.y = f32((vars.treb) < (vars.mid)) + f32((vars.q[0][0]) == (2));"
+        );
+        assert!(transpiler.transpile("x = if(bass, 1)").is_err());
     }
 }
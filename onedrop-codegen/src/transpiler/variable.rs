@@ -9,6 +9,18 @@ impl VariableMapper {
         Self
     }
 
+    /// Location of `q<n>` (1-64) in the `q: array<vec4<f32>, 16>` field,
+    /// as `(vec4 index, component)`.
+    pub fn q_slot(n: usize) -> Option<(usize, usize)> {
+        (1..=64).contains(&n).then(|| ((n - 1) / 4, (n - 1) % 4))
+    }
+
+    /// WGSL name of the function-local variable standing in for a preset's
+    /// own variable (one that isn't a shader uniform).
+    pub fn local_name(var: &str) -> String {
+        format!("local_{}", var)
+    }
+
     /// Map a Milkdrop variable to WGSL
     pub fn map_variable(&self, var: &str) -> Result<String> {
         match var {
@@ -31,13 +43,26 @@ impl VariableMapper {
             "frame" => Ok("vars.frame".to_string()),
             "fps" => Ok("vars.fps".to_string()),
 
+            // Motion
+            "decay" => Ok("vars.decay".to_string()),
+            "zoom" => Ok("vars.zoom".to_string()),
+            "zoomexp" => Ok("vars.zoomexp".to_string()),
+            "rot" => Ok("vars.rot".to_string()),
+            "warp" => Ok("vars.warp".to_string()),
+            "cx" => Ok("vars.cx".to_string()),
+            "cy" => Ok("vars.cy".to_string()),
+            "dx" => Ok("vars.dx".to_string()),
+            "dy" => Ok("vars.dy".to_string()),
+            "sx" => Ok("vars.sx".to_string()),
+            "sy" => Ok("vars.sy".to_string()),
+
             // Q variables
             var if var.starts_with('q') && var.len() > 1 => {
                 let num: usize = var[1..]
                     .parse()
                     .map_err(|_| CodegenError::InvalidVariable(var.to_string()))?;
-                if (1..=64).contains(&num) {
-                    Ok(format!("vars.q[{}]", num - 1))
+                if let Some((vec, component)) = Self::q_slot(num) {
+                    Ok(format!("vars.q[{}][{}]", vec, component))
                 } else {
                     Err(CodegenError::InvalidVariable(format!(
                         "q{} out of range (1-64)",
@@ -77,14 +102,33 @@ mod tests {
         assert_eq!(mapper.map_variable("bass").unwrap(), "vars.bass");
         assert_eq!(mapper.map_variable("mid").unwrap(), "vars.mid");
         assert_eq!(mapper.map_variable("treb").unwrap(), "vars.treb");
+        assert_eq!(mapper.map_variable("bass_att").unwrap(), "vars.bass_att");
+        assert_eq!(mapper.map_variable("mid_att").unwrap(), "vars.mid_att");
+        assert_eq!(mapper.map_variable("treb_att").unwrap(), "vars.treb_att");
+    }
+
+    #[test]
+    fn test_motion_variables() {
+        let mapper = VariableMapper::new();
+        assert_eq!(mapper.map_variable("zoom").unwrap(), "vars.zoom");
+        assert_eq!(mapper.map_variable("rot").unwrap(), "vars.rot");
+        assert_eq!(mapper.map_variable("dx").unwrap(), "vars.dx");
+        assert_eq!(mapper.map_variable("cy").unwrap(), "vars.cy");
+        assert_eq!(mapper.map_variable("sx").unwrap(), "vars.sx");
+        assert_eq!(mapper.map_variable("sy").unwrap(), "vars.sy");
+        assert_eq!(mapper.map_variable("warp").unwrap(), "vars.warp");
+        assert_eq!(mapper.map_variable("zoomexp").unwrap(), "vars.zoomexp");
+        assert_eq!(mapper.map_variable("decay").unwrap(), "vars.decay");
     }
 
     #[test]
     fn test_q_variables() {
         let mapper = VariableMapper::new();
-        assert_eq!(mapper.map_variable("q1").unwrap(), "vars.q[0]");
-        assert_eq!(mapper.map_variable("q32").unwrap(), "vars.q[31]");
-        assert_eq!(mapper.map_variable("q64").unwrap(), "vars.q[63]");
+        assert_eq!(mapper.map_variable("q1").unwrap(), "vars.q[0][0]");
+        assert_eq!(mapper.map_variable("q9").unwrap(), "vars.q[2][0]");
+        assert_eq!(mapper.map_variable("q32").unwrap(), "vars.q[7][3]");
+        assert_eq!(mapper.map_variable("q37").unwrap(), "vars.q[9][0]");
+        assert_eq!(mapper.map_variable("q64").unwrap(), "vars.q[15][3]");
     }
 
     #[test]
@@ -1,6 +1,165 @@
 //! Audio processing and analysis.
 
-use onedrop_renderer::AudioLevels;
+use crate::fft::FFTAnalyzer;
+use onedrop_renderer::{AudioLevels, SPECTRUM_BINS};
+
+/// Frequency band exposed to presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Band {
+    /// Low frequencies (`bass`, `bass_att`)
+    Bass,
+    /// Middle frequencies (`mid`, `mid_att`)
+    Mid,
+    /// High frequencies (`treb`, `treb_att`)
+    Treb,
+}
+
+impl Band {
+    /// All bands, in `bass`/`mid`/`treb` order.
+    pub const ALL: [Band; 3] = [Band::Bass, Band::Mid, Band::Treb];
+
+    /// Index of this band in [`Band::ALL`].
+    pub fn index(self) -> usize {
+        match self {
+            Band::Bass => 0,
+            Band::Mid => 1,
+            Band::Treb => 2,
+        }
+    }
+}
+
+/// How raw band magnitudes (0-1) map to band values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BandScale {
+    /// Proportional to magnitude
+    #[default]
+    Linear,
+    /// Decibels: [`LOG_SCALE_FLOOR_DB`] to 0 dB maps to 0-1
+    Log,
+    /// Stevens' power law for loudness, `magnitude^0.6`
+    Perceptual,
+}
+
+/// Quietest level shown by [`BandScale::Log`], in dB relative to full scale.
+pub const LOG_SCALE_FLOOR_DB: f32 = -60.0;
+
+impl BandScale {
+    /// Map a raw magnitude to a band value.
+    pub fn apply(self, magnitude: f32) -> f32 {
+        let magnitude = magnitude.max(0.0);
+        match self {
+            BandScale::Linear => magnitude,
+            BandScale::Log => {
+                if magnitude <= 0.0 {
+                    return 0.0;
+                }
+                let db = 20.0 * magnitude.log10();
+                ((db - LOG_SCALE_FLOOR_DB) / -LOG_SCALE_FLOOR_DB).clamp(0.0, 1.0)
+            }
+            BandScale::Perceptual => magnitude.powf(0.6),
+        }
+    }
+}
+
+/// Default silence gate threshold (overall RMS, roughly -60 dBFS).
+pub const DEFAULT_SILENCE_THRESHOLD: f32 = 0.001;
+
+/// Per-frame decay of the long-term band averages used for normalization
+/// (a time constant of roughly two seconds at 60 fps).
+pub const LONG_TERM_DECAY: f32 = 0.992;
+
+/// Faster decay for the first frames, so the long-term averages settle on
+/// the track's level quickly.
+const LONG_TERM_WARMUP_DECAY: f32 = 0.9;
+
+/// Frames using [`LONG_TERM_WARMUP_DECAY`].
+const LONG_TERM_WARMUP_FRAMES: u32 = 50;
+
+/// Default smallest long-term average levels are divided by.
+pub const DEFAULT_NORMALIZE_FLOOR: f32 = 0.001;
+
+/// Running per-band averages that center levels on 1.0, as Milkdrop does:
+/// a steady signal reads about 1.0 and transients rise above it, however
+/// loud the track is.
+#[derive(Debug, Clone)]
+pub struct BandNormalizer {
+    /// Long-term average of each band, in [`Band::ALL`] order
+    averages: [f32; 3],
+
+    /// Frames folded into the averages
+    frames: u32,
+
+    /// Smallest average levels are divided by, so silence reads 0 instead
+    /// of dividing by zero
+    floor: f32,
+}
+
+impl BandNormalizer {
+    /// Create a normalizer with no history.
+    pub fn new() -> Self {
+        Self {
+            averages: [0.0; 3],
+            frames: 0,
+            floor: DEFAULT_NORMALIZE_FLOOR,
+        }
+    }
+
+    /// Fold a frame's band levels into the averages.
+    ///
+    /// Frames where every band is below the floor are skipped: silence
+    /// would drag the averages down and make the music after it read as loud.
+    pub fn update(&mut self, levels: [f32; 3]) {
+        if levels.iter().all(|&level| level < self.floor) {
+            return;
+        }
+
+        let decay = if self.frames < LONG_TERM_WARMUP_FRAMES {
+            LONG_TERM_WARMUP_DECAY
+        } else {
+            LONG_TERM_DECAY
+        };
+        for (average, level) in self.averages.iter_mut().zip(levels) {
+            *average = if self.frames == 0 {
+                level
+            } else {
+                *average * decay + level * (1.0 - decay)
+            };
+        }
+        self.frames = self.frames.saturating_add(1);
+    }
+
+    /// Divide a level by its band's long-term average (at least the floor).
+    pub fn normalize(&self, value: f32, band: Band) -> f32 {
+        value / self.averages[band.index()].max(self.floor)
+    }
+
+    /// Long-term average of a band.
+    pub fn average(&self, band: Band) -> f32 {
+        self.averages[band.index()]
+    }
+
+    /// Set the smallest average levels are divided by.
+    pub fn set_floor(&mut self, floor: f32) {
+        self.floor = floor.max(f32::EPSILON);
+    }
+
+    /// Get the smallest average levels are divided by.
+    pub fn floor(&self) -> f32 {
+        self.floor
+    }
+
+    /// Forget the averages.
+    pub fn reset(&mut self) {
+        self.averages = [0.0; 3];
+        self.frames = 0;
+    }
+}
+
+impl Default for BandNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Audio analyzer for extracting frequency bands.
 #[allow(dead_code)]
@@ -17,8 +176,27 @@ pub struct AudioAnalyzer {
     /// Treble attenuated value
     treb_att: f32,
 
-    /// Attenuation factor (0-1)
+    /// Attenuation factor (0-1): the share of the previous `_att` value kept
+    /// each frame
     attenuation: f32,
+
+    /// Long-term band averages for normalization
+    normalizer: BandNormalizer,
+
+    /// Divide levels by their long-term average, centering them on 1.0
+    normalize: bool,
+
+    /// Overall RMS below which all bands are forced to zero
+    silence_threshold: f32,
+
+    /// Mapping from band RMS to band values
+    band_scale: BandScale,
+
+    /// FFT behind the magnitude spectrum
+    fft: FFTAnalyzer,
+
+    /// Normalized magnitudes from the last analysis
+    spectrum: [f32; SPECTRUM_BINS],
 }
 
 impl AudioAnalyzer {
@@ -30,33 +208,91 @@ impl AudioAnalyzer {
             mid_att: 0.0,
             treb_att: 0.0,
             attenuation: 0.8, // Default attenuation
+            normalizer: BandNormalizer::new(),
+            normalize: false,
+            silence_threshold: DEFAULT_SILENCE_THRESHOLD,
+            band_scale: BandScale::default(),
+            fft: FFTAnalyzer::new_or_default(SPECTRUM_BINS * 2, sample_rate),
+            spectrum: [0.0; SPECTRUM_BINS],
         }
     }
 
     /// Analyze audio samples and extract frequency bands.
+    ///
+    /// The `_att` levels are an exponential moving average of the
+    /// instantaneous ones. With [normalization](Self::set_normalize) both are
+    /// divided by the band's long-term average.
     pub fn analyze(&mut self, samples: &[f32]) -> AudioLevels {
         // Simple frequency band extraction
         // In a real implementation, this would use FFT
 
-        let bass = Self::extract_band(samples, 0, samples.len() / 4);
-        let mid = Self::extract_band(samples, samples.len() / 4, samples.len() / 2);
-        let treb = Self::extract_band(samples, samples.len() / 2, samples.len());
+        let silent = Self::extract_band(samples, 0, samples.len()) < self.silence_threshold;
+        let (bass, mid, treb) = if silent {
+            // Silence gate: ignore noise-floor jitter so presets settle
+            self.spectrum = [0.0; SPECTRUM_BINS];
+            (0.0, 0.0, 0.0)
+        } else {
+            self.update_spectrum(samples);
+            let scale = |band: f32| self.band_scale.apply(band);
+            (
+                scale(Self::extract_band(samples, 0, samples.len() / 4)),
+                scale(Self::extract_band(
+                    samples,
+                    samples.len() / 4,
+                    samples.len() / 2,
+                )),
+                scale(Self::extract_band(
+                    samples,
+                    samples.len() / 2,
+                    samples.len(),
+                )),
+            )
+        };
 
         // Update attenuated values with smoothing
         self.bass_att = self.bass_att * self.attenuation + bass * (1.0 - self.attenuation);
         self.mid_att = self.mid_att * self.attenuation + mid * (1.0 - self.attenuation);
         self.treb_att = self.treb_att * self.attenuation + treb * (1.0 - self.attenuation);
 
+        self.normalizer.update([bass, mid, treb]);
+
+        let level = |value: f32, band: Band| {
+            if self.normalize {
+                self.normalizer.normalize(value, band)
+            } else {
+                value
+            }
+        };
         AudioLevels {
-            bass,
-            mid,
-            treb,
-            bass_att: self.bass_att,
-            mid_att: self.mid_att,
-            treb_att: self.treb_att,
+            bass: level(bass, Band::Bass),
+            mid: level(mid, Band::Mid),
+            treb: level(treb, Band::Treb),
+            bass_att: level(self.bass_att, Band::Bass),
+            mid_att: level(self.mid_att, Band::Mid),
+            treb_att: level(self.treb_att, Band::Treb),
         }
     }
 
+    /// Compute the normalized magnitude spectrum of the first
+    /// `2 * SPECTRUM_BINS` samples.
+    fn update_spectrum(&mut self, samples: &[f32]) {
+        // A full-scale sine peaks at 1/4 after the Hann window and one-sided
+        // magnitude halving
+        const FULL_SCALE_PEAK: f32 = 0.25;
+
+        let bins = self.fft.analyze(samples);
+        for (out, &bin) in self.spectrum.iter_mut().zip(bins) {
+            *out = (bin / FULL_SCALE_PEAK).min(1.0);
+        }
+    }
+
+    /// Normalized (0-1) magnitude spectrum from the last [`analyze`](Self::analyze).
+    ///
+    /// Bin `i` covers `i * sample_rate / (2 * SPECTRUM_BINS)` Hz.
+    pub fn spectrum(&self) -> &[f32; SPECTRUM_BINS] {
+        &self.spectrum
+    }
+
     /// Extract a frequency band from samples.
     fn extract_band(samples: &[f32], start: usize, end: usize) -> f32 {
         if samples.is_empty() || start >= end {
@@ -76,16 +312,66 @@ impl AudioAnalyzer {
         rms.min(1.0)
     }
 
-    /// Set attenuation factor.
+    /// Set the `_att` smoothing coefficient: the share of the previous value
+    /// kept each frame. Higher values follow the instantaneous levels more
+    /// slowly.
     pub fn set_attenuation(&mut self, attenuation: f32) {
         self.attenuation = attenuation.clamp(0.0, 1.0);
     }
 
+    /// Get the `_att` smoothing coefficient.
+    pub fn attenuation(&self) -> f32 {
+        self.attenuation
+    }
+
+    /// Center levels on 1.0 by dividing them by their long-term average, so
+    /// loud and quiet tracks drive presets alike (as Milkdrop does).
+    pub fn set_normalize(&mut self, normalize: bool) {
+        self.normalize = normalize;
+    }
+
+    /// Check whether levels are normalized to their long-term average.
+    pub fn normalize(&self) -> bool {
+        self.normalize
+    }
+
+    /// Long-term average of a band, before normalization.
+    pub fn long_term_average(&self, band: Band) -> f32 {
+        self.normalizer.average(band)
+    }
+
+    /// Set the smallest long-term average levels are divided by.
+    pub fn set_normalize_floor(&mut self, floor: f32) {
+        self.normalizer.set_floor(floor);
+    }
+
+    /// Set the silence gate threshold (overall RMS). `0.0` disables the gate.
+    pub fn set_silence_threshold(&mut self, threshold: f32) {
+        self.silence_threshold = threshold.max(0.0);
+    }
+
+    /// Get the silence gate threshold.
+    pub fn silence_threshold(&self) -> f32 {
+        self.silence_threshold
+    }
+
+    /// Set how band magnitudes map to band values.
+    pub fn set_band_scale(&mut self, band_scale: BandScale) {
+        self.band_scale = band_scale;
+    }
+
+    /// Get the band scaling curve.
+    pub fn band_scale(&self) -> BandScale {
+        self.band_scale
+    }
+
     /// Reset attenuated values.
     pub fn reset(&mut self) {
         self.bass_att = 0.0;
         self.mid_att = 0.0;
         self.treb_att = 0.0;
+        self.normalizer.reset();
+        self.spectrum = [0.0; SPECTRUM_BINS];
     }
 }
 
@@ -133,6 +419,33 @@ mod tests {
         assert!(levels.treb > 0.0);
     }
 
+    #[test]
+    fn test_spectrum_pure_tone() {
+        let sample_rate = 44100.0;
+        let mut analyzer = AudioAnalyzer::new(sample_rate);
+
+        // A tone centered on bin 40
+        let bin = 40;
+        let freq = bin as f32 * sample_rate / (SPECTRUM_BINS * 2) as f32;
+        let samples: Vec<f32> = (0..SPECTRUM_BINS * 2)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect();
+
+        analyzer.analyze(&samples);
+        let spectrum = analyzer.spectrum();
+        let peak = spectrum
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(peak, bin);
+        assert_relative_eq!(spectrum[bin], 0.5, epsilon = 0.01);
+
+        analyzer.analyze(&vec![0.0; SPECTRUM_BINS * 2]);
+        assert!(analyzer.spectrum().iter().all(|&m| m == 0.0));
+    }
+
     #[test]
     fn test_attenuation() {
         let mut analyzer = AudioAnalyzer::new(44100.0);
@@ -149,4 +462,149 @@ mod tests {
         // Attenuated values should be smoothed
         assert!(levels2.bass_att > 0.0);
     }
+
+    #[test]
+    fn test_attenuation_step() {
+        let mut analyzer = AudioAnalyzer::new(44100.0);
+        analyzer.analyze(&[0.0; 1024]);
+
+        let tone: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.1).sin() * 0.5).collect();
+        let mut previous = 0.0;
+        let mut levels = analyzer.analyze(&tone);
+        assert!(levels.bass_att < levels.bass * 0.5);
+
+        // bass_att follows the step gradually
+        for _ in 0..10 {
+            assert!(levels.bass_att > previous);
+            assert!(levels.bass_att < levels.bass);
+            previous = levels.bass_att;
+            levels = analyzer.analyze(&tone);
+        }
+        for _ in 0..40 {
+            levels = analyzer.analyze(&tone);
+        }
+        assert_relative_eq!(levels.bass_att, levels.bass, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_normalized_levels() {
+        let tone = |amplitude: f32| -> Vec<f32> {
+            (0..1024)
+                .map(|i| (i as f32 * 0.1).sin() * amplitude)
+                .collect()
+        };
+        let settle = |amplitude: f32| {
+            let mut analyzer = AudioAnalyzer::new(44100.0);
+            analyzer.set_normalize(true);
+            let samples = tone(amplitude);
+            let mut levels = analyzer.analyze(&samples);
+            for _ in 0..100 {
+                levels = analyzer.analyze(&samples);
+            }
+            levels
+        };
+
+        // Loud and quiet tracks both center on 1.0
+        for levels in [settle(0.8), settle(0.05)] {
+            assert_relative_eq!(levels.bass, 1.0, epsilon = 1e-3);
+            assert_relative_eq!(levels.treb, 1.0, epsilon = 1e-3);
+            assert_relative_eq!(levels.bass_att, 1.0, epsilon = 1e-3);
+        }
+
+        // A burst stands out against the long-term average
+        let mut analyzer = AudioAnalyzer::new(44100.0);
+        analyzer.set_normalize(true);
+        for _ in 0..100 {
+            analyzer.analyze(&tone(0.1));
+        }
+        assert!(analyzer.analyze(&tone(0.3)).bass > 2.0);
+        assert_relative_eq!(
+            analyzer.long_term_average(Band::Bass),
+            0.0707,
+            epsilon = 0.01
+        );
+    }
+
+    #[test]
+    fn test_band_normalizer() {
+        let mut normalizer = BandNormalizer::new();
+
+        // A sustained tone with a little frame-to-frame jitter
+        for frame in 0..200 {
+            let jitter = if frame % 2 == 0 { 1.05 } else { 0.95 };
+            normalizer.update([0.3 * jitter, 0.02 * jitter, 0.004 * jitter]);
+        }
+        for band in Band::ALL {
+            let steady = normalizer.average(band);
+            assert_relative_eq!(normalizer.normalize(steady, band), 1.0, epsilon = 1e-6);
+        }
+        assert!((normalizer.normalize(0.3, Band::Bass) - 1.0).abs() < 0.05);
+        assert!(normalizer.normalize(0.9, Band::Bass) > 2.5);
+
+        // Silence reads 0 and leaves the averages alone
+        let bass = normalizer.average(Band::Bass);
+        normalizer.update([0.0; 3]);
+        assert_eq!(normalizer.average(Band::Bass), bass);
+        assert_eq!(normalizer.normalize(0.0, Band::Treb), 0.0);
+
+        // Without history the floor guards the division
+        let fresh = BandNormalizer::new();
+        let level = fresh.normalize(0.5, Band::Mid);
+        assert!(level.is_finite());
+        assert_relative_eq!(level, 0.5 / DEFAULT_NORMALIZE_FLOOR);
+    }
+
+    #[test]
+    fn test_silence_gate() {
+        let mut analyzer = AudioAnalyzer::new(44100.0);
+
+        // Noise well below the gate
+        let noise: Vec<f32> = (0..1024)
+            .map(|i| ((i * 7919) % 13) as f32 / 13.0 * 0.0005 - 0.00025)
+            .collect();
+        let levels = analyzer.analyze(&noise);
+        assert_eq!(levels.bass, 0.0);
+        assert_eq!(levels.mid, 0.0);
+        assert_eq!(levels.treb, 0.0);
+
+        // A clear tone passes through
+        let tone: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.1).sin() * 0.5).collect();
+        let levels = analyzer.analyze(&tone);
+        assert!(levels.bass > 0.1);
+        assert!(levels.mid > 0.1);
+        assert!(levels.treb > 0.1);
+
+        // Disabling the gate lets the noise through
+        analyzer.set_silence_threshold(0.0);
+        assert!(analyzer.analyze(&noise).bass > 0.0);
+    }
+
+    #[test]
+    fn test_band_scale_sweep() {
+        let sweep: Vec<f32> = (1..=10).map(|i| i as f32 / 10.0).collect();
+        let linear: Vec<f32> = sweep.iter().map(|m| BandScale::Linear.apply(*m)).collect();
+        let log: Vec<f32> = sweep.iter().map(|m| BandScale::Log.apply(*m)).collect();
+
+        // Both reach 1.0 at full scale
+        assert_relative_eq!(linear[9], 1.0);
+        assert_relative_eq!(log[9], 1.0);
+
+        // Log compresses the high end: the top half of the sweep spans less
+        let linear_top = linear[9] - linear[4];
+        let log_top = log[9] - log[4];
+        assert!(
+            log_top < linear_top * 0.5,
+            "log {} vs linear {}",
+            log_top,
+            linear_top
+        );
+
+        // ...and lifts quiet levels
+        assert!(log[0] > linear[0]);
+        assert_relative_eq!(BandScale::Log.apply(0.001), 0.0, epsilon = 1e-4);
+        assert_eq!(BandScale::Log.apply(0.0), 0.0);
+
+        let perceptual = BandScale::Perceptual.apply(0.1);
+        assert!(perceptual > linear[0] && perceptual < 1.0);
+    }
 }
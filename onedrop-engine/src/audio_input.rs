@@ -1,7 +1,10 @@
 //! Real-time audio input capture using cpal.
 
+use crate::audio::{Band, BandNormalizer, BandScale};
+use crate::audio_source::AudioSource;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, Host, Stream, StreamConfig};
+use cpal::{Device, Host, SampleFormat, SizedSample, Stream, StreamConfig};
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
@@ -11,9 +14,15 @@ pub enum AudioInputError {
     #[error("No audio input device available")]
     NoDevice,
 
+    #[error("Failed to list audio input devices: {0}")]
+    DevicesError(#[from] cpal::DevicesError),
+
     #[error("Failed to get default input config: {0}")]
     ConfigError(#[from] cpal::DefaultStreamConfigError),
 
+    #[error("Unsupported audio sample format: {0:?}")]
+    UnsupportedSampleFormat(SampleFormat),
+
     #[error("Failed to build audio stream: {0}")]
     BuildStreamError(#[from] cpal::BuildStreamError),
 
@@ -23,6 +32,47 @@ pub enum AudioInputError {
 
 pub type Result<T> = std::result::Result<T, AudioInputError>;
 
+/// Samples kept from the input stream: enough for FFT windows of up to
+/// this size, whatever the device's callback size.
+pub const BUFFER_CAPACITY: usize = 16384;
+
+/// Fixed-capacity buffer holding the most recent samples.
+#[derive(Debug)]
+struct SampleRing {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl SampleRing {
+    fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append samples, dropping the oldest beyond capacity.
+    fn push(&mut self, samples: impl IntoIterator<Item = f32>) {
+        self.samples.extend(samples);
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+
+    /// The latest `count` samples, oldest first. Missing history is padded
+    /// with leading zeros.
+    fn latest(&self, count: usize) -> Vec<f32> {
+        let available = count.min(self.samples.len());
+        let mut window = vec![0.0; count - available];
+        window.extend(self.samples.range(self.samples.len() - available..));
+        window
+    }
+
+    /// All buffered samples, oldest first.
+    fn to_vec(&self) -> Vec<f32> {
+        self.samples.iter().copied().collect()
+    }
+}
+
 /// Real-time audio input capture.
 pub struct AudioInput {
     /// Audio host
@@ -34,15 +84,15 @@ pub struct AudioInput {
     /// Input stream
     _stream: Stream,
 
-    /// Shared buffer for audio samples
-    buffer: Arc<Mutex<Vec<f32>>>,
+    /// Most recent samples, filled by the stream callback
+    buffer: Arc<Mutex<SampleRing>>,
 
     /// Sample rate
     sample_rate: u32,
 }
 
 impl AudioInput {
-    /// Create a new audio input capture.
+    /// Create a new audio input capture on the default input device.
     pub fn new() -> Result<Self> {
         let host = cpal::default_host();
 
@@ -51,6 +101,41 @@ impl AudioInput {
             .default_input_device()
             .ok_or(AudioInputError::NoDevice)?;
 
+        Self::from_device(host, device)
+    }
+
+    /// Create an audio input capture on the input device called `name`,
+    /// e.g. a second interface or a loopback/monitor device.
+    ///
+    /// An exact name wins; otherwise `name` may be a case-insensitive part
+    /// of exactly one device name. Returns [`AudioInputError::NoDevice`]
+    /// if no device matches.
+    pub fn with_device(name: &str) -> Result<Self> {
+        let host = cpal::default_host();
+        let mut devices: Vec<(String, Device)> = host
+            .input_devices()?
+            .filter_map(|device| Some((device.name().ok()?, device)))
+            .collect();
+
+        let names: Vec<String> = devices.iter().map(|(name, _)| name.clone()).collect();
+        let index = match_device_name(&names, name).ok_or(AudioInputError::NoDevice)?;
+        let (_, device) = devices.swap_remove(index);
+        Self::from_device(host, device)
+    }
+
+    /// Names of the available input devices, for [`with_device`](Self::with_device).
+    pub fn list_devices() -> Vec<String> {
+        match cpal::default_host().input_devices() {
+            Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+            Err(e) => {
+                log::warn!("Failed to list audio input devices: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Start capturing from `device`.
+    fn from_device(host: Host, device: Device) -> Result<Self> {
         log::info!(
             "Using audio input device: {}",
             device.name().unwrap_or_else(|_| "Unknown".to_string())
@@ -67,25 +152,18 @@ impl AudioInput {
         );
 
         // Create shared buffer
-        let buffer = Arc::new(Mutex::new(Vec::new()));
-        let buffer_clone = buffer.clone();
+        let buffer = Arc::new(Mutex::new(SampleRing::new(BUFFER_CAPACITY)));
 
-        // Build input stream
+        // Build input stream in the device's own sample format; integer
+        // samples are normalized to f32 as they arrive
+        let sample_format = config.sample_format();
         let stream_config: StreamConfig = config.into();
-        let stream = device.build_input_stream(
-            &stream_config,
-            move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                // Copy audio data to buffer (handle mutex poisoning gracefully)
-                if let Ok(mut buf) = buffer_clone.lock() {
-                    buf.clear();
-                    buf.extend_from_slice(data);
-                }
-            },
-            |err| {
-                log::error!("Audio input stream error: {}", err);
-            },
-            None,
-        )?;
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream(&device, &stream_config, &buffer, |s: f32| s)?,
+            SampleFormat::I16 => build_stream(&device, &stream_config, &buffer, i16_to_f32)?,
+            SampleFormat::U16 => build_stream(&device, &stream_config, &buffer, u16_to_f32)?,
+            other => return Err(AudioInputError::UnsupportedSampleFormat(other)),
+        };
 
         // Start the stream
         stream.play()?;
@@ -101,12 +179,12 @@ impl AudioInput {
         })
     }
 
-    /// Get the latest audio samples.
-    /// Returns a copy of the current buffer.
+    /// Get the buffered audio samples, oldest first: up to
+    /// [`BUFFER_CAPACITY`] of the most recent ones.
     pub fn get_samples(&self) -> Vec<f32> {
         self.buffer
             .lock()
-            .map(|buf| buf.clone())
+            .map(|buf| buf.to_vec())
             .unwrap_or_default()
     }
 
@@ -115,25 +193,79 @@ impl AudioInput {
         self.sample_rate
     }
 
-    /// Get a fixed number of samples for processing.
-    /// If not enough samples are available, returns zeros.
+    /// Get the latest `count` samples for processing, oldest first.
+    /// If not enough samples have arrived yet, the window starts with zeros.
     pub fn get_fixed_samples(&self, count: usize) -> Vec<f32> {
         self.buffer
             .lock()
-            .map(|buf| {
-                if buf.len() >= count {
-                    buf[..count].to_vec()
-                } else {
-                    // Pad with zeros if not enough samples
-                    let mut result = buf.clone();
-                    result.resize(count, 0.0);
-                    result
-                }
-            })
+            .map(|buf| buf.latest(count))
             .unwrap_or_else(|_| vec![0.0; count])
     }
 }
 
+impl AudioSource for AudioInput {
+    fn next_frame(&mut self, count: usize) -> Vec<f32> {
+        self.get_fixed_samples(count)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        AudioInput::sample_rate(self)
+    }
+}
+
+/// Build an input stream of `T` samples that appends incoming samples,
+/// converted with `convert`, to `buffer`.
+fn build_stream<T: SizedSample + Send + 'static>(
+    device: &Device,
+    config: &StreamConfig,
+    buffer: &Arc<Mutex<SampleRing>>,
+    convert: fn(T) -> f32,
+) -> Result<Stream> {
+    let buffer = buffer.clone();
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            // Copy audio data to buffer (handle mutex poisoning gracefully)
+            if let Ok(mut buf) = buffer.lock() {
+                buf.push(data.iter().map(|&sample| convert(sample)));
+            }
+        },
+        |err| {
+            log::error!("Audio input stream error: {}", err);
+        },
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Signed 16-bit sample to -1.0..1.0.
+fn i16_to_f32(sample: i16) -> f32 {
+    sample as f32 / 32768.0
+}
+
+/// Unsigned 16-bit sample, centered on 32768, to -1.0..1.0.
+fn u16_to_f32(sample: u16) -> f32 {
+    (sample as f32 - 32768.0) / 32768.0
+}
+
+/// Index of the device in `names` that `wanted` selects: an exact match,
+/// else the only name containing `wanted` ignoring case.
+fn match_device_name(names: &[String], wanted: &str) -> Option<usize> {
+    if let Some(index) = names.iter().position(|name| name == wanted) {
+        return Some(index);
+    }
+
+    let wanted = wanted.to_lowercase();
+    let mut matches = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.to_lowercase().contains(&wanted));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Some(index),
+        _ => None,
+    }
+}
+
 /// Audio input with FFT analysis for bass/mid/treb extraction.
 pub struct AudioAnalysisInput {
     /// Audio input
@@ -144,6 +276,12 @@ pub struct AudioAnalysisInput {
 
     /// FFT buffer size
     fft_size: usize,
+
+    /// Mapping from normalized magnitudes to band values
+    band_scale: BandScale,
+
+    /// Running band averages the levels are divided by
+    normalizer: BandNormalizer,
 }
 
 impl AudioAnalysisInput {
@@ -164,12 +302,16 @@ impl AudioAnalysisInput {
             input,
             fft,
             fft_size,
+            band_scale: BandScale::default(),
+            normalizer: BandNormalizer::new(),
         })
     }
 
     /// Analyze audio and extract bass, mid, treb levels.
-    /// Returns (bass, mid, treb) in range [0.0, 1.0].
-    pub fn analyze(&self) -> (f32, f32, f32) {
+    ///
+    /// Levels are divided by their running average, so a steady signal reads
+    /// about 1.0 and beats spike above it, as Milkdrop presets expect.
+    pub fn analyze(&mut self) -> (f32, f32, f32) {
         use rustfft::num_complex::Complex;
 
         // Get samples
@@ -226,10 +368,28 @@ impl AudioAnalysisInput {
             0.0
         };
 
-        // Normalize to [0, 1] range (approximate)
-        let normalize = |x: f32| (x * 10.0).min(1.0);
+        // Scale to roughly 0-1 for the band curve, then center on the
+        // running average
+        let levels = [bass, mid, treb].map(|x| self.band_scale.apply(x * 10.0));
+        self.normalizer.update(levels);
+        let [bass, mid, treb] = levels;
+
+        (
+            self.normalizer.normalize(bass, Band::Bass),
+            self.normalizer.normalize(mid, Band::Mid),
+            self.normalizer.normalize(treb, Band::Treb),
+        )
+    }
+
+    /// Set the smallest running average levels are divided by, so silence
+    /// doesn't divide by zero.
+    pub fn set_normalize_floor(&mut self, floor: f32) {
+        self.normalizer.set_floor(floor);
+    }
 
-        (normalize(bass), normalize(mid), normalize(treb))
+    /// Set how band magnitudes map to band values.
+    pub fn set_band_scale(&mut self, band_scale: BandScale) {
+        self.band_scale = band_scale;
     }
 
     /// Get the sample rate.
@@ -254,6 +414,75 @@ mod tests {
         assert!(input.is_ok(), "Failed to create audio input");
     }
 
+    #[test]
+    #[ignore] // Requires audio device
+    fn test_list_and_open_devices() {
+        let devices = AudioInput::list_devices();
+        assert!(!devices.is_empty(), "No input devices listed");
+        assert!(AudioInput::with_device(&devices[0]).is_ok());
+        assert!(matches!(
+            AudioInput::with_device("no such device \u{1F50A}"),
+            Err(AudioInputError::NoDevice)
+        ));
+    }
+
+    #[test]
+    fn test_ring_reads_latest_window_across_chunks() {
+        let mut ring = SampleRing::new(2048);
+        let mut pushed = Vec::new();
+        for size in [100, 700, 1500, 30, 1] {
+            let chunk: Vec<f32> = (pushed.len()..pushed.len() + size)
+                .map(|i| i as f32)
+                .collect();
+            ring.push(chunk.iter().copied());
+            pushed.extend(chunk);
+        }
+
+        // Window spans chunk boundaries and ends at the newest sample
+        assert_eq!(ring.latest(1024), pushed[pushed.len() - 1024..]);
+        // Only the newest `capacity` samples are kept
+        assert_eq!(ring.to_vec(), pushed[pushed.len() - 2048..]);
+
+        // Before enough samples arrive, older history reads as silence
+        let mut short = SampleRing::new(2048);
+        short.push([1.0, 2.0]);
+        assert_eq!(short.latest(4), [0.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_integer_sample_conversion() {
+        assert_eq!(i16_to_f32(0), 0.0);
+        assert_eq!(i16_to_f32(i16::MIN), -1.0);
+        assert_eq!(i16_to_f32(16384), 0.5);
+        assert!((i16_to_f32(i16::MAX) - 1.0).abs() < 1e-4);
+
+        assert_eq!(u16_to_f32(32768), 0.0);
+        assert_eq!(u16_to_f32(0), -1.0);
+        assert_eq!(u16_to_f32(49152), 0.5);
+        assert!((u16_to_f32(u16::MAX) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_match_device_name() {
+        let names: Vec<String> = [
+            "Built-in Microphone",
+            "Monitor of Built-in Audio",
+            "USB Audio Interface",
+            "USB Audio Interface 2",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+
+        assert_eq!(match_device_name(&names, "USB Audio Interface"), Some(2));
+        assert_eq!(match_device_name(&names, "monitor"), Some(1));
+        assert_eq!(match_device_name(&names, "interface 2"), Some(3));
+        // Ambiguous or unknown names select nothing
+        assert_eq!(match_device_name(&names, "built-in"), None);
+        assert_eq!(match_device_name(&names, "Headset"), None);
+        assert_eq!(match_device_name(&[], "Headset"), None);
+    }
+
     #[test]
     #[ignore] // Requires audio device
     fn test_audio_analysis_creation() {
@@ -276,17 +505,21 @@ mod tests {
     #[test]
     #[ignore] // Requires audio device
     fn test_audio_analysis() {
-        let input = AudioAnalysisInput::new(2048).unwrap();
+        let mut input = AudioAnalysisInput::new(2048).unwrap();
 
         // Wait a bit for samples
         std::thread::sleep(std::time::Duration::from_millis(100));
 
         let (bass, mid, treb) = input.analyze();
 
-        // Should be in valid range
-        assert!((0.0..=1.0).contains(&bass), "Bass out of range: {}", bass);
-        assert!((0.0..=1.0).contains(&mid), "Mid out of range: {}", mid);
-        assert!((0.0..=1.0).contains(&treb), "Treb out of range: {}", treb);
+        // Normalized levels are unbounded above but never negative
+        for level in [bass, mid, treb] {
+            assert!(
+                level.is_finite() && level >= 0.0,
+                "Level out of range: {}",
+                level
+            );
+        }
 
         println!(
             "Audio levels - Bass: {:.3}, Mid: {:.3}, Treb: {:.3}",
@@ -0,0 +1,414 @@
+//! Audio sources that feed samples to the engine.
+//!
+//! [`AudioSource`] is the common surface of live capture (`AudioInput`,
+//! feature `audio-input`), file playback ([`FileAudioSource`]) and the
+//! synthetic [`SineAudioSource`] and [`SilentAudioSource`], so frontends can
+//! swap one for the other or hand one to
+//! [`MilkEngine::set_audio_source`](crate::MilkEngine::set_audio_source).
+
+use crate::error::{EngineError, Result};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// A stream of mono audio samples.
+pub trait AudioSource {
+    /// Get exactly `count` samples for the next frame.
+    fn next_frame(&mut self, count: usize) -> Vec<f32>;
+
+    /// Get the sample rate.
+    fn sample_rate(&self) -> u32;
+}
+
+/// Audio decoded from a WAV or MP3 file, played back one frame at a time.
+///
+/// Each [`next_frame`](AudioSource::next_frame) call returns the next
+/// `count` samples and advances the playhead, wrapping around at the end so
+/// playback loops.
+pub struct FileAudioSource {
+    /// Mono samples
+    samples: Vec<f32>,
+
+    /// Sample rate
+    sample_rate: u32,
+
+    /// Index of the next sample to play
+    position: usize,
+}
+
+impl FileAudioSource {
+    /// Decode a WAV file with `hound`, or anything else (MP3) with
+    /// `symphonia`, mixing all channels down to mono.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let is_wav = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+
+        let source = if is_wav {
+            decode_wav(path)?
+        } else {
+            decode_symphonia(path)?
+        };
+        log::info!(
+            "Loaded {} ({} samples at {} Hz)",
+            path.display(),
+            source.samples.len(),
+            source.sample_rate
+        );
+        Ok(source)
+    }
+
+    /// Play back already decoded mono samples.
+    pub fn from_samples(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            samples,
+            sample_rate,
+            position: 0,
+        }
+    }
+
+    /// Total number of samples.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether the file holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Index of the next sample to be played.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Move the playhead, wrapping positions past the end.
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.checked_rem(self.samples.len()).unwrap_or(0);
+    }
+
+    /// Linearly resample to another rate, restarting playback.
+    pub fn resampled(self, target_rate: u32) -> Self {
+        if self.sample_rate == target_rate || self.samples.is_empty() {
+            return Self::from_samples(self.samples, target_rate);
+        }
+
+        let ratio = self.sample_rate as f64 / target_rate as f64;
+        let len = (self.samples.len() as f64 / ratio).round() as usize;
+        let last = self.samples.len() - 1;
+        let samples = (0..len)
+            .map(|i| {
+                let pos = i as f64 * ratio;
+                let idx = (pos.floor() as usize).min(last);
+                let next = (idx + 1).min(last);
+                let frac = (pos - idx as f64) as f32;
+                self.samples[idx] * (1.0 - frac) + self.samples[next] * frac
+            })
+            .collect();
+        Self::from_samples(samples, target_rate)
+    }
+}
+
+/// Average interleaved channels into a mono signal.
+fn mix_to_mono(interleaved: &[f32], channels: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+fn decode_wav(path: &Path) -> Result<FileAudioSource> {
+    let reader = hound::WavReader::open(path).map_err(|e| {
+        EngineError::InvalidAudioData(format!("Cannot open {}: {}", path.display(), e))
+    })?;
+    let spec = reader.spec();
+
+    let interleaved: std::result::Result<Vec<f32>, _> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect()
+        }
+    };
+    let interleaved = interleaved.map_err(|e| {
+        EngineError::InvalidAudioData(format!("Cannot decode {}: {}", path.display(), e))
+    })?;
+
+    Ok(FileAudioSource::from_samples(
+        mix_to_mono(&interleaved, spec.channels as usize),
+        spec.sample_rate,
+    ))
+}
+
+fn decode_symphonia(path: &Path) -> Result<FileAudioSource> {
+    let invalid = |what: &str, e: &dyn std::fmt::Display| {
+        EngineError::InvalidAudioData(format!("{} {}: {}", what, path.display(), e))
+    };
+
+    let file = std::fs::File::open(path).map_err(|e| invalid("Cannot open", &e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| invalid("Unsupported audio format in", &e))?;
+    let mut format = probed.format;
+
+    let track = format.default_track().ok_or_else(|| {
+        EngineError::InvalidAudioData(format!("No audio track in {}", path.display()))
+    })?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.ok_or_else(|| {
+        EngineError::InvalidAudioData(format!("Unknown sample rate in {}", path.display()))
+    })?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| invalid("Unsupported audio codec in", &e))?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(invalid("Cannot decode", &e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                samples.extend(mix_to_mono(buffer.samples(), spec.channels.count()));
+            }
+            // Corrupt frames are skipped rather than aborting playback
+            Err(SymphoniaError::DecodeError(e)) => log::warn!("Skipping bad audio frame: {}", e),
+            Err(e) => return Err(invalid("Cannot decode", &e)),
+        }
+    }
+
+    Ok(FileAudioSource::from_samples(samples, sample_rate))
+}
+
+impl AudioSource for FileAudioSource {
+    fn next_frame(&mut self, count: usize) -> Vec<f32> {
+        if self.samples.is_empty() {
+            return vec![0.0; count];
+        }
+
+        let start = self.position;
+        let chunk = (0..count)
+            .map(|i| self.samples[(start + i) % self.samples.len()])
+            .collect();
+        self.position = (start + count) % self.samples.len();
+        chunk
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+/// A continuous sine tone.
+#[derive(Debug, Clone)]
+pub struct SineAudioSource {
+    /// Tone frequency in Hz
+    frequency: f32,
+
+    /// Peak amplitude
+    amplitude: f32,
+
+    /// Sample rate
+    sample_rate: u32,
+
+    /// Index of the next sample, so frames join without a phase jump
+    position: u64,
+}
+
+impl SineAudioSource {
+    /// Create a tone of `frequency` Hz peaking at `amplitude`.
+    pub fn new(frequency: f32, amplitude: f32, sample_rate: u32) -> Self {
+        Self {
+            frequency,
+            amplitude,
+            sample_rate: sample_rate.max(1),
+            position: 0,
+        }
+    }
+
+    /// The demo signal used when no audio is available: a 60 Hz tone at
+    /// half amplitude, sampled at 1 kHz so a 1024-sample frame spans about
+    /// a second of it.
+    pub fn demo() -> Self {
+        Self::new(60.0, 0.5, 1000)
+    }
+}
+
+impl AudioSource for SineAudioSource {
+    fn next_frame(&mut self, count: usize) -> Vec<f32> {
+        let step = self.frequency as f64 / self.sample_rate as f64;
+        let frame = (0..count as u64)
+            .map(|i| {
+                // Keep only the fractional cycle so long runs don't lose precision
+                let phase = ((self.position + i) as f64 * step).fract();
+                (phase * std::f64::consts::TAU).sin() as f32 * self.amplitude
+            })
+            .collect();
+        self.position += count as u64;
+        frame
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+/// Silence, for rendering without audio.
+#[derive(Debug, Clone, Copy)]
+pub struct SilentAudioSource {
+    /// Sample rate
+    sample_rate: u32,
+}
+
+impl SilentAudioSource {
+    /// Create a silent source.
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate }
+    }
+}
+
+impl AudioSource for SilentAudioSource {
+    fn next_frame(&mut self, count: usize) -> Vec<f32> {
+        vec![0.0; count]
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_chunks_advance_and_wrap() {
+        let mut source = FileAudioSource::from_samples((0..10).map(|i| i as f32).collect(), 44100);
+
+        assert_eq!(source.next_frame(4), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(source.position(), 4);
+        assert_eq!(source.next_frame(4), vec![4.0, 5.0, 6.0, 7.0]);
+        assert_eq!(source.position(), 8);
+
+        // Playback loops back to the start of the file
+        assert_eq!(source.next_frame(4), vec![8.0, 9.0, 0.0, 1.0]);
+        assert_eq!(source.position(), 2);
+
+        source.seek(13);
+        assert_eq!(source.position(), 3);
+
+        let mut empty = FileAudioSource::from_samples(Vec::new(), 44100);
+        assert_eq!(empty.next_frame(3), vec![0.0; 3]);
+    }
+
+    #[test]
+    fn test_open_wav_mixes_to_mono() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("source.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..100 {
+            writer.write_sample(i16::MAX / 2).unwrap();
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let source = FileAudioSource::open(&path).unwrap();
+        assert_eq!(source.len(), 100);
+
+        // Resampled to the engine rate, the file keeps its duration
+        let source = source.resampled(44100);
+        assert_eq!(source.len(), 200);
+
+        let mut source: Box<dyn AudioSource> = Box::new(source);
+        assert_eq!(source.sample_rate(), 44100);
+        let chunk = source.next_frame(64);
+        assert_eq!(chunk.len(), 64);
+        assert!(chunk.iter().all(|s| (s - 0.25).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        assert!(FileAudioSource::open("does-not-exist.wav").is_err());
+        assert!(FileAudioSource::open("does-not-exist.mp3").is_err());
+    }
+
+    #[test]
+    fn test_resampled() {
+        let source = FileAudioSource::from_samples(vec![0.0, 1.0, 0.0], 22050).resampled(44100);
+        assert_eq!(source.sample_rate(), 44100);
+        assert_eq!(source.len(), 6);
+        assert_eq!(source.samples[..3], [0.0, 0.5, 1.0]);
+
+        let same = FileAudioSource::from_samples(vec![0.5; 4], 44100).resampled(44100);
+        assert_eq!(same.len(), 4);
+    }
+
+    #[test]
+    fn test_sine_source() {
+        let mut source = SineAudioSource::new(1000.0, 0.5, 8000);
+        let first = source.next_frame(6);
+        assert_eq!(first.len(), 6);
+        assert_eq!(source.sample_rate(), 8000);
+        assert!((first[2] - 0.5).abs() < 1e-6);
+
+        // The next frame continues the same wave (period 8 samples)
+        let second = source.next_frame(1000);
+        assert_eq!(second.len(), 1000);
+        assert!((second[0] - (-0.5)).abs() < 1e-6);
+        assert!(second.iter().all(|s| s.abs() <= 0.5 + 1e-6));
+
+        // Same signal the GUI and CLI generated by hand
+        let mut demo = SineAudioSource::demo();
+        let frame = demo.next_frame(1024);
+        let t = 10.0 * 0.001_f32;
+        assert!((frame[10] - (t * std::f32::consts::TAU * 60.0).sin() * 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_silent_source() {
+        let mut source = SilentAudioSource::new(44100);
+        assert_eq!(source.next_frame(512), vec![0.0; 512]);
+        assert_eq!(source.next_frame(0), Vec::<f32>::new());
+        assert_eq!(source.sample_rate(), 44100);
+    }
+}
@@ -59,6 +59,44 @@ impl BeatDetectionMode {
     }
 }
 
+/// Tunable thresholds and minimum delays for the HardCut modes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatThresholds {
+    /// Bass level for HardCut1 and HardCut6
+    pub bass: f32,
+
+    /// Treble level for HardCut2-5
+    pub treb: f32,
+
+    /// Treble level that triggers HardCut4 immediately
+    pub treb_immediate: f32,
+
+    /// Bass level that loads the HardCut6 special preset
+    pub bass_special: f32,
+
+    /// Minimum delay between changes for HardCut1..HardCut6, in order
+    pub min_delays: [Duration; 6],
+}
+
+impl Default for BeatThresholds {
+    fn default() -> Self {
+        Self {
+            bass: 1.5,
+            treb: 2.9,
+            treb_immediate: 8.0,
+            bass_special: 4.90,
+            min_delays: [
+                Duration::from_millis(200),
+                Duration::from_millis(500),
+                Duration::from_secs(1),
+                Duration::from_secs(3),
+                Duration::from_secs(5),
+                Duration::from_millis(200),
+            ],
+        }
+    }
+}
+
 /// Type of preset change to trigger.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PresetChange {
@@ -69,6 +107,63 @@ pub enum PresetChange {
     Specific(String),
 }
 
+/// Threshold condition that matched during a beat evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BeatCondition {
+    /// Bass exceeded the mode's threshold
+    Bass,
+
+    /// Treble exceeded the mode's threshold
+    Treb,
+
+    /// Treble exceeded the immediate threshold (ignores min delay)
+    TrebImmediate,
+
+    /// Bass exceeded the special preset threshold (ignores min delay)
+    BassSpecial,
+}
+
+/// Record of a single beat detection evaluation, for debugging/tuning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatEvent {
+    /// Bass level that was evaluated
+    pub bass: f32,
+
+    /// Mid level that was evaluated
+    pub mid: f32,
+
+    /// Treble level that was evaluated
+    pub treb: f32,
+
+    /// Threshold condition that matched, if any
+    pub condition: Option<BeatCondition>,
+
+    /// Whether a preset change was triggered.
+    /// `false` with a matching condition means the min delay blocked it.
+    pub triggered: bool,
+}
+
+/// Maximum number of evaluations kept in the trigger history.
+pub const BEAT_HISTORY_LEN: usize = 64;
+
+/// Number of frames of total energy kept for onset detection.
+const ENERGY_HISTORY_LEN: usize = 43;
+
+/// Energy must exceed the recent average by this factor to count as an onset.
+const ONSET_THRESHOLD: f32 = 1.3;
+
+/// Ignore onsets closer together than this (faster than 240 BPM).
+const MIN_ONSET_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of onset timestamps kept for BPM estimation.
+const ONSET_HISTORY_LEN: usize = 16;
+
+/// Onsets required before a BPM estimate is reported.
+const MIN_ONSETS_FOR_BPM: usize = 4;
+
+/// Range the BPM estimate is clamped to.
+const BPM_RANGE: (f32, f32) = (60.0, 200.0);
+
 /// Beat detector for automatic preset changing.
 #[derive(Debug, Clone)]
 pub struct BeatDetector {
@@ -80,6 +175,24 @@ pub struct BeatDetector {
 
     /// Enable/disable detection
     enabled: bool,
+
+    /// Recent evaluations, oldest first (bounded by `BEAT_HISTORY_LEN`)
+    history: Vec<BeatEvent>,
+
+    /// Recent total energy (bass + mid + treb), oldest first
+    energy_history: Vec<f32>,
+
+    /// Whether energy is currently above the onset threshold
+    in_onset: bool,
+
+    /// Recent onset times, oldest first
+    onsets: Vec<Instant>,
+
+    /// Thresholds consulted by the HardCut modes
+    thresholds: BeatThresholds,
+
+    /// Intensity of the most recent triggered beat
+    last_beat_intensity: f32,
 }
 
 impl BeatDetector {
@@ -89,6 +202,12 @@ impl BeatDetector {
             mode: BeatDetectionMode::Off,
             last_trigger: None,
             enabled: false,
+            history: Vec::new(),
+            energy_history: Vec::new(),
+            in_onset: false,
+            onsets: Vec::new(),
+            thresholds: BeatThresholds::default(),
+            last_beat_intensity: 0.0,
         }
     }
 
@@ -99,6 +218,12 @@ impl BeatDetector {
             mode,
             last_trigger: None,
             enabled,
+            history: Vec::new(),
+            energy_history: Vec::new(),
+            in_onset: false,
+            onsets: Vec::new(),
+            thresholds: BeatThresholds::default(),
+            last_beat_intensity: 0.0,
         }
     }
 
@@ -135,18 +260,27 @@ impl BeatDetector {
     }
 
     /// Check if a preset change should be triggered based on audio levels.
-    pub fn should_change_preset(
+    pub fn should_change_preset(&mut self, bass: f32, mid: f32, treb: f32) -> Option<PresetChange> {
+        self.should_change_preset_at(bass, mid, treb, Instant::now())
+    }
+
+    /// Same as [`should_change_preset`](Self::should_change_preset), with an
+    /// explicit timestamp for the audio frame.
+    ///
+    /// Onsets for BPM estimation are tracked even when detection is disabled.
+    pub fn should_change_preset_at(
         &mut self,
         bass: f32,
-        _mid: f32,
+        mid: f32,
         treb: f32,
+        now: Instant,
     ) -> Option<PresetChange> {
+        self.track_onset(bass + mid + treb, now);
+
         if !self.enabled || self.mode == BeatDetectionMode::Off {
             return None;
         }
 
-        let now = Instant::now();
-
         // Check if minimum delay has passed
         let can_trigger = match self.last_trigger {
             None => true,
@@ -156,83 +290,191 @@ impl BeatDetector {
             }
         };
 
-        // Check conditions based on mode
-        let change = match self.mode {
+        // Check conditions based on mode.
+        // Each match yields the condition and whether it bypasses the min delay.
+        let t = &self.thresholds;
+        let matched = match self.mode {
             BeatDetectionMode::Off => None,
 
-            BeatDetectionMode::HardCut1 => {
-                if bass > 1.5 && can_trigger {
-                    Some(PresetChange::Random)
-                } else {
-                    None
-                }
-            }
-
-            BeatDetectionMode::HardCut2 => {
-                if treb > 2.9 && can_trigger {
-                    Some(PresetChange::Random)
-                } else {
-                    None
-                }
-            }
+            BeatDetectionMode::HardCut1 => (bass > t.bass).then_some((BeatCondition::Bass, false)),
 
-            BeatDetectionMode::HardCut3 => {
-                if treb > 2.9 && can_trigger {
-                    Some(PresetChange::Random)
-                } else {
-                    None
-                }
+            BeatDetectionMode::HardCut2
+            | BeatDetectionMode::HardCut3
+            | BeatDetectionMode::HardCut5 => {
+                (treb > t.treb).then_some((BeatCondition::Treb, false))
             }
 
             BeatDetectionMode::HardCut4 => {
-                if treb > 8.0 {
+                if treb > t.treb_immediate {
                     // Immediate trigger on very high treble
-                    Some(PresetChange::Random)
-                } else if treb > 2.9 && can_trigger {
-                    Some(PresetChange::Random)
+                    Some((BeatCondition::TrebImmediate, true))
                 } else {
-                    None
+                    (treb > t.treb).then_some((BeatCondition::Treb, false))
                 }
             }
 
-            BeatDetectionMode::HardCut5 => {
-                if treb > 2.9 && can_trigger {
-                    Some(PresetChange::Random)
+            BeatDetectionMode::HardCut6 { .. } => {
+                if bass > t.bass_special {
+                    // Load special preset on very high bass
+                    Some((BeatCondition::BassSpecial, true))
                 } else {
-                    None
+                    (bass > t.bass).then_some((BeatCondition::Bass, false))
                 }
             }
+        };
 
-            BeatDetectionMode::HardCut6 { ref special_preset } => {
-                if bass > 4.90 {
-                    // Load special preset on very high bass
-                    Some(PresetChange::Specific(special_preset.clone()))
-                } else if bass > 1.5 && can_trigger {
-                    Some(PresetChange::Random)
-                } else {
-                    None
-                }
+        let change = match (&matched, &self.mode) {
+            (
+                Some((BeatCondition::BassSpecial, _)),
+                BeatDetectionMode::HardCut6 { special_preset },
+            ) => Some(PresetChange::Specific(special_preset.clone())),
+            (Some((_, bypass_delay)), _) if *bypass_delay || can_trigger => {
+                Some(PresetChange::Random)
             }
+            _ => None,
         };
 
-        // Update last trigger time if change was triggered
+        // Update last trigger time and intensity if change was triggered
         if change.is_some() {
             self.last_trigger = Some(now);
+            if let Some((condition, _)) = matched {
+                self.last_beat_intensity = self.intensity_for(condition, bass, treb);
+            }
         }
 
+        self.record_event(BeatEvent {
+            bass,
+            mid,
+            treb,
+            condition: matched.map(|(condition, _)| condition),
+            triggered: change.is_some(),
+        });
+
         change
     }
 
+    /// How far the matched level overshot its threshold, relative to the
+    /// threshold (0.0 right at the threshold, 1.0 at twice the threshold).
+    fn intensity_for(&self, condition: BeatCondition, bass: f32, treb: f32) -> f32 {
+        let t = &self.thresholds;
+        let (level, threshold) = match condition {
+            BeatCondition::Bass => (bass, t.bass),
+            BeatCondition::BassSpecial => (bass, t.bass_special),
+            BeatCondition::Treb => (treb, t.treb),
+            BeatCondition::TrebImmediate => (treb, t.treb_immediate),
+        };
+        if threshold > 0.0 {
+            ((level - threshold) / threshold).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Intensity of the most recent beat that triggered a preset change.
+    ///
+    /// Measured as the relative overshoot of the matched threshold, so weak
+    /// beats stay near 0.0 and strong spikes grow past 1.0. Useful for
+    /// scaling a visual reaction such as a zoom kick or flash.
+    pub fn last_beat_intensity(&self) -> f32 {
+        self.last_beat_intensity
+    }
+
+    /// Append an evaluation to the bounded history.
+    fn record_event(&mut self, event: BeatEvent) {
+        if self.history.len() >= BEAT_HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.history.push(event);
+    }
+
+    /// Get recent evaluations, oldest first.
+    ///
+    /// Only evaluations made while detection is enabled are recorded.
+    pub fn trigger_history(&self) -> &[BeatEvent] {
+        &self.history
+    }
+
+    /// Clear the trigger history.
+    pub fn clear_trigger_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Record a frame's energy and note an onset on a rising edge above the
+    /// recent average.
+    fn track_onset(&mut self, energy: f32, now: Instant) {
+        if !self.energy_history.is_empty() {
+            let average =
+                self.energy_history.iter().sum::<f32>() / self.energy_history.len() as f32;
+            let above = energy > average * ONSET_THRESHOLD && energy > 0.0;
+
+            if above && !self.in_onset {
+                let spaced = self
+                    .onsets
+                    .last()
+                    .is_none_or(|last| now.duration_since(*last) >= MIN_ONSET_INTERVAL);
+                if spaced {
+                    if self.onsets.len() >= ONSET_HISTORY_LEN {
+                        self.onsets.remove(0);
+                    }
+                    self.onsets.push(now);
+                }
+            }
+            self.in_onset = above;
+        }
+
+        if self.energy_history.len() >= ENERGY_HISTORY_LEN {
+            self.energy_history.remove(0);
+        }
+        self.energy_history.push(energy);
+    }
+
+    /// Estimate the tempo from the median interval between recent onsets.
+    ///
+    /// Returns `None` until enough onsets have been seen. The estimate is
+    /// clamped to 60–200 BPM.
+    pub fn estimated_bpm(&self) -> Option<f32> {
+        if self.onsets.len() < MIN_ONSETS_FOR_BPM {
+            return None;
+        }
+
+        let mut intervals: Vec<f32> = self
+            .onsets
+            .windows(2)
+            .map(|pair| pair[1].duration_since(pair[0]).as_secs_f32())
+            .collect();
+        intervals.sort_by(|a, b| a.total_cmp(b));
+
+        let mid = intervals.len() / 2;
+        let median = if intervals.len() % 2 == 0 {
+            (intervals[mid - 1] + intervals[mid]) / 2.0
+        } else {
+            intervals[mid]
+        };
+
+        Some((60.0 / median).clamp(BPM_RANGE.0, BPM_RANGE.1))
+    }
+
+    /// Replace the thresholds used by the HardCut modes.
+    pub fn set_thresholds(&mut self, thresholds: BeatThresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Get the thresholds used by the HardCut modes.
+    pub fn thresholds(&self) -> &BeatThresholds {
+        &self.thresholds
+    }
+
     /// Get the minimum delay for the current mode.
     fn get_min_delay(&self) -> Duration {
+        let delays = &self.thresholds.min_delays;
         match self.mode {
             BeatDetectionMode::Off => Duration::from_secs(0),
-            BeatDetectionMode::HardCut1 => Duration::from_millis(200),
-            BeatDetectionMode::HardCut2 => Duration::from_millis(500),
-            BeatDetectionMode::HardCut3 => Duration::from_secs(1),
-            BeatDetectionMode::HardCut4 => Duration::from_secs(3),
-            BeatDetectionMode::HardCut5 => Duration::from_secs(5),
-            BeatDetectionMode::HardCut6 { .. } => Duration::from_millis(200),
+            BeatDetectionMode::HardCut1 => delays[0],
+            BeatDetectionMode::HardCut2 => delays[1],
+            BeatDetectionMode::HardCut3 => delays[2],
+            BeatDetectionMode::HardCut4 => delays[3],
+            BeatDetectionMode::HardCut5 => delays[4],
+            BeatDetectionMode::HardCut6 { .. } => delays[5],
         }
     }
 }
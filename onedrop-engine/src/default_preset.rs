@@ -99,5 +99,9 @@ mod tests {
 
         // Should have valid parameters
         assert!(preset.parameters.zoom > 0.0);
+
+        // Animated, but still a default preset players may want to skip
+        assert!(preset.is_default());
+        assert!(!preset.is_blank());
     }
 }
@@ -1,17 +1,66 @@
 //! Main Milkdrop engine implementation.
 
-use crate::audio::AudioAnalyzer;
+use crate::audio::{AudioAnalyzer, Band, BandScale};
+use crate::audio_source::AudioSource;
 use crate::beat_detection::{BeatDetectionMode, BeatDetector, PresetChange};
 use crate::error::{EngineError, Result};
-use onedrop_eval::MilkEvaluator;
-use onedrop_parser::{MilkPreset, parse_preset};
+use crate::frame_pacer::FramePacer;
+use crate::frame_stats::{FrameTimeHistogram, FrameTimeStats};
+use crate::history::{ColorState, History, MashUpType};
+use crate::hot_reload::PresetWatcher;
+use crate::preset_cache::{PresetCache, PresetCacheStats, read_preset_file};
+use crate::preset_manager::PresetManager;
+use crate::time_source::{DeltaTimeSource, TimeSource};
+use crate::transition::{TransitionManager, TransitionMode};
+use onedrop_eval::{MeshVertexOut, MilkEvaluator};
+use onedrop_parser::{DoublePreset, MilkPreset, parse_double_preset, parse_preset};
 use onedrop_renderer::GpuContext;
-use onedrop_renderer::{MilkRenderer, MotionParams, RenderConfig, RenderState, WaveParams};
-use std::fs;
-use std::path::Path;
+use onedrop_renderer::{
+    AudioLevels, BlendRenderer, BorderParams, EchoParams, MAX_SHAPES, MilkRenderer, MotionParams,
+    PostParams, RenderConfig, RenderState, ShapeParams, TextureFormat, WAVEFORM_SAMPLES, WarpMesh,
+    WaveParams, rgba16f_to_rgba8,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use wgpu;
 
+/// Blend pattern used for preset cross-fades (plain alpha mix).
+const CROSSFADE_PATTERN: u32 = 0;
+
+/// RNG seed used in deterministic mode.
+const DETERMINISTIC_SEED: u64 = 0;
+
+/// Upper bound on the beat intensity fed into the zoom kick.
+const BEAT_PULSE_MAX: f32 = 2.0;
+
+/// Decay rate of the beat zoom kick, per second.
+const BEAT_PULSE_DECAY: f32 = 8.0;
+
+/// Zoom added per unit of bass by the default audio reactivity.
+const DEFAULT_BASS_ZOOM: f32 = 0.03;
+
+/// Rotation added per unit of treble by the default audio reactivity.
+const DEFAULT_TREB_ROT: f32 = 0.02;
+
+/// Audio variables whose use marks a preset as reacting to audio itself.
+const AUDIO_VARS: [&str; 6] = ["bass", "mid", "treb", "bass_att", "mid_att", "treb_att"];
+
+/// Vertices per row and rows of the warp mesh the per-pixel equations
+/// run on, Milkdrop's default mesh size.
+const WARP_MESH_SIZE: (usize, usize) = (32, 24);
+
+/// Loaded presets kept for mash-ups.
+const PRESET_HISTORY_SIZE: usize = 16;
+
+/// Default [`EngineConfig::color_smoothing`]: about a second to settle at 60 fps.
+const DEFAULT_COLOR_SMOOTHING: f32 = 0.05;
+
+/// Samples pulled from the audio source for each frame.
+pub const SOURCE_FRAME_SAMPLES: usize = 1024;
+
 /// Main Milkdrop visualization engine.
 pub struct MilkEngine {
     /// Renderer
@@ -26,14 +75,135 @@ pub struct MilkEngine {
     /// Beat detector for automatic preset changes
     beat_detector: BeatDetector,
 
+    /// Debug overrides pinning bass/mid/treb to fixed levels
+    band_overrides: [Option<f32>; 3],
+
     /// Current preset
     current_preset: Option<MilkPreset>,
 
+    /// Content hash of the current preset, so reloads aren't added to the
+    /// history twice
+    preset_hash: Option<u64>,
+
+    /// File the current preset was loaded from, used to detect reloads of
+    /// the same preset
+    preset_path: Option<PathBuf>,
+
+    /// Recently loaded presets, the source of mash-ups
+    preset_history: History<MilkPreset>,
+
+    /// Color drawn in the last frame, with its smoothed average
+    color_state: Option<ColorState>,
+
     /// Current render state
     state: RenderState,
 
     /// Engine configuration
     config: EngineConfig,
+
+    /// Drives cross-fade progress between presets
+    transition_manager: TransitionManager,
+
+    /// Blends the outgoing and incoming presets (created on first use)
+    blend_renderer: Option<BlendRenderer>,
+
+    /// Textures kept alive while a transition is running
+    transition_frames: Option<TransitionFrames>,
+
+    /// Preset B of a loaded double preset, blended over the main preset
+    double: Option<DoublePresetLayer>,
+
+    /// Playlist used for timed auto-advance
+    preset_manager: Option<PresetManager>,
+
+    /// Seconds since the current preset was loaded
+    preset_elapsed: f32,
+
+    /// Frames rendered since the current preset was loaded
+    preset_frames: u32,
+
+    /// Audio pulled by `update_from_source`
+    audio_source: Option<Box<dyn AudioSource>>,
+
+    /// Clock giving each frame's `time`
+    time_source: Box<dyn TimeSource>,
+
+    /// Caps the render rate to `target_fps`
+    frame_pacer: FramePacer,
+
+    /// Freezes `time`, `frame` and the rendered output
+    paused: bool,
+
+    /// Staging buffer reused by `render_to_buffer`
+    readback_buffer: Option<wgpu::Buffer>,
+
+    /// Decaying beat reaction, set from the last beat's intensity
+    beat_pulse: f32,
+
+    /// Whether the current preset's equations read the audio levels
+    preset_uses_audio: bool,
+
+    /// Reference instant that frame time is offset from in deterministic mode
+    clock_origin: Instant,
+
+    /// Wall-clock duration of each `update`, for profiling
+    frame_times: FrameTimeHistogram,
+
+    /// Preloaded presets and compiled shaders
+    preset_cache: PresetCache,
+
+    /// Directory that relative preset paths are resolved against
+    preset_directory: Option<PathBuf>,
+
+    /// Per-frame equations run after the preset's own, for live overrides
+    live_equations: Vec<String>,
+
+    /// Preset file reloaded when it changes on disk
+    hot_reload: Option<PresetWatcher>,
+}
+
+/// Textures used during a preset cross-fade.
+struct TransitionFrames {
+    /// Last frame rendered by the outgoing preset
+    old_texture: wgpu::Texture,
+    old_view: wgpu::TextureView,
+
+    /// Latest frame of the incoming preset, before blending
+    new_texture: wgpu::Texture,
+    new_view: wgpu::TextureView,
+
+    /// Blended output, copied back into the output texture
+    blend_texture: wgpu::Texture,
+    blend_view: wgpu::TextureView,
+}
+
+/// Second preset of a double preset (`.od2`), rendered next to the main one
+/// with its own evaluator and feedback textures.
+struct DoublePresetLayer {
+    /// Preset B
+    preset: MilkPreset,
+
+    /// Expression evaluator for preset B
+    evaluator: MilkEvaluator,
+
+    /// Renderer for preset B, sharing the main device
+    renderer: MilkRenderer,
+
+    /// Render state for preset B
+    state: RenderState,
+
+    /// Blend pattern index (see [`onedrop_parser::BlendPattern`])
+    blend_pattern: u32,
+
+    /// Blend amount used when not animating (0.0 = all A, 1.0 = all B)
+    blend_amount: f32,
+
+    /// Oscillation speed of the blend amount, if animated
+    animation_speed: Option<f32>,
+
+    /// Blended output, copied back into the output texture
+    blend_texture: wgpu::Texture,
+    blend_view: wgpu::TextureView,
 }
 
 /// Engine configuration.
@@ -50,6 +220,177 @@ pub struct EngineConfig {
 
     /// Enable per-pixel equations
     pub enable_per_pixel: bool,
+
+    /// Keep evaluator variables (q1-q64, custom vars) when the same preset
+    /// file is loaded again, even with edited equations, instead of
+    /// restarting the simulation
+    pub preserve_vars_on_reload: bool,
+
+    /// Advance to the next preset of the bound [`PresetManager`] every N
+    /// seconds, independent of beat detection. A preset's own
+    /// `fTimeBetweenPresets` takes precedence while it is loaded.
+    pub auto_advance_secs: Option<f32>,
+
+    /// Extra zoom applied on a triggered beat, scaled by
+    /// [`BeatDetector::last_beat_intensity`]. 0.0 disables the reaction.
+    pub beat_zoom_kick: f32,
+
+    /// Render reproducibly for golden-image tests: the RNG is pinned to a
+    /// fixed seed, beat detection follows frame time instead of the wall
+    /// clock and transitions cut immediately.
+    pub deterministic: bool,
+
+    /// How band magnitudes map to `bass`/`mid`/`treb`
+    pub band_scale: BandScale,
+
+    /// Center `bass`/`mid`/`treb` on 1.0 by dividing them by their long-term
+    /// average, as Milkdrop does, instead of reporting absolute 0-1 levels.
+    /// On in every quality preset, since preset thresholds assume it.
+    pub normalize_audio: bool,
+
+    /// Frames to run after loading a preset before its output is considered
+    /// valid, so feedback trails can build up. Headless rendering
+    /// ([`MilkEngine::render_to_buffer`]) renders these frames unseen.
+    pub warmup_frames: u32,
+
+    /// Cap the render rate to this many frames per second (see
+    /// [`MilkEngine::pace_frame`]). `None` renders as often as asked.
+    pub target_fps: Option<f32>,
+
+    /// Give presets whose equations never read `bass`/`mid`/`treb` a gentle
+    /// built-in reaction: bass pulses the zoom and treble nudges the
+    /// rotation, so silent presets still move with the music.
+    pub default_audio_reactivity: bool,
+
+    /// Weight of each new frame at 60 fps in [`MilkEngine::color_state`]'s
+    /// moving average, 0-1, scaled by `delta_time` at other frame rates.
+    /// Lower values change color more slowly.
+    pub color_smoothing: f32,
+}
+
+/// Motion of one warp mesh vertex, with the warp's scale and phase taken
+/// from the frame's motion.
+fn mesh_motion(vertex: &MeshVertexOut, frame: &MotionParams) -> MotionParams {
+    MotionParams {
+        zoom: vertex.zoom as f32,
+        zoomexp: vertex.zoomexp as f32,
+        rot: vertex.rot as f32,
+        cx: vertex.cx as f32,
+        cy: vertex.cy as f32,
+        dx: vertex.dx as f32,
+        dy: vertex.dy as f32,
+        warp: vertex.warp as f32,
+        sx: vertex.sx as f32,
+        sy: vertex.sy as f32,
+        ..*frame
+    }
+}
+
+/// Zoom and rotation layered over a preset's own motion by the beat pulse
+/// and the default audio reactivity, per frame.
+#[derive(Debug, Clone, Copy)]
+struct MotionKick {
+    /// Zoom multiplier
+    zoom: f32,
+
+    /// Rotation added, in radians
+    rot: f32,
+}
+
+impl Default for MotionKick {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            rot: 0.0,
+        }
+    }
+}
+
+impl MotionKick {
+    fn apply(&self, motion: &mut MotionParams) {
+        motion.zoom *= self.zoom;
+        motion.rot += self.rot;
+    }
+}
+
+/// Whether any of a preset's equations reads an audio level.
+fn preset_references_audio(preset: &MilkPreset) -> bool {
+    preset
+        .per_frame_init_equations
+        .iter()
+        .chain(&preset.per_frame_equations)
+        .chain(&preset.per_pixel_equations)
+        .flat_map(|equation| equation.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')))
+        .any(|word| AUDIO_VARS.contains(&word.to_ascii_lowercase().as_str()))
+}
+
+/// Colors a frame draws on top of the feedback, with the alpha they are
+/// drawn at: the waveform and the outer and inner borders.
+fn frame_colors(state: &RenderState) -> [[f32; 4]; 3] {
+    let audio = &state.audio;
+    let volume = (audio.bass + audio.mid + audio.treb) / 3.0;
+    let mut wave = state.wave.color();
+    wave[3] *= state.wave.volume_alpha(volume);
+
+    let border = &state.border;
+    let visible = |size: f32, mut color: [f32; 4]| {
+        if size <= 0.0 {
+            color[3] = 0.0;
+        }
+        color
+    };
+    [
+        wave,
+        visible(border.outer_size, border.outer_color),
+        visible(border.inner_size, border.inner_color),
+    ]
+}
+
+/// Render parameters of a preset's first [`MAX_SHAPES`] custom shapes, from
+/// their `shapecode_N` values.
+fn shape_params(preset: &MilkPreset) -> [ShapeParams; MAX_SHAPES] {
+    let mut shapes = [ShapeParams::default(); MAX_SHAPES];
+    for (params, shape) in shapes.iter_mut().zip(&preset.shapes) {
+        *params = ShapeParams {
+            enabled: shape.enabled,
+            sides: shape.sides.max(0) as u32,
+            num_inst: shape.num_inst.max(1) as u32,
+            x: shape.x,
+            y: shape.y,
+            rad: shape.rad,
+            ang: shape.ang,
+            color: [shape.r, shape.g, shape.b, shape.a],
+            edge_color: [shape.r2, shape.g2, shape.b2, shape.a2],
+            border_color: [
+                shape.border_r,
+                shape.border_g,
+                shape.border_b,
+                shape.border_a,
+            ],
+            thick_outline: shape.thick_outline,
+            additive: shape.additive,
+        };
+    }
+    shapes
+}
+
+/// The most recent [`WAVEFORM_SAMPLES`] audio samples, drawn as the
+/// waveform. Short frames are padded with silence at the start.
+fn waveform_samples(samples: &[f32]) -> [f32; WAVEFORM_SAMPLES] {
+    let mut waveform = [0.0; WAVEFORM_SAMPLES];
+    let recent = &samples[samples.len().saturating_sub(WAVEFORM_SAMPLES)..];
+    waveform[WAVEFORM_SAMPLES - recent.len()..].copy_from_slice(recent);
+    waveform
+}
+
+/// Hash a preset by its serialized `.milk` content, with equations
+/// normalized so whitespace-only edits hash the same.
+fn preset_content_hash(preset: &MilkPreset) -> u64 {
+    let mut normalized = preset.clone();
+    normalized.normalize_equations();
+    let mut hasher = DefaultHasher::new();
+    normalized.to_milk_string().hash(&mut hasher);
+    hasher.finish()
 }
 
 /// Quality preset for engine configuration.
@@ -72,18 +413,48 @@ impl EngineConfig {
                 sample_rate: 44100.0,
                 enable_per_frame: true,
                 enable_per_pixel: false,
+                preserve_vars_on_reload: false,
+                auto_advance_secs: None,
+                beat_zoom_kick: 0.0,
+                deterministic: false,
+                band_scale: BandScale::Linear,
+                normalize_audio: true,
+                warmup_frames: 0,
+                target_fps: None,
+                default_audio_reactivity: false,
+                color_smoothing: DEFAULT_COLOR_SMOOTHING,
             },
             QualityPreset::Medium => Self {
                 render_config: RenderConfig::default(),
                 sample_rate: 44100.0,
                 enable_per_frame: true,
                 enable_per_pixel: false,
+                preserve_vars_on_reload: false,
+                auto_advance_secs: None,
+                beat_zoom_kick: 0.0,
+                deterministic: false,
+                band_scale: BandScale::Linear,
+                normalize_audio: true,
+                warmup_frames: 0,
+                target_fps: None,
+                default_audio_reactivity: false,
+                color_smoothing: DEFAULT_COLOR_SMOOTHING,
             },
             QualityPreset::High => Self {
                 render_config: RenderConfig::default(),
                 sample_rate: 44100.0,
                 enable_per_frame: true,
                 enable_per_pixel: true,
+                preserve_vars_on_reload: false,
+                auto_advance_secs: None,
+                beat_zoom_kick: 0.0,
+                deterministic: false,
+                band_scale: BandScale::Linear,
+                normalize_audio: true,
+                warmup_frames: 0,
+                target_fps: None,
+                default_audio_reactivity: false,
+                color_smoothing: DEFAULT_COLOR_SMOOTHING,
             },
         }
     }
@@ -116,67 +487,367 @@ impl MilkEngine {
 
     /// Create an engine from an existing renderer.
     fn from_renderer(renderer: MilkRenderer, config: EngineConfig) -> Result<Self> {
-        let evaluator = MilkEvaluator::new();
-        let audio_analyzer = AudioAnalyzer::new(config.sample_rate);
+        let mut evaluator = MilkEvaluator::new();
+        if config.deterministic {
+            evaluator.set_seed(DETERMINISTIC_SEED);
+        }
+        let mut audio_analyzer = AudioAnalyzer::new(config.sample_rate);
+        audio_analyzer.set_band_scale(config.band_scale);
+        audio_analyzer.set_normalize(config.normalize_audio);
+        let frame_pacer = FramePacer::new(config.target_fps);
 
         Ok(Self {
             renderer,
             evaluator,
             audio_analyzer,
             beat_detector: BeatDetector::new(),
+            band_overrides: [None; 3],
             current_preset: None,
+            preset_hash: None,
+            preset_path: None,
+            preset_history: History::new(PRESET_HISTORY_SIZE),
+            color_state: None,
             state: RenderState::default(),
             config,
+            transition_manager: TransitionManager::default(),
+            blend_renderer: None,
+            transition_frames: None,
+            double: None,
+            preset_manager: None,
+            preset_elapsed: 0.0,
+            preset_frames: 0,
+            audio_source: None,
+            time_source: Box::new(DeltaTimeSource::new()),
+            frame_pacer,
+            paused: false,
+            readback_buffer: None,
+            beat_pulse: 0.0,
+            preset_uses_audio: false,
+            clock_origin: Instant::now(),
+            frame_times: FrameTimeHistogram::new(),
+            preset_cache: PresetCache::new(),
+            preset_directory: None,
+            live_equations: Vec::new(),
+            hot_reload: None,
         })
     }
 
     /// Load a preset from file.
+    ///
+    /// Relative paths are resolved against the
+    /// [preset directory](Self::set_preset_directory), if one is set.
     pub fn load_preset<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        const MAX_PRESET_SIZE: u64 = 10 * 1024 * 1024; // 10MB limit
-
-        let path_ref = path.as_ref();
+        let resolved = self.resolve_preset_path(path);
+        let path_ref = resolved.as_path();
         log::info!("Loading preset: {}", path_ref.display());
+        let content = read_preset_file(path_ref)?;
 
-        // Validate file size before loading
-        let metadata = fs::metadata(path_ref).map_err(|e| {
-            log::error!("Failed to read file metadata {}: {}", path_ref.display(), e);
-            EngineError::PresetLoadFailed(format!("Cannot read file metadata: {}", e))
-        })?;
+        // Parse preset, unless it was preloaded and hasn't changed since
+        let preset = self
+            .preset_cache
+            .get_or_parse(path_ref, &content)
+            .map_err(|e| {
+                log::error!("Failed to parse preset {}: {}", path_ref.display(), e);
+                e
+            })?;
 
-        if metadata.len() > MAX_PRESET_SIZE {
-            log::error!(
-                "Preset file too large: {} bytes (max {})",
-                metadata.len(),
-                MAX_PRESET_SIZE
+        // Validate preset
+        if preset.per_frame_equations.is_empty() && preset.per_pixel_equations.is_empty() {
+            log::warn!(
+                "Preset {} has no equations, using default parameters",
+                path_ref.display()
             );
-            return Err(EngineError::PresetLoadFailed(format!(
-                "File too large: {} bytes (max {} bytes)",
-                metadata.len(),
-                MAX_PRESET_SIZE
-            )));
         }
 
-        // Read file
-        let content = fs::read_to_string(path_ref).map_err(|e| {
-            log::error!("Failed to read preset file {}: {}", path_ref.display(), e);
-            EngineError::PresetLoadFailed(format!("Cannot read file: {}", e))
-        })?;
+        self.load_parsed_preset(preset, Some(path_ref))?;
+        self.retarget_hot_reload(path_ref);
+        Ok(())
+    }
+
+    /// Load a preset from file, cross-fading from the current output.
+    ///
+    /// The outgoing preset's last rendered frame is kept and blended with the
+    /// new preset's output over `duration` seconds of
+    /// [`update`](Self::update) `delta_time`. [`TransitionMode::Cut`]
+    /// (or a zero duration, or deterministic mode) switches immediately like
+    /// [`load_preset`](Self::load_preset).
+    pub fn load_preset_with_transition<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        mode: TransitionMode,
+        duration: f32,
+    ) -> Result<()> {
+        self.load_preset(path)?;
+
+        if mode == TransitionMode::Cut || duration <= 0.0 || self.config.deterministic {
+            self.end_transition();
+            return Ok(());
+        }
+
+        self.start_transition(mode, Duration::from_secs_f32(duration))
+    }
+
+    /// Create the blend renderer on first use, or reset its bind group cache
+    /// and follow the renderer's current filter mode.
+    fn prepare_blend_renderer(&mut self) -> Result<()> {
+        let gpu = self.renderer.gpu();
+        match &mut self.blend_renderer {
+            Some(blend_renderer) => {
+                blend_renderer.clear_cache();
+                blend_renderer.set_filter_mode(gpu.config.filter_mode);
+            }
+            None => {
+                // Blends go into single-sampled render targets
+                self.blend_renderer = Some(BlendRenderer::new(
+                    gpu.device.clone(),
+                    gpu.queue.clone(),
+                    gpu.config.texture_format.to_wgpu(),
+                    1,
+                    gpu.config.filter_mode,
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Snapshot the current frame and start a cross-fade.
+    fn start_transition(&mut self, mode: TransitionMode, duration: Duration) -> Result<()> {
+        self.prepare_blend_renderer()?;
+        let gpu = self.renderer.gpu();
+
+        let old_texture = gpu.create_render_target("Transition Old Texture");
+        let new_texture = gpu.create_render_target("Transition New Texture");
+        let blend_texture = gpu.create_render_target("Transition Blend Texture");
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Transition Snapshot Encoder"),
+            });
+        gpu.copy_texture(&mut encoder, &gpu.output_texture, &old_texture);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        self.transition_frames = Some(TransitionFrames {
+            old_view: old_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            old_texture,
+            new_view: new_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            new_texture,
+            blend_view: blend_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            blend_texture,
+        });
+        self.transition_manager
+            .start_custom_transition(mode, duration);
+
+        Ok(())
+    }
+
+    /// Blend the outgoing frame over the freshly rendered one, if transitioning.
+    fn render_transition(&mut self, delta_time: f32) -> Result<()> {
+        let Some(frames) = &self.transition_frames else {
+            return Ok(());
+        };
+
+        self.transition_manager.update(delta_time);
+        if !self.transition_manager.is_transitioning() {
+            self.end_transition();
+            return Ok(());
+        }
+
+        let (_, new_blend) = self.transition_manager.blend_factors();
+        let gpu = self.renderer.gpu();
+
+        // Keep the incoming frame unblended for external compositors
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Transition Capture Encoder"),
+            });
+        gpu.copy_texture(&mut encoder, &gpu.output_texture, &frames.new_texture);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        if let Some(blend_renderer) = &mut self.blend_renderer {
+            blend_renderer.render(
+                &frames.old_view,
+                &frames.new_view,
+                (&frames.blend_view, None),
+                CROSSFADE_PATTERN,
+                new_blend,
+                self.state.time,
+            )?;
+        }
+
+        // Present the blend through the regular output texture
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Transition Copy Encoder"),
+            });
+        gpu.copy_texture(&mut encoder, &frames.blend_texture, &gpu.output_texture);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        Ok(())
+    }
 
-        // Parse preset
-        let preset = parse_preset(&content).map_err(|e| {
-            log::error!("Failed to parse preset {}: {}", path_ref.display(), e);
+    /// Load a double preset (`.od2`) from file.
+    ///
+    /// Preset A becomes the current preset; preset B runs alongside it with
+    /// its own evaluator and feedback textures, and both outputs are blended
+    /// every frame. Loading a regular preset ends double-preset playback.
+    pub fn load_double_preset<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let resolved = self.resolve_preset_path(path);
+        let path_ref = resolved.as_path();
+        log::info!("Loading double preset: {}", path_ref.display());
+        let content = read_preset_file(path_ref)?;
+
+        let double = parse_double_preset(&content).map_err(|e| {
+            log::error!(
+                "Failed to parse double preset {}: {}",
+                path_ref.display(),
+                e
+            );
             e
         })?;
 
-        // Validate preset
-        if preset.per_frame_equations.is_empty() && preset.per_pixel_equations.is_empty() {
+        self.load_double_preset_from_data(double)
+    }
+
+    /// Load a double preset from parsed data.
+    pub fn load_double_preset_from_data(&mut self, double: DoublePreset) -> Result<()> {
+        self.load_preset_from_data(double.preset_a)?;
+        self.prepare_blend_renderer()?;
+
+        let gpu = self.renderer.gpu();
+        let renderer = MilkRenderer::from_gpu_context(GpuContext::from_device(
+            gpu.device.clone(),
+            gpu.queue.clone(),
+            gpu.config.clone(),
+        ))?;
+        let blend_texture = gpu.create_render_target("Double Preset Blend Texture");
+
+        let mut evaluator = MilkEvaluator::new();
+        if self.config.deterministic {
+            evaluator.set_seed(DETERMINISTIC_SEED);
+        }
+        let preset = double.preset_b;
+        Self::init_evaluator_from_preset(&mut evaluator, &preset);
+        if let Err(e) = evaluator.eval_per_frame_with_lines(
+            "per_frame_init",
+            &preset.per_frame_init_equations,
+            &preset.source_lines.per_frame_init,
+        ) {
             log::warn!(
-                "Preset {} has no equations, using default parameters",
-                path_ref.display()
+                "Preset B init equation evaluation failed: {}. Continuing with defaults.",
+                e
             );
         }
 
-        self.load_preset_from_data(preset)
+        self.double = Some(DoublePresetLayer {
+            preset,
+            evaluator,
+            renderer,
+            state: RenderState::default(),
+            blend_pattern: double.blend_pattern as u32,
+            blend_amount: double.blend_amount,
+            animation_speed: double.animate_blend.then_some(double.animation_speed),
+            blend_view: blend_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            blend_texture,
+        });
+
+        Ok(())
+    }
+
+    /// Check whether a double preset is playing.
+    pub fn is_double_preset(&self) -> bool {
+        self.double.is_some()
+    }
+
+    /// Render preset B and blend it with the main output, if a double preset is playing.
+    fn render_double_preset(&mut self, delta_time: f32) -> Result<()> {
+        let Some(layer) = &mut self.double else {
+            return Ok(());
+        };
+
+        layer.state.time = self.state.time;
+        layer.state.audio = self.state.audio;
+        layer.state.spectrum = self.state.spectrum;
+        layer.state.waveform = self.state.waveform;
+        Self::eval_frame(
+            &mut layer.evaluator,
+            Some(&layer.preset),
+            &[],
+            &layer.state,
+            self.config.enable_per_frame,
+        );
+        Self::update_render_state_from_evaluator(&layer.evaluator, &mut layer.state);
+        layer.state.motion.warp_time += delta_time * layer.state.motion.warp_anim_speed;
+        layer.state.shapes = shape_params(&layer.preset);
+        layer.renderer.update_state(layer.state);
+        let mesh = Self::build_warp_mesh(
+            &mut layer.evaluator,
+            Some(&layer.preset),
+            self.config.enable_per_pixel,
+            &layer.state.motion,
+            MotionKick::default(),
+        );
+        layer.renderer.set_warp_mesh(mesh);
+        layer.renderer.render()?;
+        layer.state.frame += 1;
+
+        // Animated blends sweep between all-A and all-B
+        let blend_amount = match layer.animation_speed {
+            Some(speed) => 0.5 + 0.5 * (self.state.time * speed).sin(),
+            None => layer.blend_amount,
+        };
+
+        let gpu = self.renderer.gpu();
+        if let Some(blend_renderer) = &mut self.blend_renderer {
+            blend_renderer.render(
+                &gpu.output_texture_view,
+                &layer.renderer.gpu().output_texture_view,
+                (&layer.blend_view, None),
+                layer.blend_pattern,
+                blend_amount,
+                self.state.time,
+            )?;
+        }
+
+        // Present the blend through the regular output texture
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Double Preset Copy Encoder"),
+            });
+        gpu.copy_texture(&mut encoder, &layer.blend_texture, &gpu.output_texture);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        Ok(())
+    }
+
+    /// Stop any running transition and release its textures.
+    fn end_transition(&mut self) {
+        self.transition_manager = TransitionManager::default();
+        self.transition_frames = None;
+    }
+
+    /// Check whether a preset cross-fade is in progress.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition_frames.is_some()
+    }
+
+    /// The two sides of a running cross-fade, for compositing elsewhere:
+    /// the outgoing preset's last frame, the incoming preset's latest frame
+    /// (before blending; filled by the first `update` after the transition
+    /// starts) and the progress from 0.0 to 1.0.
+    ///
+    /// The output texture already holds the engine's own blend of the two.
+    pub fn transition_textures(&self) -> Option<(&wgpu::Texture, &wgpu::Texture, f32)> {
+        self.transition_frames.as_ref().map(|frames| {
+            (
+                &frames.old_texture,
+                &frames.new_texture,
+                self.transition_manager.progress(),
+            )
+        })
     }
 
     /// Load the default preset.
@@ -188,20 +859,98 @@ impl MilkEngine {
     }
 
     /// Load a preset from parsed data.
+    ///
+    /// The preset has no file, so it's never a reload of the current one.
     pub fn load_preset_from_data(&mut self, preset: MilkPreset) -> Result<()> {
+        self.load_parsed_preset(preset, None)
+    }
+
+    /// Load a preset parsed from the file at `path`, if any.
+    fn load_parsed_preset(&mut self, preset: MilkPreset, path: Option<&Path>) -> Result<()> {
         log::info!("Loading preset version {}", preset.version);
 
-        // Initialize evaluator context with preset parameters
-        self.init_evaluator_from_preset(&preset);
+        let hash = preset_content_hash(&preset);
+        if self.preset_hash != Some(hash) {
+            self.preset_history.push(preset.clone());
+        }
+        self.activate_preset(preset, hash, path)
+    }
+
+    /// Mash up the recently loaded presets and switch to the result.
+    ///
+    /// See [`History::create_mashup`] for how the presets are combined. The
+    /// mash-up isn't added to the history, so repeated mash-ups keep drawing
+    /// from the loaded presets.
+    pub fn mashup_current(&mut self, ty: MashUpType) -> Result<()> {
+        if self.preset_history.is_empty() {
+            return Err(EngineError::Other("No presets to mash up".to_string()));
+        }
+        let mashup = self.preset_history.create_mashup(ty);
+        log::info!("Loading {:?} mash-up", ty);
+        let hash = preset_content_hash(&mashup);
+        self.activate_preset(mashup, hash, None)
+    }
+
+    /// Recently loaded presets, the current one last.
+    pub fn preset_history(&self) -> &History<MilkPreset> {
+        &self.preset_history
+    }
+
+    /// Color of the last rendered frame and its smoothed average (see
+    /// [`EngineConfig::color_smoothing`]), for tinting UI or driving ambient
+    /// lighting. `None` until a frame draws something visible.
+    pub fn color_state(&self) -> Option<&ColorState> {
+        self.color_state.as_ref()
+    }
+
+    /// Make `preset`, whose content hash is `hash` and which was loaded from
+    /// `path`, the current preset.
+    fn activate_preset(
+        &mut self,
+        preset: MilkPreset,
+        hash: u64,
+        path: Option<&Path>,
+    ) -> Result<()> {
+        // Compare canonical paths, so a relative load and the hot-reload
+        // watcher's absolute path name the same preset
+        let path = path.map(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        let reload = path.is_some() && path == self.preset_path;
+        if self.config.preserve_vars_on_reload && reload {
+            log::debug!("Reloading same preset, keeping evaluator state");
+        } else {
+            // Start from a clean context so variables don't leak between presets
+            self.evaluator.reset();
+
+            // Initialize evaluator context with preset parameters
+            Self::init_evaluator_from_preset(&mut self.evaluator, &preset);
+
+            // Run init equations once, before the first frame
+            if let Err(e) = self.evaluator.eval_per_frame_with_lines(
+                "per_frame_init",
+                &preset.per_frame_init_equations,
+                &preset.source_lines.per_frame_init,
+            ) {
+                log::warn!(
+                    "Per-frame init equation evaluation failed: {}. Continuing with defaults.",
+                    e
+                );
+            }
+        }
 
+        self.preset_uses_audio = preset_references_audio(&preset);
         self.current_preset = Some(preset);
+        self.preset_hash = Some(hash);
+        self.preset_path = path;
+        self.double = None;
+        self.preset_elapsed = 0.0;
+        self.preset_frames = 0;
 
         Ok(())
     }
 
     /// Initialize evaluator context from preset parameters.
-    fn init_evaluator_from_preset(&mut self, preset: &MilkPreset) {
-        let ctx = self.evaluator.context_mut();
+    fn init_evaluator_from_preset(evaluator: &mut MilkEvaluator, preset: &MilkPreset) {
+        let ctx = evaluator.context_mut();
         let params = &preset.parameters;
 
         // Set motion parameters
@@ -209,6 +958,8 @@ impl MilkEngine {
         ctx.set_var("zoomexp", params.zoomexp() as f64);
         ctx.set_var("rot", params.rot as f64);
         ctx.set_var("warp", params.warp as f64);
+        ctx.set_var("warp_scale", params.f_warp_scale as f64);
+        ctx.set_var("warp_anim_speed", params.f_warp_anim_speed as f64);
         ctx.set_var("cx", params.cx as f64);
         ctx.set_var("cy", params.cy as f64);
         ctx.set_var("dx", params.dx as f64);
@@ -224,12 +975,40 @@ impl MilkEngine {
         ctx.set_var("wave_x", params.wave_x as f64);
         ctx.set_var("wave_y", params.wave_y as f64);
         ctx.set_var("wave_mode", params.wave_mode() as f64);
+        ctx.set_var(
+            "wave_brighten",
+            if params.b_maximize_wave_color {
+                1.0
+            } else {
+                0.0
+            },
+        );
+        ctx.set_var("wave_scale", params.f_wave_scale as f64);
+        ctx.set_var("wave_smoothing", params.f_wave_smoothing as f64);
+        ctx.set_var("wave_mystery", params.f_wave_param as f64);
+        ctx.set_var("wave_thick", if params.b_wave_thick { 1.0 } else { 0.0 });
+        ctx.set_var(
+            "wave_additive",
+            if params.b_additive_waves { 1.0 } else { 0.0 },
+        );
+        ctx.set_var("wave_usedots", if params.b_wave_dots { 1.0 } else { 0.0 });
+        ctx.set_var(
+            "modwavealphabyvolume",
+            if params.b_mod_wave_alpha_by_volume {
+                1.0
+            } else {
+                0.0
+            },
+        );
+        ctx.set_var("modwavealphastart", params.f_mod_wave_alpha_start as f64);
+        ctx.set_var("modwavealphaend", params.f_mod_wave_alpha_end as f64);
 
         // Set other parameters
         ctx.set_var("decay", params.decay() as f64);
         ctx.set_var("gamma", params.gamma() as f64);
         ctx.set_var("echo_zoom", params.echo_zoom() as f64);
         ctx.set_var("echo_alpha", params.echo_alpha() as f64);
+        ctx.set_var("echo_orient", params.n_video_echo_orientation as f64);
         ctx.set_var(
             "darken_center",
             if params.darken_center() { 1.0 } else { 0.0 },
@@ -239,155 +1018,834 @@ impl MilkEngine {
         ctx.set_var("brighten", if params.brighten() { 1.0 } else { 0.0 });
         ctx.set_var("darken", if params.darken() { 1.0 } else { 0.0 });
         ctx.set_var("solarize", if params.solarize() { 1.0 } else { 0.0 });
+
+        // Set border parameters
+        ctx.set_var("ob_size", params.ob_size as f64);
+        ctx.set_var("ob_r", params.ob_r as f64);
+        ctx.set_var("ob_g", params.ob_g as f64);
+        ctx.set_var("ob_b", params.ob_b as f64);
+        ctx.set_var("ob_a", params.ob_a as f64);
+        ctx.set_var("ib_size", params.ib_size as f64);
+        ctx.set_var("ib_r", params.ib_r as f64);
+        ctx.set_var("ib_g", params.ib_g as f64);
+        ctx.set_var("ib_b", params.ib_b as f64);
+        ctx.set_var("ib_a", params.ib_a as f64);
     }
 
     /// Update engine with audio data and render a frame.
     /// Returns Some(PresetChange) if beat detection triggered a preset change.
+    ///
+    /// The frame's `time` comes from the [time source](Self::set_time_source);
+    /// `delta_time` feeds it and paces beat reactions and auto-advance.
     pub fn update(
         &mut self,
         audio_samples: &[f32],
         delta_time: f32,
     ) -> Result<Option<PresetChange>> {
+        let frame_start = Instant::now();
+        self.poll_hot_reload();
+
+        // Paused: keep the last frame and leave the clock where it is
+        if self.paused {
+            return Ok(None);
+        }
+
         // Analyze audio
-        let audio_levels = self.audio_analyzer.analyze(audio_samples);
+        let mut audio_levels = self.audio_analyzer.analyze(audio_samples);
 
         // Update time
-        self.state.time += delta_time;
-
-        // Update audio in state
-        self.state.audio = audio_levels;
+        self.state.time = self.time_source.frame_time(delta_time);
+        self.update_auto_advance(delta_time);
 
         // Check beat detection for automatic preset change
-        let preset_change = self.beat_detector.should_change_preset(
+        let now = if self.config.deterministic {
+            self.clock_origin + Duration::from_secs_f32(self.state.time)
+        } else {
+            Instant::now()
+        };
+        let preset_change = self.beat_detector.should_change_preset_at(
             audio_levels.bass,
             audio_levels.mid,
             audio_levels.treb,
+            now,
         );
 
-        // Update evaluator context
-        let ctx = self.evaluator.context_mut();
-        ctx.set_time(self.state.time as f64);
-        ctx.set_frame(self.state.frame as f64);
-        ctx.set_audio(
-            audio_levels.bass as f64,
-            audio_levels.mid as f64,
-            audio_levels.treb as f64,
+        // Update audio in state. Debug band overrides are applied after beat
+        // detection so pinned levels never trigger automatic preset changes.
+        self.apply_band_overrides(&mut audio_levels);
+        self.state.audio = audio_levels;
+        self.state.spectrum = *self.audio_analyzer.spectrum();
+        self.state.waveform = waveform_samples(audio_samples);
+
+        // Execute per-frame equations and update render state from evaluator
+        Self::eval_frame(
+            &mut self.evaluator,
+            self.current_preset.as_ref(),
+            &self.live_equations,
+            &self.state,
+            self.config.enable_per_frame,
         );
-        ctx.set("bass_att", audio_levels.bass_att as f64);
-        ctx.set("mid_att", audio_levels.mid_att as f64);
-        ctx.set("treb_att", audio_levels.treb_att as f64);
-
-        // Execute per-frame equations if enabled and preset loaded
-        if self.config.enable_per_frame {
-            if let Some(preset) = &self.current_preset {
-                // Try to evaluate equations, but don't fail the entire frame if one fails
-                if let Err(e) = self.evaluator.eval_per_frame(&preset.per_frame_equations) {
-                    log::warn!(
-                        "Per-frame equation evaluation failed: {}. Continuing with previous state.",
-                        e
-                    );
-                    // Continue rendering with previous state instead of failing
-                }
+        Self::update_render_state_from_evaluator(&self.evaluator, &mut self.state);
+        self.state.motion.warp_time += delta_time * self.state.motion.warp_anim_speed;
+        self.state.shapes = self
+            .current_preset
+            .as_ref()
+            .map(shape_params)
+            .unwrap_or_default();
+        let mut kick = MotionKick::default();
+        self.apply_default_audio_reactivity(&mut kick);
+        self.apply_beat_pulse(&mut kick, preset_change.is_some(), delta_time);
+        kick.apply(&mut self.state.motion);
+        let colors = frame_colors(&self.state);
+        match &mut self.color_state {
+            Some(color_state) => {
+                color_state.record(&colors, self.config.color_smoothing, delta_time)
             }
+            None => self.color_state = ColorState::new(&colors),
         }
 
-        // Update render state from evaluator
-        self.update_render_state_from_evaluator();
-
         // Update renderer state
         self.renderer.update_state(self.state);
+        self.update_warp_mesh(kick);
 
         // Render frame
         self.renderer.render()?;
+        self.render_double_preset(delta_time)?;
+        self.render_transition(delta_time)?;
 
         // Increment frame counter
         self.state.frame += 1;
+        self.preset_frames = self.preset_frames.saturating_add(1);
+        self.frame_times.record(frame_start.elapsed());
 
         Ok(preset_change)
     }
 
-    /// Execute per-frame equations.
-    /// Note: Currently unused as equations are evaluated inline in update().
-    #[allow(dead_code)]
-    fn execute_per_frame_equations(&mut self, preset: &MilkPreset) -> Result<()> {
-        for equation in &preset.per_frame_equations {
-            self.evaluator.eval(equation)?;
-        }
-        Ok(())
+    /// Run the per-pixel equations over the warp mesh when per-pixel is
+    /// enabled and the preset has any, otherwise fall back to the uniform
+    /// per-frame warp. `kick` applies to every vertex, as it does to the
+    /// per-frame motion.
+    fn update_warp_mesh(&mut self, kick: MotionKick) {
+        let mesh = Self::build_warp_mesh(
+            &mut self.evaluator,
+            self.current_preset.as_ref(),
+            self.config.enable_per_pixel,
+            &self.state.motion,
+            kick,
+        );
+        self.renderer.set_warp_mesh(mesh);
     }
 
-    /// Update render state from evaluator context.
-    fn update_render_state_from_evaluator(&mut self) {
-        let ctx = self.evaluator.context();
-
-        // Update motion parameters
-        self.state.motion = MotionParams {
-            zoom: ctx.get_var("zoom").unwrap_or(1.0) as f32,
-            rot: ctx.get_var("rot").unwrap_or(0.0) as f32,
-            cx: ctx.get_var("cx").unwrap_or(0.5) as f32,
-            cy: ctx.get_var("cy").unwrap_or(0.5) as f32,
-            dx: ctx.get_var("dx").unwrap_or(0.0) as f32,
-            dy: ctx.get_var("dy").unwrap_or(0.0) as f32,
-            warp: ctx.get_var("warp").unwrap_or(0.0) as f32,
-            sx: ctx.get_var("sx").unwrap_or(1.0) as f32,
-            sy: ctx.get_var("sy").unwrap_or(1.0) as f32,
+    /// Warp mesh from `preset`'s per-pixel equations with `kick` on each
+    /// vertex, or `None` for the uniform per-frame warp. `frame` is the
+    /// frame's motion, whose warp phase the vertices share.
+    fn build_warp_mesh(
+        evaluator: &mut MilkEvaluator,
+        preset: Option<&MilkPreset>,
+        enable_per_pixel: bool,
+        frame: &MotionParams,
+        kick: MotionKick,
+    ) -> Option<WarpMesh> {
+        let equations = match preset {
+            Some(preset) if enable_per_pixel && !preset.per_pixel_equations.is_empty() => {
+                &preset.per_pixel_equations
+            }
+            _ => return None,
         };
 
-        // Update wave parameters
-        self.state.wave = WaveParams {
-            r: ctx.get_var("wave_r").unwrap_or(1.0) as f32,
-            g: ctx.get_var("wave_g").unwrap_or(1.0) as f32,
-            b: ctx.get_var("wave_b").unwrap_or(1.0) as f32,
-            a: ctx.get_var("wave_a").unwrap_or(1.0) as f32,
-            x: ctx.get_var("wave_x").unwrap_or(0.5) as f32,
-            y: ctx.get_var("wave_y").unwrap_or(0.5) as f32,
-            mode: ctx.get_var("wave_mode").unwrap_or(0.0) as i32,
-        };
+        let (columns, rows) = WARP_MESH_SIZE;
+        match evaluator.eval_mesh(columns, rows, equations) {
+            Ok(vertices) => {
+                let motion: Vec<MotionParams> = vertices
+                    .iter()
+                    .map(|vertex| {
+                        let mut motion = mesh_motion(vertex, frame);
+                        kick.apply(&mut motion);
+                        motion
+                    })
+                    .collect();
+                Some(WarpMesh::new(columns, rows, &motion))
+            }
+            Err(e) => {
+                log::warn!("Per-pixel equations failed, using per-frame motion: {}", e);
+                None
+            }
+        }
     }
 
-    /// Get the current render texture.
-    pub fn render_texture(&self) -> &wgpu::Texture {
-        self.renderer.render_texture()
+    /// Turn per-pixel equations on or off, e.g. from a key binding. Takes
+    /// effect from the next [`update`](Self::update).
+    pub fn set_per_pixel_enabled(&mut self, enabled: bool) {
+        self.config.enable_per_pixel = enabled;
     }
 
-    /// Get current state.
-    pub fn state(&self) -> &RenderState {
-        &self.state
+    /// Whether per-pixel equations drive the warp mesh.
+    pub fn per_pixel_enabled(&self) -> bool {
+        self.config.enable_per_pixel
     }
 
-    /// Get current preset.
-    pub fn current_preset(&self) -> Option<&MilkPreset> {
-        self.current_preset.as_ref()
+    /// Pull [`SOURCE_FRAME_SAMPLES`] samples from the audio source and
+    /// [`update`](Self::update) with them. Renders silence if no source is set.
+    pub fn update_from_source(&mut self, delta_time: f32) -> Result<Option<PresetChange>> {
+        let samples = match &mut self.audio_source {
+            Some(source) => source.next_frame(SOURCE_FRAME_SAMPLES),
+            None => vec![0.0; SOURCE_FRAME_SAMPLES],
+        };
+        self.update(&samples, delta_time)
     }
 
-    /// Get the beat detector.
-    pub fn beat_detector(&self) -> &BeatDetector {
-        &self.beat_detector
+    /// Pause or resume. While paused, [`update`](Self::update) neither
+    /// advances `time`, `frame` and transitions nor runs the preset's
+    /// equations, and the last rendered frame stays in the render texture.
+    /// [`update_from_source`](Self::update_from_source) keeps pulling audio,
+    /// so capture buffers don't back up.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
     }
 
-    /// Get the beat detector mutably.
-    pub fn beat_detector_mut(&mut self) -> &mut BeatDetector {
-        &mut self.beat_detector
+    /// Whether updates are paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
 
-    /// Set beat detection mode.
-    pub fn set_beat_detection_mode(&mut self, mode: BeatDetectionMode) {
-        self.beat_detector.set_mode(mode);
+    /// Set the audio source that [`update_from_source`](Self::update_from_source)
+    /// pulls from.
+    pub fn set_audio_source(&mut self, source: Box<dyn AudioSource>) {
+        self.audio_source = Some(source);
     }
 
-    /// Toggle beat detection to next mode.
-    pub fn next_beat_detection_mode(&mut self) {
-        self.beat_detector.next_mode();
+    /// Remove and return the audio source.
+    pub fn take_audio_source(&mut self) -> Option<Box<dyn AudioSource>> {
+        self.audio_source.take()
     }
 
-    /// Enable beat detection.
-    pub fn enable_beat_detection(&mut self) {
-        self.beat_detector.enable();
+    /// Account for `elapsed` seconds of wall-clock time and decide whether to
+    /// render. Returns the delta time to pass to [`update`](Self::update)
+    /// when a frame is due under [`EngineConfig::target_fps`], or `None`
+    /// when the call came too soon and the frame should be skipped. The
+    /// returned delta covers all skipped calls, so `time` stays accurate.
+    pub fn pace_frame(&mut self, elapsed: f32) -> Option<f32> {
+        self.frame_pacer.advance(elapsed)
     }
 
-    /// Disable beat detection.
-    pub fn disable_beat_detection(&mut self) {
-        self.beat_detector.disable();
+    /// Whether enough time has accumulated through
+    /// [`pace_frame`](Self::pace_frame) for the next frame.
+    pub fn should_render_now(&self) -> bool {
+        self.frame_pacer.is_due()
+    }
+
+    /// Time left, after the time already passed to
+    /// [`pace_frame`](Self::pace_frame), until the next frame is due, so a
+    /// caller can sleep until then. `None` when the frame rate is unlimited.
+    pub fn time_until_next_frame(&self) -> Option<Duration> {
+        self.frame_pacer.remaining().map(Duration::from_secs_f32)
+    }
+
+    /// Drive `time` from another clock, e.g. an external timeline giving
+    /// absolute seconds. The default sums each frame's `delta_time`.
+    pub fn set_time_source(&mut self, source: Box<dyn TimeSource>) {
+        self.time_source = source;
+    }
+
+    /// Feed the frame's time and audio to an evaluator and run the preset's
+    /// per-frame equations, then the live equations.
+    fn eval_frame(
+        evaluator: &mut MilkEvaluator,
+        preset: Option<&MilkPreset>,
+        live_equations: &[String],
+        state: &RenderState,
+        enable_per_frame: bool,
+    ) {
+        let audio = &state.audio;
+        let ctx = evaluator.context_mut();
+        ctx.set_time(state.time as f64);
+        ctx.set_frame(state.frame as f64);
+        ctx.set_audio(audio.bass as f64, audio.mid as f64, audio.treb as f64);
+        ctx.set("bass_att", audio.bass_att as f64);
+        ctx.set("mid_att", audio.mid_att as f64);
+        ctx.set("treb_att", audio.treb_att as f64);
+        ctx.set_spectrum(&state.spectrum);
+
+        // Execute per-frame equations if enabled and preset loaded
+        if enable_per_frame {
+            if let Some(preset) = preset {
+                // Try to evaluate equations, but don't fail the entire frame if one fails
+                if let Err(e) = evaluator.eval_per_frame_with_lines(
+                    "per_frame",
+                    &preset.per_frame_equations,
+                    &preset.source_lines.per_frame,
+                ) {
+                    log::warn!(
+                        "Per-frame equation evaluation failed: {}. Continuing with previous state.",
+                        e
+                    );
+                    // Continue rendering with previous state instead of failing
+                }
+            }
+
+            // One at a time, so a broken live equation doesn't block the rest
+            for equation in live_equations {
+                if let Err(e) = evaluator.eval_per_frame(std::slice::from_ref(equation)) {
+                    log::warn!("Live equation '{}' failed: {}", equation, e);
+                }
+            }
+        }
+    }
+
+    /// Add a per-frame equation that runs after the preset's per-frame
+    /// equations every frame, e.g. `zoom = 2.0` to override the preset live.
+    ///
+    /// Live equations survive preset changes. A failing equation is logged
+    /// each frame and skipped.
+    pub fn add_live_equation(&mut self, equation: &str) {
+        self.live_equations.push(equation.to_string());
+    }
+
+    /// Remove all live equations.
+    pub fn clear_live_equations(&mut self) {
+        self.live_equations.clear();
+    }
+
+    /// Get the live equations, in evaluation order.
+    pub fn live_equations(&self) -> &[String] {
+        &self.live_equations
+    }
+
+    /// Parse presets ahead of time, so loading them later with
+    /// [`load_preset`](Self::load_preset) skips the parse.
+    ///
+    /// A preset edited after preloading is parsed again on load. There are
+    /// no shaders to compile ahead of time: the renderer builds its pipelines
+    /// once at creation and doesn't compile per-preset shaders.
+    pub fn preload_presets(&mut self, paths: &[&Path]) -> Result<()> {
+        for path in paths {
+            let resolved = self.resolve_preset_path(path);
+            self.preset_cache.preload(&resolved)?;
+        }
+        log::info!("Preloaded {} preset(s)", paths.len());
+        Ok(())
+    }
+
+    /// Like [`preload_presets`](Self::preload_presets), but on a background
+    /// thread. Presets become available to `load_preset` as they finish.
+    pub fn preload_presets_in_background(
+        &self,
+        paths: Vec<PathBuf>,
+    ) -> std::thread::JoinHandle<Result<()>> {
+        let paths = paths
+            .iter()
+            .map(|path| self.resolve_preset_path(path))
+            .collect();
+        self.preset_cache.preload_in_background(paths)
+    }
+
+    /// Set the directory that relative preset paths, including those of
+    /// [`PresetChange::Specific`], are resolved against.
+    ///
+    /// A relative directory is made absolute against the current directory.
+    pub fn set_preset_directory<P: AsRef<Path>>(&mut self, dir: P) {
+        let dir = dir.as_ref();
+        let dir = std::path::absolute(dir).unwrap_or_else(|e| {
+            log::warn!("Cannot make {} absolute: {}", dir.display(), e);
+            dir.to_path_buf()
+        });
+        self.preset_directory = Some(dir);
+    }
+
+    /// Get the preset directory, if one is set.
+    pub fn preset_directory(&self) -> Option<&Path> {
+        self.preset_directory.as_deref()
+    }
+
+    /// Resolve a preset path against the preset directory.
+    ///
+    /// Absolute paths, and all paths when no directory is set, are returned
+    /// unchanged.
+    pub fn resolve_preset_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        match &self.preset_directory {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Watch a preset file and reload it whenever it changes on disk.
+    ///
+    /// Changes are picked up at the start of the next [`update`](Self::update)
+    /// through [`reload_preset`](Self::reload_preset). Relative paths are
+    /// resolved like [`load_preset`](Self::load_preset); the file isn't
+    /// loaded until it changes, so load it first.
+    pub fn enable_hot_reload<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let resolved = self.resolve_preset_path(path);
+        let watcher = PresetWatcher::new(&resolved)?;
+        log::info!("Hot reloading {}", watcher.path().display());
+        self.hot_reload = Some(watcher);
+        Ok(())
+    }
+
+    /// Stop watching the hot-reloaded preset file.
+    pub fn disable_hot_reload(&mut self) {
+        self.hot_reload = None;
+    }
+
+    /// Re-read and load the hot-reloaded preset file.
+    ///
+    /// `time`, `frame` and the preset's elapsed time carry over, so the
+    /// visual continues rather than restarting. If the file fails to read or
+    /// parse, the error is returned and the current preset stays active.
+    pub fn reload_preset(&mut self) -> Result<()> {
+        let path = match &self.hot_reload {
+            Some(watcher) => watcher.path().to_path_buf(),
+            None => return Err(EngineError::Other("Hot reload is not enabled".to_string())),
+        };
+
+        let content = read_preset_file(&path)?;
+        let preset = parse_preset(&content)?;
+
+        let (elapsed, frames) = (self.preset_elapsed, self.preset_frames);
+        self.load_parsed_preset(preset, Some(&path))?;
+        self.preset_elapsed = elapsed;
+        self.preset_frames = frames;
+        log::info!("Reloaded {}", path.display());
+        Ok(())
+    }
+
+    /// Reload the hot-reloaded preset if its file changed since the last frame.
+    fn poll_hot_reload(&mut self) {
+        let changed = self
+            .hot_reload
+            .as_ref()
+            .is_some_and(|watcher| watcher.take_change());
+        if changed {
+            if let Err(e) = self.reload_preset() {
+                log::error!("Hot reload failed, keeping the current preset: {}", e);
+            }
+        }
+    }
+
+    /// Point hot reloading at `path` after another preset file was loaded,
+    /// so edits to the old file don't replace the new preset.
+    fn retarget_hot_reload(&mut self, path: &Path) {
+        let Some(watcher) = &self.hot_reload else {
+            return;
+        };
+        if path.canonicalize().is_ok_and(|p| p == watcher.path()) {
+            return;
+        }
+        match PresetWatcher::new(path) {
+            Ok(watcher) => {
+                log::info!("Hot reloading {}", watcher.path().display());
+                self.hot_reload = Some(watcher);
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to watch {}, hot reload disabled: {}",
+                    path.display(),
+                    e
+                );
+                self.hot_reload = None;
+            }
+        }
+    }
+
+    /// Act on a preset change returned by [`update`](Self::update).
+    ///
+    /// A specific preset is loaded relative to the preset directory; a random
+    /// one is picked from the bound playlist, if any.
+    pub fn load_preset_change(&mut self, change: &PresetChange) -> Result<()> {
+        let path = match change {
+            PresetChange::Specific(path) => PathBuf::from(path),
+            PresetChange::Random => {
+                let random = self
+                    .preset_manager
+                    .as_mut()
+                    .and_then(|manager| manager.random_preset().map(Path::to_path_buf));
+                match random {
+                    Some(path) => path,
+                    None => {
+                        log::debug!("Random preset change but no playlist is bound");
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        self.load_preset(path)
+    }
+
+    /// Statistics of the preset cache filled by preloading.
+    pub fn preset_cache_stats(&self) -> PresetCacheStats {
+        self.preset_cache.stats()
+    }
+
+    /// Get the distribution of `update` durations since creation or the last
+    /// [`reset`](Self::reset).
+    pub fn frame_time_histogram(&self) -> FrameTimeStats {
+        self.frame_times.stats()
+    }
+
+    /// Modulate zoom and rotation with the audio for presets that don't react
+    /// to it themselves, if enabled.
+    fn apply_default_audio_reactivity(&self, kick: &mut MotionKick) {
+        if !self.config.default_audio_reactivity || self.preset_uses_audio {
+            return;
+        }
+        // Normalized levels rest at 1.0; react only to what's above that
+        let rest = if self.config.normalize_audio {
+            1.0
+        } else {
+            0.0
+        };
+        let audio = &self.state.audio;
+        let bass = (audio.bass - rest).max(0.0);
+        let treb = (audio.treb - rest).max(0.0);
+        kick.zoom *= 1.0 + DEFAULT_BASS_ZOOM * bass;
+        kick.rot += DEFAULT_TREB_ROT * treb;
+    }
+
+    /// Kick the zoom on a triggered beat and let the kick decay over time.
+    fn apply_beat_pulse(&mut self, kick: &mut MotionKick, triggered: bool, delta_time: f32) {
+        if triggered {
+            self.beat_pulse = self.beat_detector.last_beat_intensity().min(BEAT_PULSE_MAX);
+        }
+        if self.beat_pulse > 0.0 {
+            kick.zoom *= 1.0 + self.config.beat_zoom_kick * self.beat_pulse;
+            self.beat_pulse *= (-BEAT_PULSE_DECAY * delta_time).exp();
+        }
+    }
+
+    /// Load the next playlist preset once the auto-advance interval elapses.
+    fn update_auto_advance(&mut self, delta_time: f32) {
+        let Some(default_interval) = self.config.auto_advance_secs else {
+            return;
+        };
+
+        self.preset_elapsed += delta_time;
+        let interval = self
+            .current_preset
+            .as_ref()
+            .and_then(|preset| preset.parameters.time_between_presets())
+            .unwrap_or(default_interval);
+        if self.preset_elapsed < interval {
+            return;
+        }
+
+        let next = self
+            .preset_manager
+            .as_mut()
+            .and_then(|manager| manager.next_preset().map(Path::to_path_buf));
+        match next {
+            Some(path) => {
+                if let Err(e) = self.load_preset(&path) {
+                    log::warn!("Auto-advance to {} failed: {}", path.display(), e);
+                }
+            }
+            None => log::debug!("Auto-advance interval elapsed but no playlist is bound"),
+        }
+        self.preset_elapsed = 0.0;
+    }
+
+    /// Bind a playlist for timed auto-advance.
+    pub fn set_preset_manager(&mut self, manager: PresetManager) {
+        self.preset_manager = Some(manager);
+    }
+
+    /// Get the bound playlist, if any.
+    pub fn preset_manager(&self) -> Option<&PresetManager> {
+        self.preset_manager.as_ref()
+    }
+
+    /// Get the bound playlist mutably, if any.
+    pub fn preset_manager_mut(&mut self) -> Option<&mut PresetManager> {
+        self.preset_manager.as_mut()
+    }
+
+    /// Replace overridden bands (and their attenuated values) with pinned levels.
+    fn apply_band_overrides(&self, levels: &mut AudioLevels) {
+        for band in Band::ALL {
+            if let Some(value) = self.band_overrides[band.index()] {
+                match band {
+                    Band::Bass => {
+                        levels.bass = value;
+                        levels.bass_att = value;
+                    }
+                    Band::Mid => {
+                        levels.mid = value;
+                        levels.mid_att = value;
+                    }
+                    Band::Treb => {
+                        levels.treb = value;
+                        levels.treb_att = value;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pin a frequency band to a constant level, or clear the override with `None`.
+    ///
+    /// Intended for debugging audio reactivity: the preset sees the pinned value
+    /// for both the instant and attenuated variables regardless of the audio input.
+    /// Beat detection keeps using the real audio.
+    pub fn set_band_override(&mut self, band: Band, value: Option<f32>) {
+        self.band_overrides[band.index()] = value;
+    }
+
+    /// Get the current override for a frequency band, if any.
+    pub fn band_override(&self, band: Band) -> Option<f32> {
+        self.band_overrides[band.index()]
+    }
+
+    /// Execute per-frame equations.
+    /// Note: Currently unused as equations are evaluated inline in update().
+    #[allow(dead_code)]
+    fn execute_per_frame_equations(&mut self, preset: &MilkPreset) -> Result<()> {
+        for equation in &preset.per_frame_equations {
+            self.evaluator.eval(equation)?;
+        }
+        Ok(())
+    }
+
+    /// Update render state from evaluator context.
+    fn update_render_state_from_evaluator(evaluator: &MilkEvaluator, state: &mut RenderState) {
+        let ctx = evaluator.context();
+
+        // Update motion parameters
+        state.motion = MotionParams {
+            zoom: ctx.get_var("zoom").unwrap_or(1.0) as f32,
+            zoomexp: ctx.get_var("zoomexp").unwrap_or(1.0) as f32,
+            rot: ctx.get_var("rot").unwrap_or(0.0) as f32,
+            cx: ctx.get_var("cx").unwrap_or(0.5) as f32,
+            cy: ctx.get_var("cy").unwrap_or(0.5) as f32,
+            dx: ctx.get_var("dx").unwrap_or(0.0) as f32,
+            dy: ctx.get_var("dy").unwrap_or(0.0) as f32,
+            warp: ctx.get_var("warp").unwrap_or(0.0) as f32,
+            warp_scale: ctx.get_var("warp_scale").unwrap_or(1.0) as f32,
+            warp_anim_speed: ctx.get_var("warp_anim_speed").unwrap_or(1.0) as f32,
+            // Advanced by the caller, so a speed change never jumps the phase
+            warp_time: state.motion.warp_time,
+            sx: ctx.get_var("sx").unwrap_or(1.0) as f32,
+            sy: ctx.get_var("sy").unwrap_or(1.0) as f32,
+        };
+
+        // Update wave parameters
+        state.wave = WaveParams {
+            r: ctx.get_var("wave_r").unwrap_or(1.0) as f32,
+            g: ctx.get_var("wave_g").unwrap_or(1.0) as f32,
+            b: ctx.get_var("wave_b").unwrap_or(1.0) as f32,
+            a: ctx.get_var("wave_a").unwrap_or(1.0) as f32,
+            x: ctx.get_var("wave_x").unwrap_or(0.5) as f32,
+            y: ctx.get_var("wave_y").unwrap_or(0.5) as f32,
+            mode: ctx.get_var("wave_mode").unwrap_or(0.0) as i32,
+            maximize_color: ctx.get_var("wave_brighten").unwrap_or(0.0) != 0.0,
+            scale: ctx.get_var("wave_scale").unwrap_or(1.0) as f32,
+            smoothing: ctx.get_var("wave_smoothing").unwrap_or(0.0) as f32,
+            mystery: ctx.get_var("wave_mystery").unwrap_or(0.0) as f32,
+            thick: ctx.get_var("wave_thick").unwrap_or(0.0) != 0.0,
+            additive: ctx.get_var("wave_additive").unwrap_or(0.0) != 0.0,
+            dots: ctx.get_var("wave_usedots").unwrap_or(0.0) != 0.0,
+            mod_alpha_by_volume: ctx.get_var("modwavealphabyvolume").unwrap_or(0.0) != 0.0,
+            mod_alpha_start: ctx.get_var("modwavealphastart").unwrap_or(0.75) as f32,
+            mod_alpha_end: ctx.get_var("modwavealphaend").unwrap_or(0.95) as f32,
+        };
+
+        // Update video echo
+        state.echo = EchoParams {
+            zoom: ctx.get_var("echo_zoom").unwrap_or(1.0) as f32,
+            alpha: ctx.get_var("echo_alpha").unwrap_or(0.0) as f32,
+            orientation: ctx.get_var("echo_orient").unwrap_or(0.0) as i32,
+        };
+
+        // Update post-processing flags
+        let flag = |name: &str| ctx.get_var(name).unwrap_or(0.0) != 0.0;
+        state.post = PostParams {
+            brighten: flag("brighten"),
+            darken: flag("darken"),
+            solarize: flag("solarize"),
+            invert: flag("invert"),
+            darken_center: flag("darken_center"),
+            gamma: ctx.get_var("gamma").unwrap_or(1.0) as f32,
+        };
+
+        // Update borders
+        let var = |name: &str| ctx.get_var(name).unwrap_or(0.0) as f32;
+        state.border = BorderParams {
+            outer_size: var("ob_size"),
+            outer_color: [var("ob_r"), var("ob_g"), var("ob_b"), var("ob_a")],
+            inner_size: var("ib_size"),
+            inner_color: [var("ib_r"), var("ib_g"), var("ib_b"), var("ib_a")],
+        };
+    }
+
+    /// Get the current render texture.
+    pub fn render_texture(&self) -> &wgpu::Texture {
+        self.renderer.render_texture()
+    }
+
+    /// Whether the current preset has run its
+    /// [`warmup_frames`](EngineConfig::warmup_frames).
+    pub fn is_warmed_up(&self) -> bool {
+        self.preset_frames >= self.config.warmup_frames
+    }
+
+    /// Run frames with the given audio until the preset is warmed up.
+    ///
+    /// Does nothing while [paused](Self::set_paused), since paused updates
+    /// don't advance the preset. Returns the number of frames run.
+    pub fn warm_up(&mut self, audio: &[f32], dt: f32) -> Result<u32> {
+        if self.paused {
+            return Ok(0);
+        }
+        let frames = self.config.warmup_frames.saturating_sub(self.preset_frames);
+        for _ in 0..frames {
+            self.update(audio, dt)?;
+        }
+        Ok(frames)
+    }
+
+    /// Render a frame offscreen and return its pixels.
+    ///
+    /// Warms the preset up first if needed, then runs a normal
+    /// [`update`](Self::update) and reads back the render texture as
+    /// `(width, height, pixels)` with tightly packed RGBA8 rows.
+    pub fn render_to_buffer(&mut self, audio: &[f32], dt: f32) -> Result<(u32, u32, Vec<u8>)> {
+        self.warm_up(audio, dt)?;
+        self.update(audio, dt)?;
+        self.read_render_texture()
+    }
+
+    /// Copy the render texture into the staging buffer and unpack it to RGBA8.
+    ///
+    /// Float render targets are tonemapped on the way.
+    fn read_render_texture(&mut self) -> Result<(u32, u32, Vec<u8>)> {
+        let gpu = self.renderer.gpu();
+        let (width, height) = gpu.physical_size();
+        let float = gpu.config.texture_format.is_float();
+        let texel_bytes = if float { 8 } else { 4 };
+        let row_bytes = width as usize * texel_bytes;
+        let padded_row_bytes =
+            row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+        let size = (padded_row_bytes * height as usize) as u64;
+
+        // Reuse the staging buffer unless the render size changed
+        if self
+            .readback_buffer
+            .as_ref()
+            .is_some_and(|b| b.size() != size)
+        {
+            self.readback_buffer = None;
+        }
+        let buffer = &*self.readback_buffer.get_or_insert_with(|| {
+            gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Readback Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            })
+        });
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &gpu.output_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes as u32),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+        gpu.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| EngineError::Other(format!("Readback failed: {}", e)))?
+            .map_err(|e| EngineError::Other(format!("Readback failed: {}", e)))?;
+
+        let bgra = matches!(
+            gpu.config.texture_format,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        );
+        let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_row_bytes) {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        buffer.unmap();
+
+        if float {
+            pixels = rgba16f_to_rgba8(&pixels);
+        }
+
+        if bgra {
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+
+        Ok((width, height, pixels))
+    }
+
+    /// Get current state.
+    pub fn state(&self) -> &RenderState {
+        &self.state
+    }
+
+    /// Get current preset.
+    pub fn current_preset(&self) -> Option<&MilkPreset> {
+        self.current_preset.as_ref()
+    }
+
+    /// Get the beat detector.
+    pub fn beat_detector(&self) -> &BeatDetector {
+        &self.beat_detector
+    }
+
+    /// Get the beat detector mutably.
+    pub fn beat_detector_mut(&mut self) -> &mut BeatDetector {
+        &mut self.beat_detector
+    }
+
+    /// Set beat detection mode.
+    pub fn set_beat_detection_mode(&mut self, mode: BeatDetectionMode) {
+        self.beat_detector.set_mode(mode);
+    }
+
+    /// Toggle beat detection to next mode.
+    pub fn next_beat_detection_mode(&mut self) {
+        self.beat_detector.next_mode();
+    }
+
+    /// Enable beat detection.
+    pub fn enable_beat_detection(&mut self) {
+        self.beat_detector.enable();
+    }
+
+    /// Disable beat detection.
+    pub fn disable_beat_detection(&mut self) {
+        self.beat_detector.disable();
     }
 
     /// Get a reference to the renderer.
@@ -403,13 +1861,55 @@ impl MilkEngine {
     /// Reset engine state.
     pub fn reset(&mut self) {
         self.state = RenderState::default();
+        self.time_source.reset();
         self.evaluator.reset();
         self.audio_analyzer.reset();
+        self.preset_hash = None;
+        self.preset_path = None;
+        self.preset_elapsed = 0.0;
+        self.preset_frames = 0;
+        self.beat_pulse = 0.0;
+        self.frame_times.clear();
+        self.frame_pacer.reset();
+        self.end_transition();
+        if let Some(layer) = &mut self.double {
+            layer.evaluator.reset();
+            layer.state = RenderState::default();
+        }
     }
 
-    /// Resize the renderer.
+    /// Resize the renderer to a new logical size.
     pub fn resize(&mut self, width: u32, height: u32) {
-        self.renderer.resize(width, height);
+        self.resize_renderers(|renderer| renderer.resize(width, height));
+    }
+
+    /// Set the HiDPI scale factor, so render targets match the window's
+    /// physical pixels.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.config.render_config.scale_factor = scale_factor;
+        self.resize_renderers(|renderer| renderer.set_scale_factor(scale_factor));
+    }
+
+    /// Apply a size change to every renderer and rebuild what depends on it.
+    fn resize_renderers(&mut self, apply: impl Fn(&mut MilkRenderer)) {
+        // Transition textures no longer match the render size
+        self.end_transition();
+        apply(&mut self.renderer);
+
+        if let Some(layer) = &mut self.double {
+            apply(&mut layer.renderer);
+            layer.blend_texture = self
+                .renderer
+                .gpu()
+                .create_render_target("Double Preset Blend Texture");
+            layer.blend_view = layer
+                .blend_texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+        }
+        // Views replaced in place can alias cached bind group keys
+        if let Some(blend_renderer) = &mut self.blend_renderer {
+            blend_renderer.clear_cache();
+        }
     }
 }
 
@@ -458,5 +1958,1041 @@ mod tests {
         }
 
         assert_eq!(engine.state().frame, 60);
+
+        let stats = engine.frame_time_histogram();
+        assert_eq!(stats.frames, 60);
+        assert_eq!(stats.buckets.iter().sum::<u64>(), 60);
+        assert!(stats.p50_ms <= stats.p99_ms && stats.p99_ms <= stats.max_ms);
+
+        engine.reset();
+        assert_eq!(engine.frame_time_histogram().frames, 0);
+    }
+
+    #[test]
+    fn test_band_override() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig::default();
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+
+        let preset = MilkPreset {
+            per_frame_equations: vec!["zoom = 1 + bass".to_string()],
+            ..Default::default()
+        };
+        engine.load_preset_from_data(preset).unwrap();
+
+        let silence = vec![0.0; 1024];
+        engine.update(&silence, 0.016).unwrap();
+        assert!((engine.state().motion.zoom - 1.0).abs() < 1e-6);
+
+        engine.set_band_override(Band::Bass, Some(1.0));
+        assert_eq!(engine.band_override(Band::Bass), Some(1.0));
+        engine.update(&silence, 0.016).unwrap();
+        assert!((engine.state().motion.zoom - 2.0).abs() < 1e-6);
+        assert_eq!(engine.state().audio.bass_att, 1.0);
+        assert_eq!(engine.state().audio.mid, 0.0);
+
+        engine.set_band_override(Band::Bass, None);
+        engine.update(&silence, 0.016).unwrap();
+        assert!((engine.state().motion.zoom - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_color_state_follows_wave_color() {
+        let config = EngineConfig {
+            color_smoothing: 0.5,
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        assert!(engine.color_state().is_none());
+
+        let mut preset = crate::default_preset();
+        preset.per_frame_equations = vec!["wave_r = 1; wave_g = 0; wave_b = 0;".to_string()];
+        preset.parameters.ob_size = 0.0;
+        preset.parameters.ib_size = 0.0;
+        engine.load_preset_from_data(preset).unwrap();
+        engine.update(&[0.0; 1024], 1.0 / 60.0).unwrap();
+        assert_eq!(engine.color_state().unwrap().average, [1.0, 0.0, 0.0]);
+
+        let mut blue = crate::default_preset();
+        blue.per_frame_equations = vec!["wave_r = 0; wave_g = 0; wave_b = 1;".to_string()];
+        blue.parameters.ob_size = 0.0;
+        blue.parameters.ib_size = 0.0;
+        engine.load_preset_from_data(blue).unwrap();
+        engine.update(&[0.0; 1024], 1.0 / 60.0).unwrap();
+        let state = engine.color_state().unwrap();
+        assert_eq!(state.current, [0.0, 0.0, 1.0]);
+        assert_eq!(state.average, [0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_mashup_current() {
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        assert!(engine.mashup_current(MashUpType::Deep).is_err());
+
+        let mut first = crate::default_preset();
+        first.parameters.wave_r = 0.1;
+        first.warp_shader = Some("warp a".to_string());
+        let mut second = crate::default_preset();
+        second.parameters.zoom = 1.1;
+        second.parameters.wave_r = 0.9;
+        engine.load_preset_from_data(first).unwrap();
+        engine.load_preset_from_data(second).unwrap();
+        assert_eq!(engine.preset_history().len(), 2);
+
+        engine.mashup_current(MashUpType::Deep).unwrap();
+        let mashup = engine.current_preset().unwrap();
+        assert_eq!(mashup.parameters.zoom, 1.1);
+        assert_eq!(mashup.parameters.wave_r, 0.1);
+        assert_eq!(mashup.warp_shader.as_deref(), Some("warp a"));
+
+        // The mash-up itself isn't recorded
+        engine.mashup_current(MashUpType::Regular).unwrap();
+        assert_eq!(engine.preset_history().len(), 2);
+        assert_eq!(engine.current_preset().unwrap().parameters.wave_r, 0.9);
+    }
+
+    #[test]
+    fn test_per_frame_init_runs_once() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig::default();
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+
+        let preset = MilkPreset {
+            per_frame_init_equations: vec!["q1 = 0.25".to_string()],
+            per_frame_equations: vec!["zoom = 1 + q1".to_string(), "q1 = q1 + 1".to_string()],
+            ..Default::default()
+        };
+        engine.load_preset_from_data(preset).unwrap();
+
+        let silence = vec![0.0; 1024];
+        engine.update(&silence, 0.016).unwrap();
+        assert!((engine.state().motion.zoom - 1.25).abs() < 1e-6);
+
+        // Init must not re-run: q1 keeps accumulating across frames
+        engine.update(&silence, 0.016).unwrap();
+        assert!((engine.state().motion.zoom - 2.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_preset_shapes_reach_render_state() {
+        env_logger::try_init().ok();
+
+        let preset = onedrop_parser::parse_preset(
+            "MILKDROP_PRESET_VERSION=201
+[preset00]
+shapecode_1_enabled=1
+shapecode_1_sides=5
+shapecode_1_num_inst=3
+shapecode_1_rad=0.25
+shapecode_1_a2=0.5
+",
+        )
+        .unwrap();
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_preset_from_data(preset).unwrap();
+        engine.update(&[0.0; 1024], 0.016).unwrap();
+
+        let shapes = &engine.state().shapes;
+        assert!(!shapes[0].enabled);
+        assert!(shapes[1].enabled);
+        assert_eq!((shapes[1].sides, shapes[1].num_inst), (5, 3));
+        assert_eq!(shapes[1].rad, 0.25);
+        assert_eq!(shapes[1].edge_color[3], 0.5);
+    }
+
+    #[test]
+    fn test_per_pixel_toggle_drives_warp_mesh() {
+        env_logger::try_init().ok();
+
+        let preset = onedrop_parser::parse_preset(
+            "MILKDROP_PRESET_VERSION=201
+[preset00]
+fZoomExponent=1
+zoom=1
+cx=0.5
+cy=0.5
+sx=1
+sy=1
+per_pixel_1=zoom = 1 + 0.2*rad;
+",
+        )
+        .unwrap();
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_preset_from_data(preset).unwrap();
+        assert!(!engine.per_pixel_enabled());
+
+        engine.update(&[0.0; 1024], 0.016).unwrap();
+        assert!(engine.renderer().warp_mesh().is_none());
+
+        engine.set_per_pixel_enabled(true);
+        engine.update(&[0.0; 1024], 0.016).unwrap();
+        let mesh = engine.renderer().warp_mesh().expect("per-pixel mesh");
+        assert_eq!((mesh.columns(), mesh.rows()), WARP_MESH_SIZE);
+        // Corners zoom more than the center, so they sample further inward
+        let corner = mesh.vertices()[0].uv;
+        assert!(corner[0] > 0.0 && corner[1] > 0.0, "{:?}", corner);
+
+        engine.set_per_pixel_enabled(false);
+        engine.update(&[0.0; 1024], 0.016).unwrap();
+        assert!(engine.renderer().warp_mesh().is_none());
+    }
+
+    #[test]
+    fn test_pause_freezes_time_and_frame() {
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_default_preset().unwrap();
+        let audio = [0.0; 1024];
+        engine.update(&audio, 0.016).unwrap();
+
+        engine.set_paused(true);
+        assert!(engine.is_paused());
+        let (time, frame) = (engine.state().time, engine.state().frame);
+        for _ in 0..3 {
+            engine.update(&audio, 0.016).unwrap();
+            engine.update_from_source(0.016).unwrap();
+        }
+        assert_eq!((engine.state().time, engine.state().frame), (time, frame));
+
+        engine.set_paused(false);
+        engine.update(&audio, 0.016).unwrap();
+        assert!(engine.state().time > time);
+        assert_eq!(engine.state().frame, frame + 1);
+    }
+
+    #[test]
+    fn test_default_audio_reactivity() {
+        env_logger::try_init().ok();
+
+        let quiet = MilkPreset {
+            per_frame_equations: vec!["zoom = 1.0".to_string()],
+            ..Default::default()
+        };
+        let quiet_bass = crate::SineAudioSource::new(60.0, 0.1, 44100).next_frame(1024);
+        let loud_bass = crate::SineAudioSource::new(60.0, 0.8, 44100).next_frame(1024);
+        let motion_after = |reactive: bool, preset: &MilkPreset, audio: &[f32]| {
+            let config = EngineConfig {
+                render_config: RenderConfig {
+                    width: 32,
+                    height: 32,
+                    ..Default::default()
+                },
+                default_audio_reactivity: reactive,
+                ..Default::default()
+            };
+            let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+            engine.load_preset_from_data(preset.clone()).unwrap();
+            // Settle the normalized levels on quiet music first
+            for _ in 0..30 {
+                engine.update(&quiet_bass, 0.016).unwrap();
+            }
+            engine.render_to_buffer(audio, 0.016).unwrap();
+            engine.state().motion
+        };
+
+        let silent = motion_after(true, &quiet, &[0.0; 1024]);
+        let loud = motion_after(true, &quiet, &loud_bass);
+        assert_eq!(silent.zoom, 1.0);
+        assert!(loud.zoom > silent.zoom, "zoom {}", loud.zoom);
+
+        // Off by default
+        assert_eq!(motion_after(false, &quiet, &loud_bass).zoom, 1.0);
+
+        // Presets that react to audio themselves are left alone
+        let reactive = MilkPreset {
+            per_frame_equations: vec!["zoom = 1.0 + 0.0*bass_att".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(motion_after(true, &reactive, &loud_bass).zoom, 1.0);
+    }
+
+    #[test]
+    fn test_audio_kick_moves_warp_mesh() {
+        env_logger::try_init().ok();
+
+        let preset = onedrop_parser::parse_preset(
+            "MILKDROP_PRESET_VERSION=201
+[preset00]
+zoom=1
+cx=0.5
+cy=0.5
+sx=1
+sy=1
+per_pixel_1=warp = 0;
+",
+        )
+        .unwrap();
+        let quiet = crate::SineAudioSource::new(60.0, 0.1, 44100).next_frame(1024);
+        let loud = crate::SineAudioSource::new(60.0, 0.8, 44100).next_frame(1024);
+        let corner_uv = |reactive: bool| {
+            let config = EngineConfig {
+                enable_per_pixel: true,
+                default_audio_reactivity: reactive,
+                ..Default::default()
+            };
+            let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+            engine.load_preset_from_data(preset.clone()).unwrap();
+            for _ in 0..30 {
+                engine.update(&quiet, 0.016).unwrap();
+            }
+            engine.update(&loud, 0.016).unwrap();
+            engine
+                .renderer()
+                .warp_mesh()
+                .expect("per-pixel mesh")
+                .vertices()[0]
+                .uv
+        };
+
+        // The bass zoom reaches per-pixel presets: the corner samples further inward
+        let still = corner_uv(false);
+        let kicked = corner_uv(true);
+        assert!(kicked[0] > still[0] + 1e-4, "{:?} vs {:?}", kicked, still);
+    }
+
+    #[test]
+    fn test_per_pixel_warp_moves_warp_mesh() {
+        env_logger::try_init().ok();
+
+        let vertex_uv = |warp: &str| {
+            let preset = onedrop_parser::parse_preset(&format!(
+                "MILKDROP_PRESET_VERSION=201
+[preset00]
+zoom=1
+cx=0.5
+cy=0.5
+sx=1
+sy=1
+warp=0
+per_pixel_1=warp = {};
+",
+                warp
+            ))
+            .unwrap();
+            let config = EngineConfig {
+                enable_per_pixel: true,
+                default_audio_reactivity: false,
+                ..Default::default()
+            };
+            let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+            engine.load_preset_from_data(preset).unwrap();
+            engine.update(&[0.0; 1024], 0.5).unwrap();
+            engine
+                .renderer()
+                .warp_mesh()
+                .expect("per-pixel mesh")
+                .vertices()[10]
+                .uv
+        };
+
+        // A per-pixel warp bends the mesh even though the frame's warp is 0
+        let flat = vertex_uv("0");
+        let warped = vertex_uv("2");
+        assert!(
+            (warped[0] - flat[0]).abs() + (warped[1] - flat[1]).abs() > 1e-4,
+            "{:?} vs {:?}",
+            warped,
+            flat
+        );
+    }
+
+    #[test]
+    fn test_warp_time_accumulates() {
+        env_logger::try_init().ok();
+
+        let preset = onedrop_parser::parse_preset(
+            "MILKDROP_PRESET_VERSION=201
+[preset00]
+per_frame_1=warp_anim_speed = if(above(time, 1), 4, 1);
+",
+        )
+        .unwrap();
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_preset_from_data(preset).unwrap();
+
+        let mut previous = engine.state().motion.warp_time;
+        for frame in 0..20 {
+            engine.update(&[0.0; 1024], 0.1).unwrap();
+            let warp_time = engine.state().motion.warp_time;
+            let speed = engine.state().motion.warp_anim_speed;
+
+            // Each frame advances the phase by the current speed only, so a
+            // speed change never makes the pattern jump
+            assert!(
+                (warp_time - previous - 0.1 * speed).abs() < 1e-4,
+                "frame {}: {} -> {} at speed {}",
+                frame,
+                previous,
+                warp_time,
+                speed
+            );
+            previous = warp_time;
+        }
+        assert_eq!(engine.state().motion.warp_anim_speed, 4.0);
+    }
+
+    #[test]
+    fn test_double_preset_layer_uses_warp_mesh() {
+        env_logger::try_init().ok();
+
+        let double = parse_double_preset(
+            "[DoublePreset]
+BlendPattern=0
+BlendAmount=0.5
+
+[PresetA]
+[preset00]
+zoom=1
+
+[PresetB]
+[preset00]
+zoom=1
+sx=1
+sy=1
+per_pixel_1=zoom = 1 + 0.2*rad;
+",
+        )
+        .unwrap();
+        let config = EngineConfig {
+            enable_per_pixel: true,
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_double_preset_from_data(double).unwrap();
+        engine.update(&[0.0; 1024], 0.016).unwrap();
+
+        let layer = engine.double.as_ref().unwrap();
+        assert!(layer.renderer.warp_mesh().is_some());
+        assert!(engine.renderer().warp_mesh().is_none());
+    }
+
+    #[test]
+    fn test_normalized_audio_levels() {
+        env_logger::try_init().ok();
+
+        let steady = crate::SineAudioSource::new(60.0, 0.1, 44100).next_frame(1024);
+        let loud = crate::SineAudioSource::new(60.0, 0.5, 44100).next_frame(1024);
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_default_preset().unwrap();
+
+        // A steady signal settles around 1.0, whatever its volume
+        for _ in 0..100 {
+            engine.update(&steady, 0.016).unwrap();
+        }
+        let bass = engine.state().audio.bass;
+        assert!((bass - 1.0).abs() < 0.1, "bass {}", bass);
+
+        // A loud hit clears Milkdrop's `bass > 1.5` threshold, in the state
+        // and in the variables presets see
+        engine.update(&loud, 0.016).unwrap();
+        assert!(
+            engine.state().audio.bass > 1.5,
+            "{:?}",
+            engine.state().audio
+        );
+        assert!(engine.evaluator.context().get("bass").unwrap() > 1.5);
+
+        // Without normalization levels stay absolute
+        let config = EngineConfig {
+            normalize_audio: false,
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+        engine.update(&loud, 0.016).unwrap();
+        assert!(
+            engine.state().audio.bass < 1.0,
+            "{:?}",
+            engine.state().audio
+        );
+    }
+
+    #[test]
+    fn test_reload_preserves_vars() {
+        env_logger::try_init().ok();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("live.milk");
+        let other = dir.join("other.milk");
+        let preset = |zoom: f32| {
+            format!(
+                "[preset00]\nper_frame_init_1=q1 = 1;\nper_frame_1=zoom = {};\n",
+                zoom
+            )
+        };
+        std::fs::write(&path, preset(1.0)).unwrap();
+        std::fs::write(&other, preset(1.0)).unwrap();
+
+        let config = EngineConfig {
+            preserve_vars_on_reload: true,
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_preset(&path).unwrap();
+        engine.evaluator.eval("q1 = 5").unwrap();
+
+        // Same file with edited equations: state is kept and init equations
+        // are not re-run
+        std::fs::write(&path, preset(1.1)).unwrap();
+        engine.load_preset(&path).unwrap();
+        assert_eq!(engine.evaluator.context().get("q1"), Some(5.0));
+        assert_eq!(
+            engine.current_preset().unwrap().per_frame_equations,
+            vec!["zoom = 1.1;".to_string()]
+        );
+
+        // Another file, even with the same content: fresh start
+        engine.load_preset(&other).unwrap();
+        assert_eq!(engine.evaluator.context().get("q1"), Some(1.0));
+
+        // Presets without a file are never reloads
+        let parsed = engine.current_preset().unwrap().clone();
+        engine.evaluator.eval("q1 = 5").unwrap();
+        engine.load_preset_from_data(parsed).unwrap();
+        assert_eq!(engine.evaluator.context().get("q1"), Some(1.0));
+
+        // Without preservation, reloading restarts the simulation
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_preset(&path).unwrap();
+        engine.evaluator.eval("q1 = 5").unwrap();
+        engine.load_preset(&path).unwrap();
+        assert_eq!(engine.evaluator.context().get("q1"), Some(1.0));
+    }
+
+    #[test]
+    fn test_content_hash_ignores_whitespace() {
+        let preset = MilkPreset {
+            per_frame_equations: vec!["wave_r=0.5*sin(time)".to_string()],
+            ..Default::default()
+        };
+        let respaced = MilkPreset {
+            per_frame_equations: vec!["wave_r = 0.5 * sin( time )".to_string()],
+            ..Default::default()
+        };
+        let edited = MilkPreset {
+            per_frame_equations: vec!["wave_r = 0.6 * sin(time)".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(preset_content_hash(&preset), preset_content_hash(&respaced));
+        assert_ne!(preset_content_hash(&preset), preset_content_hash(&edited));
+    }
+
+    #[test]
+    fn test_load_preset_with_transition() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig::default();
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+
+        let silence = vec![0.0; 1024];
+        engine.update(&silence, 0.016).unwrap();
+
+        engine
+            .load_preset_with_transition("../test-presets/144.milk", TransitionMode::Fade, 0.1)
+            .unwrap();
+        assert!(engine.is_transitioning());
+
+        // Still blending part-way through
+        engine.update(&silence, 0.016).unwrap();
+        assert!(engine.is_transitioning());
+
+        engine.update(&silence, 0.12).unwrap();
+        assert!(!engine.is_transitioning());
+
+        // Cuts switch immediately
+        engine
+            .load_preset_with_transition("../test-presets/144.milk", TransitionMode::Cut, 1.0)
+            .unwrap();
+        assert!(!engine.is_transitioning());
+    }
+
+    #[test]
+    fn test_transition_textures() {
+        env_logger::try_init().ok();
+
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_default_preset().unwrap();
+        assert!(engine.transition_textures().is_none());
+
+        let silence = vec![0.0; 1024];
+        engine.update(&silence, 0.016).unwrap();
+        engine
+            .load_preset_with_transition("../test-presets/144.milk", TransitionMode::Fade, 1.0)
+            .unwrap();
+
+        engine.update(&silence, 0.05).unwrap();
+        let (old, new, progress) = engine.transition_textures().unwrap();
+        assert!(progress > 0.0 && progress < 1.0, "progress {}", progress);
+        let (width, height) = engine.renderer().gpu().physical_size();
+        for texture in [old, new] {
+            assert_eq!((texture.width(), texture.height()), (width, height));
+        }
+
+        // Paused updates don't advance the transition
+        engine.set_paused(true);
+        engine.update(&silence, 1.0).unwrap();
+        assert!(engine.transition_textures().is_some());
+
+        engine.set_paused(false);
+        engine.update(&silence, 1.0).unwrap();
+        assert!(engine.transition_textures().is_none());
+    }
+
+    #[test]
+    fn test_preset_directory() {
+        env_logger::try_init().ok();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("Bass")).unwrap();
+        std::fs::copy("../test-presets/144.milk", dir.join("Bass/WHITE.milk")).unwrap();
+
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.set_preset_directory(dir);
+        engine.set_beat_detection_mode(BeatDetectionMode::HardCut6 {
+            special_preset: "Bass/WHITE.milk".to_string(),
+        });
+
+        let change = engine
+            .beat_detector_mut()
+            .should_change_preset(5.0, 0.5, 0.5)
+            .unwrap();
+        let PresetChange::Specific(path) = &change else {
+            panic!("expected a specific preset change, got {:?}", change);
+        };
+        let resolved = engine.resolve_preset_path(path);
+        assert!(resolved.is_absolute());
+        assert_eq!(resolved, dir.join("Bass/WHITE.milk"));
+
+        engine.load_preset_change(&change).unwrap();
+        let expected =
+            parse_preset(&std::fs::read_to_string("../test-presets/144.milk").unwrap()).unwrap();
+        assert_eq!(engine.current_preset(), Some(&expected));
+
+        // Absolute paths bypass the directory
+        let absolute = std::path::absolute("../test-presets/207.milk").unwrap();
+        assert_eq!(engine.resolve_preset_path(&absolute), absolute);
+    }
+
+    #[test]
+    fn test_live_equations() {
+        env_logger::try_init().ok();
+
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        let preset =
+            parse_preset("[preset00]\nzoom=1.0\nper_frame_1=zoom = 0.5;\nper_frame_2=rot = 0.1;\n")
+                .unwrap();
+        engine.load_preset_from_data(preset).unwrap();
+
+        let silence = vec![0.0; 1024];
+        engine.update(&silence, 1.0 / 60.0).unwrap();
+        assert_eq!(engine.state().motion.zoom, 0.5);
+
+        // A broken live equation is skipped without failing the frame
+        engine.add_live_equation("zoom = (");
+        engine.add_live_equation("zoom = 2.0");
+        engine.update(&silence, 1.0 / 60.0).unwrap();
+        assert_eq!(engine.state().motion.zoom, 2.0);
+        assert!((engine.state().motion.rot - 0.1).abs() < 1e-6);
+        assert_eq!(engine.live_equations().len(), 2);
+
+        engine.clear_live_equations();
+        engine.update(&silence, 1.0 / 60.0).unwrap();
+        assert_eq!(engine.state().motion.zoom, 0.5);
+    }
+
+    #[test]
+    fn test_time_source() {
+        env_logger::try_init().ok();
+
+        /// Timeline returning fixed timestamps, like a DAW transport
+        struct Timeline(std::vec::IntoIter<f32>);
+
+        impl TimeSource for Timeline {
+            fn frame_time(&mut self, _delta_time: f32) -> f32 {
+                self.0.next().unwrap()
+            }
+        }
+
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        let preset = parse_preset("[preset00]\nper_frame_1=rot = time;\n").unwrap();
+        engine.load_preset_from_data(preset).unwrap();
+
+        let times = vec![1.0, 2.5, 2.0, 10.0];
+        engine.set_time_source(Box::new(Timeline(times.clone().into_iter())));
+        let silence = vec![0.0; 1024];
+        for time in times {
+            engine.update(&silence, 1.0 / 60.0).unwrap();
+            assert_eq!(engine.state().time, time);
+            assert_eq!(engine.state().motion.rot, time);
+        }
+
+        // Back on the default clock, time accumulates deltas from zero
+        engine.set_time_source(Box::new(DeltaTimeSource::new()));
+        engine.reset();
+        engine.update(&silence, 0.5).unwrap();
+        engine.update(&silence, 0.25).unwrap();
+        assert_eq!(engine.state().time, 0.75);
+    }
+
+    #[test]
+    fn test_auto_advance() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig {
+            auto_advance_secs: Some(2.0),
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+
+        let mut manager = PresetManager::new();
+        manager.add_presets(&["../test-presets/144.milk", "../test-presets/207.milk"]);
+        engine
+            .load_preset(manager.current_preset().unwrap())
+            .unwrap();
+        let first = engine.current_preset().cloned();
+        engine.set_preset_manager(manager);
+
+        let silence = vec![0.0; 1024];
+        for _ in 0..3 {
+            engine.update(&silence, 0.5).unwrap();
+        }
+        assert_eq!(engine.current_preset().cloned(), first);
+
+        // ~2 seconds of simulated time: switch to the second preset
+        engine.update(&silence, 0.5).unwrap();
+        assert_ne!(engine.current_preset().cloned(), first);
+        assert_eq!(
+            engine.preset_manager().unwrap().current_preset(),
+            Some(Path::new("../test-presets/207.milk"))
+        );
+    }
+
+    #[test]
+    fn test_render_to_buffer_float_target() {
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 16,
+                height: 16,
+                texture_format: TextureFormat::Rgba16Float,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+
+        // Half-float texels are tonemapped down to RGBA8
+        let silence = vec![0.0; 1024];
+        let (width, height, pixels) = engine.render_to_buffer(&silence, 0.016).unwrap();
+        assert_eq!((width, height), (16, 16));
+        assert_eq!(pixels.len(), 16 * 16 * 4);
+        assert!(pixels.chunks_exact(4).all(|px| px[3] == 255));
+    }
+
+    /// Render one frame of a still preset whose only content is its
+    /// waveform, and return the rows holding lit pixels.
+    fn render_wave_rows(
+        wave: impl Fn(&mut onedrop_parser::preset::PresetParameters),
+    ) -> Vec<usize> {
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 64,
+                height: 64,
+                texture_format: TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        let mut preset = crate::default_preset();
+        preset.per_frame_equations.clear();
+        preset.parameters.f_wave_alpha = 1.0;
+        preset.parameters.f_wave_smoothing = 0.0;
+        (preset.parameters.wave_r, preset.parameters.wave_g) = (1.0, 1.0);
+        preset.parameters.wave_b = 1.0;
+        wave(&mut preset.parameters);
+        engine.load_preset_from_data(preset).unwrap();
+
+        let silence = vec![0.0; 1024];
+        let (width, _, pixels) = engine.render_to_buffer(&silence, 0.016).unwrap();
+        pixels
+            .chunks(width as usize * 4)
+            .enumerate()
+            .filter(|(_, row)| row.chunks(4).any(|px| px[0] > 128))
+            .map(|(y, _)| y)
+            .collect()
+    }
+
+    #[test]
+    fn test_waveform_drawn_from_preset_parameters() {
+        env_logger::try_init().ok();
+
+        // A flat wave across the middle of the screen
+        let centered = render_wave_rows(|_| {});
+        assert!(!centered.is_empty());
+        assert!(
+            centered.iter().all(|y| (28..36).contains(y)),
+            "{:?}",
+            centered
+        );
+
+        // wave_y moves it, wave_a hides it, wave_thick widens it
+        let raised = render_wave_rows(|p| p.wave_y = 0.75);
+        assert!(raised.iter().all(|y| (12..20).contains(y)), "{:?}", raised);
+        assert!(render_wave_rows(|p| p.f_wave_alpha = 0.0).is_empty());
+        let thick = render_wave_rows(|p| p.b_wave_thick = true);
+        assert!(
+            thick.len() > centered.len(),
+            "{:?} vs {:?}",
+            thick,
+            centered
+        );
+
+        // The circular mode spreads over many rows
+        let ring = render_wave_rows(|p| p.n_wave_mode = 4);
+        assert!(ring.len() > 10, "{:?}", ring);
+    }
+
+    #[test]
+    fn test_render_to_buffer() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 64,
+                height: 64,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+
+        let silence = vec![0.0; 1024];
+        let (width, height, pixels) = engine.render_to_buffer(&silence, 0.016).unwrap();
+        assert_eq!((width, height), (64, 64));
+        assert_eq!(pixels.len(), 64 * 64 * 4);
+
+        // The staging buffer is reused across frames
+        engine.render_to_buffer(&silence, 0.016).unwrap();
+        assert_eq!(engine.readback_buffer.as_ref().unwrap().size(), 256 * 64);
+    }
+
+    #[test]
+    fn test_update_from_source() {
+        env_logger::try_init().ok();
+
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        engine.load_default_preset().unwrap();
+
+        // No source: silence
+        engine.update_from_source(0.016).unwrap();
+        assert_eq!(engine.state().audio.bass, 0.0);
+
+        engine.set_audio_source(Box::new(crate::SineAudioSource::new(60.0, 0.8, 44100)));
+        engine.update_from_source(0.016).unwrap();
+        assert!(engine.state().audio.bass > 0.0);
+        assert_eq!(engine.state().frame, 2);
+
+        assert!(engine.take_audio_source().is_some());
+        assert!(engine.take_audio_source().is_none());
+    }
+
+    #[test]
+    fn test_frame_pacing_skips_early_updates() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 64,
+                height: 64,
+                ..Default::default()
+            },
+            target_fps: Some(50.0),
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+
+        // A fixed 5 ms clock, four times faster than the 20 ms interval
+        let mut rendered = 0;
+        for _ in 0..12 {
+            if let Some(delta_time) = engine.pace_frame(0.005) {
+                engine.update(&[0.0; 1024], delta_time).unwrap();
+                rendered += 1;
+            }
+        }
+        assert_eq!(rendered, 3);
+        assert_eq!(engine.state().frame, 3);
+        assert!((engine.state().time - 0.06).abs() < 1e-5);
+        assert!(!engine.should_render_now());
+    }
+
+    #[test]
+    fn test_hot_reload_applies_new_parameters() {
+        env_logger::try_init().ok();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("live.milk");
+        let preset =
+            |zoom: f32| format!("MILKDROP_PRESET_VERSION=201\n[preset00]\nzoom={}\n", zoom);
+        std::fs::write(&path, preset(1.0)).unwrap();
+
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        assert!(engine.reload_preset().is_err());
+        engine.load_preset(&path).unwrap();
+        engine.enable_hot_reload(&path).unwrap();
+        for _ in 0..3 {
+            engine.update(&[0.0; 1024], 0.1).unwrap();
+        }
+
+        std::fs::write(&path, preset(1.25)).unwrap();
+        engine.reload_preset().unwrap();
+        assert_eq!(engine.current_preset().unwrap().parameters.zoom, 1.25);
+        engine.update(&[0.0; 1024], 0.1).unwrap();
+        assert!((engine.state().motion.zoom - 1.25).abs() < 1e-6);
+        // The visual continues instead of restarting
+        assert_eq!(engine.state().frame, 4);
+        assert!((engine.state().time - 0.4).abs() < 1e-5);
+
+        // A broken edit keeps the previous preset
+        std::fs::write(&path, "MILKDROP_PRESET_VERSION=abc\n[preset00]\nzoom=2.0\n").unwrap();
+        assert!(engine.reload_preset().is_err());
+        assert_eq!(engine.current_preset().unwrap().parameters.zoom, 1.25);
+
+        // Loading another preset moves the watch to it
+        let other = dir.join("other.milk");
+        std::fs::write(&other, preset(0.9)).unwrap();
+        engine.load_preset(&other).unwrap();
+        std::fs::write(&path, preset(1.5)).unwrap();
+        engine.reload_preset().unwrap();
+        assert_eq!(engine.current_preset().unwrap().parameters.zoom, 0.9);
+    }
+
+    #[test]
+    fn test_warmup_frames() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 64,
+                height: 64,
+                ..Default::default()
+            },
+            warmup_frames: 10,
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+
+        let silence = vec![0.0; 1024];
+        for _ in 0..9 {
+            engine.update(&silence, 0.016).unwrap();
+            assert!(!engine.is_warmed_up());
+        }
+        engine.update(&silence, 0.016).unwrap();
+        assert!(engine.is_warmed_up());
+
+        // Loading a preset starts a new warm-up, which headless output skips
+        engine.load_default_preset().unwrap();
+        assert!(!engine.is_warmed_up());
+        let frame = engine.state().frame;
+        engine.render_to_buffer(&silence, 0.016).unwrap();
+        assert_eq!(engine.state().frame, frame + 11);
+    }
+
+    #[test]
+    fn test_render_to_buffer_while_paused() {
+        env_logger::try_init().ok();
+
+        let config = EngineConfig {
+            render_config: RenderConfig {
+                width: 64,
+                height: 64,
+                ..Default::default()
+            },
+            warmup_frames: 5,
+            ..Default::default()
+        };
+        let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+        engine.load_default_preset().unwrap();
+        engine.set_paused(true);
+
+        let silence = vec![0.0; 1024];
+        let (width, height, pixels) = engine.render_to_buffer(&silence, 0.016).unwrap();
+        assert_eq!((width, height), (64, 64));
+        assert_eq!(pixels.len(), 64 * 64 * 4);
+        assert_eq!(engine.state().frame, 0);
+        assert!(!engine.is_warmed_up());
+    }
+
+    #[test]
+    fn test_deterministic_render() {
+        env_logger::try_init().ok();
+
+        let render = || {
+            let config = EngineConfig {
+                render_config: RenderConfig {
+                    width: 64,
+                    height: 64,
+                    ..Default::default()
+                },
+                deterministic: true,
+                ..Default::default()
+            };
+            let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+            engine.load_default_preset().unwrap();
+            let preset = MilkPreset {
+                per_frame_equations: vec!["zoom = 1 + rand(100) / 1000".to_string()],
+                ..engine.current_preset.clone().unwrap()
+            };
+            engine.load_preset_from_data(preset).unwrap();
+
+            let silence = vec![0.0; 1024];
+            let mut frames = Vec::new();
+            for _ in 0..5 {
+                frames.push(engine.render_to_buffer(&silence, 1.0 / 60.0).unwrap().2);
+            }
+            (frames, engine.state().motion.zoom)
+        };
+
+        let (first, zoom_a) = render();
+        let (second, zoom_b) = render();
+        assert_eq!(zoom_a, zoom_b);
+        assert!(first == second, "deterministic renders differ");
+    }
+
+    #[test]
+    fn test_preload_presets_skips_parse_on_load() {
+        env_logger::try_init().ok();
+        let mut engine = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        let first = Path::new("../test-presets/144.milk");
+        let second = Path::new("../test-presets/207.milk");
+        let unloaded = Path::new("../test-presets/427.milk");
+
+        engine.preload_presets(&[first, second]).unwrap();
+        assert_eq!(engine.preset_cache_stats().len, 2);
+
+        engine.load_preset(second).unwrap();
+        engine.load_preset(first).unwrap();
+        engine.load_preset(unloaded).unwrap();
+        let stats = engine.preset_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+
+        // The background variant fills the same cache
+        let mut other = pollster::block_on(MilkEngine::new(EngineConfig::default())).unwrap();
+        other
+            .preload_presets_in_background(vec![first.to_path_buf()])
+            .join()
+            .unwrap()
+            .unwrap();
+        other.load_preset(first).unwrap();
+        assert_eq!(other.preset_cache_stats().hits, 1);
     }
 }
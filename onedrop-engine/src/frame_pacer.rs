@@ -0,0 +1,83 @@
+//! Frame pacing for capping the render rate.
+
+/// Caps updates to a target frame rate by accumulating the elapsed time
+/// between calls and releasing a frame once a full interval has passed.
+///
+/// The released delta is the whole accumulated time, not the nominal
+/// interval, so a clock summing it stays in step with the wall clock.
+#[derive(Debug, Clone, Default)]
+pub struct FramePacer {
+    /// Seconds between frames, or `None` for unlimited
+    interval: Option<f32>,
+
+    /// Time elapsed since the last released frame
+    accumulated: f32,
+}
+
+impl FramePacer {
+    /// Create a pacer for `target_fps` frames per second. `None`, zero or a
+    /// negative rate disables pacing.
+    pub fn new(target_fps: Option<f32>) -> Self {
+        Self {
+            interval: target_fps.filter(|fps| *fps > 0.0).map(|fps| 1.0 / fps),
+            accumulated: 0.0,
+        }
+    }
+
+    /// Seconds between frames, if pacing is enabled.
+    pub fn interval(&self) -> Option<f32> {
+        self.interval
+    }
+
+    /// Whether enough time has accumulated for the next frame.
+    pub fn is_due(&self) -> bool {
+        self.interval
+            .is_none_or(|interval| self.accumulated >= interval)
+    }
+
+    /// Seconds left until the next frame is due, or `None` if pacing is
+    /// disabled.
+    pub fn remaining(&self) -> Option<f32> {
+        self.interval
+            .map(|interval| (interval - self.accumulated).max(0.0))
+    }
+
+    /// Add `elapsed` seconds. Returns the delta time to render the next frame
+    /// with when one is due, or `None` to skip this call.
+    pub fn advance(&mut self, elapsed: f32) -> Option<f32> {
+        self.accumulated += elapsed.max(0.0);
+        if !self.is_due() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.accumulated))
+    }
+
+    /// Drop any accumulated time.
+    pub fn reset(&mut self) {
+        self.accumulated = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skips_until_interval_elapsed() {
+        let mut pacer = FramePacer::new(Some(50.0));
+        assert_eq!(pacer.advance(0.008), None);
+        assert_eq!(pacer.advance(0.008), None);
+        let delta = pacer.advance(0.008).unwrap();
+        assert!((delta - 0.024).abs() < 1e-6);
+        assert_eq!(pacer.advance(0.008), None);
+        assert!((pacer.remaining().unwrap() - 0.012).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unlimited_releases_every_call() {
+        let mut pacer = FramePacer::new(None);
+        assert_eq!(pacer.advance(0.001), Some(0.001));
+        assert_eq!(FramePacer::new(Some(0.0)).interval(), None);
+        assert_eq!(pacer.remaining(), None);
+    }
+}
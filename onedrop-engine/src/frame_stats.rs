@@ -0,0 +1,196 @@
+//! Frame-time histogram for profiling long sessions.
+
+use std::time::Duration;
+
+/// Upper edges of the reported buckets, in milliseconds. Frames slower than
+/// the last edge fall into one extra open-ended bucket.
+pub const FRAME_TIME_BUCKET_EDGES_MS: [f32; 7] = [4.0, 8.0, 16.7, 33.3, 50.0, 100.0, 250.0];
+
+/// Resolution of the internal histogram used for percentiles.
+const FINE_BIN_MS: f32 = 0.1;
+
+/// Number of fine bins (0.1 ms each, up to 1 s; slower frames share the last bin).
+const FINE_BINS: usize = 10_000;
+
+/// Snapshot of the frame-time distribution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTimeStats {
+    /// Frame counts per bucket. `buckets[i]` counts frames up to
+    /// `FRAME_TIME_BUCKET_EDGES_MS[i]`; the last entry counts slower frames.
+    pub buckets: [u64; FRAME_TIME_BUCKET_EDGES_MS.len() + 1],
+
+    /// Number of recorded frames
+    pub frames: u64,
+
+    /// Fastest frame in milliseconds
+    pub min_ms: f32,
+
+    /// Mean frame time in milliseconds
+    pub mean_ms: f32,
+
+    /// Median frame time in milliseconds
+    pub p50_ms: f32,
+
+    /// 95th percentile frame time in milliseconds
+    pub p95_ms: f32,
+
+    /// 99th percentile frame time in milliseconds
+    pub p99_ms: f32,
+
+    /// Slowest frame in milliseconds
+    pub max_ms: f32,
+}
+
+/// Accumulates frame times into a fixed-size histogram.
+///
+/// Memory use is constant regardless of session length; percentiles are
+/// accurate to 0.1 ms.
+#[derive(Debug, Clone)]
+pub struct FrameTimeHistogram {
+    /// Counts per reported bucket
+    buckets: [u64; FRAME_TIME_BUCKET_EDGES_MS.len() + 1],
+
+    /// Counts per 0.1 ms bin, for percentiles
+    fine: Vec<u32>,
+
+    /// Number of recorded frames
+    frames: u64,
+
+    /// Fastest frame in milliseconds
+    min_ms: f32,
+
+    /// Sum of all frame times in milliseconds, for the mean
+    total_ms: f64,
+
+    /// Slowest frame in milliseconds
+    max_ms: f32,
+}
+
+impl FrameTimeHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self {
+            buckets: [0; FRAME_TIME_BUCKET_EDGES_MS.len() + 1],
+            fine: vec![0; FINE_BINS],
+            frames: 0,
+            min_ms: f32::INFINITY,
+            total_ms: 0.0,
+            max_ms: 0.0,
+        }
+    }
+
+    /// Record one frame's duration.
+    pub fn record(&mut self, frame_time: Duration) {
+        let ms = frame_time.as_secs_f32() * 1000.0;
+
+        let bucket = FRAME_TIME_BUCKET_EDGES_MS
+            .iter()
+            .position(|edge| ms <= *edge)
+            .unwrap_or(FRAME_TIME_BUCKET_EDGES_MS.len());
+        self.buckets[bucket] += 1;
+
+        let bin = ((ms / FINE_BIN_MS) as usize).min(FINE_BINS - 1);
+        self.fine[bin] = self.fine[bin].saturating_add(1);
+
+        self.frames += 1;
+        self.min_ms = self.min_ms.min(ms);
+        self.total_ms += ms as f64;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    /// Number of recorded frames.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Forget all recorded frames.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Frame time below which a fraction `p` (0.0-1.0) of frames fall, in ms.
+    fn percentile(&self, p: f32) -> f32 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+
+        let rank = ((self.frames as f32 * p).ceil() as u64).max(1);
+        let mut seen = 0u64;
+        for (bin, count) in self.fine.iter().enumerate() {
+            seen += *count as u64;
+            if seen >= rank {
+                // Upper edge of the bin, but never beyond the slowest frame
+                return ((bin + 1) as f32 * FINE_BIN_MS).min(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+
+    /// Summarize the recorded frames.
+    pub fn stats(&self) -> FrameTimeStats {
+        FrameTimeStats {
+            buckets: self.buckets,
+            frames: self.frames,
+            min_ms: if self.frames == 0 { 0.0 } else { self.min_ms },
+            mean_ms: if self.frames == 0 {
+                0.0
+            } else {
+                (self.total_ms / self.frames as f64) as f32
+            },
+            p50_ms: self.percentile(0.50),
+            p95_ms: self.percentile(0.95),
+            p99_ms: self.percentile(0.99),
+            max_ms: self.max_ms,
+        }
+    }
+}
+
+impl Default for FrameTimeHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_histogram() {
+        let stats = FrameTimeHistogram::new().stats();
+        assert_eq!(stats.frames, 0);
+        assert_eq!(stats.p99_ms, 0.0);
+        assert_eq!(stats.min_ms, 0.0);
+        assert_eq!(stats.mean_ms, 0.0);
+        assert!(stats.buckets.iter().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn test_buckets_and_percentiles() {
+        let mut histogram = FrameTimeHistogram::new();
+        for _ in 0..90 {
+            histogram.record(Duration::from_millis(10));
+        }
+        for _ in 0..9 {
+            histogram.record(Duration::from_millis(30));
+        }
+        histogram.record(Duration::from_millis(400));
+
+        let stats = histogram.stats();
+        assert_eq!(stats.frames, 100);
+        assert_eq!(stats.buckets, [0, 0, 90, 9, 0, 0, 0, 1]);
+        assert!((stats.p50_ms - 10.0).abs() <= 0.15, "p50 {}", stats.p50_ms);
+        assert!((stats.p95_ms - 30.0).abs() <= 0.15, "p95 {}", stats.p95_ms);
+        assert!((stats.p99_ms - 30.0).abs() <= 0.15, "p99 {}", stats.p99_ms);
+        assert!((stats.max_ms - 400.0).abs() < 1e-3);
+        assert!((stats.min_ms - 10.0).abs() < 1e-3);
+        assert!(
+            (stats.mean_ms - 15.7).abs() < 1e-3,
+            "mean {}",
+            stats.mean_ms
+        );
+
+        histogram.clear();
+        assert_eq!(histogram.frames(), 0);
+    }
+}
@@ -1,5 +1,6 @@
 //! History management for presets, mash-ups, and colors.
 
+use onedrop_parser::MilkPreset;
 use std::collections::VecDeque;
 
 /// Generic history structure with back/forward navigation.
@@ -139,6 +140,101 @@ impl<T: Clone> Default for History<T> {
     }
 }
 
+impl History<MilkPreset> {
+    /// Presets played up to the current one, most recent first. Presets the
+    /// user went back past aren't counted.
+    fn recent(&self) -> impl Iterator<Item = &MilkPreset> {
+        let end = self.current_index.map_or(0, |idx| idx + 1);
+        self.items.range(..end).rev()
+    }
+
+    /// Combine recently played presets into a new one.
+    ///
+    /// A preset is split into five bins: motion (motion parameters and the
+    /// per-frame and per-pixel equations), waves (wave parameters and custom
+    /// waves), custom shapes, the warp shader and the comp shader.
+    ///
+    /// - [`MashUpType::Regular`] keeps the current preset but takes its warp
+    ///   shader from the previous one.
+    /// - [`MashUpType::Deep`] takes each bin in turn from the recent presets,
+    ///   starting with the current one: with two presets, motion, shapes and
+    ///   the comp shader come from the current preset and waves and the warp
+    ///   shader from the previous one.
+    ///
+    /// With a single preset the mash-up is that preset; with none it's
+    /// [`MilkPreset::default`].
+    pub fn create_mashup(&self, ty: MashUpType) -> MilkPreset {
+        let recent: Vec<&MilkPreset> = self.recent().collect();
+        let Some(&current) = recent.first() else {
+            return MilkPreset::default();
+        };
+        let source = |bin: usize| match ty {
+            MashUpType::Regular if bin == WARP_BIN => recent.get(1).copied().unwrap_or(current),
+            MashUpType::Regular => current,
+            MashUpType::Deep => recent[bin % recent.len()],
+        };
+
+        let mut mashup = current.clone();
+        copy_motion(&mut mashup, source(MOTION_BIN));
+        copy_waves(&mut mashup, source(WAVES_BIN));
+        mashup.shapes = source(SHAPES_BIN).shapes.clone();
+        mashup.warp_shader = source(WARP_BIN).warp_shader.clone();
+        mashup.comp_shader = source(COMP_BIN).comp_shader.clone();
+        mashup
+    }
+}
+
+const MOTION_BIN: usize = 0;
+const WAVES_BIN: usize = 1;
+const SHAPES_BIN: usize = 2;
+const WARP_BIN: usize = 3;
+const COMP_BIN: usize = 4;
+
+/// Copy the motion bin of `src` into `dst`.
+fn copy_motion(dst: &mut MilkPreset, src: &MilkPreset) {
+    let (d, s) = (&mut dst.parameters, &src.parameters);
+    d.zoom = s.zoom;
+    d.rot = s.rot;
+    d.cx = s.cx;
+    d.cy = s.cy;
+    d.dx = s.dx;
+    d.dy = s.dy;
+    d.warp = s.warp;
+    d.sx = s.sx;
+    d.sy = s.sy;
+    d.f_decay = s.f_decay;
+    d.f_warp_anim_speed = s.f_warp_anim_speed;
+    d.f_warp_scale = s.f_warp_scale;
+    d.f_zoom_exponent = s.f_zoom_exponent;
+    dst.per_frame_init_equations = src.per_frame_init_equations.clone();
+    dst.per_frame_equations = src.per_frame_equations.clone();
+    dst.per_pixel_equations = src.per_pixel_equations.clone();
+    dst.source_lines = src.source_lines.clone();
+}
+
+/// Copy the waves bin of `src` into `dst`.
+fn copy_waves(dst: &mut MilkPreset, src: &MilkPreset) {
+    let (d, s) = (&mut dst.parameters, &src.parameters);
+    d.n_wave_mode = s.n_wave_mode;
+    d.b_additive_waves = s.b_additive_waves;
+    d.b_wave_dots = s.b_wave_dots;
+    d.b_wave_thick = s.b_wave_thick;
+    d.b_mod_wave_alpha_by_volume = s.b_mod_wave_alpha_by_volume;
+    d.b_maximize_wave_color = s.b_maximize_wave_color;
+    d.f_wave_alpha = s.f_wave_alpha;
+    d.f_wave_scale = s.f_wave_scale;
+    d.f_wave_smoothing = s.f_wave_smoothing;
+    d.f_wave_param = s.f_wave_param;
+    d.f_mod_wave_alpha_start = s.f_mod_wave_alpha_start;
+    d.f_mod_wave_alpha_end = s.f_mod_wave_alpha_end;
+    d.wave_r = s.wave_r;
+    d.wave_g = s.wave_g;
+    d.wave_b = s.wave_b;
+    d.wave_x = s.wave_x;
+    d.wave_y = s.wave_y;
+    dst.waves = src.waves.clone();
+}
+
 /// State for mash-up operations.
 #[derive(Debug, Clone, PartialEq)]
 pub struct MashUpState {
@@ -162,14 +258,60 @@ pub enum MashUpType {
     Deep,
 }
 
-/// State for color randomization.
-#[derive(Debug, Clone, PartialEq)]
+/// Smoothed color of the rendered frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ColorState {
-    /// Color values (RGB)
-    pub colors: Vec<[f32; 3]>,
+    /// Alpha-weighted mean of the colors drawn in the last frame that drew
+    /// anything visible
+    pub current: [f32; 3],
 
-    /// Timestamp when created
-    pub timestamp: std::time::SystemTime,
+    /// Exponential moving average of [`current`](Self::current), for tinting
+    /// UI or ambient lighting without flicker
+    pub average: [f32; 3],
+}
+
+/// Frame rate [`ColorState::record`]'s coefficient is given at.
+const COLOR_REFERENCE_FPS: f32 = 60.0;
+
+impl ColorState {
+    /// Start from the RGBA colors drawn in one frame, or `None` if none of
+    /// them is visible.
+    pub fn new(colors: &[[f32; 4]]) -> Option<Self> {
+        weighted_mean(colors).map(|mean| Self {
+            current: mean,
+            average: mean,
+        })
+    }
+
+    /// Record the RGBA colors drawn in a frame `delta_time` seconds after
+    /// the previous one.
+    ///
+    /// The [`average`](Self::average) moves towards their alpha-weighted
+    /// mean by `coefficient` (0-1) per 1/60 s: 1.0 follows each frame
+    /// exactly, smaller values smooth more. Frames drawing nothing visible
+    /// leave the state unchanged.
+    pub fn record(&mut self, colors: &[[f32; 4]], coefficient: f32, delta_time: f32) {
+        let Some(mean) = weighted_mean(colors) else {
+            return;
+        };
+        let keep = 1.0 - coefficient.clamp(0.0, 1.0);
+        let weight = 1.0 - keep.powf(delta_time.max(0.0) * COLOR_REFERENCE_FPS);
+        for (avg, new) in self.average.iter_mut().zip(mean) {
+            *avg += (new - *avg) * weight;
+        }
+        self.current = mean;
+    }
+}
+
+/// Mean of `colors` weighted by their alpha, `None` if all are transparent.
+fn weighted_mean(colors: &[[f32; 4]]) -> Option<[f32; 3]> {
+    let (sum, total) = colors
+        .iter()
+        .fold(([0.0; 3], 0.0), |(sum, total), &[r, g, b, a]| {
+            let a = a.clamp(0.0, 1.0);
+            ([sum[0] + r * a, sum[1] + g * a, sum[2] + b * a], total + a)
+        });
+    (total > 0.0).then(|| sum.map(|channel| channel / total))
 }
 
 #[cfg(test)]
@@ -268,4 +410,96 @@ mod tests {
         assert!(history.is_empty());
         assert_eq!(history.current(), None);
     }
+
+    fn preset(zoom: f32, wave_r: f32, warp: &str, comp: &str) -> MilkPreset {
+        let mut preset = crate::default_preset();
+        preset.parameters.zoom = zoom;
+        preset.parameters.wave_r = wave_r;
+        preset.per_frame_equations = vec![format!("zoom = {};", zoom)];
+        preset.warp_shader = Some(warp.to_string());
+        preset.comp_shader = Some(comp.to_string());
+        preset
+    }
+
+    #[test]
+    fn test_create_mashup() {
+        let mut history = History::new(5);
+        assert_eq!(
+            history.create_mashup(MashUpType::Deep),
+            MilkPreset::default()
+        );
+
+        history.push(preset(0.9, 0.1, "warp a", "comp a"));
+        history.push(preset(1.1, 0.8, "warp b", "comp b"));
+
+        // Regular: the current preset with the previous one's warp shader
+        let regular = history.create_mashup(MashUpType::Regular);
+        assert_eq!(regular.parameters.zoom, 1.1);
+        assert_eq!(regular.parameters.wave_r, 0.8);
+        assert_eq!(regular.warp_shader.as_deref(), Some("warp a"));
+        assert_eq!(regular.comp_shader.as_deref(), Some("comp b"));
+
+        // Deep: motion from the current preset, wave colors from the previous
+        let deep = history.create_mashup(MashUpType::Deep);
+        assert_eq!(deep.parameters.zoom, 1.1);
+        assert_eq!(deep.per_frame_equations, ["zoom = 1.1;"]);
+        assert_eq!(deep.parameters.wave_r, 0.1);
+        assert_eq!(deep.warp_shader.as_deref(), Some("warp a"));
+        assert_eq!(deep.comp_shader.as_deref(), Some("comp b"));
+
+        // Going back makes the first preset current, with nothing before it
+        history.back();
+        let single = history.create_mashup(MashUpType::Deep);
+        assert_eq!(single.parameters.wave_r, 0.1);
+        assert_eq!(single.comp_shader.as_deref(), Some("comp a"));
+    }
+
+    #[test]
+    fn test_color_state_smoothing() {
+        let frame = 1.0 / 60.0;
+        assert!(ColorState::new(&[[1.0, 1.0, 1.0, 0.0]]).is_none());
+
+        // The first frame is taken as is, weighted by alpha
+        let mut state = ColorState::new(&[[1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]]).unwrap();
+        assert_eq!(state.average, [0.5, 0.0, 0.5]);
+        let tinted = ColorState::new(&[[1.0, 0.0, 0.0, 0.75], [0.0, 0.0, 1.0, 0.25]]).unwrap();
+        assert_eq!(tinted.current, [0.75, 0.0, 0.25]);
+
+        // A switch to white is approached geometrically
+        let mut expected = [0.5, 0.0, 0.5];
+        for _ in 0..3 {
+            state.record(&[[1.0, 1.0, 1.0, 1.0]], 0.5, frame);
+            expected = expected.map(|c| c + (1.0 - c) * 0.5);
+            for (avg, exp) in state.average.iter().zip(expected) {
+                assert!((avg - exp).abs() < 1e-6, "{:?}", state.average);
+            }
+        }
+
+        // A one-frame flash barely moves a heavily smoothed average
+        state.record(&[[0.0, 0.0, 0.0, 1.0]], 0.1, frame);
+        assert_eq!(state.current, [0.0, 0.0, 0.0]);
+        assert!((state.average[1] - 0.7875).abs() < 1e-6);
+
+        // Invisible frames are ignored
+        let before = state;
+        state.record(&[[1.0, 0.0, 0.0, 0.0]], 1.0, frame);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn test_color_state_frame_rate_independent() {
+        let start = [[0.0, 0.0, 0.0, 1.0]];
+        let white = [[1.0, 1.0, 1.0, 1.0]];
+
+        let mut at_60 = ColorState::new(&start).unwrap();
+        let mut at_240 = at_60;
+        for _ in 0..60 {
+            at_60.record(&white, 0.05, 1.0 / 60.0);
+        }
+        for _ in 0..240 {
+            at_240.record(&white, 0.05, 1.0 / 240.0);
+        }
+        assert!((at_60.average[0] - at_240.average[0]).abs() < 1e-4);
+        assert!(at_60.average[0] > 0.9);
+    }
 }
@@ -0,0 +1,98 @@
+//! Watching a preset file for edits, for live reloading.
+
+use crate::error::{EngineError, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+
+/// Reports when a preset file is written.
+///
+/// The file's directory is watched rather than the file itself, since many
+/// editors save by writing a new file and renaming it over the old one.
+pub struct PresetWatcher {
+    /// Watched preset file
+    path: PathBuf,
+
+    /// Kept alive for as long as events are wanted
+    _watcher: RecommendedWatcher,
+
+    /// File system events for the preset's directory
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl PresetWatcher {
+    /// Start watching `path`.
+    pub fn new(path: &Path) -> Result<Self> {
+        let watch_error = |e: notify::Error| {
+            EngineError::Other(format!("Failed to watch {}: {}", path.display(), e))
+        };
+
+        let path = path.canonicalize()?;
+        let directory = path
+            .parent()
+            .ok_or_else(|| EngineError::PresetLoadFailed(path.display().to_string()))?;
+
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+
+        Ok(Self {
+            path,
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Watched preset file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Drain pending events and report whether the preset was created or
+    /// modified since the last call.
+    pub fn take_change(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    changed |= matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event.paths.iter().any(|p| p == &self.path);
+                }
+                Err(e) => log::warn!("Error watching {}: {}", self.path.display(), e),
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_reports_writes_to_watched_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("watched.milk");
+        std::fs::write(&path, "[preset00]\nzoom=1.0\n").unwrap();
+
+        let watcher = PresetWatcher::new(&path).unwrap();
+        assert!(!watcher.take_change());
+
+        // Other files in the directory are ignored
+        std::fs::write(dir.join("other.milk"), "[preset00]\n").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!watcher.take_change());
+
+        std::fs::write(&path, "[preset00]\nzoom=1.5\n").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watcher.take_change() {
+            assert!(Instant::now() < deadline, "no change reported");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -8,32 +8,52 @@
 pub mod audio;
 #[cfg(feature = "audio-input")]
 pub mod audio_input;
+pub mod audio_source;
 pub mod beat_detection;
 pub mod default_preset;
 pub mod engine;
 pub mod error;
 pub mod fft;
+pub mod frame_pacer;
+pub mod frame_stats;
 pub mod history;
+pub mod hot_reload;
+pub mod preset_cache;
 pub mod preset_manager;
 pub mod safe_loader;
+pub mod time_source;
 pub mod transition;
 
-pub use audio::AudioAnalyzer;
+pub use audio::{
+    AudioAnalyzer, Band, BandNormalizer, BandScale, DEFAULT_NORMALIZE_FLOOR,
+    DEFAULT_SILENCE_THRESHOLD, LOG_SCALE_FLOOR_DB, LONG_TERM_DECAY,
+};
 #[cfg(feature = "audio-input")]
 pub use audio_input::{AudioAnalysisInput, AudioInput, AudioInputError};
-pub use beat_detection::{BeatDetectionMode, BeatDetector, PresetChange};
+pub use audio_source::{AudioSource, FileAudioSource, SilentAudioSource, SineAudioSource};
+pub use beat_detection::{
+    BEAT_HISTORY_LEN, BeatCondition, BeatDetectionMode, BeatDetector, BeatEvent, BeatThresholds,
+    PresetChange,
+};
 pub use default_preset::default_preset;
-pub use engine::{EngineConfig, MilkEngine, QualityPreset};
+pub use engine::{EngineConfig, MilkEngine, QualityPreset, SOURCE_FRAME_SAMPLES};
 pub use error::{EngineError, Result};
 pub use fft::FFTAnalyzer;
+pub use frame_pacer::FramePacer;
+pub use frame_stats::{FRAME_TIME_BUCKET_EDGES_MS, FrameTimeHistogram, FrameTimeStats};
 pub use history::{ColorState, History, MashUpState, MashUpType};
+pub use hot_reload::PresetWatcher;
+pub use preset_cache::{PresetCache, PresetCacheStats};
 pub use preset_manager::{PresetManager, TransitionState};
 pub use safe_loader::SafePresetLoader;
+pub use time_source::{DeltaTimeSource, TimeSource, WallClockTimeSource};
 pub use transition::{Transition, TransitionManager, TransitionMode};
 
 // Re-export commonly used types
-pub use onedrop_parser::MilkPreset;
-pub use onedrop_renderer::{AudioLevels, MotionParams, RenderConfig, RenderState, WaveParams};
+pub use onedrop_parser::{DoublePreset, MilkPreset};
+pub use onedrop_renderer::{
+    AudioLevels, MotionParams, RenderConfig, RenderState, SPECTRUM_BINS, WaveParams,
+};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,260 @@
+//! Preloaded presets.
+//!
+//! Only the parse is cached; loading a preset compiles no shaders, preloaded
+//! or not.
+
+use crate::error::{EngineError, Result};
+use onedrop_parser::{MilkPreset, parse_preset};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+
+/// A parsed preset and the hash of the file content it was parsed from.
+#[derive(Clone)]
+struct CachedPreset {
+    content_hash: u64,
+    preset: MilkPreset,
+}
+
+/// Parsed presets keyed by path.
+///
+/// Entries are only served while the file still has the content they were
+/// parsed from, so an edited preset is never loaded stale. Clones share the
+/// cache, so a clone can preload on another thread while the engine keeps
+/// rendering.
+#[derive(Clone, Default)]
+pub struct PresetCache {
+    /// Parsed presets by path
+    presets: Arc<Mutex<HashMap<PathBuf, CachedPreset>>>,
+
+    /// Lookups served from the cache
+    hits: Arc<AtomicUsize>,
+
+    /// Lookups that had to parse the file
+    misses: Arc<AtomicUsize>,
+}
+
+impl PresetCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lock the preset map. A preload thread that panicked while holding the
+    /// lock leaves the map usable, so the render thread never panics on it.
+    fn presets(&self) -> MutexGuard<'_, HashMap<PathBuf, CachedPreset>> {
+        self.presets.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Parse a preset file into the cache.
+    pub fn preload(&mut self, path: &Path) -> Result<()> {
+        let content = read_preset_file(path)?;
+        let preset = parse_preset(&content)?;
+        self.presets().insert(
+            path.to_path_buf(),
+            CachedPreset {
+                content_hash: content_hash(&content),
+                preset,
+            },
+        );
+        Ok(())
+    }
+
+    /// Preload presets on a background thread.
+    ///
+    /// Stops at the first preset that fails to load and returns its error.
+    pub fn preload_in_background(&self, paths: Vec<PathBuf>) -> JoinHandle<Result<()>> {
+        let mut cache = self.clone();
+        std::thread::spawn(move || {
+            for path in &paths {
+                cache.preload(path)?;
+            }
+            log::info!("Preloaded {} preset(s)", paths.len());
+            Ok(())
+        })
+    }
+
+    /// Parse the preset file `content` read from `path`, reusing the
+    /// preloaded preset if the content is unchanged since.
+    pub fn get_or_parse(&self, path: &Path, content: &str) -> Result<MilkPreset> {
+        let hash = content_hash(content);
+        let cached = self.presets().get(path).cloned();
+        if let Some(cached) = cached {
+            if cached.content_hash == hash {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(cached.preset);
+            }
+            log::debug!("Preloaded preset {} changed on disk", path.display());
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let preset = parse_preset(content)?;
+        let mut presets = self.presets();
+        if let Some(cached) = presets.get_mut(path) {
+            // Keep preloaded entries current, but don't cache every load
+            *cached = CachedPreset {
+                content_hash: hash,
+                preset: preset.clone(),
+            };
+        }
+        Ok(preset)
+    }
+
+    /// Number of preloaded presets.
+    pub fn len(&self) -> usize {
+        self.presets().len()
+    }
+
+    /// Whether no presets are preloaded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookup statistics.
+    pub fn stats(&self) -> PresetCacheStats {
+        PresetCacheStats {
+            len: self.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Forget all preloaded presets.
+    pub fn clear(&mut self) {
+        self.presets().clear();
+    }
+}
+
+/// Preset cache statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresetCacheStats {
+    /// Number of preloaded presets
+    pub len: usize,
+
+    /// Loads served from a preloaded preset
+    pub hits: usize,
+
+    /// Loads that parsed the file
+    pub misses: usize,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Read a preset file, rejecting files too large to be a preset.
+pub(crate) fn read_preset_file(path_ref: &Path) -> Result<String> {
+    const MAX_PRESET_SIZE: u64 = 10 * 1024 * 1024; // 10MB limit
+
+    // Validate file size before loading
+    let metadata = std::fs::metadata(path_ref).map_err(|e| {
+        log::error!("Failed to read file metadata {}: {}", path_ref.display(), e);
+        EngineError::PresetLoadFailed(format!("Cannot read file metadata: {}", e))
+    })?;
+
+    if metadata.len() > MAX_PRESET_SIZE {
+        log::error!(
+            "Preset file too large: {} bytes (max {})",
+            metadata.len(),
+            MAX_PRESET_SIZE
+        );
+        return Err(EngineError::PresetLoadFailed(format!(
+            "File too large: {} bytes (max {} bytes)",
+            metadata.len(),
+            MAX_PRESET_SIZE
+        )));
+    }
+
+    // Read file
+    std::fs::read_to_string(path_ref).map_err(|e| {
+        log::error!("Failed to read preset file {}: {}", path_ref.display(), e);
+        EngineError::PresetLoadFailed(format!("Cannot read file: {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESET: &str = "[preset00]\nzoom=1.5\n";
+
+    #[test]
+    fn test_preloaded_preset_is_reused() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("cached.milk");
+        std::fs::write(&path, PRESET).unwrap();
+
+        let mut cache = PresetCache::new();
+        cache.preload(&path).unwrap();
+        let preset = cache.get_or_parse(&path, PRESET).unwrap();
+        assert_eq!(preset.parameters.zoom, 1.5);
+        assert_eq!(
+            cache.stats(),
+            PresetCacheStats {
+                len: 1,
+                hits: 1,
+                misses: 0
+            }
+        );
+
+        // Presets that were never preloaded are parsed
+        let other = dir.join("other.milk");
+        cache.get_or_parse(&other, PRESET).unwrap();
+        assert_eq!(cache.stats().misses, 1);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_poisoned_lock_is_recovered() {
+        let cache = PresetCache::new();
+        let worker = cache.clone();
+        std::thread::spawn(move || {
+            let _guard = worker.presets.lock().unwrap();
+            panic!("preload thread panicked");
+        })
+        .join()
+        .unwrap_err();
+        assert!(cache.presets.is_poisoned());
+
+        let path = Path::new("poisoned.milk");
+        let preset = cache.get_or_parse(path, PRESET).unwrap();
+        assert_eq!(preset.parameters.zoom, 1.5);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_edited_preset_is_not_served_stale() {
+        let path = Path::new("edited.milk");
+        let mut cache = PresetCache::new();
+        cache.presets.lock().unwrap().insert(
+            path.to_path_buf(),
+            CachedPreset {
+                content_hash: content_hash(PRESET),
+                preset: parse_preset(PRESET).unwrap(),
+            },
+        );
+
+        let edited = "[preset00]\nzoom=2\n";
+        assert_eq!(
+            cache.get_or_parse(path, edited).unwrap().parameters.zoom,
+            2.0
+        );
+        assert_eq!(cache.stats().misses, 1);
+
+        // The entry now holds the edit
+        assert_eq!(
+            cache.get_or_parse(path, edited).unwrap().parameters.zoom,
+            2.0
+        );
+        assert_eq!(cache.stats().hits, 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
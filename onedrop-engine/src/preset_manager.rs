@@ -92,6 +92,26 @@ impl PresetManager {
             .map(|p| p.as_path())
     }
 
+    /// Drop blank and default/warm-up presets from the queue.
+    ///
+    /// Each preset is read and parsed; files that fail to load are kept so the
+    /// usual load error handling still applies. Returns the number removed.
+    pub fn remove_blank_presets(&mut self) -> usize {
+        let before = self.preset_queue.len();
+        self.preset_queue.retain(|path| {
+            let blank = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| onedrop_parser::parse_preset(&content).ok())
+                .is_some_and(|preset| preset.is_default());
+            !blank
+        });
+
+        if self.current_index >= self.preset_queue.len() {
+            self.current_index = 0;
+        }
+        before - self.preset_queue.len()
+    }
+
     /// Get a random preset path.
     pub fn random_preset(&mut self) -> Option<&Path> {
         if self.preset_queue.is_empty() {
@@ -309,4 +329,21 @@ mod tests {
         // Just check that we can still navigate
         assert!(manager.current_preset().is_some());
     }
+
+    #[test]
+    fn test_remove_blank_presets() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let blank = dir.join("blank.milk");
+        let rich = dir.join("rich.milk");
+        std::fs::write(&blank, "[preset00]\nzoom=1.0\n").unwrap();
+        std::fs::write(&rich, "[preset00]\nper_pixel_1=zoom = zoom + 0.1*rad;\n").unwrap();
+
+        let mut manager = PresetManager::new();
+        manager.add_presets(&[&blank, &rich, &dir.join("missing.milk")]);
+        assert_eq!(manager.remove_blank_presets(), 1);
+        assert_eq!(manager.preset_count(), 2);
+        assert_eq!(manager.current_preset(), Some(rich.as_path()));
+    }
 }
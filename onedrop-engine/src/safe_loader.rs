@@ -1,13 +1,108 @@
 //! Safe preset loading with error recovery.
+//!
+//! A [`SafePresetLoader`] instance parses preset files without ever failing
+//! or panicking: each file that can't be read or parsed is recorded in
+//! [`errors`](SafePresetLoader::errors) and replaced by the default preset
+//! ([`load`](SafePresetLoader::load)) or skipped
+//! ([`load_dir`](SafePresetLoader::load_dir)), so one malformed preset never
+//! aborts a batch. The associated functions load straight into an engine.
 
+use crate::default_preset::default_preset;
 use crate::engine::MilkEngine;
 use crate::error::{EngineError, Result};
-use std::path::Path;
+use crate::preset_cache::read_preset_file;
+use onedrop_parser::{MilkPreset, ParseError};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 
 /// Safe preset loader with automatic fallback.
-pub struct SafePresetLoader;
+#[derive(Debug, Default)]
+pub struct SafePresetLoader {
+    /// Files that failed to load, with the reason
+    errors: Vec<(PathBuf, ParseError)>,
+}
 
 impl SafePresetLoader {
+    /// Create a loader with no recorded errors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Files that failed to read or parse so far, in the order they were tried.
+    pub fn errors(&self) -> &[(PathBuf, ParseError)] {
+        &self.errors
+    }
+
+    /// Forget the recorded errors.
+    pub fn clear_errors(&mut self) {
+        self.errors.clear();
+    }
+
+    /// Parse a preset file, falling back to [`default_preset`] if it can't be
+    /// read or parsed. The failure is recorded in [`errors`](Self::errors).
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> MilkPreset {
+        self.try_parse(path.as_ref()).unwrap_or_else(default_preset)
+    }
+
+    /// Parse every `.milk` file in a directory, sorted by path.
+    ///
+    /// Files that fail are skipped and recorded in [`errors`](Self::errors);
+    /// all successfully parsed presets are returned. An unreadable directory
+    /// is recorded as an error and yields no presets.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> Vec<MilkPreset> {
+        let dir_ref = dir.as_ref();
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir_ref) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("milk"))
+                .collect(),
+            Err(e) => {
+                log::error!(
+                    "Failed to read preset directory {}: {}",
+                    dir_ref.display(),
+                    e
+                );
+                self.errors
+                    .push((dir_ref.to_path_buf(), ParseError::IoError(e.to_string())));
+                return Vec::new();
+            }
+        };
+        paths.sort();
+
+        let presets: Vec<MilkPreset> = paths
+            .iter()
+            .filter_map(|path| self.try_parse(path))
+            .collect();
+        log::info!(
+            "Loaded {} of {} presets in {}",
+            presets.len(),
+            paths.len(),
+            dir_ref.display()
+        );
+        presets
+    }
+
+    /// Read and parse one file, recording any failure. A panic in the parser
+    /// is caught and recorded like a parse error.
+    fn try_parse(&mut self, path: &Path) -> Option<MilkPreset> {
+        let result = read_preset_file(path)
+            .map_err(|e| ParseError::IoError(e.to_string()))
+            .and_then(|content| {
+                panic::catch_unwind(AssertUnwindSafe(|| onedrop_parser::parse_preset(&content)))
+                    .unwrap_or_else(|_| Err(ParseError::ParseFailed("parser panicked".to_string())))
+            });
+
+        match result {
+            Ok(preset) => Some(preset),
+            Err(e) => {
+                log::warn!("Skipping invalid preset {}: {}", path.display(), e);
+                self.errors.push((path.to_path_buf(), e));
+                None
+            }
+        }
+    }
+
     /// Try to load a preset, falling back to default on error.
     ///
     /// This method:
@@ -183,6 +278,40 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_load_dir_skips_invalid_presets() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::write(
+            dir.join("good.milk"),
+            "MILKDROP_PRESET_VERSION=201\n[preset00]\nzoom=1.5\n",
+        )
+        .unwrap();
+        let bad = dir.join("bad.milk");
+        std::fs::write(&bad, "MILKDROP_PRESET_VERSION=abc\n[preset00]\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a preset").unwrap();
+
+        let mut loader = SafePresetLoader::new();
+        let presets = loader.load_dir(dir);
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].parameters.zoom, 1.5);
+        assert_eq!(loader.errors().len(), 1);
+        assert_eq!(loader.errors()[0].0, bad);
+        assert!(matches!(
+            loader.errors()[0].1,
+            ParseError::InvalidVersion(_)
+        ));
+
+        // A single bad file falls back to the default preset
+        loader.clear_errors();
+        assert_eq!(loader.load(&bad), default_preset());
+        assert_eq!(loader.errors().len(), 1);
+
+        // A missing directory is recorded, not fatal
+        assert!(loader.load_dir(dir.join("missing")).is_empty());
+        assert_eq!(loader.errors().len(), 2);
+    }
+
     #[test]
     fn test_validate_preset() {
         // Invalid path should fail
@@ -0,0 +1,104 @@
+//! Clocks that drive the engine's `time`.
+//!
+//! Every [`MilkEngine::update`](crate::MilkEngine::update) asks its
+//! [`TimeSource`] for the frame's time. The default [`DeltaTimeSource`] sums
+//! the `delta_time` passed to each update; [`WallClockTimeSource`] follows
+//! the system clock, and external timelines (a DAW transport, SMPTE, a
+//! network clock) can implement the trait to pin frames to their own
+//! timestamps.
+
+use std::time::Instant;
+
+/// A clock giving the time of each rendered frame.
+pub trait TimeSource {
+    /// Get the time in seconds of the frame being rendered. `delta_time` is
+    /// the value passed to `update`; sources with their own clock may
+    /// ignore it.
+    fn frame_time(&mut self, delta_time: f32) -> f32;
+
+    /// Restart from zero. Called by [`MilkEngine::reset`](crate::MilkEngine::reset).
+    fn reset(&mut self) {}
+}
+
+/// Sums the `delta_time` of each frame.
+#[derive(Debug, Clone, Default)]
+pub struct DeltaTimeSource {
+    /// Time of the last frame
+    time: f32,
+}
+
+impl DeltaTimeSource {
+    /// Create a source starting at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TimeSource for DeltaTimeSource {
+    fn frame_time(&mut self, delta_time: f32) -> f32 {
+        self.time += delta_time;
+        self.time
+    }
+
+    fn reset(&mut self) {
+        self.time = 0.0;
+    }
+}
+
+/// Seconds of wall-clock time since creation or the last reset, regardless
+/// of the `delta_time` passed in.
+#[derive(Debug, Clone)]
+pub struct WallClockTimeSource {
+    /// Instant that time is measured from
+    origin: Instant,
+}
+
+impl WallClockTimeSource {
+    /// Create a source starting now.
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for WallClockTimeSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for WallClockTimeSource {
+    fn frame_time(&mut self, _delta_time: f32) -> f32 {
+        self.origin.elapsed().as_secs_f32()
+    }
+
+    fn reset(&mut self) {
+        self.origin = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_time_source() {
+        let mut source = DeltaTimeSource::new();
+        assert_eq!(source.frame_time(0.5), 0.5);
+        assert_eq!(source.frame_time(0.25), 0.75);
+        source.reset();
+        assert_eq!(source.frame_time(0.5), 0.5);
+    }
+
+    #[test]
+    fn test_wall_clock_time_source() {
+        let mut source = WallClockTimeSource::new();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let time = source.frame_time(100.0);
+        assert!((0.02..1.0).contains(&time), "time {}", time);
+
+        source.reset();
+        assert!(source.frame_time(0.0) < time);
+    }
+}
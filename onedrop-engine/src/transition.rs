@@ -1,6 +1,6 @@
 //! Preset transition system with blending.
 
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 /// Transition mode between presets.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,8 +24,8 @@ pub struct Transition {
     /// Transition duration
     duration: Duration,
 
-    /// Start time
-    start_time: Instant,
+    /// Time elapsed since the transition started
+    elapsed: Duration,
 
     /// Current progress (0.0 to 1.0)
     progress: f32,
@@ -40,7 +40,7 @@ impl Transition {
         Self {
             mode,
             duration,
-            start_time: Instant::now(),
+            elapsed: Duration::ZERO,
             progress: 0.0,
             active: false,
         }
@@ -48,19 +48,19 @@ impl Transition {
 
     /// Start the transition.
     pub fn start(&mut self) {
-        self.start_time = Instant::now();
+        self.elapsed = Duration::ZERO;
         self.progress = 0.0;
         self.active = true;
     }
 
-    /// Update transition progress.
-    pub fn update(&mut self) {
+    /// Advance the transition by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f32) {
         if !self.active {
             return;
         }
 
-        let elapsed = self.start_time.elapsed();
-        let t = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        self.elapsed += Duration::from_secs_f32(delta_time.max(0.0));
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
 
         if t >= 1.0 {
             self.progress = 1.0;
@@ -147,10 +147,10 @@ impl TransitionManager {
         self.transition = Some(transition);
     }
 
-    /// Update the current transition.
-    pub fn update(&mut self) {
+    /// Advance the current transition by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f32) {
         if let Some(ref mut transition) = self.transition {
-            transition.update();
+            transition.update(delta_time);
 
             if transition.is_complete() {
                 self.transition = None;
@@ -190,7 +190,6 @@ impl Default for TransitionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
 
     #[test]
     fn test_transition_progress() {
@@ -200,13 +199,11 @@ mod tests {
         assert_eq!(transition.progress(), 0.0);
         assert!(!transition.is_complete());
 
-        thread::sleep(Duration::from_millis(50));
-        transition.update();
+        transition.update(0.05);
 
         assert!(transition.progress() > 0.0 && transition.progress() < 1.0);
 
-        thread::sleep(Duration::from_millis(60));
-        transition.update();
+        transition.update(0.06);
 
         assert_eq!(transition.progress(), 1.0);
         assert!(transition.is_complete());
@@ -245,8 +242,7 @@ mod tests {
         manager.start_transition();
         assert!(manager.is_transitioning());
 
-        thread::sleep(Duration::from_millis(100));
-        manager.update();
+        manager.update(0.1);
 
         assert!(manager.progress() > 0.0);
     }
@@ -1,8 +1,10 @@
 //! Comprehensive tests for beat detection functionality.
 
-use onedrop_engine::{BeatDetectionMode, BeatDetector, PresetChange};
+use onedrop_engine::{
+    BEAT_HISTORY_LEN, BeatCondition, BeatDetectionMode, BeatDetector, BeatThresholds, PresetChange,
+};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_beat_detector_default_state() {
@@ -357,3 +359,170 @@ fn test_preset_change_types() {
     // Test inequality
     assert_ne!(random, specific);
 }
+
+#[test]
+fn test_trigger_history() {
+    let mut detector = BeatDetector::with_mode(BeatDetectionMode::HardCut1);
+
+    detector.should_change_preset(1.0, 0.2, 0.3); // below threshold
+    detector.should_change_preset(2.0, 0.2, 0.3); // triggers
+    detector.should_change_preset(2.5, 0.2, 0.3); // blocked by min delay
+
+    let history = detector.trigger_history();
+    assert_eq!(history.len(), 3);
+
+    assert_eq!(history[0].bass, 1.0);
+    assert_eq!(history[0].condition, None);
+    assert!(!history[0].triggered);
+
+    assert_eq!(history[1].condition, Some(BeatCondition::Bass));
+    assert!(history[1].triggered);
+
+    assert_eq!(history[2].bass, 2.5);
+    assert_eq!(history[2].condition, Some(BeatCondition::Bass));
+    assert!(!history[2].triggered);
+}
+
+#[test]
+fn test_trigger_history_records_special_condition() {
+    let mut detector = BeatDetector::with_mode(BeatDetectionMode::HardCut6 {
+        special_preset: "Bass/WHITE.milk".to_string(),
+    });
+
+    detector.should_change_preset(5.0, 0.5, 0.5);
+    let last = detector.trigger_history().last().unwrap();
+    assert_eq!(last.condition, Some(BeatCondition::BassSpecial));
+    assert!(last.triggered);
+}
+
+#[test]
+fn test_trigger_history_is_bounded() {
+    let mut detector = BeatDetector::with_mode(BeatDetectionMode::HardCut2);
+
+    for i in 0..(BEAT_HISTORY_LEN + 10) {
+        detector.should_change_preset(0.0, 0.0, i as f32 * 0.01);
+    }
+
+    let history = detector.trigger_history();
+    assert_eq!(history.len(), BEAT_HISTORY_LEN);
+    // Oldest entries were dropped
+    assert!((history[0].treb - 0.1).abs() < 1e-6);
+
+    detector.clear_trigger_history();
+    assert!(detector.trigger_history().is_empty());
+}
+
+#[test]
+fn test_trigger_history_empty_when_disabled() {
+    let mut detector = BeatDetector::new();
+    detector.should_change_preset(5.0, 5.0, 5.0);
+    assert!(detector.trigger_history().is_empty());
+}
+
+/// Feed 60fps frames with a bass hit every `frames_per_beat` frames.
+fn feed_beats(detector: &mut BeatDetector, frames: usize, frames_per_beat: usize) {
+    let start = Instant::now();
+    for i in 0..frames {
+        let bass = if i % frames_per_beat == 0 { 3.0 } else { 0.5 };
+        let now = start + Duration::from_secs_f64(i as f64 / 60.0);
+        detector.should_change_preset_at(bass, 0.5, 0.5, now);
+    }
+}
+
+#[test]
+fn test_bpm_estimate_120() {
+    let mut detector = BeatDetector::new();
+    assert_eq!(detector.estimated_bpm(), None);
+
+    // 120 BPM = one beat every 0.5s = 30 frames at 60fps
+    feed_beats(&mut detector, 60 * 8, 30);
+
+    let bpm = detector
+        .estimated_bpm()
+        .expect("should have a BPM estimate");
+    assert!((bpm - 120.0).abs() <= 5.0, "estimated {} BPM", bpm);
+}
+
+#[test]
+fn test_bpm_needs_enough_onsets() {
+    let mut detector = BeatDetector::new();
+    feed_beats(&mut detector, 61, 30);
+    assert_eq!(detector.estimated_bpm(), None);
+}
+
+#[test]
+fn test_bpm_clamped_to_range() {
+    let mut detector = BeatDetector::new();
+
+    // 30 BPM: one beat every 2s
+    feed_beats(&mut detector, 60 * 12, 120);
+    assert_eq!(detector.estimated_bpm(), Some(60.0));
+}
+
+#[test]
+fn test_custom_bass_threshold() {
+    let mut detector = BeatDetector::with_mode(BeatDetectionMode::HardCut1);
+
+    // Default threshold (1.5) ignores bass=1.0
+    assert_eq!(detector.should_change_preset(1.0, 0.5, 0.5), None);
+
+    detector.set_thresholds(BeatThresholds {
+        bass: 0.8,
+        ..Default::default()
+    });
+    assert_eq!(
+        detector.should_change_preset(1.0, 0.5, 0.5),
+        Some(PresetChange::Random)
+    );
+}
+
+#[test]
+fn test_custom_min_delay() {
+    let mut detector = BeatDetector::with_mode(BeatDetectionMode::HardCut1);
+    let mut thresholds = BeatThresholds::default();
+    thresholds.min_delays[0] = Duration::from_secs(10);
+    detector.set_thresholds(thresholds);
+
+    let start = Instant::now();
+    assert!(
+        detector
+            .should_change_preset_at(2.0, 0.5, 0.5, start)
+            .is_some()
+    );
+    // Past the default 0.2s, but inside the custom 10s delay
+    let later = start + Duration::from_secs(1);
+    assert!(
+        detector
+            .should_change_preset_at(2.0, 0.5, 0.5, later)
+            .is_none()
+    );
+}
+
+#[test]
+fn test_beat_intensity_weak_vs_strong() {
+    let mut detector = BeatDetector::with_mode(BeatDetectionMode::HardCut1);
+    assert_eq!(detector.last_beat_intensity(), 0.0);
+
+    let start = Instant::now();
+    assert!(
+        detector
+            .should_change_preset_at(1.6, 0.5, 0.5, start)
+            .is_some()
+    );
+    let weak = detector.last_beat_intensity();
+
+    let later = start + Duration::from_secs(1);
+    assert!(
+        detector
+            .should_change_preset_at(4.5, 0.5, 0.5, later)
+            .is_some()
+    );
+    let strong = detector.last_beat_intensity();
+
+    assert!(weak < 0.1, "weak spike reported {}", weak);
+    assert!(strong > 1.0, "strong spike reported {}", strong);
+
+    // Blocked beats leave the last intensity untouched
+    detector.should_change_preset_at(1.6, 0.5, 0.5, later);
+    assert_eq!(detector.last_beat_intensity(), strong);
+}
@@ -223,3 +223,45 @@ fn test_different_audio_patterns() {
     engine.update(&loud, 0.016).unwrap();
     assert!(engine.state().audio.bass > 0.0);
 }
+
+#[test]
+fn test_double_preset_playback() {
+    env_logger::try_init().ok();
+
+    let od2 = "\
+[DoublePreset]
+BlendPattern=13
+BlendAmount=0.5
+AnimateBlend=1
+AnimationSpeed=2.0
+
+[PresetA]
+[preset00]
+zoom=1.01
+per_frame_1=rot = 0.1 * sin(time);
+
+[PresetB]
+[preset00]
+zoom=0.99
+per_frame_1=q1 = bass;
+";
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("double.od2");
+    std::fs::write(&path, od2).unwrap();
+
+    let config = EngineConfig::default();
+    let mut engine = pollster::block_on(MilkEngine::new(config)).unwrap();
+    engine.load_double_preset(&path).unwrap();
+    assert!(engine.is_double_preset());
+
+    let audio_samples = vec![0.0; 1024];
+    for i in 0..10 {
+        let result = engine.update(&audio_samples, 0.016);
+        assert!(result.is_ok(), "Failed at frame {}", i);
+    }
+    assert_eq!(engine.state().frame, 10);
+
+    // Loading a regular preset ends double-preset playback
+    engine.load_default_preset().unwrap();
+    assert!(!engine.is_double_preset());
+}
@@ -0,0 +1,74 @@
+//! Benchmark comparing evalexpr and bytecode per-pixel evaluation.
+//!
+//! Both paths evaluate the same per-pixel equations over a warp mesh; the
+//! bytecode VM should be noticeably faster.
+
+use onedrop_eval::{MilkEvaluator, Program};
+use std::time::{Duration, Instant};
+
+/// Mesh size (MilkDrop's default warp grid).
+const MESH_WIDTH: usize = 48;
+const MESH_HEIGHT: usize = 36;
+
+/// Number of frames to evaluate.
+const FRAMES: usize = 10;
+
+const EQUATIONS: [&str; 4] = [
+    "zoom = zoom + 0.02 * sin(rad * 6 + time) * bass",
+    "rot = rot + 0.01 * cos(ang * 3 - time * 0.7)",
+    "dx = 0.005 * sin(y * 12 + time); dy = 0.005 * cos(x * 12 + time)",
+    "warp = warp * if(above(rad, 0.5), 1.2, 0.8)",
+];
+
+fn run<F: FnMut(&mut MilkEvaluator, f64, f64, f64, f64)>(mut per_pixel: F) -> Duration {
+    let mut evaluator = MilkEvaluator::new();
+    evaluator.context_mut().set_audio(0.8, 0.6, 0.4);
+
+    let start = Instant::now();
+    for frame in 0..FRAMES {
+        evaluator.context_mut().set_time(frame as f64 / 60.0);
+        evaluator
+            .eval_per_frame(&[])
+            .expect("Failed to evaluate per-frame block");
+
+        for j in 0..MESH_HEIGHT {
+            for i in 0..MESH_WIDTH {
+                let x = i as f64 / (MESH_WIDTH - 1) as f64;
+                let y = j as f64 / (MESH_HEIGHT - 1) as f64;
+                let (cx, cy) = (x - 0.5, y - 0.5);
+                let rad = (cx * cx + cy * cy).sqrt();
+                let ang = cy.atan2(cx);
+                per_pixel(&mut evaluator, x, y, rad, ang);
+            }
+        }
+    }
+    start.elapsed() / FRAMES as u32
+}
+
+fn main() {
+    let equations: Vec<String> = EQUATIONS.iter().map(|s| s.to_string()).collect();
+    let programs: Vec<Program> = EQUATIONS
+        .iter()
+        .map(|s| Program::compile(s).expect("Failed to compile equation"))
+        .collect();
+
+    let evalexpr_time = run(|eval, x, y, rad, ang| {
+        eval.eval_per_pixel(x, y, rad, ang, &equations)
+            .expect("Failed to evaluate per-pixel block");
+    });
+    let bytecode_time = run(|eval, x, y, rad, ang| {
+        eval.eval_per_pixel_bytecode(x, y, rad, ang, &programs);
+    });
+
+    println!("\n=== Per-Pixel Bytecode Benchmark ===");
+    println!("Mesh: {}x{}", MESH_WIDTH, MESH_HEIGHT);
+    println!("Equations: {}", EQUATIONS.len());
+    println!("\nResults (avg per frame):");
+    println!("  evalexpr: {:?}", evalexpr_time);
+    println!("  bytecode: {:?}", bytecode_time);
+    println!(
+        "  Speedup: {:.2}x",
+        evalexpr_time.as_secs_f64() / bytecode_time.as_secs_f64()
+    );
+    println!("====================================\n");
+}
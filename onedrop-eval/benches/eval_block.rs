@@ -0,0 +1,57 @@
+//! Benchmark for evaluating a large per-frame equation block.
+//!
+//! The first frame pays for regex preprocessing of every equation; later
+//! frames reuse the cached result and should be noticeably faster.
+
+use onedrop_eval::MilkEvaluator;
+use std::time::{Duration, Instant};
+
+/// Number of equations in the per-frame block.
+const EQUATIONS: usize = 40;
+
+/// Number of frames to evaluate (one second at 60fps).
+const FRAMES: usize = 60;
+
+fn build_block() -> Vec<String> {
+    (0..EQUATIONS)
+        .map(|i| match i % 4 {
+            0 => format!("q{} = sin(time * {}) * bass", i % 32 + 1, i + 1),
+            1 => format!("v{} = if(above(mid, 0.5), v{} + 1, 0)", i, i),
+            2 => format!("zoom = zoom + 0.01 * cos(time + {})", i),
+            _ => format!("rot = rot * 0.9 + 0.1 * treb_att * q{}", i % 32 + 1),
+        })
+        .collect()
+}
+
+fn main() {
+    let equations = build_block();
+    let mut evaluator = MilkEvaluator::new();
+
+    let mut frame_times: Vec<Duration> = Vec::with_capacity(FRAMES);
+    for frame in 0..FRAMES {
+        evaluator.context_mut().set_time(frame as f64 / 60.0);
+        evaluator.context_mut().set_audio(0.8, 0.6, 0.4);
+
+        let start = Instant::now();
+        evaluator
+            .eval_per_frame(&equations)
+            .expect("Failed to evaluate block");
+        frame_times.push(start.elapsed());
+    }
+
+    let first = frame_times[0];
+    let rest = &frame_times[1..];
+    let avg_rest = rest.iter().sum::<Duration>() / rest.len() as u32;
+
+    println!("\n=== Per-Frame Evaluation Benchmark ===");
+    println!("Equations: {}", EQUATIONS);
+    println!("Frames: {}", FRAMES);
+    println!("\nResults:");
+    println!("  First frame (cold preprocessing): {:?}", first);
+    println!("  Avg later frame (cached): {:?}", avg_rest);
+    println!(
+        "  Speedup: {:.2}x",
+        first.as_secs_f64() / avg_rest.as_secs_f64()
+    );
+    println!("======================================\n");
+}
@@ -0,0 +1,836 @@
+//! Bytecode compiler and VM for MilkDrop equations.
+//!
+//! Equations are compiled once into a flat, stack-based instruction sequence
+//! and executed directly against a [`MilkContext`], bypassing evalexpr's tree
+//! walk for hot paths such as per-pixel evaluation.
+//!
+//! Operator precedence and associativity follow evalexpr, so a compiled
+//! [`Program`] produces the same results as [`MilkEvaluator::eval`]. The one
+//! deliberate difference is that the VM computes everything in `f64`, as
+//! MilkDrop does: evalexpr's integer-only operations (e.g. `1/2` between two
+//! integer literals) are evaluated as floats here.
+//!
+//! [`MilkEvaluator::eval`]: crate::MilkEvaluator::eval
+
+use crate::context::MilkContext;
+use crate::error::{EvalError, Result};
+use std::collections::HashMap;
+
+/// Built-in function callable from bytecode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sqrt,
+    Pow,
+    Exp,
+    Ln,
+    Log10,
+    Abs,
+    Sign,
+    Fract,
+    Trunc,
+    Floor,
+    Ceil,
+    Round,
+    Fmod,
+    Clamp,
+    Sinh,
+    Cosh,
+    Tanh,
+    Sqr,
+    Rad,
+    Deg,
+    Rand,
+    RandInt,
+    Above,
+    Below,
+    Equal,
+    Bnot,
+    Band,
+    Bor,
+    /// Variadic minimum (argument count in the instruction)
+    Min,
+    /// Variadic maximum (argument count in the instruction)
+    Max,
+    /// `if(cond, a, b)` / `milkif(cond, a, b)`
+    If,
+    Megabuf,
+    Gmegabuf,
+    MegabufSet,
+    GmegabufSet,
+}
+
+impl Intrinsic {
+    /// Look up an intrinsic and its accepted argument counts by name.
+    fn from_name(name: &str) -> Option<(Self, usize, usize)> {
+        use Intrinsic::*;
+        let entry = match name {
+            "sin" => (Sin, 1, 1),
+            "cos" => (Cos, 1, 1),
+            "tan" => (Tan, 1, 1),
+            "asin" => (Asin, 1, 1),
+            "acos" => (Acos, 1, 1),
+            "atan" => (Atan, 1, 1),
+            "atan2" => (Atan2, 2, 2),
+            "sqrt" => (Sqrt, 1, 1),
+            "pow" => (Pow, 2, 2),
+            "exp" => (Exp, 1, 1),
+            "log" | "ln" => (Ln, 1, 1),
+            "log10" => (Log10, 1, 1),
+            "abs" => (Abs, 1, 1),
+            "sign" => (Sign, 1, 1),
+            "fract" => (Fract, 1, 1),
+            "trunc" | "int" => (Trunc, 1, 1),
+            "floor" => (Floor, 1, 1),
+            "ceil" => (Ceil, 1, 1),
+            "round" => (Round, 1, 1),
+            "fmod" => (Fmod, 2, 2),
+            "clamp" => (Clamp, 3, 3),
+            "sinh" => (Sinh, 1, 1),
+            "cosh" => (Cosh, 1, 1),
+            "tanh" => (Tanh, 1, 1),
+            "sqr" => (Sqr, 1, 1),
+            "rad" => (Rad, 1, 1),
+            "deg" => (Deg, 1, 1),
+            "rand" => (Rand, 1, 1),
+            "rand_int" => (RandInt, 1, 1),
+            "above" => (Above, 2, 2),
+            "below" => (Below, 2, 2),
+            "equal" => (Equal, 2, 2),
+            "bnot" => (Bnot, 1, 1),
+            "band" => (Band, 2, 2),
+            "bor" => (Bor, 2, 2),
+            "min" => (Min, 1, usize::MAX),
+            "max" => (Max, 1, usize::MAX),
+            "if" | "milkif" => (If, 3, 3),
+            "megabuf" => (Megabuf, 1, 1),
+            "gmegabuf" => (Gmegabuf, 1, 1),
+            "megabuf_set" => (MegabufSet, 2, 2),
+            "gmegabuf_set" => (GmegabufSet, 2, 2),
+            _ => return None,
+        };
+        Some(entry)
+    }
+}
+
+/// A single VM instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    /// Push a constant
+    Const(f64),
+    /// Push a variable register
+    Load(usize),
+    /// Pop into a variable register
+    Store(usize),
+    /// Discard the top of the stack
+    Pop,
+    Neg,
+    Not,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    /// Call an intrinsic with the given number of stack arguments
+    Call(Intrinsic, usize),
+}
+
+/// A compiled equation line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    ops: Vec<Op>,
+
+    /// Variable names, indexed by register
+    vars: Vec<String>,
+
+    /// Registers written by the program, flushed back to the context
+    stored: Vec<usize>,
+
+    /// Maximum stack depth
+    max_stack: usize,
+}
+
+impl Program {
+    /// Compile an equation line (one or more `;`-separated statements).
+    pub fn compile(equation: &str) -> Result<Self> {
+        let mut compiler = Compiler::default();
+        let mut first = true;
+
+        for statement in crate::evaluator::split_statements(equation) {
+            let tokens = tokenize(statement)?;
+            if tokens.is_empty() {
+                continue;
+            }
+            if !first {
+                compiler.emit(Op::Pop);
+            }
+            first = false;
+
+            let mut parser = Parser {
+                tokens: &tokens,
+                pos: 0,
+                compiler: &mut compiler,
+                source: statement,
+            };
+            parser.statement()?;
+        }
+
+        if first {
+            compiler.emit(Op::Const(0.0));
+        }
+
+        Ok(compiler.finish())
+    }
+
+    /// Get the instruction sequence.
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Execute against a context and return the value of the last statement.
+    ///
+    /// Referenced variables missing from the context read as `0.0`.
+    pub fn execute(&self, ctx: &mut MilkContext) -> f64 {
+        let mut regs: Vec<f64> = self
+            .vars
+            .iter()
+            .map(|name| ctx.get(name).unwrap_or(0.0))
+            .collect();
+        let mut stack: Vec<f64> = Vec::with_capacity(self.max_stack);
+
+        for op in &self.ops {
+            match *op {
+                Op::Const(v) => stack.push(v),
+                Op::Load(r) => stack.push(regs[r]),
+                Op::Store(r) => regs[r] = pop(&mut stack),
+                Op::Pop => {
+                    pop(&mut stack);
+                }
+                Op::Neg => unary(&mut stack, |a| -a),
+                Op::Not => unary(&mut stack, |a| bool_f64(a == 0.0)),
+                Op::Add => binary(&mut stack, |a, b| a + b),
+                Op::Sub => binary(&mut stack, |a, b| a - b),
+                Op::Mul => binary(&mut stack, |a, b| a * b),
+                Op::Div => binary(&mut stack, |a, b| a / b),
+                Op::Rem => binary(&mut stack, |a, b| a % b),
+                Op::Pow => binary(&mut stack, f64::powf),
+                Op::Eq => binary(&mut stack, |a, b| bool_f64(a == b)),
+                Op::Ne => binary(&mut stack, |a, b| bool_f64(a != b)),
+                Op::Lt => binary(&mut stack, |a, b| bool_f64(a < b)),
+                Op::Le => binary(&mut stack, |a, b| bool_f64(a <= b)),
+                Op::Gt => binary(&mut stack, |a, b| bool_f64(a > b)),
+                Op::Ge => binary(&mut stack, |a, b| bool_f64(a >= b)),
+                Op::And => binary(&mut stack, |a, b| bool_f64(a != 0.0 && b != 0.0)),
+                Op::Or => binary(&mut stack, |a, b| bool_f64(a != 0.0 || b != 0.0)),
+                Op::Call(f, argc) => call(f, argc, &mut stack, ctx),
+            }
+        }
+
+        for &r in &self.stored {
+            ctx.set(&self.vars[r], regs[r]);
+        }
+
+        stack.pop().unwrap_or(0.0)
+    }
+}
+
+fn pop(stack: &mut Vec<f64>) -> f64 {
+    stack.pop().unwrap_or(0.0)
+}
+
+fn bool_f64(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+fn unary(stack: &mut Vec<f64>, f: impl Fn(f64) -> f64) {
+    let a = pop(stack);
+    stack.push(f(a));
+}
+
+fn binary(stack: &mut Vec<f64>, f: impl Fn(f64, f64) -> f64) {
+    let b = pop(stack);
+    let a = pop(stack);
+    stack.push(f(a, b));
+}
+
+fn call(f: Intrinsic, argc: usize, stack: &mut Vec<f64>, ctx: &MilkContext) {
+    use Intrinsic::*;
+    match f {
+        Sin => unary(stack, f64::sin),
+        Cos => unary(stack, f64::cos),
+        Tan => unary(stack, f64::tan),
+        Asin => unary(stack, f64::asin),
+        Acos => unary(stack, f64::acos),
+        Atan => unary(stack, f64::atan),
+        Atan2 => binary(stack, f64::atan2),
+        Sqrt => unary(stack, f64::sqrt),
+        Pow => binary(stack, f64::powf),
+        Exp => unary(stack, f64::exp),
+        Ln => unary(stack, f64::ln),
+        Log10 => unary(stack, f64::log10),
+        Abs => unary(stack, f64::abs),
+        Sign => unary(stack, |n| {
+            if n > 0.0 {
+                1.0
+            } else if n < 0.0 {
+                -1.0
+            } else {
+                0.0
+            }
+        }),
+        Fract => unary(stack, f64::fract),
+        Trunc => unary(stack, f64::trunc),
+        Floor => unary(stack, f64::floor),
+        Ceil => unary(stack, f64::ceil),
+        Round => unary(stack, f64::round),
+        Fmod => binary(stack, |a, b| a % b),
+        Clamp => {
+            let max_val = pop(stack);
+            let min_val = pop(stack);
+            let value = pop(stack);
+            stack.push(value.max(min_val).min(max_val));
+        }
+        Sinh => unary(stack, f64::sinh),
+        Cosh => unary(stack, f64::cosh),
+        Tanh => unary(stack, f64::tanh),
+        Sqr => unary(stack, |n| n * n),
+        Rad => unary(stack, f64::to_radians),
+        Deg => unary(stack, f64::to_degrees),
+        Rand => unary(stack, |max| ctx.rng().next_f64() * max),
+        RandInt => unary(stack, |max| (ctx.rng().next_f64() * max.trunc()).floor()),
+        Above => binary(stack, |a, b| bool_f64(a > b)),
+        Below => binary(stack, |a, b| bool_f64(a < b)),
+        Equal => binary(stack, |a, b| bool_f64((a - b).abs() < 1e-10)),
+        Bnot => unary(stack, |n| bool_f64(n == 0.0)),
+        Band => binary(stack, |a, b| bool_f64(a != 0.0 && b != 0.0)),
+        Bor => binary(stack, |a, b| bool_f64(a != 0.0 || b != 0.0)),
+        Min | Max => {
+            let args = stack.split_off(stack.len().saturating_sub(argc));
+            let init = if f == Min {
+                f64::INFINITY
+            } else {
+                f64::NEG_INFINITY
+            };
+            let result =
+                args.into_iter().fold(
+                    init,
+                    |acc, v| {
+                        if f == Min { acc.min(v) } else { acc.max(v) }
+                    },
+                );
+            stack.push(result);
+        }
+        If => {
+            let else_val = pop(stack);
+            let then_val = pop(stack);
+            let cond = pop(stack);
+            stack.push(if cond != 0.0 { then_val } else { else_val });
+        }
+        Megabuf => unary(stack, |i| ctx.megabuf().get(i)),
+        Gmegabuf => unary(stack, |i| ctx.gmegabuf().get(i)),
+        MegabufSet | GmegabufSet => {
+            let value = pop(stack);
+            let index = pop(stack);
+            let buf = if f == MegabufSet {
+                ctx.megabuf()
+            } else {
+                ctx.gmegabuf()
+            };
+            buf.set(index, value);
+            stack.push(value);
+        }
+    }
+}
+
+/// Instruction emitter tracking registers and stack depth.
+#[derive(Default)]
+struct Compiler {
+    ops: Vec<Op>,
+    vars: Vec<String>,
+    slots: HashMap<String, usize>,
+    stored: Vec<usize>,
+    depth: usize,
+    max_stack: usize,
+}
+
+impl Compiler {
+    fn slot(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.slots.get(name) {
+            return slot;
+        }
+        let slot = self.vars.len();
+        self.vars.push(name.to_string());
+        self.slots.insert(name.to_string(), slot);
+        slot
+    }
+
+    fn emit(&mut self, op: Op) {
+        let (pops, pushes) = match op {
+            Op::Const(_) | Op::Load(_) => (0, 1),
+            Op::Store(_) | Op::Pop => (1, 0),
+            Op::Neg | Op::Not => (1, 1),
+            Op::Call(_, argc) => (argc, 1),
+            _ => (2, 1),
+        };
+        if let Op::Store(r) = op {
+            if !self.stored.contains(&r) {
+                self.stored.push(r);
+            }
+        }
+        self.depth = self.depth.saturating_sub(pops) + pushes;
+        self.max_stack = self.max_stack.max(self.depth);
+        self.ops.push(op);
+    }
+
+    fn finish(self) -> Program {
+        Program {
+            ops: self.ops,
+            vars: self.vars,
+            stored: self.stored,
+            max_stack: self.max_stack,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 23] = [
+    "+=", "-=", "*=", "/=", "%=", "^=", "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/",
+    "%", "^", "(", ")", ",", "<", ">",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let bytes = source.as_bytes();
+    let mut i = 0;
+
+    'outer: while i < bytes.len() {
+        let c = bytes[i] as char;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                i += 1;
+            }
+            // Exponent (e.g. 1e-3)
+            if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+                let mut j = i + 1;
+                if j < bytes.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+                    j += 1;
+                }
+                if j < bytes.len() && bytes[j].is_ascii_digit() {
+                    i = j;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text = &source[start..i];
+            let value = text
+                .parse::<f64>()
+                .map_err(|_| syntax(source, "invalid number"))?;
+            tokens.push(Token::Num(value));
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(source[start..i].to_string()));
+            continue;
+        }
+
+        for sym in SYMBOLS {
+            if source[i..].starts_with(sym) {
+                tokens.push(Token::Sym(sym));
+                i += sym.len();
+                continue 'outer;
+            }
+        }
+        match c {
+            '=' => tokens.push(Token::Sym("=")),
+            '!' => tokens.push(Token::Sym("!")),
+            _ => return Err(syntax(source, &format!("unexpected character '{}'", c))),
+        }
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+fn syntax(source: &str, reason: &str) -> EvalError {
+    EvalError::SyntaxError {
+        expression: source.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Recursive-descent parser emitting bytecode as it goes.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    compiler: &'a mut Compiler,
+    source: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_sym(&self) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Sym(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<()> {
+        if self.peek_sym() == Some(sym) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", sym)))
+        }
+    }
+
+    fn error(&self, reason: &str) -> EvalError {
+        syntax(self.source, reason)
+    }
+
+    fn statement(&mut self) -> Result<()> {
+        // megabuf(i) = v / gmegabuf(i) = v
+        if let Some(setter) = self.megabuf_assignment() {
+            self.pos += 2;
+            self.expr()?;
+            self.expect(")")?;
+            self.expect("=")?;
+            self.expr()?;
+            self.compiler.emit(Op::Call(setter, 2));
+            self.compiler.emit(Op::Pop);
+            self.compiler.emit(Op::Const(0.0));
+        } else if let (Some(Token::Ident(name)), Some(Token::Sym(op))) =
+            (self.tokens.first(), self.tokens.get(1))
+        {
+            let compound = match *op {
+                "=" => Some(None),
+                "+=" => Some(Some(Op::Add)),
+                "-=" => Some(Some(Op::Sub)),
+                "*=" => Some(Some(Op::Mul)),
+                "/=" => Some(Some(Op::Div)),
+                "%=" => Some(Some(Op::Rem)),
+                "^=" => Some(Some(Op::Pow)),
+                _ => None,
+            };
+            match compound {
+                Some(binop) => {
+                    let slot = self.compiler.slot(name);
+                    self.pos = 2;
+                    if binop.is_some() {
+                        self.compiler.emit(Op::Load(slot));
+                    }
+                    self.expr()?;
+                    if let Some(binop) = binop {
+                        self.compiler.emit(binop);
+                    }
+                    self.compiler.emit(Op::Store(slot));
+                    // Assignments evaluate to 0, like evalexpr's empty value
+                    self.compiler.emit(Op::Const(0.0));
+                }
+                None => self.expr()?,
+            }
+        } else {
+            self.expr()?;
+        }
+
+        if self.pos != self.tokens.len() {
+            return Err(self.error("unexpected trailing input"));
+        }
+        Ok(())
+    }
+
+    /// Detect `megabuf(...) =` at the start of the statement.
+    fn megabuf_assignment(&self) -> Option<Intrinsic> {
+        let setter = match self.tokens.first() {
+            Some(Token::Ident(name)) if name == "megabuf" => Intrinsic::MegabufSet,
+            Some(Token::Ident(name)) if name == "gmegabuf" => Intrinsic::GmegabufSet,
+            _ => return None,
+        };
+        if self.tokens.get(1) != Some(&Token::Sym("(")) {
+            return None;
+        }
+
+        let mut depth = 0usize;
+        for (i, token) in self.tokens.iter().enumerate().skip(1) {
+            match token {
+                Token::Sym("(") => depth += 1,
+                Token::Sym(")") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return (self.tokens.get(i + 1) == Some(&Token::Sym("=")))
+                            .then_some(setter);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn expr(&mut self) -> Result<()> {
+        self.binary_level(0)
+    }
+
+    /// Left-associative binary operators, loosest first.
+    fn binary_level(&mut self, level: usize) -> Result<()> {
+        const LEVELS: [&[(&str, Op)]; 5] = [
+            &[("||", Op::Or)],
+            &[("&&", Op::And)],
+            &[
+                ("==", Op::Eq),
+                ("!=", Op::Ne),
+                ("<", Op::Lt),
+                ("<=", Op::Le),
+                (">", Op::Gt),
+                (">=", Op::Ge),
+            ],
+            &[("+", Op::Add), ("-", Op::Sub)],
+            &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Rem)],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        self.binary_level(level + 1)?;
+        while let Some(sym) = self.peek_sym() {
+            let Some((_, op)) = LEVELS[level].iter().find(|(s, _)| *s == sym) else {
+                break;
+            };
+            self.pos += 1;
+            self.binary_level(level + 1)?;
+            self.compiler.emit(*op);
+        }
+        Ok(())
+    }
+
+    /// Unary minus/not bind looser than `^` (so `-a^b` is `-(a^b)`).
+    fn unary(&mut self) -> Result<()> {
+        match self.peek_sym() {
+            Some("-") => {
+                self.pos += 1;
+                self.unary()?;
+                self.compiler.emit(Op::Neg);
+                Ok(())
+            }
+            Some("!") => {
+                self.pos += 1;
+                self.unary()?;
+                self.compiler.emit(Op::Not);
+                Ok(())
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// Left-associative `^`.
+    fn power(&mut self) -> Result<()> {
+        self.primary()?;
+        while self.peek_sym() == Some("^") {
+            self.pos += 1;
+            self.unary_operand()?;
+            self.compiler.emit(Op::Pow);
+        }
+        Ok(())
+    }
+
+    /// Right-hand side of `^`, allowing a leading sign (`a^-b`).
+    fn unary_operand(&mut self) -> Result<()> {
+        if self.peek_sym() == Some("-") {
+            self.pos += 1;
+            self.unary_operand()?;
+            self.compiler.emit(Op::Neg);
+            Ok(())
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<()> {
+        match self.peek().cloned() {
+            Some(Token::Num(v)) => {
+                self.pos += 1;
+                self.compiler.emit(Op::Const(v));
+                Ok(())
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                if self.peek_sym() == Some("(") {
+                    self.call(&name)
+                } else {
+                    let slot = self.compiler.slot(&name);
+                    self.compiler.emit(Op::Load(slot));
+                    Ok(())
+                }
+            }
+            Some(Token::Sym("(")) => {
+                self.pos += 1;
+                self.expr()?;
+                self.expect(")")
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<()> {
+        let (intrinsic, min_args, max_args) = Intrinsic::from_name(name)
+            .ok_or_else(|| self.error(&format!("unknown function '{}'", name)))?;
+
+        self.expect("(")?;
+        let mut argc = 0;
+        if self.peek_sym() != Some(")") {
+            loop {
+                self.expr()?;
+                argc += 1;
+                if self.peek_sym() == Some(",") {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(")")?;
+
+        if argc < min_args || argc > max_args {
+            return Err(self.error(&format!(
+                "wrong number of arguments for '{}': {}",
+                name, argc
+            )));
+        }
+        self.compiler.emit(Op::Call(intrinsic, argc));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MilkEvaluator;
+
+    /// Representative preset equations; integer-only division is avoided
+    /// since evalexpr would truncate it.
+    const EQUATIONS: &[&str] = &[
+        "zoom = zoom + 0.01*sin(time*1.3)",
+        "rot = rot + 0.05*cos(rad*3.14 + time) * bass_att",
+        "dx = 0.01 * above(bass, 1.2) - 0.01 * below(treb, 0.5)",
+        "q1 = if(above(mid, 0.5), sqrt(abs(mid)), pow(treb, 2.5))",
+        "wave_r = 0.5 + 0.5*sin(time*1.1); wave_g = min(wave_r, 0.7)",
+        "sx = sx + 0.1*sign(sin(ang*6))*fract(time)",
+        "cx = clamp(x + 0.1*atan2(y - 0.5, x - 0.5), 0, 1)",
+        "warp = bnot(equal(bass, mid)) * band(bass, treb) + bor(0, mid)",
+        "q2 = -q1^2 + 3 % 2.5 - 2^3^0.5",
+        "zoom += 0.1 * (bass - 1); rot *= 0.9",
+        "x = x + 0.02*sin(y*10 + time)",
+        "q3 = floor(time*4) + ceil(bass) + round(mid*10)/10 + int(treb*3)",
+        "q4 = log(1 + bass) + exp(-mid) + log10(10 + treb) + sqr(time)",
+        "megabuf(3) = q1 * 2; q5 = megabuf(3) + megabuf(4)",
+        "q6 = max(bass, mid, treb) - tanh(rot) + deg(0.5) + rad(30)",
+        "q7 = if(bass > mid, 2, 0.5) + if(treb <= 0.25 && time == 1.5, 3, 0)",
+        "q8 = 1.5e-2 * time + .5",
+    ];
+
+    /// Variables read back after each equation.
+    const CHECKED: &[&str] = &[
+        "zoom", "rot", "dx", "sx", "cx", "warp", "x", "wave_r", "wave_g", "q1", "q2", "q3", "q4",
+        "q5", "q6", "q7", "q8",
+    ];
+
+    fn setup(eval: &mut MilkEvaluator) {
+        let ctx = eval.context_mut();
+        ctx.set_time(1.5);
+        ctx.set_audio(1.3, 0.7, 0.25);
+        ctx.set("bass_att", 1.1);
+        ctx.set_pixel(0.3, 0.8, 0.4, 1.2);
+        ctx.set("q1", 0.75);
+    }
+
+    #[test]
+    fn test_matches_evalexpr() {
+        for equation in EQUATIONS {
+            let mut reference = MilkEvaluator::new();
+            let mut vm = MilkEvaluator::new();
+            setup(&mut reference);
+            setup(&mut vm);
+
+            let expected = reference.eval(equation).unwrap();
+            let program = Program::compile(equation).unwrap();
+            let actual = program.execute(vm.context_mut());
+            assert_eq!(expected, actual, "result of {}", equation);
+
+            for name in CHECKED {
+                assert_eq!(
+                    reference.context().get(name),
+                    vm.context().get(name),
+                    "{} after {}",
+                    name,
+                    equation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_expression_results() {
+        let mut eval = MilkEvaluator::new();
+        setup(&mut eval);
+
+        for expression in ["2 + 3 * 4", "-2^2", "(1 + 2) * 3", "bass * 2 - 0.5"] {
+            let expected = eval.eval(expression).unwrap();
+            let actual = Program::compile(expression)
+                .unwrap()
+                .execute(eval.context_mut());
+            assert_eq!(expected, actual, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(Program::compile("x = unknown_fn(1)").is_err());
+        assert!(Program::compile("x = (1 + 2").is_err());
+        assert!(Program::compile("x = sin(1, 2)").is_err());
+        assert!(Program::compile("x = 1 $ 2").is_err());
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let mut ctx = MilkContext::new();
+        assert_eq!(Program::compile(" ; ").unwrap().execute(&mut ctx), 0.0);
+    }
+}
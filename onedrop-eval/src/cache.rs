@@ -1,13 +1,41 @@
 //! Expression cache for performance optimization.
+//!
+//! # Concurrency model
+//!
+//! [`ExpressionCache`] is owned by a single thread (normally the main/render
+//! thread) and is the only place where expressions are compiled and inserted.
+//! The compiled map is stored behind an [`Arc`], so [`ExpressionCache::snapshot`]
+//! is a cheap pointer copy that yields a [`SharedExpressionCache`]: an immutable
+//! view that can be cloned into worker threads (e.g. for parallel per-pixel
+//! evaluation) and queried without locking.
+//!
+//! Writes after a snapshot has been taken use copy-on-write, so existing
+//! snapshots never observe partially updated state. Workers should take a new
+//! snapshot once the main thread has compiled the expressions they need.
+//!
+//! The cache is bounded: compiling past capacity evicts the least recently
+//! used expression. Recency is tracked on the shared key, so a cache hit
+//! doesn't allocate.
 
 use evalexpr::Node;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-/// Cache for compiled expressions.
+/// Bounded LRU cache for compiled expressions.
 #[derive(Debug, Clone)]
 pub struct ExpressionCache {
-    /// Cached compiled expressions
-    cache: HashMap<String, Node>,
+    /// Cached compiled expressions (shared with snapshots)
+    cache: Arc<HashMap<Arc<str>, Node>>,
+
+    /// Last-use tick of each cached expression
+    last_used: HashMap<Arc<str>, u64>,
+
+    /// Cached expressions ordered by last use, oldest first
+    recency: BTreeMap<u64, Arc<str>>,
+
+    /// Incremented on every lookup
+    tick: u64,
 
     /// Cache hit count
     hits: usize,
@@ -15,6 +43,9 @@ pub struct ExpressionCache {
     /// Cache miss count
     misses: usize,
 
+    /// Number of expressions evicted to stay within capacity
+    evictions: usize,
+
     /// Maximum cache size
     max_size: usize,
 }
@@ -26,42 +57,83 @@ impl ExpressionCache {
     }
 
     /// Create a new expression cache with specified capacity.
+    ///
+    /// The cache always keeps the most recently compiled expression, so a
+    /// capacity of zero behaves like one.
     pub fn with_capacity(max_size: usize) -> Self {
         Self {
-            cache: HashMap::with_capacity(max_size),
+            cache: Arc::new(HashMap::with_capacity(max_size)),
+            last_used: HashMap::with_capacity(max_size),
+            recency: BTreeMap::new(),
+            tick: 0,
             hits: 0,
             misses: 0,
+            evictions: 0,
             max_size,
         }
     }
 
+    /// Maximum number of cached expressions.
+    pub fn capacity(&self) -> usize {
+        self.max_size
+    }
+
     /// Get a compiled expression from cache, or compile and cache it.
-    pub fn get_or_compile(&mut self, expression: &str) -> Result<Node, evalexpr::EvalexprError> {
+    ///
+    /// A full cache evicts its least recently used expression to make room.
+    pub fn get_or_compile(&mut self, expression: &str) -> Result<&Node, evalexpr::EvalexprError> {
+        self.tick += 1;
+
         // Check cache first
-        if let Some(node) = self.cache.get(expression) {
+        if let Some(previous) = self.last_used.get_mut(expression) {
             self.hits += 1;
-            return Ok(node.clone());
+            if let Some(key) = self.recency.remove(previous) {
+                self.recency.insert(self.tick, key);
+            }
+            *previous = self.tick;
+            return Ok(&self.cache[expression]);
         }
 
         // Cache miss - compile the expression
         self.misses += 1;
         let node = evalexpr::build_operator_tree(expression)?;
 
-        // Add to cache if not full
-        if self.cache.len() < self.max_size {
-            self.cache.insert(expression.to_string(), node.clone());
+        // Make room by dropping the least recently used expression
+        if self.cache.len() >= self.max_size.max(1) {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.last_used.remove(&oldest);
+                Arc::make_mut(&mut self.cache).remove(&oldest);
+                self.evictions += 1;
+            }
         }
 
-        Ok(node)
+        let key: Arc<str> = Arc::from(expression);
+        self.last_used.insert(Arc::clone(&key), self.tick);
+        self.recency.insert(self.tick, Arc::clone(&key));
+        Ok(Arc::make_mut(&mut self.cache).entry(key).or_insert(node))
+    }
+
+    /// Take a read-only snapshot of the compiled expressions.
+    ///
+    /// The snapshot shares storage with this cache and can be sent to other
+    /// threads. Expressions compiled after the snapshot is taken are not visible
+    /// through it.
+    pub fn snapshot(&self) -> SharedExpressionCache {
+        SharedExpressionCache {
+            cache: Arc::clone(&self.cache),
+            hits: Arc::new(AtomicUsize::new(0)),
+            misses: Arc::new(AtomicUsize::new(0)),
+        }
     }
 
     /// Get cache statistics.
     pub fn stats(&self) -> CacheStats {
         CacheStats {
-            size: self.cache.len(),
+            len: self.cache.len(),
             max_size: self.max_size,
             hits: self.hits,
             misses: self.misses,
+            evictions: self.evictions,
             hit_rate: if self.hits + self.misses > 0 {
                 self.hits as f64 / (self.hits + self.misses) as f64
             } else {
@@ -72,9 +144,12 @@ impl ExpressionCache {
 
     /// Clear the cache.
     pub fn clear(&mut self) {
-        self.cache.clear();
+        self.cache = Arc::new(HashMap::with_capacity(self.max_size));
+        self.last_used.clear();
+        self.recency.clear();
         self.hits = 0;
         self.misses = 0;
+        self.evictions = 0;
     }
 
     /// Get the number of cached expressions.
@@ -94,11 +169,75 @@ impl Default for ExpressionCache {
     }
 }
 
+/// Read-only, thread-safe view of an [`ExpressionCache`].
+///
+/// Cloning is cheap; all clones share the same compiled map and hit/miss
+/// counters. Lookups never compile, so a miss means the main thread has not
+/// compiled that expression yet.
+#[derive(Debug, Clone)]
+pub struct SharedExpressionCache {
+    /// Compiled expressions at the time of the snapshot
+    cache: Arc<HashMap<Arc<str>, Node>>,
+
+    /// Cache hit count across all clones
+    hits: Arc<AtomicUsize>,
+
+    /// Cache miss count across all clones
+    misses: Arc<AtomicUsize>,
+}
+
+impl SharedExpressionCache {
+    /// Look up a compiled expression.
+    pub fn get(&self, expression: &str) -> Option<&Node> {
+        let node = self.cache.get(expression);
+        let counter = if node.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        node
+    }
+
+    /// Check whether an expression is present without touching the counters.
+    pub fn contains(&self, expression: &str) -> bool {
+        self.cache.contains_key(expression)
+    }
+
+    /// Get lookup statistics accumulated across all clones of this snapshot.
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        CacheStats {
+            len: self.cache.len(),
+            max_size: self.cache.len(),
+            hits,
+            misses,
+            evictions: 0,
+            hit_rate: if hits + misses > 0 {
+                hits as f64 / (hits + misses) as f64
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Get the number of cached expressions.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Check if the snapshot is empty.
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
 /// Cache statistics.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CacheStats {
-    /// Current cache size
-    pub size: usize,
+    /// Number of cached expressions
+    pub len: usize,
 
     /// Maximum cache size
     pub max_size: usize,
@@ -109,6 +248,9 @@ pub struct CacheStats {
     /// Number of cache misses
     pub misses: usize,
 
+    /// Number of expressions evicted to stay within capacity
+    pub evictions: usize,
+
     /// Hit rate (0.0 to 1.0)
     pub hit_rate: f64,
 }
@@ -176,4 +318,92 @@ mod tests {
         let result = cache.get_or_compile("invalid expression +++");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = ExpressionCache::with_capacity(3);
+        assert_eq!(cache.capacity(), 3);
+
+        for expr in ["x = 1", "x = 2", "x = 3"] {
+            cache.get_or_compile(expr).unwrap();
+        }
+        // Use the oldest entry so "x = 2" becomes least recently used
+        cache.get_or_compile("x = 1").unwrap();
+        cache.get_or_compile("x = 4").unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.len, 3);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 4);
+        assert_eq!(stats.evictions, 1);
+
+        let snapshot = cache.snapshot();
+        assert!(snapshot.contains("x = 1"));
+        assert!(!snapshot.contains("x = 2"));
+        assert!(snapshot.contains("x = 3"));
+        assert!(snapshot.contains("x = 4"));
+
+        // Filling further keeps evicting in least-recently-used order
+        cache.get_or_compile("x = 5").unwrap();
+        cache.get_or_compile("x = 6").unwrap();
+        let snapshot = cache.snapshot();
+        assert_eq!(cache.stats().evictions, 3);
+        assert!(!snapshot.contains("x = 1"));
+        assert!(!snapshot.contains("x = 3"));
+        assert!(snapshot.contains("x = 4"));
+
+        // Snapshots taken before an eviction still see the evicted entry
+        let before = cache.snapshot();
+        cache.get_or_compile("x = 7").unwrap();
+        assert!(before.contains("x = 4"));
+        assert!(!cache.snapshot().contains("x = 4"));
+    }
+
+    #[test]
+    fn test_snapshot_shared_across_threads() {
+        let mut cache = ExpressionCache::new();
+        let equations = ["zoom = zoom + 0.1 * rad", "rot = rot * 0.5", "dx = x - 0.5"];
+
+        for eq in &equations {
+            cache.get_or_compile(eq).unwrap();
+        }
+        assert_eq!(cache.stats().misses, equations.len());
+
+        let snapshot = cache.snapshot();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let snapshot = snapshot.clone();
+                std::thread::spawn(move || {
+                    for eq in &equations {
+                        assert!(snapshot.get(eq).is_some());
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stats = snapshot.stats();
+        assert_eq!(stats.hits, 4 * equations.len());
+        assert_eq!(stats.misses, 0);
+
+        // Nothing was recompiled on the main thread either
+        assert_eq!(cache.stats().misses, equations.len());
+        assert_eq!(cache.len(), equations.len());
+    }
+
+    #[test]
+    fn test_snapshot_isolated_from_later_writes() {
+        let mut cache = ExpressionCache::new();
+        cache.get_or_compile("1 + 1").unwrap();
+
+        let snapshot = cache.snapshot();
+        cache.get_or_compile("2 + 2").unwrap();
+
+        assert_eq!(snapshot.len(), 1);
+        assert!(snapshot.get("2 + 2").is_none());
+        assert_eq!(snapshot.stats().misses, 1);
+        assert_eq!(cache.len(), 2);
+    }
 }
@@ -1,10 +1,33 @@
 //! Execution context for Milkdrop expressions.
 
+use crate::megabuf::{MegaBuf, register_megabuf_functions};
+use crate::rng::MilkRng;
+use crate::spectrum::{Spectrum, register_spectrum_function};
 use evalexpr::{Context, ContextWithMutableVariables, HashMapContext, Value};
 use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Motion variables that per-pixel equations may override per pixel.
+///
+/// `sx`/`sy` are the per-frame stretch. Per-pixel equations that reuse them
+/// as a scale only change their own pixel's copy.
+const MOTION_VARS: [&str; 10] = [
+    "zoom", "zoomexp", "rot", "warp", "cx", "cy", "dx", "dy", "sx", "sy",
+];
+
+/// Names of q1-q64, built once so snapshots don't format them per pixel.
+static Q_NAMES: LazyLock<[String; 64]> =
+    LazyLock::new(|| std::array::from_fn(|i| format!("q{}", i + 1)));
+
+/// Saved q1-q64 and motion variables, taken after per-frame evaluation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextSnapshot {
+    q_vars: [f64; 64],
+    motion: [f64; MOTION_VARS.len()],
+}
 
 /// Execution context containing all Milkdrop variables.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct MilkContext {
     /// Internal evalexpr context
     context: HashMapContext,
@@ -14,6 +37,18 @@ pub struct MilkContext {
 
     /// Custom variables defined in equations
     custom_vars: HashMap<String, f64>,
+
+    /// Generator behind `rand()` / `rand_int()`
+    rng: MilkRng,
+
+    /// Per-preset `megabuf` scratch memory
+    megabuf: MegaBuf,
+
+    /// Process-wide `gmegabuf` scratch memory
+    gmegabuf: MegaBuf,
+
+    /// Audio magnitude spectrum read by `spectrum(i)`
+    spectrum: Spectrum,
 }
 
 impl MilkContext {
@@ -27,13 +62,58 @@ impl MilkContext {
         // Register all math functions
         crate::math_functions::register_math_functions(&mut context);
 
+        // Time-seeded until `set_seed` is called
+        let rng = MilkRng::from_time();
+        crate::math_functions::register_rand_functions(&mut context, &rng);
+
+        let megabuf = MegaBuf::new();
+        let gmegabuf = MegaBuf::global();
+        register_megabuf_functions(&mut context, &megabuf, &gmegabuf);
+
+        let spectrum = Spectrum::new();
+        register_spectrum_function(&mut context, &spectrum);
+
         Self {
             context,
             q_vars: [0.0; 64],
             custom_vars: HashMap::new(),
+            rng,
+            megabuf,
+            gmegabuf,
+            spectrum,
         }
     }
 
+    /// Get the per-preset `megabuf`.
+    pub fn megabuf(&self) -> &MegaBuf {
+        &self.megabuf
+    }
+
+    /// Get the shared `gmegabuf`.
+    pub fn gmegabuf(&self) -> &MegaBuf {
+        &self.gmegabuf
+    }
+
+    /// Get the spectrum read by `spectrum(i)`.
+    pub fn spectrum(&self) -> &Spectrum {
+        &self.spectrum
+    }
+
+    /// Set the magnitudes read by `spectrum(i)`.
+    pub fn set_spectrum(&mut self, magnitudes: &[f32]) {
+        self.spectrum.set(magnitudes);
+    }
+
+    /// Get the `rand()` generator.
+    pub fn rng(&self) -> &MilkRng {
+        &self.rng
+    }
+
+    /// Seed the `rand()` generator for reproducible evaluation.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.set_seed(seed);
+    }
+
     /// Initialize default values for built-in variables.
     fn init_defaults(ctx: &mut HashMapContext) {
         // Time variables
@@ -240,6 +320,31 @@ impl MilkContext {
         ) || (name.starts_with('q') && name.len() > 1)
     }
 
+    /// Capture q1-q64 and the motion variables.
+    pub fn snapshot(&self) -> ContextSnapshot {
+        let mut q_vars = [0.0; 64];
+        for (q, name) in q_vars.iter_mut().zip(Q_NAMES.iter()) {
+            *q = self.get(name).unwrap_or(0.0);
+        }
+
+        let mut motion = [0.0; MOTION_VARS.len()];
+        for (value, name) in motion.iter_mut().zip(MOTION_VARS) {
+            *value = self.get(name).unwrap_or(0.0);
+        }
+
+        ContextSnapshot { q_vars, motion }
+    }
+
+    /// Restore variables captured by [`snapshot`](Self::snapshot).
+    pub fn restore(&mut self, snapshot: &ContextSnapshot) {
+        for (name, value) in Q_NAMES.iter().zip(snapshot.q_vars) {
+            self.set(name, value);
+        }
+        for (name, value) in MOTION_VARS.iter().zip(snapshot.motion) {
+            self.set(name, value);
+        }
+    }
+
     /// Get the internal evalexpr context.
     pub fn inner(&self) -> &HashMapContext {
         &self.context
@@ -296,6 +401,28 @@ impl MilkContext {
     }
 }
 
+impl Clone for MilkContext {
+    fn clone(&self) -> Self {
+        // Give the clone its own generator and megabuf so the two don't
+        // interleave draws or writes; gmegabuf and the spectrum stay shared
+        let mut context = self.context.clone();
+        let rng = self.rng.fork();
+        crate::math_functions::register_rand_functions(&mut context, &rng);
+        let megabuf = self.megabuf.fork();
+        register_megabuf_functions(&mut context, &megabuf, &self.gmegabuf);
+
+        Self {
+            context,
+            q_vars: self.q_vars,
+            custom_vars: self.custom_vars.clone(),
+            rng,
+            megabuf,
+            gmegabuf: self.gmegabuf.clone(),
+            spectrum: self.spectrum.clone(),
+        }
+    }
+}
+
 impl Default for MilkContext {
     fn default() -> Self {
         Self::new()
@@ -351,4 +478,22 @@ mod tests {
         assert_eq!(ctx.custom_vars().get("my_var"), Some(&123.0));
         assert_eq!(ctx.custom_vars().get("another_var"), Some(&456.0));
     }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut ctx = MilkContext::new();
+        ctx.set("q3", 2.0);
+        ctx.set("zoom", 1.5);
+        let snapshot = ctx.snapshot();
+
+        ctx.set("q3", 9.0);
+        ctx.set("zoom", 0.5);
+        ctx.set("bass", 0.7);
+        ctx.restore(&snapshot);
+
+        assert_eq!(ctx.get("q3"), Some(2.0));
+        assert_eq!(ctx.get("zoom"), Some(1.5));
+        // Only q and motion variables are restored
+        assert_eq!(ctx.get("bass"), Some(0.7));
+    }
 }
@@ -25,6 +25,24 @@ pub enum EvalError {
 
     /// Generic evaluation error
     EvalFailed(String),
+
+    /// Error in an equation read from a known preset line
+    AtLine {
+        section: String,
+        line: usize,
+        source: Box<EvalError>,
+    },
+}
+
+impl EvalError {
+    /// Attach the preset section and source line the failing equation came from.
+    pub fn at_line(self, section: &str, line: usize) -> Self {
+        EvalError::AtLine {
+            section: section.to_string(),
+            line,
+            source: Box::new(self),
+        }
+    }
 }
 
 impl fmt::Display for EvalError {
@@ -48,11 +66,25 @@ impl fmt::Display for EvalError {
             EvalError::EvalFailed(msg) => {
                 write!(f, "Evaluation failed: {}", msg)
             }
+            EvalError::AtLine {
+                section,
+                line,
+                source,
+            } => {
+                write!(f, "{} line {}: {}", section, line, source)
+            }
         }
     }
 }
 
-impl std::error::Error for EvalError {}
+impl std::error::Error for EvalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EvalError::AtLine { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<evalexpr::EvalexprError> for EvalError {
     fn from(err: evalexpr::EvalexprError) -> Self {
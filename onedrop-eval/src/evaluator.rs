@@ -1,56 +1,63 @@
 //! Evaluator for Milkdrop expressions.
 
-use crate::context::MilkContext;
+use crate::bytecode::Program;
+use crate::cache::ExpressionCache;
+use crate::context::{ContextSnapshot, MilkContext};
 use crate::error::{EvalError, Result};
-use evalexpr::{Node, eval_with_context_mut};
+use evalexpr::Node;
 use regex::Regex;
+use std::collections::HashMap;
 use std::sync::LazyLock;
 
 // Pre-compiled regex patterns for performance
 static VAR_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\b([a-zA-Z_][a-zA-Z0-9_]*)\b").unwrap());
 
+// The literal must end at a non-word character so the mantissa of `1e-3`
+// or the `0` of a hex literal isn't taken for an integer
 static ASSIGNMENT_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"(\w+)\s*=\s*(-?\d+)([^\d\.]|$)").unwrap());
+    LazyLock::new(|| Regex::new(r"(\w+)\s*=\s*(-?\d+)([^\w\.]|$)").unwrap());
+
+// `0x1F`, or ns-eel's `$x1F`
+static HEX_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:\b0[xX]|\$[xX])([0-9a-fA-F]+)\b").unwrap());
 
 static IF_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bif\s*\(").unwrap());
 
 /// Maximum expression length to prevent DoS attacks
 const MAX_EXPRESSION_LENGTH: usize = 100_000;
 
-/// Evaluator for Milkdrop expressions.
-pub struct MilkEvaluator {
-    /// Execution context
-    context: MilkContext,
-
-    /// Compiled expressions cache
-    compiled_cache: Vec<(String, Node)>,
-}
+/// Maximum number of cached preprocessed expressions before the cache is reset,
+/// and capacity of the compiled expression cache
+const MAX_PREPROCESSED_ENTRIES: usize = 4096;
 
-impl MilkEvaluator {
-    /// Create a new evaluator.
-    pub fn new() -> Self {
-        Self {
-            context: MilkContext::new(),
-            compiled_cache: Vec::new(),
-        }
-    }
+/// Result of the regex preprocessing pass for one expression.
+#[derive(Debug, Clone)]
+struct Preprocessed {
+    /// Expression rewritten for evalexpr
+    processed: String,
 
-    /// Get a reference to the context.
-    pub fn context(&self) -> &MilkContext {
-        &self.context
-    }
+    /// Variables referenced by the expression (functions and keywords excluded)
+    variables: Vec<String>,
+}
 
-    /// Get a mutable reference to the context.
-    pub fn context_mut(&mut self) -> &mut MilkContext {
-        &mut self.context
-    }
+impl Preprocessed {
+    fn new(expr: &str) -> Self {
+        // `megabuf(i) = v` is a buffer write, not a variable assignment
+        let rewritten = crate::megabuf::rewrite_assignment(expr);
+        let expr = rewritten.as_deref().unwrap_or(expr);
 
-    /// Pre-process expression to handle auto-initialization and type conversion.
-    fn preprocess_expression(&mut self, expression: &str) -> String {
-        let expr = expression.trim();
+        // Hex literals become floats, so `$xff / 2` doesn't divide integers
+        let expr = HEX_REGEX.replace_all(expr, |cap: &regex::Captures| {
+            match u64::from_str_radix(&cap[1], 16) {
+                Ok(value) => format!("{}.0", value),
+                Err(_) => cap[0].to_string(),
+            }
+        });
+        let expr = expr.as_ref();
 
         // Extract variable names from the expression using pre-compiled regex
+        let mut variables: Vec<String> = Vec::new();
         for cap in VAR_REGEX.captures_iter(expr) {
             let var_name = &cap[1];
 
@@ -73,6 +80,12 @@ impl MilkEvaluator {
                     | "ceil"
                     | "round"
                     | "rand"
+                    | "rand_int"
+                    | "megabuf"
+                    | "gmegabuf"
+                    | "megabuf_set"
+                    | "gmegabuf_set"
+                    | "spectrum"
                     | "above"
                     | "below"
                     | "equal"
@@ -99,26 +112,174 @@ impl MilkEvaluator {
                 continue;
             }
 
-            // Auto-initialize undefined variables to 0
-            if self.context.get(var_name).is_none() {
-                self.context.set(var_name, 0.0);
+            if !variables.iter().any(|v| v == var_name) {
+                variables.push(var_name.to_string());
             }
         }
 
         // Convert integer literals to floats in assignments
         // e.g., "zoom = 1" -> "zoom = 1.0"
-        let mut result = ASSIGNMENT_REGEX
+        let mut processed = ASSIGNMENT_REGEX
             .replace_all(expr, "$1 = $2.0$3")
             .to_string();
 
         // Replace if( or if ( with milkif( to use our custom if function
         // This allows Float conditions (0.0 = false, non-zero = true)
-        result = IF_REGEX.replace_all(&result, "milkif(").to_string();
+        processed = IF_REGEX.replace_all(&processed, "milkif(").to_string();
 
-        result
+        Self {
+            processed,
+            variables,
+        }
     }
+}
 
-    /// Evaluate a single expression.
+/// Split an equation line on top-level `;`, skipping empty statements.
+///
+/// Semicolons inside parentheses (function arguments) or string literals are
+/// left alone.
+pub(crate) fn split_statements(line: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut start = 0;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth = depth.saturating_sub(1),
+            ';' if !in_string && depth == 0 => {
+                statements.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&line[start..]);
+
+    statements.retain(|s| !s.trim().is_empty());
+    statements
+}
+
+/// Motion variables produced by the per-pixel equations at one mesh vertex.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MeshVertexOut {
+    /// Vertex position (0-1)
+    pub x: f64,
+    pub y: f64,
+
+    pub zoom: f64,
+    pub zoomexp: f64,
+    pub rot: f64,
+    pub warp: f64,
+    pub cx: f64,
+    pub cy: f64,
+    pub dx: f64,
+    pub dy: f64,
+    pub sx: f64,
+    pub sy: f64,
+}
+
+/// A per-pixel statement compiled for [`MilkEvaluator::eval_mesh`].
+enum MeshStatement {
+    /// Bytecode run by the VM
+    Program(Program),
+    /// Preprocessed text of an evalexpr tree in the compiled cache
+    Tree(String),
+}
+
+/// A [`MeshStatement`] resolved for the rows to run.
+enum MeshStep<'a> {
+    Program(&'a Program),
+    /// Tree and the statement it was compiled from, for errors
+    Tree(&'a Node, &'a str),
+}
+
+/// Evaluator for Milkdrop expressions.
+pub struct MilkEvaluator {
+    /// Execution context
+    context: MilkContext,
+
+    /// Compiled expressions keyed by the preprocessed source text
+    compiled_cache: ExpressionCache,
+
+    /// Preprocessed expressions keyed by the cleaned source text
+    preprocessed: HashMap<String, Preprocessed>,
+
+    /// Per-pixel bytecode keyed by the cleaned statement text; `None` when
+    /// the VM can't compile a statement and evalexpr runs it instead
+    programs: HashMap<String, Option<Program>>,
+
+    /// Seed applied via `set_seed`, reapplied on `reset`
+    seed: Option<u64>,
+
+    /// q and motion state after the last per-frame pass, restored before each pixel
+    per_frame_snapshot: Option<ContextSnapshot>,
+
+    /// Per-pixel equations ran since the last per-frame pass
+    in_pixel_pass: bool,
+}
+
+impl MilkEvaluator {
+    /// Create a new evaluator.
+    pub fn new() -> Self {
+        Self {
+            context: MilkContext::new(),
+            compiled_cache: ExpressionCache::with_capacity(MAX_PREPROCESSED_ENTRIES),
+            preprocessed: HashMap::new(),
+            programs: HashMap::new(),
+            seed: None,
+            per_frame_snapshot: None,
+            in_pixel_pass: false,
+        }
+    }
+
+    /// Seed `rand()` / `rand_int()` so evaluation is reproducible.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.context.set_seed(seed);
+    }
+
+    /// Get a reference to the context.
+    pub fn context(&self) -> &MilkContext {
+        &self.context
+    }
+
+    /// Get a mutable reference to the context.
+    pub fn context_mut(&mut self) -> &mut MilkContext {
+        &mut self.context
+    }
+
+    /// Pre-process expression to handle auto-initialization and type conversion.
+    ///
+    /// The regex work is done once per distinct expression and cached; later
+    /// calls only re-check that the referenced variables exist.
+    fn preprocess_expression(&mut self, expr: &str) -> String {
+        if !self.preprocessed.contains_key(expr) {
+            if self.preprocessed.len() >= MAX_PREPROCESSED_ENTRIES {
+                self.preprocessed.clear();
+            }
+            self.preprocessed
+                .insert(expr.to_string(), Preprocessed::new(expr));
+        }
+        let entry = &self.preprocessed[expr];
+
+        // Auto-initialize undefined variables to 0
+        for var_name in &entry.variables {
+            if self.context.get(var_name).is_none() {
+                self.context.set(var_name, 0.0);
+            }
+        }
+
+        entry.processed.clone()
+    }
+
+    /// Evaluate an equation line.
+    ///
+    /// Lines may hold several `;`-separated statements
+    /// (e.g. `q1=bass; q2=treb; zoom=1+0.1*q1;`); they are evaluated in order
+    /// against the same context and the value of the last one is returned.
     pub fn eval(&mut self, expression: &str) -> Result<f64> {
         // Security check: limit expression length to prevent DoS
         if expression.len() > MAX_EXPRESSION_LENGTH {
@@ -132,8 +293,17 @@ impl MilkEvaluator {
             });
         }
 
-        // Clean the expression (remove trailing semicolon, trim whitespace)
-        let expr = expression.trim().trim_end_matches(';').trim();
+        let mut result = 0.0;
+        for statement in split_statements(expression) {
+            result = self.eval_statement(statement)?;
+        }
+        Ok(result)
+    }
+
+    /// Evaluate a single statement.
+    fn eval_statement(&mut self, expression: &str) -> Result<f64> {
+        // Clean the expression (trim whitespace)
+        let expr = expression.trim();
 
         if expr.is_empty() {
             return Ok(0.0);
@@ -142,37 +312,67 @@ impl MilkEvaluator {
         // Pre-process to handle auto-initialization and type conversion
         let processed_expr = self.preprocess_expression(expr);
 
-        // Evaluate with context
-        match eval_with_context_mut(&processed_expr, self.context.inner_mut()) {
-            Ok(value) => {
-                // Convert result to f64
-                match value {
-                    evalexpr::Value::Float(f) => Ok(f),
-                    evalexpr::Value::Int(i) => Ok(i as f64),
-                    evalexpr::Value::Boolean(b) => Ok(if b { 1.0 } else { 0.0 }),
-                    evalexpr::Value::Empty => Ok(0.0), // Assignments return Empty
-                    _ => Err(EvalError::TypeError {
-                        expected: "number".to_string(),
-                        got: format!("{:?}", value),
-                    }),
-                }
-            }
-            Err(e) => Err(EvalError::SyntaxError {
+        // Compile once, then reuse the tree on every later frame
+        let node = self
+            .compiled_cache
+            .get_or_compile(&processed_expr)
+            .map_err(|e| EvalError::SyntaxError {
                 expression: expr.to_string(),
                 reason: e.to_string(),
-            }),
-        }
+            })?;
+
+        eval_node(node, &mut self.context, expr)
     }
 
     /// Evaluate multiple expressions (per-frame equations).
+    ///
+    /// The resulting q1-q64 and motion variables become the baseline that
+    /// [`eval_per_pixel`](Self::eval_per_pixel) restores before every pixel.
+    ///
+    /// As in Milkdrop, per-pixel equations work on copies of these
+    /// variables: the motion variables (including the stretch `sx`/`sy`)
+    /// seen here are the ones the last per-frame pass left, never a value
+    /// written by the last pixel.
     pub fn eval_per_frame(&mut self, equations: &[String]) -> Result<()> {
-        for equation in equations {
-            self.eval(equation)?;
+        self.eval_per_frame_with_lines("per_frame", equations, &[])
+    }
+
+    /// Like [`eval_per_frame`](Self::eval_per_frame), but an error names the
+    /// source line of the failing equation, taken from `lines` (parallel to
+    /// `equations`, e.g. a preset's `source_lines`).
+    pub fn eval_per_frame_with_lines(
+        &mut self,
+        section: &str,
+        equations: &[String],
+        lines: &[usize],
+    ) -> Result<()> {
+        self.end_pixel_pass();
+        for (i, equation) in equations.iter().enumerate() {
+            self.eval(equation).map_err(|e| match lines.get(i) {
+                Some(&line) => e.at_line(section, line),
+                None => e,
+            })?;
         }
+        self.per_frame_snapshot = Some(self.context.snapshot());
         Ok(())
     }
 
+    /// Compile a statement to bytecode once, caching the outcome in
+    /// `programs`.
+    fn compile_program(&mut self, statement: &str) {
+        if !self.programs.contains_key(statement) {
+            if self.programs.len() >= MAX_PREPROCESSED_ENTRIES {
+                self.programs.clear();
+            }
+            let program = Program::compile(statement).ok();
+            self.programs.insert(statement.to_string(), program);
+        }
+    }
+
     /// Evaluate per-pixel equations for a single pixel.
+    ///
+    /// Statements run as bytecode; any the VM can't compile fall back to
+    /// evalexpr.
     pub fn eval_per_pixel(
         &mut self,
         x: f64,
@@ -181,17 +381,179 @@ impl MilkEvaluator {
         ang: f64,
         equations: &[String],
     ) -> Result<()> {
+        // Discard writes from the previous pixel
+        if let Some(snapshot) = &self.per_frame_snapshot {
+            self.context.restore(snapshot);
+        }
+        self.in_pixel_pass = true;
+
         // Set pixel position
         self.context.set_pixel(x, y, rad, ang);
 
         // Evaluate all per-pixel equations
         for equation in equations {
-            self.eval(equation)?;
+            for statement in split_statements(equation) {
+                let statement = statement.trim();
+                if statement.is_empty() {
+                    continue;
+                }
+                self.compile_program(statement);
+                match &self.programs[statement] {
+                    Some(program) => {
+                        program.execute(&mut self.context);
+                    }
+                    None => {
+                        self.eval_statement(statement)?;
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Evaluate per-pixel equations over a `width`×`height` vertex mesh.
+    ///
+    /// Every row starts from its own copy of the per-frame state and its
+    /// pixels run in order, so a row never sees writes from another row.
+    /// Statements run as bytecode, falling back to evalexpr for any the VM
+    /// can't compile. Rows share the programs and the evalexpr trees (through
+    /// a [`SharedExpressionCache`] snapshot) and only copy the variable
+    /// context. With the `rayon`
+    /// feature rows are evaluated in parallel; the output is the same
+    /// either way. Vertices are returned row by row.
+    ///
+    /// [`SharedExpressionCache`]: crate::cache::SharedExpressionCache
+    pub fn eval_mesh(
+        &mut self,
+        width: usize,
+        height: usize,
+        equations: &[String],
+    ) -> Result<Vec<MeshVertexOut>> {
+        // Compile up front. Statements the VM rejects are preprocessed for
+        // evalexpr, which also auto-initializes the variables they use, so
+        // row contexts start complete
+        let mut statements = Vec::new();
+        for equation in equations {
+            for statement in split_statements(equation) {
+                let statement = statement.trim();
+                if statement.is_empty() {
+                    continue;
+                }
+                self.compile_program(statement);
+                let compiled = match &self.programs[statement] {
+                    Some(program) => MeshStatement::Program(program.clone()),
+                    None => {
+                        let processed = self.preprocess_expression(statement);
+                        self.compiled_cache
+                            .get_or_compile(&processed)
+                            .map_err(|e| EvalError::SyntaxError {
+                                expression: statement.to_string(),
+                                reason: e.to_string(),
+                            })?;
+                        MeshStatement::Tree(processed)
+                    }
+                };
+                statements.push((statement, compiled));
+            }
+        }
+        let trees = self.compiled_cache.snapshot();
+        let steps = statements
+            .iter()
+            .map(|(statement, compiled)| match compiled {
+                MeshStatement::Program(program) => Ok(MeshStep::Program(program)),
+                MeshStatement::Tree(processed) => trees
+                    .get(processed)
+                    .map(|node| MeshStep::Tree(node, statement))
+                    .ok_or_else(|| EvalError::SyntaxError {
+                        expression: statement.to_string(),
+                        reason: "expression was evicted from the compiled cache".to_string(),
+                    }),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let snapshot = match &self.per_frame_snapshot {
+            Some(snapshot) => snapshot.clone(),
+            None => self.context.snapshot(),
+        };
+
+        let context = &self.context;
+        let eval_row = |j: usize| -> Result<Vec<MeshVertexOut>> {
+            let mut context = context.clone();
+            let y = j as f64 / height.saturating_sub(1).max(1) as f64;
+            (0..width)
+                .map(|i| {
+                    let x = i as f64 / width.saturating_sub(1).max(1) as f64;
+                    let (cx, cy) = (x - 0.5, y - 0.5);
+                    context.restore(&snapshot);
+                    context.set_pixel(x, y, (cx * cx + cy * cy).sqrt(), cy.atan2(cx));
+                    for step in &steps {
+                        match *step {
+                            MeshStep::Program(program) => {
+                                program.execute(&mut context);
+                            }
+                            MeshStep::Tree(node, statement) => {
+                                eval_node(node, &mut context, statement)?;
+                            }
+                        }
+                    }
+                    Ok(mesh_vertex(&context, x, y))
+                })
+                .collect()
+        };
+
+        #[cfg(feature = "rayon")]
+        let rows: Vec<Vec<MeshVertexOut>> = {
+            use rayon::prelude::*;
+            (0..height)
+                .into_par_iter()
+                .map(eval_row)
+                .collect::<Result<_>>()?
+        };
+        #[cfg(not(feature = "rayon"))]
+        let rows: Vec<Vec<MeshVertexOut>> = (0..height).map(eval_row).collect::<Result<_>>()?;
+
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    /// Evaluate precompiled per-pixel programs for a single pixel.
+    ///
+    /// Equivalent to [`eval_per_pixel`](Self::eval_per_pixel) but runs
+    /// bytecode compiled with [`Program::compile`] instead of evalexpr.
+    pub fn eval_per_pixel_bytecode(
+        &mut self,
+        x: f64,
+        y: f64,
+        rad: f64,
+        ang: f64,
+        programs: &[Program],
+    ) {
+        if let Some(snapshot) = &self.per_frame_snapshot {
+            self.context.restore(snapshot);
+        }
+        self.in_pixel_pass = true;
+
+        self.context.set_pixel(x, y, rad, ang);
+
+        for program in programs {
+            program.execute(&mut self.context);
+        }
+    }
+
+    /// Number of compiled expression trees cached for reuse.
+    pub fn compiled_cache_len(&self) -> usize {
+        self.compiled_cache.len()
+    }
+
+    /// Drop the last pixel's writes to q and motion variables.
+    fn end_pixel_pass(&mut self) {
+        if self.in_pixel_pass {
+            if let Some(snapshot) = &self.per_frame_snapshot {
+                self.context.restore(snapshot);
+            }
+            self.in_pixel_pass = false;
+        }
+    }
+
     /// Parse an assignment expression and update context.
     /// Returns the assigned value.
     pub fn eval_assignment(&mut self, expression: &str) -> Result<f64> {
@@ -209,7 +571,14 @@ impl MilkEvaluator {
     /// Reset the evaluator to initial state.
     pub fn reset(&mut self) {
         self.context = MilkContext::new();
+        if let Some(seed) = self.seed {
+            self.context.set_seed(seed);
+        }
         self.compiled_cache.clear();
+        self.preprocessed.clear();
+        self.programs.clear();
+        self.per_frame_snapshot = None;
+        self.in_pixel_pass = false;
     }
 }
 
@@ -219,11 +588,79 @@ impl Default for MilkEvaluator {
     }
 }
 
+/// Evaluate a compiled statement against `context`. `expr` names the
+/// statement in errors.
+fn eval_node(node: &Node, context: &mut MilkContext, expr: &str) -> Result<f64> {
+    match node.eval_with_context_mut(context.inner_mut()) {
+        Ok(value) => {
+            // Convert result to f64
+            match value {
+                evalexpr::Value::Float(f) => Ok(f),
+                evalexpr::Value::Int(i) => Ok(i as f64),
+                evalexpr::Value::Boolean(b) => Ok(if b { 1.0 } else { 0.0 }),
+                evalexpr::Value::Empty => Ok(0.0), // Assignments return Empty
+                _ => Err(EvalError::TypeError {
+                    expected: "number".to_string(),
+                    got: format!("{:?}", value),
+                }),
+            }
+        }
+        Err(e) => Err(EvalError::SyntaxError {
+            expression: expr.to_string(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+/// Read the motion variables left by the per-pixel equations.
+fn mesh_vertex(context: &MilkContext, x: f64, y: f64) -> MeshVertexOut {
+    let get = |name| context.get(name).unwrap_or(0.0);
+    MeshVertexOut {
+        x,
+        y,
+        zoom: get("zoom"),
+        zoomexp: get("zoomexp"),
+        rot: get("rot"),
+        warp: get("warp"),
+        cx: get("cx"),
+        cy: get("cy"),
+        dx: get("dx"),
+        dy: get("dy"),
+        sx: get("sx"),
+        sy: get("sy"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use approx::assert_relative_eq;
 
+    #[test]
+    fn test_scientific_notation() {
+        let mut eval = MilkEvaluator::new();
+        eval.eval("x = 1e-3").unwrap();
+        assert_relative_eq!(eval.context().get("x").unwrap(), 0.001);
+        eval.eval("y = 2.5E4; z = -1e+2").unwrap();
+        assert_relative_eq!(eval.context().get("y").unwrap(), 25000.0);
+        assert_relative_eq!(eval.context().get("z").unwrap(), -100.0);
+
+        // The integer-to-float rewrite leaves the mantissa alone
+        assert_eq!(Preprocessed::new("x = 1e-3").processed, "x = 1e-3");
+        assert_eq!(Preprocessed::new("y = 2.5E4").processed, "y = 2.5E4");
+        assert_eq!(Preprocessed::new("z = 3;").processed, "z = 3.0;");
+    }
+
+    #[test]
+    fn test_hex_literals() {
+        let mut eval = MilkEvaluator::new();
+        eval.eval("x = 0x1F").unwrap();
+        assert_relative_eq!(eval.context().get("x").unwrap(), 31.0);
+        eval.eval("y = $xff / 2").unwrap();
+        assert_relative_eq!(eval.context().get("y").unwrap(), 127.5);
+        assert!(eval.context().get("xff").is_none());
+    }
+
     #[test]
     fn test_simple_expression() {
         let mut eval = MilkEvaluator::new();
@@ -245,6 +682,15 @@ mod tests {
         assert_relative_eq!(result, 4.0);
     }
 
+    #[test]
+    fn test_min_max_assignment() {
+        let mut eval = MilkEvaluator::new();
+
+        // Integer arguments still assign a float
+        eval.eval("zoom = min(2, 3) + floor(2.7)").unwrap();
+        assert_relative_eq!(eval.context().get_var("zoom").unwrap(), 4.0);
+    }
+
     #[test]
     fn test_variable_assignment() {
         let mut eval = MilkEvaluator::new();
@@ -313,4 +759,268 @@ mod tests {
         assert_relative_eq!(eval.context().get_var("q1").unwrap(), 42.0);
         assert_relative_eq!(eval.context().get_var("q2").unwrap(), 84.0);
     }
+
+    #[test]
+    fn test_seeded_rand_is_reproducible() {
+        let sample = |eval: &mut MilkEvaluator| -> Vec<f64> {
+            (0..10)
+                .map(|_| eval.eval("rand(100) + rand_int(8)").unwrap())
+                .collect()
+        };
+
+        let mut a = MilkEvaluator::new();
+        let mut b = MilkEvaluator::new();
+        a.set_seed(99);
+        b.set_seed(99);
+        let first = sample(&mut a);
+        assert_eq!(first, sample(&mut b));
+
+        // Reset keeps the seed and restarts the sequence
+        a.reset();
+        assert_eq!(first, sample(&mut a));
+
+        b.set_seed(100);
+        assert_ne!(first, sample(&mut b));
+    }
+
+    #[test]
+    fn test_preprocessing_is_cached() {
+        let mut eval = MilkEvaluator::new();
+
+        eval.eval("counter = counter + 1").unwrap();
+        eval.eval("counter = counter + 1").unwrap();
+        assert_eq!(eval.preprocessed.len(), 1);
+        assert_relative_eq!(eval.context().get_var("counter").unwrap(), 2.0);
+
+        // Variables are still auto-initialized after a context reset
+        eval.reset();
+        eval.eval("counter = counter + 1").unwrap();
+        assert_relative_eq!(eval.context().get_var("counter").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_multi_statement_line() {
+        let mut eval = MilkEvaluator::new();
+        eval.context_mut().set_audio(0.5, 0.0, 0.25);
+
+        let result = eval.eval("q1=bass; q2=treb; zoom=1+0.1*q1;").unwrap();
+        assert_eq!(result, 0.0); // last statement is an assignment
+
+        assert_relative_eq!(eval.context().get_var("q1").unwrap(), 0.5);
+        assert_relative_eq!(eval.context().get_var("q2").unwrap(), 0.25);
+        assert_relative_eq!(eval.context().get_var("zoom").unwrap(), 1.05);
+
+        // The last statement's value is returned
+        assert_relative_eq!(eval.eval("a = 2; a * 3").unwrap(), 6.0);
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(split_statements("a=1; b=2;"), vec!["a=1", " b=2"]);
+        assert_eq!(split_statements(";;"), Vec::<&str>::new());
+        assert_eq!(
+            split_statements("x = f(a; b); y = \"c;d\""),
+            vec!["x = f(a; b)", " y = \"c;d\""]
+        );
+    }
+
+    #[test]
+    fn test_megabuf_persists_across_frames() {
+        let mut eval = MilkEvaluator::new();
+        let frame = vec![
+            "megabuf(7) = megabuf(7) + 1".to_string(),
+            "q1 = megabuf(7)".to_string(),
+        ];
+
+        for _ in 0..3 {
+            eval.eval_per_frame(&frame).unwrap();
+        }
+        assert_relative_eq!(eval.context().get_var("q1").unwrap(), 3.0);
+        assert_relative_eq!(eval.eval("megabuf(8)").unwrap(), 0.0);
+        assert!(eval.context().get("megabuf").is_none());
+
+        // gmegabuf is shared between evaluators, megabuf is not
+        let mut other = MilkEvaluator::new();
+        eval.eval("gmegabuf(90001) = 42").unwrap();
+        assert_relative_eq!(other.eval("gmegabuf(90001)").unwrap(), 42.0);
+        assert_relative_eq!(other.eval("megabuf(7)").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_per_pixel_writes_do_not_leak() {
+        let mut eval = MilkEvaluator::new();
+        eval.eval_per_frame(&["zoom = 1.5".to_string(), "q1 = 2".to_string()])
+            .unwrap();
+
+        let per_pixel = vec![
+            "zoom = zoom + 0.1 * x".to_string(),
+            "q1 = q1 + 5".to_string(),
+        ];
+
+        // Pixel A
+        eval.eval_per_pixel(1.0, 0.0, 0.5, 0.0, &per_pixel).unwrap();
+        assert_relative_eq!(eval.context().get_var("zoom").unwrap(), 1.6);
+        assert_relative_eq!(eval.context().get_var("q1").unwrap(), 7.0);
+
+        // Pixel B starts from the per-frame values, not pixel A's writes
+        eval.eval_per_pixel(0.0, 0.0, 0.5, 0.0, &per_pixel).unwrap();
+        assert_relative_eq!(eval.context().get_var("zoom").unwrap(), 1.5);
+        assert_relative_eq!(eval.context().get_var("q1").unwrap(), 7.0);
+    }
+
+    #[test]
+    fn test_compiled_cache_reused_across_frames() {
+        let mut eval = MilkEvaluator::new();
+        for _ in 0..10_000 {
+            eval.eval("acc = acc + 0.5 * sin(time) + 1").unwrap();
+        }
+        assert_eq!(eval.compiled_cache_len(), 1);
+        assert_relative_eq!(eval.context().get_var("acc").unwrap(), 10_000.0);
+
+        eval.reset();
+        assert_eq!(eval.compiled_cache_len(), 0);
+    }
+
+    #[test]
+    fn test_eval_mesh_matches_sequential() {
+        let per_pixel: Vec<String> = [
+            "zoom = zoom + 0.1 * rad * q1",
+            "rot = 0.05 * sin(ang * 3)",
+            "dx = 0.01 * x * y; dy = -0.01 * x",
+            "cx = if(above(x, 0.5), 0.3, 0.7)",
+            "warp = warp * min(x, y, 0.5)",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let mut eval = MilkEvaluator::new();
+        eval.eval_per_frame(&["zoom = 1.02".to_string(), "q1 = 2".to_string()])
+            .unwrap();
+        let mesh = eval.eval_mesh(16, 16, &per_pixel).unwrap();
+        assert_eq!(mesh.len(), 16 * 16);
+
+        // Same pixels, one at a time on a single evaluator
+        let mut expected = Vec::new();
+        for j in 0..16 {
+            for i in 0..16 {
+                let (x, y) = (i as f64 / 15.0, j as f64 / 15.0);
+                let (cx, cy) = (x - 0.5, y - 0.5);
+                eval.eval_per_pixel(x, y, (cx * cx + cy * cy).sqrt(), cy.atan2(cx), &per_pixel)
+                    .unwrap();
+                expected.push(mesh_vertex(eval.context(), x, y));
+            }
+        }
+        assert_eq!(mesh, expected);
+
+        let corner = mesh[16 * 16 - 1];
+        assert_relative_eq!(corner.cx, 0.3);
+        assert_relative_eq!(corner.dx, 0.01);
+        assert_relative_eq!(corner.zoom, 1.02 + 0.2 * 0.5f64.sqrt(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_eval_mesh_bytecode_matches_evalexpr() {
+        let per_frame = vec!["zoom = 1.02".to_string(), "q1 = 2".to_string()];
+        let per_pixel: Vec<String> = [
+            "zoom = zoom + 0.1 * rad * q1",
+            "rot = 0.05 * sin(ang * 3) + pow(x, 2)",
+            "dx = 0.01 * x * y; dy = -0.01 * x",
+            "cx = if(above(x, 0.5), 0.3, 0.7)",
+            "warp = warp * min(x, y, 0.5)",
+            // `$x` hex literals aren't bytecode, so this one runs on evalexpr
+            "sy = 1 + $x10 * 0.001 * y",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let mut eval = MilkEvaluator::new();
+        eval.eval_per_frame(&per_frame).unwrap();
+        let mesh = eval.eval_mesh(8, 8, &per_pixel).unwrap();
+        assert!(eval.programs["rot = 0.05 * sin(ang * 3) + pow(x, 2)"].is_some());
+        assert!(eval.programs["sy = 1 + $x10 * 0.001 * y"].is_none());
+
+        // Every statement through evalexpr, on a fresh evaluator per pixel
+        for (index, vertex) in mesh.iter().enumerate() {
+            let (x, y) = ((index % 8) as f64 / 7.0, (index / 8) as f64 / 7.0);
+            let (cx, cy) = (x - 0.5, y - 0.5);
+            let mut reference = MilkEvaluator::new();
+            reference.eval_per_frame(&per_frame).unwrap();
+            reference
+                .context_mut()
+                .set_pixel(x, y, (cx * cx + cy * cy).sqrt(), cy.atan2(cx));
+            for equation in &per_pixel {
+                reference.eval(equation).unwrap();
+            }
+            let expected = mesh_vertex(reference.context(), x, y);
+            for (got, want) in [
+                (vertex.zoom, expected.zoom),
+                (vertex.rot, expected.rot),
+                (vertex.warp, expected.warp),
+                (vertex.cx, expected.cx),
+                (vertex.dx, expected.dx),
+                (vertex.dy, expected.dy),
+                (vertex.sy, expected.sy),
+            ] {
+                assert_relative_eq!(got, want, epsilon = 1e-12);
+            }
+        }
+        assert_relative_eq!(mesh[63].sy, 1.016);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_eval_mesh_parallel_matches_single_thread() {
+        let per_pixel: Vec<String> = [
+            "q2 = q2 + x",
+            "zoom = zoom + 0.05 * q2 * rad",
+            "rot = rot + 0.1 * cos(ang * 2 + q1)",
+            "sx = 1 + 0.1 * y; sy = sy - 0.1 * x",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let mesh = |threads: usize| {
+            let mut eval = MilkEvaluator::new();
+            eval.eval_per_frame(&["q1 = 0.5".to_string(), "rot = 0.01".to_string()])
+                .unwrap();
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| eval.eval_mesh(48, 36, &per_pixel))
+                .unwrap()
+        };
+
+        let single = mesh(1);
+        assert_eq!(single.len(), 48 * 36);
+        assert_eq!(mesh(4), single);
+    }
+
+    #[test]
+    fn test_per_pixel_stretch_does_not_clobber_per_frame() {
+        let mut eval = MilkEvaluator::new();
+        let per_frame = vec!["sx = 1.1".to_string(), "sy = sy + 0.25".to_string()];
+        let per_pixel = vec![
+            "seen_sx = sx".to_string(),
+            "sx = sx * (1 + x)".to_string(),
+            "sy = 0".to_string(),
+        ];
+
+        eval.context_mut().set_var("sy", 1.0);
+        eval.eval_per_frame(&per_frame).unwrap();
+        for x in [0.0, 0.5, 1.0] {
+            eval.eval_per_pixel(x, 0.5, 0.5, 0.0, &per_pixel).unwrap();
+            // Each pixel reads the per-frame stretch, not the previous pixel's scale
+            assert_relative_eq!(eval.context().get_var("seen_sx").unwrap(), 1.1);
+        }
+        assert_relative_eq!(eval.context().get_var("sx").unwrap(), 2.2);
+
+        // The next frame starts from the per-frame values, not the last pixel
+        eval.eval_per_frame(&per_frame).unwrap();
+        assert_relative_eq!(eval.context().get_var("sx").unwrap(), 1.1);
+        assert_relative_eq!(eval.context().get_var("sy").unwrap(), 1.5);
+    }
 }
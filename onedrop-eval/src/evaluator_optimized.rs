@@ -231,11 +231,11 @@ mod tests {
         eval.eval("1 + 1").ok();
         eval.eval("2 + 2").ok();
 
-        assert_eq!(eval.cache_stats().size, 2);
+        assert_eq!(eval.cache_stats().len, 2);
 
         eval.clear_cache();
 
-        assert_eq!(eval.cache_stats().size, 0);
+        assert_eq!(eval.cache_stats().len, 0);
         assert_eq!(eval.cache_stats().hits, 0);
         assert_eq!(eval.cache_stats().misses, 0);
     }
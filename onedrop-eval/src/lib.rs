@@ -5,19 +5,29 @@
 //! This crate provides functionality to evaluate mathematical expressions
 //! used in Milkdrop presets, with support for all Milkdrop variables and functions.
 
+pub mod bytecode;
 pub mod cache;
 pub mod context;
 pub mod error;
 pub mod evaluator;
 pub mod evaluator_optimized;
 pub mod math_functions;
+pub mod megabuf;
+pub mod rng;
+pub mod spectrum;
+pub mod validate;
 
-pub use cache::{CacheStats, ExpressionCache};
-pub use context::MilkContext;
+pub use bytecode::Program;
+pub use cache::{CacheStats, ExpressionCache, SharedExpressionCache};
+pub use context::{ContextSnapshot, MilkContext};
 pub use error::{EvalError, Result};
-pub use evaluator::MilkEvaluator;
+pub use evaluator::{MeshVertexOut, MilkEvaluator};
 pub use evaluator_optimized::OptimizedEvaluator;
-pub use math_functions::{list_math_functions, register_math_functions};
+pub use math_functions::{list_math_functions, register_math_functions, register_rand_functions};
+pub use megabuf::{MEGABUF_SIZE, MegaBuf};
+pub use rng::MilkRng;
+pub use spectrum::Spectrum;
+pub use validate::equation_errors;
 
 /// Evaluate a simple expression with default context.
 ///
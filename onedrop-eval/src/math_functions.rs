@@ -3,6 +3,7 @@
 //! This module provides all the mathematical functions needed for MilkDrop presets,
 //! as evalexpr 13.0 does not include trigonometric or advanced math functions by default.
 
+use crate::rng::MilkRng;
 use evalexpr::{ContextWithMutableFunctions, DefaultNumericTypes, Function, HashMapContext, Value};
 
 /// Register all MilkDrop math functions in a HashMapContext.
@@ -170,6 +171,43 @@ pub fn register_math_functions(context: &mut HashMapContext<DefaultNumericTypes>
         )
         .ok();
 
+    context
+        .set_function(
+            "floor".into(),
+            Function::new(|arg| arg.as_number().map(|n: f64| Value::Float(n.floor()))),
+        )
+        .ok();
+
+    context
+        .set_function(
+            "ceil".into(),
+            Function::new(|arg| arg.as_number().map(|n: f64| Value::Float(n.ceil()))),
+        )
+        .ok();
+
+    context
+        .set_function(
+            "round".into(),
+            Function::new(|arg| arg.as_number().map(|n: f64| Value::Float(n.round()))),
+        )
+        .ok();
+
+    // Minimum and maximum of two or more values (override evalexpr's
+    // builtins, which return Int for integer arguments)
+    context
+        .set_function(
+            "min".into(),
+            Function::new(|arg| fold_numbers(arg, f64::INFINITY, f64::min)),
+        )
+        .ok();
+
+    context
+        .set_function(
+            "max".into(),
+            Function::new(|arg| fold_numbers(arg, f64::NEG_INFINITY, f64::max)),
+        )
+        .ok();
+
     // Modulo and clamping
     context
         .set_function(
@@ -263,22 +301,7 @@ pub fn register_math_functions(context: &mut HashMapContext<DefaultNumericTypes>
         .ok();
 
     // Random and comparison functions
-    context
-        .set_function(
-            "rand".into(),
-            Function::new(|arg| {
-                use std::time::{SystemTime, UNIX_EPOCH};
-                let max = arg.as_number()?;
-                let max: f64 = max;
-                let seed = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos();
-                let random = ((seed % 1000000) as f64 / 1000000.0) * max;
-                Ok(Value::Float(random))
-            }),
-        )
-        .ok();
+    register_rand_functions(context, &MilkRng::from_time());
 
     context
         .set_function(
@@ -450,6 +473,52 @@ pub fn register_math_functions(context: &mut HashMapContext<DefaultNumericTypes>
         .ok();
 }
 
+/// Fold a single number or a tuple of numbers into a float.
+fn fold_numbers(
+    arg: &Value<DefaultNumericTypes>,
+    init: f64,
+    f: fn(f64, f64) -> f64,
+) -> evalexpr::EvalexprResult<Value<DefaultNumericTypes>, DefaultNumericTypes> {
+    let values = match arg {
+        Value::Tuple(values) => values.as_slice(),
+        single => std::slice::from_ref(single),
+    };
+    let mut result = init;
+    for value in values {
+        let n: f64 = value.as_number()?;
+        result = f(result, n);
+    }
+    Ok(Value::Float(result))
+}
+
+/// Register `rand(max)` and `rand_int(max)` drawing from the given generator.
+///
+/// `rand(max)` returns a float in `[0, max)`; `rand_int(max)` returns an
+/// integer-valued float in `[0, max)`.
+pub fn register_rand_functions(context: &mut HashMapContext<DefaultNumericTypes>, rng: &MilkRng) {
+    let float_rng = rng.clone();
+    context
+        .set_function(
+            "rand".into(),
+            Function::new(move |arg| {
+                let max: f64 = arg.as_number()?;
+                Ok(Value::Float(float_rng.next_f64() * max))
+            }),
+        )
+        .ok();
+
+    let int_rng = rng.clone();
+    context
+        .set_function(
+            "rand_int".into(),
+            Function::new(move |arg| {
+                let max: f64 = arg.as_number()?;
+                Ok(Value::Float((int_rng.next_f64() * max.trunc()).floor()))
+            }),
+        )
+        .ok();
+}
+
 /// List of all registered math functions.
 pub fn list_math_functions() -> Vec<&'static str> {
     vec![
@@ -458,11 +527,12 @@ pub fn list_math_functions() -> Vec<&'static str> {
         // Exponential and logarithmic
         "sqrt", "pow", "exp", "log", "ln", "log10", // Absolute and sign
         "abs", "sign", // Rounding
-        "fract", "trunc", // Modulo and clamping
+        "fract", "trunc", "floor", "ceil", "round", // Minimum and maximum
+        "min", "max", // Modulo and clamping
         "fmod", "clamp", // Hyperbolic
         "sinh", "cosh", "tanh", // Additional
         "sqr", "rad", "deg", // Random and comparison
-        "rand", "above", "below", "equal", // Boolean
+        "rand", "rand_int", "above", "below", "equal", // Boolean
         "bnot", "band", "bor", // Type conversion
         "int", // Control flow
         "milkif",
@@ -551,4 +621,43 @@ mod tests {
         let expected = time.sin() * (time * 2.0).cos() + (time - 0.5).abs().sqrt();
         assert_relative_eq!(result, expected, epsilon = 1e-10);
     }
+
+    #[test]
+    fn test_rand_functions_seeded() {
+        let mut a = HashMapContext::<DefaultNumericTypes>::new();
+        let mut b = HashMapContext::<DefaultNumericTypes>::new();
+        register_rand_functions(&mut a, &MilkRng::from_seed(1234));
+        register_rand_functions(&mut b, &MilkRng::from_seed(1234));
+
+        for _ in 0..20 {
+            let x = evalexpr::eval_number_with_context("rand(10)", &a).unwrap();
+            let y = evalexpr::eval_number_with_context("rand(10)", &b).unwrap();
+            assert_eq!(x, y);
+            assert!((0.0..10.0).contains(&x));
+
+            let i = evalexpr::eval_number_with_context("rand_int(6)", &a).unwrap();
+            let j = evalexpr::eval_number_with_context("rand_int(6)", &b).unwrap();
+            assert_eq!(i, j);
+            assert_eq!(i, i.trunc());
+            assert!((0.0..6.0).contains(&i));
+        }
+    }
+
+    #[test]
+    fn test_min_max_and_rounding() {
+        let mut context = HashMapContext::<DefaultNumericTypes>::new();
+        register_math_functions(&mut context);
+
+        let eval = |expr| evalexpr::eval_with_context(expr, &context).unwrap();
+        assert_eq!(eval("min(2,3)"), Value::Float(2.0));
+        assert_eq!(eval("max(2,3)"), Value::Float(3.0));
+        assert_eq!(eval("floor(2.7)"), Value::Float(2.0));
+        assert_eq!(eval("ceil(2.1)"), Value::Float(3.0));
+        assert_eq!(eval("round(2.5)"), Value::Float(3.0));
+        assert_eq!(eval("max(1, 4, 2)"), Value::Float(4.0));
+
+        for name in ["min", "max", "floor", "ceil", "round"] {
+            assert!(list_math_functions().contains(&name));
+        }
+    }
 }
@@ -0,0 +1,201 @@
+//! `megabuf` / `gmegabuf` scratch memory for MilkDrop expressions.
+//!
+//! `megabuf` is local to a [`MilkContext`](crate::MilkContext) (one per
+//! preset), while `gmegabuf` is a single process-wide buffer shared by every
+//! context. Buffers grow lazily up to [`MEGABUF_SIZE`] slots; unwritten slots
+//! read as `0.0`.
+
+use evalexpr::{ContextWithMutableFunctions, DefaultNumericTypes, Function, HashMapContext, Value};
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Maximum number of slots in a megabuf (MilkDrop's limit).
+pub const MEGABUF_SIZE: usize = 1_048_576;
+
+static GLOBAL_MEGABUF: LazyLock<MegaBuf> = LazyLock::new(MegaBuf::new);
+
+/// Lazily grown scratch buffer with shared ownership.
+#[derive(Debug, Clone, Default)]
+pub struct MegaBuf {
+    data: Arc<RwLock<Vec<f64>>>,
+}
+
+impl MegaBuf {
+    /// Create an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle to the process-wide `gmegabuf`.
+    pub fn global() -> Self {
+        GLOBAL_MEGABUF.clone()
+    }
+
+    /// Read a slot; out-of-range or unwritten slots are `0.0`.
+    pub fn get(&self, index: f64) -> f64 {
+        match Self::slot(index) {
+            Some(i) => self
+                .data
+                .read()
+                .map(|data| data.get(i).copied().unwrap_or(0.0))
+                .unwrap_or(0.0),
+            None => 0.0,
+        }
+    }
+
+    /// Write a slot, growing the buffer as needed. Out-of-range writes are ignored.
+    pub fn set(&self, index: f64, value: f64) {
+        if let Some(i) = Self::slot(index) {
+            if let Ok(mut data) = self.data.write() {
+                if data.len() <= i {
+                    data.resize(i + 1, 0.0);
+                }
+                data[i] = value;
+            }
+        }
+    }
+
+    /// Number of allocated slots.
+    pub fn len(&self) -> usize {
+        self.data.read().map(|data| data.len()).unwrap_or(0)
+    }
+
+    /// Check whether nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Create an independent copy of the current contents.
+    pub fn fork(&self) -> Self {
+        let data = self
+            .data
+            .read()
+            .map(|data| data.clone())
+            .unwrap_or_default();
+        Self {
+            data: Arc::new(RwLock::new(data)),
+        }
+    }
+
+    fn slot(index: f64) -> Option<usize> {
+        let i = index.floor();
+        if i >= 0.0 && i < MEGABUF_SIZE as f64 {
+            Some(i as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Register `megabuf`/`gmegabuf` readers and the `megabuf_set`/`gmegabuf_set`
+/// writers that the evaluator rewrites `megabuf(i) = v` into.
+pub fn register_megabuf_functions(
+    context: &mut HashMapContext<DefaultNumericTypes>,
+    local: &MegaBuf,
+    global: &MegaBuf,
+) {
+    for (name, buf) in [("megabuf", local), ("gmegabuf", global)] {
+        let reader = buf.clone();
+        context
+            .set_function(
+                name.into(),
+                Function::new(move |arg| {
+                    let index: f64 = arg.as_number()?;
+                    Ok(Value::Float(reader.get(index)))
+                }),
+            )
+            .ok();
+
+        let writer = buf.clone();
+        context
+            .set_function(
+                format!("{}_set", name),
+                Function::new(move |arg| {
+                    let tuple = arg.as_fixed_len_tuple(2)?;
+                    let index: f64 = tuple[0].as_number()?;
+                    let value: f64 = tuple[1].as_number()?;
+                    writer.set(index, value);
+                    Ok(Value::Float(value))
+                }),
+            )
+            .ok();
+    }
+}
+
+/// Rewrite `megabuf(i) = v` / `gmegabuf(i) = v` into a setter call.
+///
+/// Returns `None` if the statement is not a megabuf assignment.
+pub(crate) fn rewrite_assignment(statement: &str) -> Option<String> {
+    let trimmed = statement.trim_start();
+    let name = ["gmegabuf", "megabuf"]
+        .into_iter()
+        .find(|name| trimmed.starts_with(name))?;
+
+    let rest = trimmed[name.len()..].trim_start();
+    if !rest.starts_with('(') {
+        return None;
+    }
+
+    // Find the parenthesis closing the index expression
+    let mut depth = 0usize;
+    let mut close = None;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+
+    let index = &rest[1..close];
+    let value = rest[close + 1..].trim_start().strip_prefix('=')?;
+    if value.starts_with('=') {
+        // Comparison, not assignment
+        return None;
+    }
+
+    Some(format!("{}_set({}, {})", name, index.trim(), value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_growth_and_bounds() {
+        let buf = MegaBuf::new();
+        assert_eq!(buf.get(10.0), 0.0);
+        assert!(buf.is_empty());
+
+        buf.set(10.0, 3.5);
+        assert_eq!(buf.len(), 11);
+        assert_eq!(buf.get(10.0), 3.5);
+        assert_eq!(buf.get(9.0), 0.0);
+
+        buf.set(MEGABUF_SIZE as f64, 1.0);
+        buf.set(-1.0, 1.0);
+        assert_eq!(buf.len(), 11);
+        assert_eq!(buf.get(MEGABUF_SIZE as f64), 0.0);
+    }
+
+    #[test]
+    fn test_rewrite_assignment() {
+        assert_eq!(
+            rewrite_assignment("megabuf(5) = x").as_deref(),
+            Some("megabuf_set(5, x)")
+        );
+        assert_eq!(
+            rewrite_assignment(" gmegabuf(floor(i + 1)) = sin(time)").as_deref(),
+            Some("gmegabuf_set(floor(i + 1), sin(time))")
+        );
+        assert_eq!(rewrite_assignment("megabuf(5) == x"), None);
+        assert_eq!(rewrite_assignment("x = megabuf(5)"), None);
+        assert_eq!(rewrite_assignment("megabuffer = 1"), None);
+    }
+}
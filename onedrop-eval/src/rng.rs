@@ -0,0 +1,119 @@
+//! Seedable random number generator backing `rand()` and `rand_int()`.
+//!
+//! The generator is a small xorshift64* shared between the owning
+//! [`MilkContext`](crate::MilkContext) and the closures registered in its
+//! evalexpr context, so reseeding takes effect without re-registering.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seed used when the system clock is unavailable.
+const FALLBACK_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// Deterministic xorshift64* generator with shared state.
+#[derive(Debug, Clone)]
+pub struct MilkRng {
+    state: Arc<AtomicU64>,
+}
+
+impl MilkRng {
+    /// Create a generator with a fixed seed.
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            state: Arc::new(AtomicU64::new(Self::scramble(seed))),
+        }
+    }
+
+    /// Create a generator seeded from the system clock.
+    pub fn from_time() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(FALLBACK_SEED);
+        Self::from_seed(seed)
+    }
+
+    /// Reset the generator to the start of the sequence for `seed`.
+    pub fn set_seed(&self, seed: u64) {
+        self.state.store(Self::scramble(seed), Ordering::Relaxed);
+    }
+
+    /// Create an independent generator continuing from the current state.
+    pub fn fork(&self) -> Self {
+        Self {
+            state: Arc::new(AtomicU64::new(self.state.load(Ordering::Relaxed))),
+        }
+    }
+
+    /// Next raw 64-bit value.
+    pub fn next_u64(&self) -> u64 {
+        let prev = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+                Some(Self::step(x))
+            })
+            .unwrap_or(FALLBACK_SEED);
+        Self::step(prev).wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Next value uniformly distributed in `[0, 1)`.
+    pub fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn step(mut x: u64) -> u64 {
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        x
+    }
+
+    /// Spread the seed with splitmix64 so that small seeds (and 0) give a
+    /// well-mixed, non-zero xorshift state.
+    fn scramble(seed: u64) -> u64 {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        if z == 0 { FALLBACK_SEED } else { z }
+    }
+}
+
+impl Default for MilkRng {
+    fn default() -> Self {
+        Self::from_time()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let a = MilkRng::from_seed(42);
+        let b = MilkRng::from_seed(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_zero_seed_and_range() {
+        let rng = MilkRng::from_seed(0);
+        for _ in 0..1000 {
+            let v = rng.next_f64();
+            assert!((0.0..1.0).contains(&v));
+        }
+    }
+
+    #[test]
+    fn test_fork_is_independent() {
+        let a = MilkRng::from_seed(7);
+        let b = a.fork();
+        assert_eq!(a.next_u64(), b.next_u64());
+        a.next_u64();
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+}
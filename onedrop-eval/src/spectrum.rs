@@ -0,0 +1,91 @@
+//! `spectrum(i)` access to the audio magnitude spectrum.
+//!
+//! The engine writes the current frame's normalized FFT magnitudes into a
+//! [`Spectrum`] before per-frame evaluation; expressions read bin `i` with
+//! `spectrum(i)`. Out-of-range bins read as `0.0`.
+
+use evalexpr::{ContextWithMutableFunctions, DefaultNumericTypes, Function, HashMapContext, Value};
+use std::sync::{Arc, RwLock};
+
+/// Magnitude spectrum shared between a context and its `spectrum` function.
+#[derive(Debug, Clone, Default)]
+pub struct Spectrum {
+    bins: Arc<RwLock<Vec<f64>>>,
+}
+
+impl Spectrum {
+    /// Create an empty spectrum.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the bins with a new frame's magnitudes.
+    pub fn set(&self, magnitudes: &[f32]) {
+        if let Ok(mut bins) = self.bins.write() {
+            bins.clear();
+            bins.extend(magnitudes.iter().map(|&m| m as f64));
+        }
+    }
+
+    /// Read a bin; out-of-range bins are `0.0`.
+    pub fn get(&self, index: f64) -> f64 {
+        let i = index.floor();
+        if i < 0.0 {
+            return 0.0;
+        }
+        self.bins
+            .read()
+            .map(|bins| bins.get(i as usize).copied().unwrap_or(0.0))
+            .unwrap_or(0.0)
+    }
+
+    /// Number of bins.
+    pub fn len(&self) -> usize {
+        self.bins.read().map(|bins| bins.len()).unwrap_or(0)
+    }
+
+    /// Check whether no spectrum has been set.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Register the `spectrum(i)` reader.
+pub fn register_spectrum_function(
+    context: &mut HashMapContext<DefaultNumericTypes>,
+    spectrum: &Spectrum,
+) {
+    let reader = spectrum.clone();
+    context
+        .set_function(
+            "spectrum".into(),
+            Function::new(move |arg| {
+                let index: f64 = arg.as_number()?;
+                Ok(Value::Float(reader.get(index)))
+            }),
+        )
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MilkEvaluator;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_spectrum_function() {
+        let mut eval = MilkEvaluator::new();
+        assert_relative_eq!(eval.eval("spectrum(3)").unwrap(), 0.0);
+
+        eval.context_mut().set_spectrum(&[0.1, 0.2, 0.9, 0.4]);
+        assert_relative_eq!(eval.eval("spectrum(2)").unwrap(), 0.9, epsilon = 1e-6);
+        assert_relative_eq!(eval.eval("spectrum(1.7)").unwrap(), 0.2, epsilon = 1e-6);
+        assert_relative_eq!(eval.eval("spectrum(4)").unwrap(), 0.0);
+        assert_relative_eq!(eval.eval("spectrum(-1)").unwrap(), 0.0);
+
+        // Clones read the same frame's spectrum
+        let clone = eval.context().clone();
+        assert_eq!(clone.spectrum().len(), 4);
+        assert_relative_eq!(clone.spectrum().get(0.0), 0.1, epsilon = 1e-6);
+    }
+}
@@ -0,0 +1,78 @@
+//! Checking a preset's equations without rendering it.
+
+use crate::error::EvalError;
+use crate::evaluator::MilkEvaluator;
+use onedrop_parser::MilkPreset;
+
+/// Evaluate a preset's init, per-frame and per-pixel equations once, one at
+/// a time, and collect every failure with the preset line it came from.
+///
+/// Per-pixel equations run for a single pixel at the center of the screen.
+pub fn equation_errors(preset: &MilkPreset) -> Vec<EvalError> {
+    let mut evaluator = MilkEvaluator::new();
+    let per_frame_sections = [
+        (
+            "per_frame_init",
+            &preset.per_frame_init_equations,
+            &preset.source_lines.per_frame_init,
+        ),
+        (
+            "per_frame",
+            &preset.per_frame_equations,
+            &preset.source_lines.per_frame,
+        ),
+    ];
+
+    let mut errors = Vec::new();
+    for (section, equations, lines) in per_frame_sections {
+        for (i, equation) in equations.iter().enumerate() {
+            let lines = lines.get(i..=i).unwrap_or_default();
+            if let Err(e) =
+                evaluator.eval_per_frame_with_lines(section, std::slice::from_ref(equation), lines)
+            {
+                errors.push(e);
+            }
+        }
+    }
+
+    for (i, equation) in preset.per_pixel_equations.iter().enumerate() {
+        if let Err(e) = evaluator.eval_per_pixel(0.5, 0.5, 0.0, 0.0, std::slice::from_ref(equation))
+        {
+            errors.push(match preset.source_lines.per_pixel.get(i) {
+                Some(&line) => e.at_line("per_pixel", line),
+                None => e,
+            });
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_section_is_checked() {
+        let preset = onedrop_parser::parse_preset(
+            "[preset00]
+per_frame_init_1=q1 = 1;
+per_frame_1=zoom = (1 +;
+per_frame_2=rot = q1 * 0.1;
+per_pixel_1=warp = rad * 0.5;
+per_pixel_2=zoom = (zoom *;
+",
+        )
+        .unwrap();
+
+        let errors = equation_errors(&preset);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            EvalError::AtLine { section, line: 3, .. } if section == "per_frame"
+        ));
+        assert!(matches!(
+            &errors[1],
+            EvalError::AtLine { section, line: 6, .. } if section == "per_pixel"
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+// Blit shader - draws the engine's output texture onto the window surface
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Full-screen quad
+    let x = f32((vertex_index & 1u) << 2u);
+    let y = f32((vertex_index & 2u) << 1u);
+
+    output.position = vec4<f32>(x - 1.0, 1.0 - y, 0.0, 1.0);
+    output.uv = vec2<f32>(x * 0.5, y * 0.5);
+
+    return output;
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+// Same curve as onedrop_renderer::tonemap: colors in range are unchanged,
+// brighter ones have their peak scaled to 1.0 and fade towards white
+fn tonemap(c: vec3<f32>) -> vec3<f32> {
+    let v = max(c, vec3<f32>(0.0));
+    let peak = max(v.r, max(v.g, v.b));
+    if (peak <= 1.0) {
+        return v;
+    }
+    return v / (peak * peak) + (1.0 - 1.0 / peak);
+}
+
+// Source and surface agree on sRGB: copy as is
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(source_texture, source_sampler, input.uv).rgb, 1.0);
+}
+
+// Plain source, sRGB surface: decode so the surface's encoding restores the stored values
+@fragment
+fn fs_decode(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, input.uv).rgb;
+    return vec4<f32>(srgb_to_linear(color), 1.0);
+}
+
+// Float source, plain surface: compress values above 1.0 into range
+@fragment
+fn fs_tonemap(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, input.uv).rgb;
+    return vec4<f32>(tonemap(color), 1.0);
+}
+
+// Float source, sRGB surface: tonemap, then decode like a plain source
+@fragment
+fn fs_tonemap_decode(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, input.uv).rgb;
+    return vec4<f32>(srgb_to_linear(tonemap(color)), 1.0);
+}
+
+// sRGB source, plain surface: encode the linear samples
+@fragment
+fn fs_encode(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, input.uv).rgb;
+    return vec4<f32>(linear_to_srgb(max(color, vec3<f32>(0.0))), 1.0);
+}
@@ -0,0 +1,35 @@
+// Overlay shader - draws the status text texture as a screen-space quad
+
+// Quad corners in clip space: left, top, right, bottom
+@group(0) @binding(0)
+var<uniform> rect: vec4<f32>;
+
+@group(0) @binding(1)
+var text_texture: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Quad covering `rect`
+    let u = f32(vertex_index & 1u);
+    let v = f32((vertex_index >> 1u) & 1u);
+
+    output.position = vec4<f32>(mix(rect.x, rect.z, u), mix(rect.y, rect.w, v), 0.0, 1.0);
+    output.uv = vec2<f32>(u, v);
+
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Nearest texel, so the font stays crisp when scaled up
+    let size = vec2<f32>(textureDimensions(text_texture));
+    let texel = min(vec2<i32>(input.uv * size), vec2<i32>(size) - 1);
+    return textureLoad(text_texture, texel, 0);
+}
@@ -1,12 +1,21 @@
 //! OneDrop GUI - Graphical user interface for Milkdrop visualizations
 
+#[cfg(feature = "gui-overlay")]
+mod overlay;
+mod surface_blit;
+
 use anyhow::Result;
+use clap::Parser;
 use onedrop_engine::{
-    AudioInput, BeatDetectionMode, EngineConfig, MilkEngine, PresetChange, PresetManager,
-    RenderConfig,
+    AudioInput, AudioSource, BeatDetectionMode, EngineConfig, FileAudioSource, MilkEngine,
+    PresetChange, PresetManager, RenderConfig, SineAudioSource,
 };
+#[cfg(feature = "gui-overlay")]
+use overlay::Overlay;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
+use surface_blit::SurfaceBlit;
 use winit::{
     application::ApplicationHandler,
     event::*,
@@ -15,6 +24,22 @@ use winit::{
     window::{Window, WindowId},
 };
 
+#[derive(Parser)]
+#[command(name = "onedrop-gui")]
+#[command(author = "Manus AI")]
+#[command(version = "0.1.0")]
+#[command(about = "OneDrop - Milkdrop visualizer window", long_about = None)]
+struct Cli {
+    /// WAV or MP3 file to play instead of capturing live audio
+    #[arg(long)]
+    audio: Option<PathBuf>,
+
+    /// Capture from this input device instead of the default one: its exact
+    /// name, or a case-insensitive part of exactly one device name
+    #[arg(long, conflicts_with = "audio")]
+    device: Option<String>,
+}
+
 struct App {
     window: Option<Arc<Window>>,
     surface: Option<wgpu::Surface<'static>>,
@@ -22,16 +47,21 @@ struct App {
     device: Option<Arc<wgpu::Device>>,
     queue: Option<Arc<wgpu::Queue>>,
     engine: Option<MilkEngine>,
-    audio_input: Option<AudioInput>,
+    /// Draws the engine's output onto the surface
+    blit: Option<SurfaceBlit>,
+    /// FPS, preset and beat mode display, toggled with F1
+    #[cfg(feature = "gui-overlay")]
+    overlay: Option<Overlay>,
+    /// Audio handed to the engine once it is created
+    audio_source: Option<Box<dyn AudioSource>>,
     preset_manager: PresetManager,
     last_frame: Instant,
-    frame_count: u32,
     /// Fallback to demo mode if audio input fails
     demo_mode: bool,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(cli: &Cli) -> Self {
         let mut preset_manager = PresetManager::new();
 
         // Add some default presets if available
@@ -44,21 +74,26 @@ impl App {
             }
         }
 
-        // Try to initialize audio input
-        let audio_input = match AudioInput::new() {
-            Ok(input) => {
-                log::info!("Real audio input initialized successfully");
-                Some(input)
-            }
-            Err(e) => {
-                log::warn!(
-                    "Failed to initialize audio input: {}. Falling back to demo mode.",
-                    e
-                );
-                None
-            }
+        // Play the --audio file if one was given, else try live capture
+        let audio_input: Option<Box<dyn AudioSource>> = match &cli.audio {
+            Some(path) => match FileAudioSource::open(path) {
+                Ok(source) => {
+                    log::info!("Playing audio from {}", path.display());
+                    Some(Box::new(source))
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to open {}: {}. Falling back to demo mode.",
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            },
+            None => Self::open_audio_input(cli.device.as_deref()),
         };
         let demo_mode = audio_input.is_none();
+        let audio_source = audio_input.unwrap_or_else(|| Box::new(SineAudioSource::demo()));
 
         Self {
             window: None,
@@ -67,14 +102,44 @@ impl App {
             device: None,
             queue: None,
             engine: None,
-            audio_input,
+            blit: None,
+            #[cfg(feature = "gui-overlay")]
+            overlay: None,
+            audio_source: Some(audio_source),
             preset_manager,
             last_frame: Instant::now(),
-            frame_count: 0,
             demo_mode,
         }
     }
 
+    /// Start live audio capture on the named device (`--device`), or the
+    /// default one. `None` falls back to demo mode.
+    fn open_audio_input(device: Option<&str>) -> Option<Box<dyn AudioSource>> {
+        let input = match device {
+            Some(name) => AudioInput::with_device(name).inspect_err(|_| {
+                log::warn!(
+                    "No input device matches \"{}\". Available: {}",
+                    name,
+                    AudioInput::list_devices().join(", ")
+                );
+            }),
+            None => AudioInput::new(),
+        };
+        match input {
+            Ok(input) => {
+                log::info!("Real audio input initialized successfully");
+                Some(Box::new(input))
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to initialize audio input: {}. Falling back to demo mode.",
+                    e
+                );
+                None
+            }
+        }
+    }
+
     fn init_graphics(&mut self, window: Arc<Window>) -> Result<()> {
         let size = window.inner_size();
 
@@ -131,19 +196,27 @@ impl App {
 
         surface.configure(&device, &config);
 
-        // Create engine with shared device
+        // Create engine with shared device, rendering at the window's physical resolution
+        let scale_factor = window.scale_factor();
+        let logical_size = size.to_logical::<u32>(scale_factor);
         let engine_config = EngineConfig {
             render_config: RenderConfig {
-                width: size.width,
-                height: size.height,
+                width: logical_size.width,
+                height: logical_size.height,
+                scale_factor: scale_factor as f32,
                 ..Default::default()
             },
+            target_fps: Some(60.0),
             ..Default::default()
         };
 
         // Share device and queue with engine
-        let engine =
+        let mut engine =
             MilkEngine::from_device(Arc::clone(&device), Arc::clone(&queue), engine_config)?;
+        engine.set_preset_directory("../test-presets");
+        if let Some(source) = self.audio_source.take() {
+            engine.set_audio_source(source);
+        }
 
         // Update window title to show audio mode
         let title = if self.demo_mode {
@@ -153,12 +226,19 @@ impl App {
         };
         window.set_title(title);
 
+        let blit = SurfaceBlit::new(&device, engine.render_texture().format(), surface_format);
+        #[cfg(feature = "gui-overlay")]
+        {
+            self.overlay = Some(Overlay::new(&device, surface_format));
+        }
+
         self.window = Some(window);
         self.surface = Some(surface);
         self.surface_config = Some(config);
         self.device = Some(device);
         self.queue = Some(queue);
         self.engine = Some(engine);
+        self.blit = Some(blit);
 
         // Load first preset if available
         if let Some(preset_path) = self.preset_manager.current_preset() {
@@ -191,28 +271,21 @@ impl App {
             .engine
             .as_mut()
             .ok_or_else(|| anyhow::anyhow!("Graphics not initialized: engine"))?;
+        let blit = self
+            .blit
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Graphics not initialized: blit"))?;
 
-        // Calculate delta time
+        // Calculate delta time, skipping the frame if it comes too soon
         let now = Instant::now();
-        let delta_time = (now - self.last_frame).as_secs_f32();
+        let elapsed = (now - self.last_frame).as_secs_f32();
         self.last_frame = now;
-
-        // Get audio samples - use real audio input or fall back to demo mode
-        let audio_samples: Vec<f32> = if let Some(ref audio_input) = self.audio_input {
-            // Use real audio capture
-            audio_input.get_fixed_samples(1024)
-        } else {
-            // Fallback: generate demo audio (sine wave)
-            (0..1024)
-                .map(|i| {
-                    let t = (self.frame_count * 1024 + i) as f32 * 0.001;
-                    (t * 2.0 * std::f32::consts::PI * 60.0).sin() * 0.5
-                })
-                .collect()
+        let Some(delta_time) = engine.pace_frame(elapsed) else {
+            return Ok(());
         };
 
-        // Update engine
-        let preset_change = engine.update(&audio_samples, delta_time)?;
+        // Update engine with the next frame of audio (live, file or demo sine)
+        let preset_change = engine.update_from_source(delta_time)?;
 
         // Handle automatic preset change from beat detection
         if let Some(change) = preset_change {
@@ -243,7 +316,7 @@ impl App {
 
         // Get surface texture
         let output = surface.get_current_texture()?;
-        let _view = output
+        let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
@@ -252,49 +325,48 @@ impl App {
             label: Some("Render Encoder"),
         });
 
-        // Copy MilkEngine texture to surface
-        let render_texture = engine.renderer().render_texture();
-        encoder.copy_texture_to_texture(
-            wgpu::ImageCopyTexture {
-                texture: render_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            wgpu::ImageCopyTexture {
-                texture: &output.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            wgpu::Extent3d {
-                width: self
-                    .surface_config
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("Graphics not initialized: surface_config"))?
-                    .width,
-                height: self
-                    .surface_config
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("Graphics not initialized: surface_config"))?
-                    .height,
-                depth_or_array_layers: 1,
-            },
-        );
+        // Draw the engine's output over the whole surface
+        blit.render(device, &mut encoder, engine.render_texture(), &view);
+
+        #[cfg(feature = "gui-overlay")]
+        if let (Some(overlay), Some(surface_config)) = (&mut self.overlay, &self.surface_config) {
+            if overlay.frame(delta_time) {
+                let preset = self
+                    .preset_manager
+                    .current_preset()
+                    .and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "none".to_string());
+                let lines = [
+                    format!("FPS: {:.1}", overlay.fps()),
+                    format!("Preset: {}", preset),
+                    format!("Beat: {}", engine.beat_detector().mode().name()),
+                ];
+                overlay.set_text(queue, &lines);
+            }
+            overlay.render(
+                queue,
+                &mut encoder,
+                &view,
+                (surface_config.width, surface_config.height),
+            );
+        }
 
         queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
-        self.frame_count += 1;
-
         Ok(())
     }
 
     fn handle_keyboard(&mut self, key_code: KeyCode) {
         match key_code {
             KeyCode::Space => {
-                // Toggle play/pause (not implemented yet)
-                log::info!("Space pressed");
+                // Toggle play/pause
+                if let Some(engine) = &mut self.engine {
+                    let paused = !engine.is_paused();
+                    engine.set_paused(paused);
+                    log::info!("{}", if paused { "Paused" } else { "Playing" });
+                }
             }
             KeyCode::ArrowRight | KeyCode::KeyN => {
                 // Next preset
@@ -327,6 +399,25 @@ impl App {
                     log::info!("Engine reset");
                 }
             }
+            KeyCode::F7 => {
+                // Toggle per-pixel equations
+                if let Some(engine) = &mut self.engine {
+                    let enabled = !engine.per_pixel_enabled();
+                    engine.set_per_pixel_enabled(enabled);
+                    log::info!(
+                        "Per-pixel equations: {}",
+                        if enabled { "on" } else { "off" }
+                    );
+                }
+            }
+            #[cfg(feature = "gui-overlay")]
+            KeyCode::F1 => {
+                // Toggle the status overlay
+                if let Some(overlay) = &mut self.overlay {
+                    let visible = overlay.toggle();
+                    log::info!("Overlay: {}", if visible { "shown" } else { "hidden" });
+                }
+            }
             KeyCode::F8 => {
                 // Toggle beat detection mode
                 if let Some(engine) = &mut self.engine {
@@ -420,27 +511,54 @@ impl ApplicationHandler for App {
                         config.height = physical_size.height;
                         surface.configure(device, config);
 
-                        if let Some(engine) = &mut self.engine {
-                            engine.resize(physical_size.width, physical_size.height);
+                        if let (Some(engine), Some(window)) = (&mut self.engine, &self.window) {
+                            let logical_size =
+                                physical_size.to_logical::<u32>(window.scale_factor());
+                            engine.resize(logical_size.width, logical_size.height);
                         }
                     }
                 }
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // A Resized event with the new physical size follows
+                if let Some(engine) = &mut self.engine {
+                    engine.set_scale_factor(scale_factor as f32);
+                }
+            }
             WindowEvent::RedrawRequested => {
                 if let Err(e) = self.render() {
                     log::error!("Render error: {}", e);
                 }
-
-                if let Some(window) = &self.window {
-                    window.request_redraw();
-                }
             }
             _ => {}
         }
     }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let (Some(window), Some(engine)) = (&self.window, &self.engine) else {
+            return;
+        };
+
+        // Sleep until the next frame is due instead of spinning; unlimited
+        // rates redraw right away and are held back by the surface's vsync
+        let deadline = engine
+            .time_until_next_frame()
+            .map(|wait| self.last_frame + wait);
+        match deadline {
+            Some(deadline) if deadline > Instant::now() => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(deadline));
+            }
+            _ => {
+                event_loop.set_control_flow(ControlFlow::Wait);
+                window.request_redraw();
+            }
+        }
+    }
 }
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     env_logger::Builder::from_default_env()
         .filter_level(log::LevelFilter::Info)
         .init();
@@ -448,9 +566,8 @@ fn main() -> Result<()> {
     log::info!("Starting OneDrop GUI...");
 
     let event_loop = EventLoop::new()?;
-    event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::new();
+    let mut app = App::new(&cli);
 
     event_loop.run_app(&mut app)?;
 
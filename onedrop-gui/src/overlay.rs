@@ -0,0 +1,343 @@
+//! On-screen status overlay: frame rate, preset name and beat detection
+//! mode, drawn with the built-in bitmap font.
+//!
+//! The text is rasterized on the CPU into a small texture, which is only
+//! re-uploaded when the text is refreshed, at most once per second. Drawing
+//! it is a single alpha-blended quad, so rendering never waits on it.
+
+use onedrop_renderer::bitmap_font::{GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, glyph};
+
+/// Characters per line; longer lines are cut off.
+const MAX_CHARS: u32 = 48;
+
+/// Lines of text.
+const MAX_LINES: u32 = 3;
+
+/// Height of one line including spacing, in texels.
+const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+/// Blank border around the text, in texels.
+const PADDING: u32 = 2;
+
+/// On-screen pixels per texel.
+const SCALE: u32 = 2;
+
+/// Distance from the top-left corner of the window, in pixels.
+const MARGIN: u32 = 8;
+
+/// Texture size in texels.
+const TEXTURE_WIDTH: u32 = MAX_CHARS * GLYPH_ADVANCE + 2 * PADDING;
+const TEXTURE_HEIGHT: u32 = MAX_LINES * LINE_HEIGHT + 2 * PADDING;
+
+/// Translucent backdrop behind the text.
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+
+/// Text color.
+const FOREGROUND: [u8; 4] = [255, 255, 255, 255];
+
+/// Seconds between text refreshes.
+const REFRESH_INTERVAL: f32 = 1.0;
+
+/// Frame rate measured over the frames rendered in each refresh interval.
+#[derive(Debug, Default)]
+pub struct FpsCounter {
+    /// Frames since the last measurement
+    frames: u32,
+
+    /// Time since the last measurement
+    elapsed: f32,
+
+    /// Last measured frame rate
+    fps: f32,
+}
+
+impl FpsCounter {
+    /// Count a frame that took `delta_time` seconds. Returns `true` when a
+    /// new measurement was taken.
+    pub fn frame(&mut self, delta_time: f32) -> bool {
+        self.frames += 1;
+        self.elapsed += delta_time.max(0.0);
+        if self.elapsed < REFRESH_INTERVAL {
+            return false;
+        }
+        self.fps = self.frames as f32 / self.elapsed;
+        self.frames = 0;
+        self.elapsed = 0.0;
+        true
+    }
+
+    /// Last measured frame rate.
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+/// Rasterize `lines` into an RGBA8 image of `TEXTURE_WIDTH`×`TEXTURE_HEIGHT`.
+fn rasterize(lines: &[String]) -> Vec<u8> {
+    let mut pixels = BACKGROUND.repeat((TEXTURE_WIDTH * TEXTURE_HEIGHT) as usize);
+    for (line, text) in lines.iter().take(MAX_LINES as usize).enumerate() {
+        let top = PADDING + line as u32 * LINE_HEIGHT;
+        for (i, c) in text.chars().take(MAX_CHARS as usize).enumerate() {
+            let left = PADDING + i as u32 * GLYPH_ADVANCE;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> col) != 0 {
+                        let texel = (top + row as u32) * TEXTURE_WIDTH + left + col;
+                        let i = texel as usize * 4;
+                        pixels[i..i + 4].copy_from_slice(&FOREGROUND);
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
+
+/// Status overlay, toggled with a key.
+pub struct Overlay {
+    visible: bool,
+    fps: FpsCounter,
+
+    /// Whether the texture holds no text yet
+    stale: bool,
+
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    texture: wgpu::Texture,
+
+    /// Overlay rectangle in clip space (left, top, right, bottom)
+    rect_buffer: wgpu::Buffer,
+}
+
+impl Overlay {
+    /// Create a hidden overlay drawing onto `surface_format` targets.
+    pub fn new(device: &wgpu::Device, surface_format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/overlay.wgsl").into()),
+        });
+
+        // Uploaded texels are colors as stored; an sRGB texture matches an
+        // sRGB surface so they come out unchanged either way
+        let texture_format = if surface_format.is_srgb() {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Overlay Texture"),
+            size: wgpu::Extent3d {
+                width: TEXTURE_WIDTH,
+                height: TEXTURE_HEIGHT,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let rect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Rect"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Overlay Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Overlay Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: rect_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            visible: false,
+            fps: FpsCounter::default(),
+            stale: true,
+            pipeline,
+            bind_group,
+            texture,
+            rect_buffer,
+        }
+    }
+
+    /// Show or hide the overlay. Returns whether it is now visible.
+    pub fn toggle(&mut self) -> bool {
+        self.visible = !self.visible;
+        self.visible
+    }
+
+    /// Count a rendered frame. Returns `true` when the text should be
+    /// refreshed with [`set_text`](Self::set_text).
+    pub fn frame(&mut self, delta_time: f32) -> bool {
+        let measured = self.fps.frame(delta_time);
+        self.visible && (measured || self.stale)
+    }
+
+    /// Last measured frame rate.
+    pub fn fps(&self) -> f32 {
+        self.fps.fps()
+    }
+
+    /// Replace the displayed text, one entry per line.
+    pub fn set_text(&mut self, queue: &wgpu::Queue, lines: &[String]) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &rasterize(lines),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(TEXTURE_WIDTH * 4),
+                rows_per_image: Some(TEXTURE_HEIGHT),
+            },
+            self.texture.size(),
+        );
+        self.stale = false;
+    }
+
+    /// Draw the overlay onto `target`, a `size` pixel surface, if visible.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        size: (u32, u32),
+    ) {
+        if !self.visible || self.stale {
+            return;
+        }
+
+        let (width, height) = (size.0.max(1) as f32, size.1.max(1) as f32);
+        let to_clip_x = |x: u32| x as f32 / width * 2.0 - 1.0;
+        let to_clip_y = |y: u32| 1.0 - y as f32 / height * 2.0;
+        let rect = [
+            to_clip_x(MARGIN),
+            to_clip_y(MARGIN),
+            to_clip_x(MARGIN + TEXTURE_WIDTH * SCALE),
+            to_clip_y(MARGIN + TEXTURE_HEIGHT * SCALE),
+        ];
+        queue.write_buffer(&self.rect_buffer, 0, &rect.map(f32::to_ne_bytes).concat());
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overlay Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fps_measured_once_per_second() {
+        let mut counter = FpsCounter::default();
+        for _ in 0..29 {
+            assert!(!counter.frame(1.0 / 30.0));
+        }
+        assert!(counter.frame(1.0 / 30.0 + 1e-4));
+        assert!((counter.fps() - 30.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_rasterize_draws_text_on_backdrop() {
+        let pixels = rasterize(&["I".to_string()]);
+        assert_eq!(pixels.len(), (TEXTURE_WIDTH * TEXTURE_HEIGHT * 4) as usize);
+        assert_eq!(pixels[..4], BACKGROUND);
+
+        // Top row of `I` spans columns 1-3 of the glyph
+        let texel = (PADDING * TEXTURE_WIDTH + PADDING + 1) as usize * 4;
+        assert_eq!(pixels[texel..texel + 4], FOREGROUND);
+    }
+}
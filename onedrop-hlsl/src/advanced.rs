@@ -48,6 +48,7 @@ impl AdvancedTranslator {
 
     /// Translate complex HLSL shader to WGSL
     pub fn translate(&mut self, hlsl: &str) -> Result<String> {
+        crate::check_supported(hlsl)?;
         let mut wgsl = hlsl.to_string();
 
         // 1. Control flow
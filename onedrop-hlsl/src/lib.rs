@@ -23,6 +23,18 @@ static TEX2D_REGEX: LazyLock<Regex> =
 static SEMANTICS_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r":\s*[A-Z_][A-Z0-9_]*").unwrap());
 
+// Constructs the translator has no rewrite for and would pass through as
+// invalid WGSL
+static UNSUPPORTED_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\b(?:cbuffer|tbuffer|SamplerState|SamplerComparisonState",
+        r"|Texture[123]D|TextureCube)\b",
+        r"|\.Sample(?:Level|Grad|Bias|Cmp)?\s*\(",
+        r"|#\s*(?:define|include|if|ifdef|ifndef|pragma)\b",
+    ))
+    .unwrap()
+});
+
 #[derive(Error, Debug)]
 pub enum TranslationError {
     #[error("Translation error: {0}")]
@@ -35,7 +47,11 @@ pub enum TranslationError {
 pub type Result<T> = std::result::Result<T, TranslationError>;
 
 /// Translate HLSL shader code to WGSL
+///
+/// Fails with [`TranslationError::Unsupported`] if the shader uses a
+/// construct the translator can't rewrite (see [`check_supported`]).
 pub fn translate_shader(hlsl: &str) -> Result<String> {
+    check_supported(hlsl)?;
     let mut wgsl = hlsl.to_string();
 
     // Type replacements
@@ -53,6 +69,27 @@ pub fn translate_shader(hlsl: &str) -> Result<String> {
     Ok(wgsl)
 }
 
+/// Reject HLSL constructs the translator has no rewrite for: `cbuffer`
+/// blocks, `SamplerState`/`Texture2D` objects and their `.Sample()` calls,
+/// and preprocessor directives.
+///
+/// The error names the first offending token and its (1-based) line.
+/// Line comments are ignored.
+pub fn check_supported(hlsl: &str) -> Result<()> {
+    for (number, line) in hlsl.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or(line);
+        if let Some(token) = UNSUPPORTED_REGEX.find(code) {
+            return Err(TranslationError::Unsupported(format!(
+                "`{}` on line {}: {}",
+                token.as_str().trim_end_matches('(').trim(),
+                number + 1,
+                line.trim()
+            )));
+        }
+    }
+    Ok(())
+}
+
 fn replace_types(code: &str) -> String {
     let mut result = code.to_string();
 
@@ -126,6 +163,27 @@ mod tests {
         assert!(wgsl.contains("clamp"));
     }
 
+    #[test]
+    fn test_unsupported_constructs() {
+        let err = translate_shader("cbuffer Foo {}").unwrap_err();
+        assert!(matches!(err, TranslationError::Unsupported(_)));
+        assert!(err.to_string().contains("`cbuffer` on line 1"), "{}", err);
+
+        let hlsl = "float4 c = tex2D(sampler_main, uv);\nc = tex.Sample(samp, uv);";
+        let err = translate_shader(hlsl).unwrap_err();
+        assert!(err.to_string().contains("`.Sample` on line 2"), "{}", err);
+
+        for hlsl in ["#define PI 3.14159", "SamplerState samp;", "Texture2D tex;"] {
+            assert!(matches!(
+                translate_shader(hlsl),
+                Err(TranslationError::Unsupported(_))
+            ));
+        }
+
+        // Comments and lookalike identifiers are fine
+        assert!(translate_shader("// cbuffer in a comment\nfloat mycbuffer = 1.0;").is_ok());
+    }
+
     #[test]
     fn test_texture_sampling() {
         let hlsl = "color = tex2D(sampler0, uv);";
@@ -7,12 +7,15 @@
 
 pub mod double_preset;
 pub mod error;
+pub mod normalize;
 pub mod parser;
 pub mod preset;
 
 pub use double_preset::{BlendPattern, DoublePreset, parse_double_preset};
 pub use error::{ParseError, Result};
-pub use preset::MilkPreset;
+pub use normalize::normalize_equation;
+pub use parser::ParseOptions;
+pub use preset::{CodeSourceLines, DEFAULT_RATING, MilkPreset, SourceLines};
 
 /// Parse a `.milk` preset file from a string.
 ///
@@ -33,6 +36,40 @@ pub fn parse_preset(input: &str) -> Result<MilkPreset> {
     parser::parse_milk_preset(input)
 }
 
+/// Parse a `.milk` preset file, choosing how malformed values are handled.
+///
+/// [`parse_preset`] is lenient: unparseable wave and shape values fall back
+/// to their defaults. With `strict` set they are reported as
+/// [`ParseError::InvalidParameter`] instead.
+///
+/// # Examples
+///
+/// ```
+/// use onedrop_parser::{ParseOptions, parse_preset_with_options};
+///
+/// let content = "[preset00]\nshapecode_0_sides=abc\n";
+/// assert!(parse_preset_with_options(content, ParseOptions::default()).is_ok());
+/// assert!(parse_preset_with_options(content, ParseOptions { strict: true }).is_err());
+/// ```
+pub fn parse_preset_with_options(input: &str, options: ParseOptions) -> Result<MilkPreset> {
+    parser::parse_milk_preset_with_options(input, options)
+}
+
+/// Parse every preset of a bank: a file with several `[presetNN]` sections.
+///
+/// # Examples
+///
+/// ```
+/// use onedrop_parser::parse_preset_bank;
+///
+/// let content = "MILKDROP_PRESET_VERSION=201\n[preset00]\nzoom=1.01\n[preset01]\nzoom=0.95\n";
+/// let presets = parse_preset_bank(content).unwrap();
+/// assert_eq!(presets.len(), 2);
+/// ```
+pub fn parse_preset_bank(input: &str) -> Result<Vec<MilkPreset>> {
+    parser::parse_milk_preset_bank(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +97,22 @@ per_pixel_1=zoom=zoom+0.1;
         let preset = result.unwrap();
         assert_eq!(preset.version, 201);
     }
+
+    #[test]
+    fn test_normalize_equations() {
+        let mut a =
+            parse_preset("[preset00]\nper_frame_1=wave_r=0.5;\nshapecode_0_per_frame1=x=x+0.1;\n")
+                .unwrap();
+        let mut b = parse_preset(
+            "[preset00]\nper_frame_1=wave_r = 0.5 ;\nshapecode_0_per_frame1=x = x+0.1;\n",
+        )
+        .unwrap();
+        assert_ne!(a, b);
+
+        a.normalize_equations();
+        b.normalize_equations();
+        assert_eq!(a, b);
+        assert_eq!(a.per_frame_equations, vec!["wave_r = 0.5;"]);
+        assert_eq!(a.shapes[0].per_frame_equations, vec!["x = x + 0.1;"]);
+    }
 }
@@ -0,0 +1,184 @@
+//! Canonical whitespace for equations.
+//!
+//! Equations in the wild mix `wave_r=0.5` and `wave_r = 0.5`. Normalizing
+//! them puts single spaces around assignment and binary operators, after
+//! commas and semicolons, and nowhere else, so equivalent code compares,
+//! hashes and caches equal.
+
+/// Operators, longest first so `==` wins over `=`.
+const OPERATORS: &[&str] = &[
+    "+=", "-=", "*=", "/=", "%=", "==", "!=", "<=", ">=", "&&", "||", "=", "+", "-", "*", "/", "%",
+    "<", ">", "&", "|", "^", "!",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    /// Identifier or number
+    Word(&'a str),
+    Operator(&'a str),
+    Open,
+    Close,
+    Comma,
+    Semicolon,
+}
+
+/// Rewrite an equation with canonical spacing.
+///
+/// Unary signs stay attached to their operand (`x = -1`, `a * -b`), and a
+/// trailing `//` comment is kept as written.
+pub fn normalize_equation(equation: &str) -> String {
+    let (code, comment) = match equation.find("//") {
+        Some(pos) => (&equation[..pos], Some(equation[pos..].trim_end())),
+        None => (equation, None),
+    };
+
+    let mut out = String::with_capacity(equation.len());
+    let mut prev: Option<(Token, bool)> = None;
+    for token in tokenize(code) {
+        let unary = match token {
+            Token::Operator(op) => is_unary(op, prev.map(|(t, _)| t)),
+            _ => false,
+        };
+        if let Some(prev) = prev {
+            if needs_space(prev, (token, unary)) {
+                out.push(' ');
+            }
+        }
+        match token {
+            Token::Word(text) | Token::Operator(text) => out.push_str(text),
+            Token::Open => out.push('('),
+            Token::Close => out.push(')'),
+            Token::Comma => out.push(','),
+            Token::Semicolon => out.push(';'),
+        }
+        prev = Some((token, unary));
+    }
+
+    if let Some(comment) = comment {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(comment);
+    }
+    out
+}
+
+/// Whether `op` is a sign or negation rather than a binary operator.
+fn is_unary(op: &str, prev: Option<Token>) -> bool {
+    matches!(op, "-" | "+" | "!")
+        && matches!(
+            prev,
+            None | Some(Token::Operator(_) | Token::Open | Token::Comma | Token::Semicolon)
+        )
+}
+
+/// Whether one space goes between two adjacent tokens (each paired with
+/// whether it is a unary operator).
+fn needs_space(prev: (Token, bool), next: (Token, bool)) -> bool {
+    match (prev, next) {
+        (_, (Token::Close | Token::Comma | Token::Semicolon, _)) => false,
+        ((Token::Open, _), _) => false,
+        ((Token::Operator(_), true), _) => false,
+        ((Token::Operator(_), false) | (Token::Comma | Token::Semicolon, _), _) => true,
+        (_, (Token::Operator(_), _)) => true,
+        (_, (Token::Open, _)) => false,
+        (_, (Token::Word(_), _)) => true,
+    }
+}
+
+fn tokenize(code: &str) -> Vec<Token<'_>> {
+    let bytes = code.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        match c {
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            b'(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            b',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            b';' => {
+                tokens.push(Token::Semicolon);
+                i += 1;
+            }
+            _ if c.is_ascii_alphanumeric() || c == b'_' || c == b'.' || c == b'$' => {
+                i += 1;
+                while i < bytes.len() {
+                    let c = bytes[i];
+                    // Keep exponents such as 1e-3 inside the number
+                    let exponent_sign = (c == b'-' || c == b'+')
+                        && matches!(bytes[i - 1], b'e' | b'E')
+                        && bytes[start].is_ascii_digit();
+                    if c.is_ascii_alphanumeric()
+                        || c == b'_'
+                        || c == b'.'
+                        || c == b'$'
+                        || exponent_sign
+                    {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Word(&code[start..i]));
+            }
+            _ => {
+                let rest = &code[i..];
+                match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                    Some(op) => {
+                        tokens.push(Token::Operator(op));
+                        i += op.len();
+                    }
+                    None => {
+                        // Unknown character: keep it as its own word
+                        let len = rest.chars().next().map_or(1, char::len_utf8);
+                        tokens.push(Token::Word(&code[i..i + len]));
+                        i += len;
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_equation() {
+        assert_eq!(normalize_equation("wave_r=0.5"), "wave_r = 0.5");
+        assert_eq!(
+            normalize_equation("wave_r=0.5"),
+            normalize_equation("wave_r  =   0.5")
+        );
+        assert_eq!(
+            normalize_equation("zoom=zoom+0.1*sin( time*1.3 );"),
+            "zoom = zoom + 0.1 * sin(time * 1.3);"
+        );
+        assert_eq!(
+            normalize_equation("x=-1;y = a*-b ;z=-(a+b)"),
+            "x = -1; y = a * -b; z = -(a + b)"
+        );
+        assert_eq!(
+            normalize_equation("q1=if(above(bass,1),1e-3,-2.5E+2);"),
+            "q1 = if(above(bass, 1), 1e-3, -2.5E+2);"
+        );
+        assert_eq!(normalize_equation("!a||b"), "!a || b");
+        assert_eq!(
+            normalize_equation("a+=b==c // tweak=this"),
+            "a += b == c // tweak=this"
+        );
+    }
+}
@@ -3,8 +3,31 @@
 use crate::error::{ParseError, Result};
 use crate::preset::*;
 
-/// Parse a complete .milk preset file.
+/// How forgiving the parser is with malformed values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    /// Reject unparseable wave and shape values instead of falling back to
+    /// their defaults
+    pub strict: bool,
+}
+
+impl ParseOptions {
+    /// Options that reject malformed values.
+    pub fn strict() -> Self {
+        Self { strict: true }
+    }
+}
+
+/// Parse a complete .milk preset file, leniently.
+///
+/// Parsing stops at a second `[presetNN]` section; use
+/// [`parse_milk_preset_bank`] for files holding several presets.
 pub fn parse_milk_preset(input: &str) -> Result<MilkPreset> {
+    parse_milk_preset_with_options(input, ParseOptions::default())
+}
+
+/// Parse a complete .milk preset file with the given options.
+pub fn parse_milk_preset_with_options(input: &str, options: ParseOptions) -> Result<MilkPreset> {
     let mut preset = MilkPreset::default();
     let mut lines = input.lines().enumerate();
 
@@ -27,52 +50,91 @@ pub fn parse_milk_preset(input: &str) -> Result<MilkPreset> {
         }
     }
 
+    // Init and wave/shape equations are collected with their index and line, and sorted afterwards
+    let mut init_equations: Vec<(usize, usize, String)> = Vec::new();
+    let mut wave_equations: Vec<(usize, CodeEquation)> = Vec::new();
+    let mut shape_equations: Vec<(usize, CodeEquation)> = Vec::new();
+
+    // Shader block currently being assembled (for raw continuation lines)
+    let mut shader_block: Option<ShaderBlock> = None;
+
     // Parse preset body
-    for (_line_num, line) in lines {
-        let line = line.trim();
+    for (line_index, raw_line) in lines {
+        let line_number = line_index + 1;
+        let line = raw_line.trim();
         if line.is_empty() {
             continue;
         }
 
+        // Lines inside a shader block that aren't a recognized key are raw
+        // HLSL continuation lines; keep them verbatim
+        if let Some(block) = shader_block {
+            if !is_recognized_key(line) {
+                let code = raw_line.trim_end();
+                let code = code.strip_prefix('`').unwrap_or(code);
+                let shader = match block {
+                    ShaderBlock::Warp => &mut preset.warp_shader,
+                    ShaderBlock::Comp => &mut preset.comp_shader,
+                };
+                append_shader_line(shader, code);
+                continue;
+            }
+            shader_block = None;
+        }
+
+        // The next preset of a bank starts here
+        if is_preset_header(line) {
+            break;
+        }
+
+        // Parse per-frame init equations (must precede the per_frame_ check)
+        if line.starts_with("per_frame_init_") {
+            if let Some(equation) = parse_equation_line(line) {
+                let index = parse_equation_index(line, "per_frame_init_");
+                init_equations.push((index, line_number, equation));
+            }
+        }
         // Parse per-frame equations
-        if line.starts_with("per_frame_") {
+        else if line.starts_with("per_frame_") {
             if let Some(equation) = parse_equation_line(line) {
                 preset.per_frame_equations.push(equation);
+                preset.source_lines.per_frame.push(line_number);
             }
         }
         // Parse per-pixel equations
         else if line.starts_with("per_pixel_") {
             if let Some(equation) = parse_equation_line(line) {
                 preset.per_pixel_equations.push(equation);
+                preset.source_lines.per_pixel.push(line_number);
             }
         }
         // Parse warp shader
         else if line.starts_with("warp_") {
-            let shader_line = parse_shader_line(line);
-            if let Some(ref mut shader) = preset.warp_shader {
-                shader.push_str(&shader_line);
-                shader.push('\n');
-            } else {
-                preset.warp_shader = Some(shader_line + "\n");
-            }
+            append_shader_line(&mut preset.warp_shader, &parse_shader_line(line));
+            shader_block = Some(ShaderBlock::Warp);
         }
         // Parse comp shader
         else if line.starts_with("comp_") {
-            let shader_line = parse_shader_line(line);
-            if let Some(ref mut shader) = preset.comp_shader {
-                shader.push_str(&shader_line);
-                shader.push('\n');
-            } else {
-                preset.comp_shader = Some(shader_line + "\n");
-            }
+            append_shader_line(&mut preset.comp_shader, &parse_shader_line(line));
+            shader_block = Some(ShaderBlock::Comp);
+        }
+        // Parse per-wave equations (wave_N_per_pointK=..., wavecode_N_per_frameK=...)
+        else if let Some(entry) = parse_code_equation_line(line, WAVE_PREFIXES, true) {
+            wave_equations.push((line_number, entry));
         }
-        // Parse wavecode
-        else if line.starts_with("wavecode_") {
-            parse_wavecode_line(line, &mut preset.waves)?;
+        // Parse per-shape equations (shape_N_per_frameK=..., shapecode_N_initK=...)
+        else if let Some(entry) = parse_code_equation_line(line, SHAPE_PREFIXES, false) {
+            shape_equations.push((line_number, entry));
         }
-        // Parse shapecode
-        else if line.starts_with("shapecode_") {
-            parse_shapecode_line(line, &mut preset.shapes)?;
+        // Parse wavecode (wavecode_N_param=..., or legacy wave_N_param=...)
+        else if let Some((index, param, value)) = split_code_line(line, WAVE_PREFIXES) {
+            let key = CodeKey::new("wavecode", index, param, options);
+            parse_wavecode_param(&key, value, &mut preset.waves)?;
+        }
+        // Parse shapecode (shapecode_N_param=..., or legacy shape_N_param=...)
+        else if let Some((index, param, value)) = split_code_line(line, SHAPE_PREFIXES) {
+            let key = CodeKey::new("shapecode", index, param, options);
+            parse_shapecode_param(&key, value, &mut preset.shapes)?;
         }
         // Parse regular parameters
         else if let Some((key, value)) = line.split_once('=') {
@@ -80,9 +142,302 @@ pub fn parse_milk_preset(input: &str) -> Result<MilkPreset> {
         }
     }
 
+    // Stable sort keeps file order for duplicate indices
+    init_equations.sort_by_key(|(index, _, _)| *index);
+    for (_, line_number, equation) in init_equations {
+        preset.per_frame_init_equations.push(equation);
+        preset.source_lines.per_frame_init.push(line_number);
+    }
+
+    wave_equations.sort_by_key(|(_, entry)| entry.number);
+    for (line_number, entry) in wave_equations {
+        let Some(wave) = ensure_wave(&mut preset.waves, entry.index) else {
+            continue;
+        };
+        let lines = code_source_lines(&mut preset.source_lines.waves, entry.index);
+        match entry.kind {
+            CodeEquationKind::Init => {
+                wave.per_frame_init_equations.push(entry.equation);
+                lines.per_frame_init.push(line_number);
+            }
+            CodeEquationKind::PerFrame => {
+                wave.per_frame_equations.push(entry.equation);
+                lines.per_frame.push(line_number);
+            }
+            CodeEquationKind::PerPoint => {
+                wave.per_point_equations.push(entry.equation);
+                lines.per_point.push(line_number);
+            }
+        }
+    }
+
+    shape_equations.sort_by_key(|(_, entry)| entry.number);
+    for (line_number, entry) in shape_equations {
+        let Some(shape) = ensure_shape(&mut preset.shapes, entry.index) else {
+            continue;
+        };
+        let lines = code_source_lines(&mut preset.source_lines.shapes, entry.index);
+        match entry.kind {
+            CodeEquationKind::Init => {
+                shape.per_frame_init_equations.push(entry.equation);
+                lines.per_frame_init.push(line_number);
+            }
+            CodeEquationKind::PerFrame => {
+                shape.per_frame_equations.push(entry.equation);
+                lines.per_frame.push(line_number);
+            }
+            CodeEquationKind::PerPoint => unreachable!("shapes have no per-point equations"),
+        }
+    }
+
     Ok(preset)
 }
 
+/// Parse every `[presetNN]` section of a preset bank.
+///
+/// Header lines before the first section (version, shader versions) apply to
+/// every preset. Input without a section header parses as a single preset.
+pub fn parse_milk_preset_bank(input: &str) -> Result<Vec<MilkPreset>> {
+    let mut header = String::new();
+    let mut sections: Vec<String> = Vec::new();
+
+    for line in input.lines() {
+        if is_preset_header(line.trim()) {
+            sections.push(String::new());
+        }
+        let text = sections.last_mut().unwrap_or(&mut header);
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    if sections.is_empty() {
+        return Ok(vec![parse_milk_preset(input)?]);
+    }
+
+    sections
+        .iter()
+        .map(|section| parse_milk_preset(&format!("{}{}", header, section)))
+        .collect()
+}
+
+/// Check whether a line is a `[presetNN]` section header.
+fn is_preset_header(line: &str) -> bool {
+    line.strip_prefix("[preset")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Parse the numeric suffix of an equation key (e.g. "per_frame_init_12=..." -> 12).
+/// Keys without a valid number sort last.
+fn parse_equation_index(line: &str, prefix: &str) -> usize {
+    line.split_once('=')
+        .and_then(|(key, _)| key.trim().strip_prefix(prefix))
+        .and_then(|n| n.trim().parse().ok())
+        .unwrap_or(usize::MAX)
+}
+
+/// Kind of equation attached to a custom wave or shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CodeEquationKind {
+    Init,
+    PerFrame,
+    PerPoint,
+}
+
+/// An equation line belonging to a custom wave or shape.
+#[derive(Debug, Clone, PartialEq)]
+struct CodeEquation {
+    /// Wave/shape index
+    index: usize,
+    kind: CodeEquationKind,
+    /// Equation number within its block (for ordering)
+    number: usize,
+    equation: String,
+}
+
+/// Custom waves (and likewise shapes) a preset can have, as in Milkdrop.
+const MAX_CUSTOM_CODES: usize = 4;
+
+/// Key prefixes of custom wave lines, current form first.
+const WAVE_PREFIXES: &[&str] = &["wavecode_", "wave_"];
+
+/// Key prefixes of custom shape lines, current form first.
+const SHAPE_PREFIXES: &[&str] = &["shapecode_", "shape_"];
+
+/// Split a custom wave/shape line into its index, name and value.
+///
+/// `shapecode_0_border_r = 0.5` gives `(0, "border_r", "0.5")`. The name is
+/// everything after the index, so it may contain underscores or spaces.
+/// Returns `None` unless one of the prefixes is followed by a numeric index,
+/// which keeps global parameters such as `wave_r` out.
+fn split_code_line<'a>(line: &'a str, prefixes: &[&str]) -> Option<(usize, &'a str, &'a str)> {
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim();
+    let rest = prefixes.iter().find_map(|p| key.strip_prefix(p))?;
+    let (index, name) = rest.split_once('_')?;
+    if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((index.parse().ok()?, name, value.trim()))
+}
+
+/// Parse a wave/shape equation line such as "wave_0_per_point3=x = 0.5;".
+///
+/// Accepts any of the given prefixes followed by `N_` and one of
+/// `per_frame_init`, `init`, `per_frame` or (if allowed) `per_point`,
+/// then an optional equation number. Returns `None` for scalar parameters.
+fn parse_code_equation_line(
+    line: &str,
+    prefixes: &[&str],
+    allow_per_point: bool,
+) -> Option<CodeEquation> {
+    let (index, name, equation) = split_code_line(line, prefixes)?;
+
+    let (kind, number) = if let Some(n) = name.strip_prefix("per_frame_init") {
+        (CodeEquationKind::Init, n)
+    } else if let Some(n) = name.strip_prefix("init") {
+        (CodeEquationKind::Init, n)
+    } else if let Some(n) = name.strip_prefix("per_frame") {
+        (CodeEquationKind::PerFrame, n)
+    } else if let Some(n) = name.strip_prefix("per_point").filter(|_| allow_per_point) {
+        (CodeEquationKind::PerPoint, n)
+    } else {
+        return None;
+    };
+
+    let number = number.trim_start_matches('_');
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(CodeEquation {
+        index,
+        kind,
+        number: number.parse().unwrap_or(usize::MAX),
+        equation: equation.to_string(),
+    })
+}
+
+/// Shader section a continuation line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShaderBlock {
+    Warp,
+    Comp,
+}
+
+/// Key prefixes that always start a new preset entry.
+const KEY_PREFIXES: &[&str] = &[
+    "per_frame_",
+    "per_pixel_",
+    "warp_",
+    "comp_",
+    "wavecode_",
+    "shapecode_",
+    "wave_",
+    "shape_",
+];
+
+/// Parameter keys understood by `parse_parameter`.
+const KNOWN_PARAMETERS: &[&str] = &[
+    "fRating",
+    "fGammaAdj",
+    "fDecay",
+    "fVideoEchoZoom",
+    "fVideoEchoAlpha",
+    "nVideoEchoOrientation",
+    "nWaveMode",
+    "bAdditiveWaves",
+    "bWaveDots",
+    "bWaveThick",
+    "bModWaveAlphaByVolume",
+    "bMaximizeWaveColor",
+    "bTexWrap",
+    "bDarkenCenter",
+    "bRedBlueStereo",
+    "bBrighten",
+    "bDarken",
+    "bSolarize",
+    "bInvert",
+    "fWaveAlpha",
+    "fWaveScale",
+    "fWaveSmoothing",
+    "fWaveParam",
+    "fModWaveAlphaStart",
+    "fModWaveAlphaEnd",
+    "fWarpAnimSpeed",
+    "fWarpScale",
+    "fZoomExponent",
+    "fShader",
+    "zoom",
+    "rot",
+    "cx",
+    "cy",
+    "dx",
+    "dy",
+    "warp",
+    "sx",
+    "sy",
+    "wave_r",
+    "wave_g",
+    "wave_b",
+    "wave_x",
+    "wave_y",
+    "ob_size",
+    "ob_r",
+    "ob_g",
+    "ob_b",
+    "ob_a",
+    "ib_size",
+    "ib_r",
+    "ib_g",
+    "ib_b",
+    "ib_a",
+    "nMotionVectorsX",
+    "nMotionVectorsY",
+    "mv_dx",
+    "mv_dy",
+    "mv_l",
+    "mv_r",
+    "mv_g",
+    "mv_b",
+    "mv_a",
+    "b1n",
+    "b2n",
+    "b3n",
+    "b1x",
+    "b2x",
+    "b3x",
+    "b1ed",
+];
+
+/// Check whether a line starts a preset entry (section header, known key or
+/// key with a known prefix) rather than being shader source.
+fn is_recognized_key(line: &str) -> bool {
+    if line.starts_with('[') {
+        return true;
+    }
+
+    let Some((key, _)) = line.split_once('=') else {
+        return false;
+    };
+
+    !key.is_empty()
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && (KEY_PREFIXES.iter().any(|prefix| key.starts_with(prefix))
+            || KNOWN_PARAMETERS.contains(&key))
+}
+
+/// Append a line of source to a shader, creating it if needed.
+fn append_shader_line(shader: &mut Option<String>, code: &str) {
+    let shader = shader.get_or_insert_with(String::new);
+    shader.push_str(code);
+    shader.push('\n');
+}
+
 /// Parse version line (e.g., "MILKDROP_PRESET_VERSION=201")
 fn parse_version_line(line: &str) -> Result<u32> {
     line.split('=')
@@ -106,24 +461,50 @@ fn parse_equation_line(line: &str) -> Option<String> {
 }
 
 /// Parse shader line (e.g., "warp_1=`shader_body")
+///
+/// Some exporters write a whole shader on one line with `\n` escapes; those
+/// are expanded back into separate lines.
 fn parse_shader_line(line: &str) -> String {
     line.split_once('=')
         .map(|(_, code)| {
             // Remove backtick prefix if present
-            code.trim().trim_start_matches('`').to_string()
+            let code = code.trim().trim_start_matches('`');
+            code.replace("\\r\\n", "\n").replace("\\n", "\n")
         })
         .unwrap_or_default()
 }
 
+/// Parse a float written with a comma decimal separator (e.g. `0,95`).
+///
+/// Only values with exactly one comma are accepted, so lists such as
+/// `1,2,3` are still rejected.
+fn parse_decimal_comma(value: &str) -> Option<f32> {
+    if value.matches(',').count() != 1 {
+        return None;
+    }
+    value.replace(',', ".").parse().ok()
+}
+
 /// Parse a parameter and store it in PresetParameters
 fn parse_parameter(key: &str, value: &str, params: &mut PresetParameters) -> Result<()> {
-    // Helper to parse float
+    // Helper to parse float, tolerating European decimal commas
     let parse_f32 = |v: &str| -> Result<f32> {
-        v.parse().map_err(|_| ParseError::InvalidParameter {
-            name: key.to_string(),
-            value: v.to_string(),
-            reason: "Expected float".to_string(),
-        })
+        v.parse()
+            .or_else(|_| {
+                let recovered = parse_decimal_comma(v).ok_or(())?;
+                log::warn!(
+                    "Parameter {}={} uses a decimal comma, reading it as {}",
+                    key,
+                    v,
+                    recovered
+                );
+                Ok(recovered)
+            })
+            .map_err(|_: ()| ParseError::InvalidParameter {
+                name: key.to_string(),
+                value: v.to_string(),
+                reason: "Expected float".to_string(),
+            })
     };
 
     // Helper to parse int
@@ -150,7 +531,17 @@ fn parse_parameter(key: &str, value: &str, params: &mut PresetParameters) -> Res
 
     match key {
         // Float parameters
-        "fRating" => params.f_rating = parse_f32(value)?,
+        // Rating is informational only, so tolerate empty or "n/a" values
+        "fRating" => {
+            params.f_rating = value.parse().unwrap_or_else(|_| {
+                log::warn!(
+                    "Invalid fRating '{}', using default {}",
+                    value,
+                    DEFAULT_RATING
+                );
+                DEFAULT_RATING
+            })
+        }
         "fGammaAdj" => params.f_gamma_adj = parse_f32(value)?,
         "fDecay" => params.f_decay = parse_f32(value)?,
         "fVideoEchoZoom" => params.f_video_echo_zoom = parse_f32(value)?,
@@ -243,149 +634,458 @@ fn parse_parameter(key: &str, value: &str, params: &mut PresetParameters) -> Res
     Ok(())
 }
 
-/// Parse wavecode line
-fn parse_wavecode_line(line: &str, waves: &mut Vec<WaveCode>) -> Result<()> {
-    // Extract wave index and parameter name
-    // Format: wavecode_N_param=value
-    let parts: Vec<&str> = line.split('_').collect();
-    if parts.len() < 3 {
-        return Ok(()); // Skip malformed lines
-    }
-
-    let index: usize = parts[1].parse().unwrap_or(0);
-    let param_and_value = line.split_once('=');
-
-    if let Some((param_full, value)) = param_and_value {
-        let param = param_full.split('_').skip(2).collect::<Vec<_>>().join("_");
-
-        // Ensure wave exists
-        while waves.len() <= index {
-            waves.push(WaveCode {
-                index: waves.len(),
-                enabled: false,
-                samples: 512,
-                sep: 0,
-                b_spectrum: false,
-                b_use_dots: false,
-                b_draw_thick: false,
-                b_additive: false,
-                scaling: 1.0,
-                smoothing: 0.5,
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-                a: 1.0,
-                per_frame_equations: Vec::new(),
-                per_point_equations: Vec::new(),
-                per_frame_init_equations: Vec::new(),
-            });
+/// Line numbers of custom wave/shape `index`, growing `lines` as needed.
+fn code_source_lines(lines: &mut Vec<CodeSourceLines>, index: usize) -> &mut CodeSourceLines {
+    if lines.len() <= index {
+        lines.resize_with(index + 1, CodeSourceLines::default);
+    }
+    &mut lines[index]
+}
+
+/// Get the wave at `index`, creating default waves up to it if needed.
+///
+/// Indices past Milkdrop's custom waves are ignored with a warning.
+fn ensure_wave(waves: &mut Vec<WaveCode>, index: usize) -> Option<&mut WaveCode> {
+    if index >= MAX_CUSTOM_CODES {
+        log::warn!(
+            "Ignoring custom wave {}, presets have at most {}",
+            index,
+            MAX_CUSTOM_CODES
+        );
+        return None;
+    }
+    while waves.len() <= index {
+        waves.push(WaveCode {
+            index: waves.len(),
+            enabled: false,
+            samples: 512,
+            sep: 0,
+            b_spectrum: false,
+            b_use_dots: false,
+            b_draw_thick: false,
+            b_additive: false,
+            scaling: 1.0,
+            smoothing: 0.5,
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+            a: 1.0,
+            per_frame_equations: Vec::new(),
+            per_point_equations: Vec::new(),
+            per_frame_init_equations: Vec::new(),
+        });
+    }
+
+    Some(&mut waves[index])
+}
+
+/// Get the shape at `index`, creating default shapes up to it if needed.
+///
+/// Indices past Milkdrop's custom shapes are ignored with a warning.
+fn ensure_shape(shapes: &mut Vec<ShapeCode>, index: usize) -> Option<&mut ShapeCode> {
+    if index >= MAX_CUSTOM_CODES {
+        log::warn!(
+            "Ignoring custom shape {}, presets have at most {}",
+            index,
+            MAX_CUSTOM_CODES
+        );
+        return None;
+    }
+    while shapes.len() <= index {
+        shapes.push(ShapeCode {
+            index: shapes.len(),
+            enabled: false,
+            sides: 4,
+            additive: false,
+            thick_outline: false,
+            textured: false,
+            num_inst: 1,
+            x: 0.5,
+            y: 0.5,
+            rad: 0.1,
+            ang: 0.0,
+            tex_ang: 0.0,
+            tex_zoom: 1.0,
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+            a: 1.0,
+            r2: 0.0,
+            g2: 0.0,
+            b2: 0.0,
+            a2: 0.0,
+            border_r: 1.0,
+            border_g: 1.0,
+            border_b: 1.0,
+            border_a: 0.0,
+            per_frame_equations: Vec::new(),
+            per_frame_init_equations: Vec::new(),
+        });
+    }
+
+    Some(&mut shapes[index])
+}
+
+/// A `wavecode_N_param` or `shapecode_N_param` key being parsed.
+struct CodeKey<'a> {
+    prefix: &'static str,
+    index: usize,
+    param: &'a str,
+    options: ParseOptions,
+}
+
+impl<'a> CodeKey<'a> {
+    fn new(prefix: &'static str, index: usize, param: &'a str, options: ParseOptions) -> Self {
+        Self {
+            prefix,
+            index,
+            param,
+            options,
         }
+    }
+
+    fn name(&self) -> String {
+        format!("{}_{}_{}", self.prefix, self.index, self.param)
+    }
 
-        // Parse parameter
-        let wave = &mut waves[index];
-        match param.as_str() {
-            "enabled" => wave.enabled = value == "1",
-            "samples" => wave.samples = value.parse().unwrap_or(512),
-            "sep" => wave.sep = value.parse().unwrap_or(0),
-            "bSpectrum" => wave.b_spectrum = value == "1",
-            "bUseDots" => wave.b_use_dots = value == "1",
-            "bDrawThick" => wave.b_draw_thick = value == "1",
-            "bAdditive" => wave.b_additive = value == "1",
-            "scaling" => wave.scaling = value.parse().unwrap_or(1.0),
-            "smoothing" => wave.smoothing = value.parse().unwrap_or(0.5),
-            "r" => wave.r = value.parse().unwrap_or(1.0),
-            "g" => wave.g = value.parse().unwrap_or(1.0),
-            "b" => wave.b = value.parse().unwrap_or(1.0),
-            "a" => wave.a = value.parse().unwrap_or(1.0),
-            _ => {} // Ignore unknown parameters
+    /// Parse a numeric value, using `default` for malformed input unless strict.
+    fn number<T: std::str::FromStr + std::fmt::Display>(
+        &self,
+        value: &str,
+        default: T,
+    ) -> Result<T> {
+        match value.parse() {
+            Ok(parsed) => Ok(parsed),
+            Err(_) => self.invalid(value, default, "Expected a number"),
         }
     }
 
-    Ok(())
-}
+    /// Parse a 0/1 flag, treating anything else as off unless strict.
+    fn flag(&self, value: &str) -> Result<bool> {
+        match value {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            _ => self.invalid(value, false, "Expected 0 or 1"),
+        }
+    }
 
-/// Parse shapecode line
-fn parse_shapecode_line(line: &str, shapes: &mut Vec<ShapeCode>) -> Result<()> {
-    // Extract shape index and parameter name
-    // Format: shapecode_N_param=value
-    let parts: Vec<&str> = line.split('_').collect();
-    if parts.len() < 3 {
-        return Ok(()); // Skip malformed lines
-    }
-
-    let index: usize = parts[1].parse().unwrap_or(0);
-    let param_and_value = line.split_once('=');
-
-    if let Some((param_full, value)) = param_and_value {
-        let param = param_full.split('_').skip(2).collect::<Vec<_>>().join("_");
-
-        // Ensure shape exists
-        while shapes.len() <= index {
-            shapes.push(ShapeCode {
-                index: shapes.len(),
-                enabled: false,
-                sides: 4,
-                additive: false,
-                thick_outline: false,
-                textured: false,
-                num_inst: 1,
-                x: 0.5,
-                y: 0.5,
-                rad: 0.1,
-                ang: 0.0,
-                tex_ang: 0.0,
-                tex_zoom: 1.0,
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-                a: 1.0,
-                r2: 0.0,
-                g2: 0.0,
-                b2: 0.0,
-                a2: 0.0,
-                border_r: 1.0,
-                border_g: 1.0,
-                border_b: 1.0,
-                border_a: 0.0,
-                per_frame_equations: Vec::new(),
-                per_frame_init_equations: Vec::new(),
+    fn invalid<T: std::fmt::Display>(&self, value: &str, default: T, reason: &str) -> Result<T> {
+        if self.options.strict {
+            return Err(ParseError::InvalidParameter {
+                name: self.name(),
+                value: value.to_string(),
+                reason: reason.to_string(),
             });
         }
+        log::warn!(
+            "Invalid {}={}, using default {}",
+            self.name(),
+            value,
+            default
+        );
+        Ok(default)
+    }
+}
 
-        // Parse parameter
-        let shape = &mut shapes[index];
-        match param.as_str() {
-            "enabled" => shape.enabled = value == "1",
-            "sides" => shape.sides = value.parse().unwrap_or(4),
-            "additive" => shape.additive = value == "1",
-            "thickOutline" => shape.thick_outline = value == "1",
-            "textured" => shape.textured = value == "1",
-            "num_inst" | "num inst" => shape.num_inst = value.parse().unwrap_or(1),
-            "x" => shape.x = value.parse().unwrap_or(0.5),
-            "y" => shape.y = value.parse().unwrap_or(0.5),
-            "rad" => shape.rad = value.parse().unwrap_or(0.1),
-            "ang" => shape.ang = value.parse().unwrap_or(0.0),
-            "tex_ang" | "tex ang" => shape.tex_ang = value.parse().unwrap_or(0.0),
-            "tex_zoom" | "tex zoom" => shape.tex_zoom = value.parse().unwrap_or(1.0),
-            "r" => shape.r = value.parse().unwrap_or(1.0),
-            "g" => shape.g = value.parse().unwrap_or(1.0),
-            "b" => shape.b = value.parse().unwrap_or(1.0),
-            "a" => shape.a = value.parse().unwrap_or(1.0),
-            "r2" => shape.r2 = value.parse().unwrap_or(0.0),
-            "g2" => shape.g2 = value.parse().unwrap_or(0.0),
-            "b2" => shape.b2 = value.parse().unwrap_or(0.0),
-            "a2" => shape.a2 = value.parse().unwrap_or(0.0),
-            "border_r" | "border r" => shape.border_r = value.parse().unwrap_or(1.0),
-            "border_g" | "border g" => shape.border_g = value.parse().unwrap_or(1.0),
-            "border_b" | "border b" => shape.border_b = value.parse().unwrap_or(1.0),
-            "border_a" | "border a" => shape.border_a = value.parse().unwrap_or(0.0),
-            _ => {} // Ignore unknown parameters
-        }
+/// Apply one `wavecode_N_param` value to its wave.
+fn parse_wavecode_param(key: &CodeKey, value: &str, waves: &mut Vec<WaveCode>) -> Result<()> {
+    let Some(wave) = ensure_wave(waves, key.index) else {
+        return Ok(());
+    };
+    match key.param {
+        "enabled" => wave.enabled = key.flag(value)?,
+        "samples" => wave.samples = key.number(value, 512)?,
+        "sep" => wave.sep = key.number(value, 0)?,
+        "bSpectrum" => wave.b_spectrum = key.flag(value)?,
+        "bUseDots" => wave.b_use_dots = key.flag(value)?,
+        "bDrawThick" => wave.b_draw_thick = key.flag(value)?,
+        "bAdditive" => wave.b_additive = key.flag(value)?,
+        "scaling" => wave.scaling = key.number(value, 1.0)?,
+        "smoothing" => wave.smoothing = key.number(value, 0.5)?,
+        "r" => wave.r = key.number(value, 1.0)?,
+        "g" => wave.g = key.number(value, 1.0)?,
+        "b" => wave.b = key.number(value, 1.0)?,
+        "a" => wave.a = key.number(value, 1.0)?,
+        _ => {} // Ignore unknown parameters
     }
+    Ok(())
+}
 
+/// Apply one `shapecode_N_param` value to its shape.
+fn parse_shapecode_param(key: &CodeKey, value: &str, shapes: &mut Vec<ShapeCode>) -> Result<()> {
+    let Some(shape) = ensure_shape(shapes, key.index) else {
+        return Ok(());
+    };
+    match key.param {
+        "enabled" => shape.enabled = key.flag(value)?,
+        "sides" => shape.sides = key.number(value, 4)?,
+        "additive" => shape.additive = key.flag(value)?,
+        "thickOutline" => shape.thick_outline = key.flag(value)?,
+        "textured" => shape.textured = key.flag(value)?,
+        "num_inst" | "num inst" => shape.num_inst = key.number(value, 1)?,
+        "x" => shape.x = key.number(value, 0.5)?,
+        "y" => shape.y = key.number(value, 0.5)?,
+        "rad" => shape.rad = key.number(value, 0.1)?,
+        "ang" => shape.ang = key.number(value, 0.0)?,
+        "tex_ang" | "tex ang" => shape.tex_ang = key.number(value, 0.0)?,
+        "tex_zoom" | "tex zoom" => shape.tex_zoom = key.number(value, 1.0)?,
+        "r" => shape.r = key.number(value, 1.0)?,
+        "g" => shape.g = key.number(value, 1.0)?,
+        "b" => shape.b = key.number(value, 1.0)?,
+        "a" => shape.a = key.number(value, 1.0)?,
+        "r2" => shape.r2 = key.number(value, 0.0)?,
+        "g2" => shape.g2 = key.number(value, 0.0)?,
+        "b2" => shape.b2 = key.number(value, 0.0)?,
+        "a2" => shape.a2 = key.number(value, 0.0)?,
+        "border_r" | "border r" => shape.border_r = key.number(value, 1.0)?,
+        "border_g" | "border g" => shape.border_g = key.number(value, 1.0)?,
+        "border_b" | "border b" => shape.border_b = key.number(value, 1.0)?,
+        "border_a" | "border a" => shape.border_a = key.number(value, 0.0)?,
+        _ => {} // Ignore unknown parameters
+    }
     Ok(())
 }
 
+/// Serialize a preset back to `.milk` text.
+///
+/// The output is canonical rather than byte-identical to the original file:
+/// all known parameters are written in a fixed order, followed by the `extra`
+/// map (sorted by key), wave and shape blocks, equations and finally shaders.
+/// Parsing the result yields a preset equal to the input.
+pub fn serialize_milk_preset(preset: &MilkPreset) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+
+    // Header
+    writeln!(out, "MILKDROP_PRESET_VERSION={}", preset.version).ok();
+    writeln!(
+        out,
+        "PSVERSION={}",
+        preset.ps_version_warp.max(preset.ps_version_comp)
+    )
+    .ok();
+    writeln!(out, "PSVERSION_WARP={}", preset.ps_version_warp).ok();
+    writeln!(out, "PSVERSION_COMP={}", preset.ps_version_comp).ok();
+    out.push_str("[preset00]\n");
+
+    // Parameters
+    for (key, value) in parameter_entries(&preset.parameters) {
+        writeln!(out, "{}={}", key, value).ok();
+    }
+
+    // Unknown parameters, sorted for deterministic output.
+    // Written before equations and shaders so they never follow a shader block.
+    let mut extra: Vec<_> = preset.parameters.extra.iter().collect();
+    extra.sort();
+    for (key, value) in extra {
+        writeln!(out, "{}={}", key, value).ok();
+    }
+
+    // Custom waves
+    for wave in &preset.waves {
+        let i = wave.index;
+        writeln!(out, "wavecode_{}_enabled={}", i, wave.enabled as i32).ok();
+        writeln!(out, "wavecode_{}_samples={}", i, wave.samples).ok();
+        writeln!(out, "wavecode_{}_sep={}", i, wave.sep).ok();
+        writeln!(out, "wavecode_{}_bSpectrum={}", i, wave.b_spectrum as i32).ok();
+        writeln!(out, "wavecode_{}_bUseDots={}", i, wave.b_use_dots as i32).ok();
+        writeln!(
+            out,
+            "wavecode_{}_bDrawThick={}",
+            i, wave.b_draw_thick as i32
+        )
+        .ok();
+        writeln!(out, "wavecode_{}_bAdditive={}", i, wave.b_additive as i32).ok();
+        writeln!(out, "wavecode_{}_scaling={}", i, wave.scaling).ok();
+        writeln!(out, "wavecode_{}_smoothing={}", i, wave.smoothing).ok();
+        writeln!(out, "wavecode_{}_r={}", i, wave.r).ok();
+        writeln!(out, "wavecode_{}_g={}", i, wave.g).ok();
+        writeln!(out, "wavecode_{}_b={}", i, wave.b).ok();
+        writeln!(out, "wavecode_{}_a={}", i, wave.a).ok();
+        write_equations(
+            &mut out,
+            &format!("wave_{}_init", i),
+            &wave.per_frame_init_equations,
+        );
+        write_equations(
+            &mut out,
+            &format!("wave_{}_per_frame", i),
+            &wave.per_frame_equations,
+        );
+        write_equations(
+            &mut out,
+            &format!("wave_{}_per_point", i),
+            &wave.per_point_equations,
+        );
+    }
+
+    // Custom shapes
+    for shape in &preset.shapes {
+        let i = shape.index;
+        writeln!(out, "shapecode_{}_enabled={}", i, shape.enabled as i32).ok();
+        writeln!(out, "shapecode_{}_sides={}", i, shape.sides).ok();
+        writeln!(out, "shapecode_{}_additive={}", i, shape.additive as i32).ok();
+        writeln!(
+            out,
+            "shapecode_{}_thickOutline={}",
+            i, shape.thick_outline as i32
+        )
+        .ok();
+        writeln!(out, "shapecode_{}_textured={}", i, shape.textured as i32).ok();
+        writeln!(out, "shapecode_{}_num_inst={}", i, shape.num_inst).ok();
+        writeln!(out, "shapecode_{}_x={}", i, shape.x).ok();
+        writeln!(out, "shapecode_{}_y={}", i, shape.y).ok();
+        writeln!(out, "shapecode_{}_rad={}", i, shape.rad).ok();
+        writeln!(out, "shapecode_{}_ang={}", i, shape.ang).ok();
+        writeln!(out, "shapecode_{}_tex_ang={}", i, shape.tex_ang).ok();
+        writeln!(out, "shapecode_{}_tex_zoom={}", i, shape.tex_zoom).ok();
+        writeln!(out, "shapecode_{}_r={}", i, shape.r).ok();
+        writeln!(out, "shapecode_{}_g={}", i, shape.g).ok();
+        writeln!(out, "shapecode_{}_b={}", i, shape.b).ok();
+        writeln!(out, "shapecode_{}_a={}", i, shape.a).ok();
+        writeln!(out, "shapecode_{}_r2={}", i, shape.r2).ok();
+        writeln!(out, "shapecode_{}_g2={}", i, shape.g2).ok();
+        writeln!(out, "shapecode_{}_b2={}", i, shape.b2).ok();
+        writeln!(out, "shapecode_{}_a2={}", i, shape.a2).ok();
+        writeln!(out, "shapecode_{}_border_r={}", i, shape.border_r).ok();
+        writeln!(out, "shapecode_{}_border_g={}", i, shape.border_g).ok();
+        writeln!(out, "shapecode_{}_border_b={}", i, shape.border_b).ok();
+        writeln!(out, "shapecode_{}_border_a={}", i, shape.border_a).ok();
+        write_equations(
+            &mut out,
+            &format!("shape_{}_init", i),
+            &shape.per_frame_init_equations,
+        );
+        write_equations(
+            &mut out,
+            &format!("shape_{}_per_frame", i),
+            &shape.per_frame_equations,
+        );
+    }
+
+    // Main equations
+    write_equations(
+        &mut out,
+        "per_frame_init_",
+        &preset.per_frame_init_equations,
+    );
+    write_equations(&mut out, "per_frame_", &preset.per_frame_equations);
+    write_equations(&mut out, "per_pixel_", &preset.per_pixel_equations);
+
+    // Shaders
+    write_shader(&mut out, "warp_", preset.warp_shader.as_deref());
+    write_shader(&mut out, "comp_", preset.comp_shader.as_deref());
+
+    out
+}
+
+/// Known parameters as `(key, value)` pairs in canonical order.
+fn parameter_entries(params: &PresetParameters) -> Vec<(&'static str, String)> {
+    let b = |v: bool| (v as i32).to_string();
+
+    vec![
+        ("fRating", params.f_rating.to_string()),
+        ("fGammaAdj", params.f_gamma_adj.to_string()),
+        ("fDecay", params.f_decay.to_string()),
+        ("fVideoEchoZoom", params.f_video_echo_zoom.to_string()),
+        ("fVideoEchoAlpha", params.f_video_echo_alpha.to_string()),
+        (
+            "nVideoEchoOrientation",
+            params.n_video_echo_orientation.to_string(),
+        ),
+        ("nWaveMode", params.n_wave_mode.to_string()),
+        ("bAdditiveWaves", b(params.b_additive_waves)),
+        ("bWaveDots", b(params.b_wave_dots)),
+        ("bWaveThick", b(params.b_wave_thick)),
+        (
+            "bModWaveAlphaByVolume",
+            b(params.b_mod_wave_alpha_by_volume),
+        ),
+        ("bMaximizeWaveColor", b(params.b_maximize_wave_color)),
+        ("bTexWrap", b(params.b_tex_wrap)),
+        ("bDarkenCenter", b(params.b_darken_center)),
+        ("bRedBlueStereo", b(params.b_red_blue_stereo)),
+        ("bBrighten", b(params.b_brighten)),
+        ("bDarken", b(params.b_darken)),
+        ("bSolarize", b(params.b_solarize)),
+        ("bInvert", b(params.b_invert)),
+        ("fWaveAlpha", params.f_wave_alpha.to_string()),
+        ("fWaveScale", params.f_wave_scale.to_string()),
+        ("fWaveSmoothing", params.f_wave_smoothing.to_string()),
+        ("fWaveParam", params.f_wave_param.to_string()),
+        (
+            "fModWaveAlphaStart",
+            params.f_mod_wave_alpha_start.to_string(),
+        ),
+        ("fModWaveAlphaEnd", params.f_mod_wave_alpha_end.to_string()),
+        ("fWarpAnimSpeed", params.f_warp_anim_speed.to_string()),
+        ("fWarpScale", params.f_warp_scale.to_string()),
+        ("fZoomExponent", params.f_zoom_exponent.to_string()),
+        ("fShader", params.f_shader.to_string()),
+        ("zoom", params.zoom.to_string()),
+        ("rot", params.rot.to_string()),
+        ("cx", params.cx.to_string()),
+        ("cy", params.cy.to_string()),
+        ("dx", params.dx.to_string()),
+        ("dy", params.dy.to_string()),
+        ("warp", params.warp.to_string()),
+        ("sx", params.sx.to_string()),
+        ("sy", params.sy.to_string()),
+        ("wave_r", params.wave_r.to_string()),
+        ("wave_g", params.wave_g.to_string()),
+        ("wave_b", params.wave_b.to_string()),
+        ("wave_x", params.wave_x.to_string()),
+        ("wave_y", params.wave_y.to_string()),
+        ("ob_size", params.ob_size.to_string()),
+        ("ob_r", params.ob_r.to_string()),
+        ("ob_g", params.ob_g.to_string()),
+        ("ob_b", params.ob_b.to_string()),
+        ("ob_a", params.ob_a.to_string()),
+        ("ib_size", params.ib_size.to_string()),
+        ("ib_r", params.ib_r.to_string()),
+        ("ib_g", params.ib_g.to_string()),
+        ("ib_b", params.ib_b.to_string()),
+        ("ib_a", params.ib_a.to_string()),
+        ("nMotionVectorsX", params.n_motion_vectors_x.to_string()),
+        ("nMotionVectorsY", params.n_motion_vectors_y.to_string()),
+        ("mv_dx", params.mv_dx.to_string()),
+        ("mv_dy", params.mv_dy.to_string()),
+        ("mv_l", params.mv_l.to_string()),
+        ("mv_r", params.mv_r.to_string()),
+        ("mv_g", params.mv_g.to_string()),
+        ("mv_b", params.mv_b.to_string()),
+        ("mv_a", params.mv_a.to_string()),
+        ("b1n", params.b1n.to_string()),
+        ("b2n", params.b2n.to_string()),
+        ("b3n", params.b3n.to_string()),
+        ("b1x", params.b1x.to_string()),
+        ("b2x", params.b2x.to_string()),
+        ("b3x", params.b3x.to_string()),
+        ("b1ed", params.b1ed.to_string()),
+    ]
+}
+
+/// Write numbered equation lines (e.g. "per_frame_1=...").
+fn write_equations(out: &mut String, prefix: &str, equations: &[String]) {
+    use std::fmt::Write;
+
+    for (i, equation) in equations.iter().enumerate() {
+        writeln!(out, "{}{}={}", prefix, i + 1, equation).ok();
+    }
+}
+
+/// Write numbered backtick-prefixed shader lines (e.g. "warp_1=`...").
+fn write_shader(out: &mut String, prefix: &str, shader: Option<&str>) {
+    use std::fmt::Write;
+
+    if let Some(shader) = shader {
+        for (i, line) in shader.lines().enumerate() {
+            writeln!(out, "{}{}=`{}", prefix, i + 1, line).ok();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -407,4 +1107,381 @@ mod tests {
         let line = "warp_1=`shader_body";
         assert_eq!(parse_shader_line(line), "shader_body");
     }
+
+    #[test]
+    fn test_parse_single_line_escaped_shader() {
+        let input = "[preset00]\nwarp_1=`line1\\nline2\n";
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.warp_shader.as_deref(), Some("line1\nline2\n"));
+        assert_eq!(preset.warp_shader.unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+PSVERSION_WARP=2
+PSVERSION_COMP=3
+[preset00]
+fRating=4.5
+zoom=0.99197
+bTexWrap=1
+wavecode_0_enabled=1
+wavecode_0_r=0.25
+shapecode_1_sides=6
+per_frame_1=wave_r = 0.5;
+per_pixel_1=zoom = zoom + 0.1*rad;
+warp_1=`shader_body
+warp_2=`{
+warp_3=`}
+MyCustomKey=abc
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+        let serialized = serialize_milk_preset(&preset);
+
+        assert!(serialized.starts_with("MILKDROP_PRESET_VERSION=201\n"));
+        assert!(serialized.contains("PSVERSION_COMP=3\n"));
+        assert!(serialized.contains("per_frame_1=wave_r = 0.5;\n"));
+        assert!(serialized.contains("warp_2=`{\n"));
+        assert!(serialized.contains("MyCustomKey=abc\n"));
+
+        assert_eq!(parse_milk_preset(&serialized).unwrap(), preset);
+    }
+
+    #[test]
+    fn test_unspaced_hlsl_stays_in_shader_block() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+MyCustomKey=abc
+warp_1=`shader_body
+{
+ret=tex2D(sampler_main,uv).xyz;
+ret.x=1;
+}
+zoom=0.9
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert_eq!(
+            preset.warp_shader.as_deref(),
+            Some("shader_body\n{\nret=tex2D(sampler_main,uv).xyz;\nret.x=1;\n}\n")
+        );
+        assert_eq!(preset.parameters.zoom, 0.9);
+        assert_eq!(preset.parameters.extra["MyCustomKey"], "abc");
+        assert!(!preset.parameters.extra.contains_key("ret"));
+    }
+
+    #[test]
+    fn test_parse_per_frame_init_equations() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+per_frame_init_2=q2 = 2;
+per_frame_init_10=q10 = 10;
+per_frame_init_1=q1 = 1;
+per_frame_1=zoom = q1;
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert_eq!(
+            preset.per_frame_init_equations,
+            vec!["q1 = 1;", "q2 = 2;", "q10 = 10;"]
+        );
+        assert_eq!(preset.per_frame_equations, vec!["zoom = q1;"]);
+    }
+
+    #[test]
+    fn test_parse_wave_per_point_equations() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+wavecode_0_enabled=1
+wavecode_0_per_point1=x = sample;
+wavecode_0_per_point3=r = 0.5;
+wavecode_0_per_point2=y = 0.5 + value1;
+wavecode_0_per_frame1=t1 = time;
+wave_1_init1=t2 = 0;
+wave_1_per_point1=a = 1;
+wave_r=0.25
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert_eq!(
+            preset.waves[0].per_point_equations,
+            vec!["x = sample;", "y = 0.5 + value1;", "r = 0.5;"]
+        );
+        assert_eq!(preset.waves[0].per_frame_equations, vec!["t1 = time;"]);
+        assert_eq!(preset.waves[1].per_frame_init_equations, vec!["t2 = 0;"]);
+        assert_eq!(preset.waves[1].per_point_equations, vec!["a = 1;"]);
+
+        // Scalar wave parameters are untouched
+        assert_eq!(preset.parameters.wave_r, 0.25);
+        assert!(preset.parameters.extra.is_empty());
+    }
+
+    #[test]
+    fn test_decimal_comma_values() {
+        let input = "MILKDROP_PRESET_VERSION=201\n[preset00]\nfDecay=0,95\nzoom=1,01\n";
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.parameters.decay(), 0.95);
+        assert_eq!(preset.parameters.zoom, 1.01);
+
+        assert_eq!(parse_decimal_comma("0,95"), Some(0.95));
+        assert_eq!(parse_decimal_comma("0.95"), None);
+        assert_eq!(parse_decimal_comma("1,2,3"), None);
+
+        let input = "MILKDROP_PRESET_VERSION=201\n[preset00]\nfDecay=1,2,3\n";
+        assert!(parse_milk_preset(input).is_err());
+    }
+
+    #[test]
+    fn test_time_between_presets() {
+        let input = "MILKDROP_PRESET_VERSION=201\n[preset00]\nfTimeBetweenPresets=12.5\n";
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.parameters.time_between_presets(), Some(12.5));
+
+        let input = "MILKDROP_PRESET_VERSION=201\n[preset00]\nzoom=1.0\n";
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.parameters.time_between_presets(), None);
+    }
+
+    #[test]
+    fn test_parse_shape_equations() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+shapecode_2_sides=5
+shape_2_init1=t1 = 1;
+shape_2_per_frame2=ang = time;
+shape_2_per_frame1=x = 0.5;
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert_eq!(preset.shapes.len(), 3);
+        assert_eq!(preset.shapes[2].sides, 5);
+        assert_eq!(preset.shapes[2].per_frame_init_equations, vec!["t1 = 1;"]);
+        assert_eq!(
+            preset.shapes[2].per_frame_equations,
+            vec!["x = 0.5;", "ang = time;"]
+        );
+    }
+
+    #[test]
+    fn test_parse_code_param_variants() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+wave_r=0.3
+shapecode_0_thickOutline=1
+shapecode_0_border_r=0.5
+shape_1_num_inst = 3
+wave_0_bAdditive=1
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert!(preset.shapes[0].thick_outline);
+        assert_eq!(preset.shapes[0].border_r, 0.5);
+        assert_eq!(preset.shapes[1].num_inst, 3);
+        assert!(preset.waves[0].b_additive);
+        // A global parameter sharing the legacy prefix is left alone
+        assert_eq!(preset.parameters.wave_r, 0.3);
+        assert_eq!(preset.waves.len(), 1);
+    }
+
+    #[test]
+    fn test_out_of_range_code_index_ignored() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+wavecode_1_enabled=1
+wavecode_4_enabled=1
+wave_4_per_frame1=r = 1;
+shapecode_1000000_sides=5
+shape_7_init1=t1 = 1;
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert_eq!(preset.waves.len(), 2);
+        assert!(preset.waves[1].enabled);
+        assert!(preset.shapes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_preset_bank() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+PSVERSION_WARP=2
+[preset00]
+zoom=1.01
+per_frame_1=q1 = bass;
+warp_1=`shader_body {
+warp_2=`}
+[preset01]
+zoom=0.95
+per_frame_1=q2 = treb;
+per_pixel_1=rot = rot + 0.01;
+"#;
+        let presets = parse_milk_preset_bank(input).unwrap();
+        assert_eq!(presets.len(), 2);
+
+        assert_eq!(presets[0].parameters.zoom, 1.01);
+        assert_eq!(presets[0].per_frame_equations, vec!["q1 = bass;"]);
+        assert!(presets[0].per_pixel_equations.is_empty());
+        assert!(presets[0].warp_shader.is_some());
+
+        assert_eq!(presets[1].parameters.zoom, 0.95);
+        assert_eq!(presets[1].per_frame_equations, vec!["q2 = treb;"]);
+        assert_eq!(presets[1].per_pixel_equations, vec!["rot = rot + 0.01;"]);
+        assert!(presets[1].warp_shader.is_none());
+
+        // The shared header applies to every preset
+        assert!(
+            presets
+                .iter()
+                .all(|p| p.version == 201 && p.ps_version_warp == 2)
+        );
+
+        // A single-preset parse keeps only the first section
+        assert_eq!(parse_milk_preset(input).unwrap(), presets[0]);
+    }
+
+    #[test]
+    fn test_parse_invalid_rating_uses_default() {
+        for rating in ["", "n/a", "N/A"] {
+            let input = format!(
+                "MILKDROP_PRESET_VERSION=201\n[preset00]\nfRating={}\nzoom=1.5\n",
+                rating
+            );
+            let preset = parse_milk_preset(&input).unwrap();
+            assert_eq!(preset.parameters.f_rating, DEFAULT_RATING);
+            assert_eq!(preset.parameters.zoom, 1.5);
+        }
+
+        // Missing rating line also gets the neutral default
+        let preset =
+            parse_milk_preset("MILKDROP_PRESET_VERSION=201\n[preset00]\nzoom=1.5\n").unwrap();
+        assert_eq!(preset.parameters.f_rating, DEFAULT_RATING);
+        assert_eq!(PresetParameters::default().f_rating, DEFAULT_RATING);
+    }
+
+    #[test]
+    fn test_strict_code_values() {
+        let input = "MILKDROP_PRESET_VERSION=201\n[preset00]\nshapecode_0_sides=abc\n";
+
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.shapes[0].sides, 4);
+
+        let err = parse_milk_preset_with_options(input, ParseOptions::strict()).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::InvalidParameter {
+                name: "shapecode_0_sides".to_string(),
+                value: "abc".to_string(),
+                reason: "Expected a number".to_string(),
+            }
+        );
+
+        // Flags are checked too; well-formed presets parse the same either way
+        let flag = "[preset00]\nwavecode_1_enabled=yes\n";
+        assert!(!parse_milk_preset(flag).unwrap().waves[1].enabled);
+        assert!(parse_milk_preset_with_options(flag, ParseOptions::strict()).is_err());
+
+        let valid = "[preset00]\nwavecode_0_enabled=1\nwavecode_0_samples=256\n";
+        assert_eq!(
+            parse_milk_preset_with_options(valid, ParseOptions::strict()).unwrap(),
+            parse_milk_preset(valid).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_equation_source_lines() {
+        let input = "MILKDROP_PRESET_VERSION=201
+[preset00]
+zoom=1.0
+per_frame_init_2=q2 = 2;
+per_frame_1=wave_r = 0.5;
+
+per_frame_2=wave_g = bass;
+per_pixel_1=zoom = zoom + 0.1;
+per_frame_init_1=q1 = 1;
+";
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.per_frame_equations[1], "wave_g = bass;");
+        assert_eq!(preset.source_lines.per_frame, vec![5, 7]);
+        assert_eq!(preset.source_lines.per_pixel, vec![8]);
+        // Init equations are sorted by index; their lines follow them
+        assert_eq!(preset.per_frame_init_equations[0], "q1 = 1;");
+        assert_eq!(preset.source_lines.per_frame_init, vec![9, 4]);
+
+        // Line numbers don't affect preset equality
+        let mut moved = preset.clone();
+        moved.source_lines = SourceLines::default();
+        assert_ne!(moved.source_lines, preset.source_lines);
+        assert_eq!(moved, preset);
+    }
+
+    #[test]
+    fn test_code_equation_source_lines() {
+        let input = "MILKDROP_PRESET_VERSION=201
+[preset00]
+wavecode_1_enabled=1
+wave_1_per_point2=y = 0.5;
+wave_1_per_frame1=t1 = 1;
+wave_1_per_point1=x = 0.5;
+shapecode_0_enabled=1
+shape_0_init1=t1 = 0;
+shape_0_per_frame1=x = 0.5;
+";
+        let preset = parse_milk_preset(input).unwrap();
+        assert_eq!(preset.waves[1].per_point_equations[0], "x = 0.5;");
+        assert_eq!(
+            preset.source_lines.waves[1],
+            CodeSourceLines {
+                per_frame_init: vec![],
+                per_frame: vec![5],
+                per_point: vec![6, 4],
+            }
+        );
+        assert_eq!(preset.source_lines.waves[0], CodeSourceLines::default());
+        assert_eq!(preset.source_lines.shapes[0].per_frame_init, vec![8]);
+        assert_eq!(preset.source_lines.shapes[0].per_frame, vec![9]);
+    }
+
+    #[test]
+    fn test_parse_multiline_warp_shader() {
+        let input = r#"MILKDROP_PRESET_VERSION=201
+[preset00]
+zoom=1.0
+warp_1=`shader_body
+{
+    float2 uv2 = uv * 0.5;
+    ret = tex2D(sampler_main, uv2).xyz;
+}
+comp_1=`shader_body { ret = 1; }
+per_frame_1=zoom = 1.1;
+"#;
+        let preset = parse_milk_preset(input).unwrap();
+
+        assert_eq!(
+            preset.warp_shader.as_deref(),
+            Some(
+                "shader_body\n{\n    float2 uv2 = uv * 0.5;\n    ret = tex2D(sampler_main, uv2).xyz;\n}\n"
+            )
+        );
+        assert_eq!(preset.warp_shader.as_deref().unwrap().lines().count(), 5);
+        assert_eq!(
+            preset.comp_shader.as_deref(),
+            Some("shader_body { ret = 1; }\n")
+        );
+        assert_eq!(preset.per_frame_equations, vec!["zoom = 1.1;"]);
+        assert!(preset.parameters.extra.is_empty());
+
+        // Continuation lines survive a serialize/parse round trip
+        assert_eq!(
+            parse_milk_preset(&serialize_milk_preset(&preset)).unwrap(),
+            preset
+        );
+    }
+
+    #[test]
+    fn test_known_parameters_match_serializer() {
+        let keys: Vec<&str> = parameter_entries(&PresetParameters::default())
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, KNOWN_PARAMETERS);
+    }
 }
@@ -3,8 +3,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Rating used when a preset has no `fRating`, or an empty/non-numeric one.
+pub const DEFAULT_RATING: f32 = 3.0;
+
 /// A complete Milkdrop preset.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MilkPreset {
     /// Preset version (e.g., 201 for Milkdrop 2.0)
     pub version: u32,
@@ -38,10 +41,76 @@ pub struct MilkPreset {
 
     /// Composite shader code (HLSL/GLSL)
     pub comp_shader: Option<String>,
+
+    /// Line numbers the equations were parsed from
+    #[serde(skip)]
+    pub source_lines: SourceLines,
+}
+
+/// Presets are equal when their contents are: the
+/// [`source_lines`](MilkPreset::source_lines) they were parsed from aren't
+/// part of a preset's identity.
+impl PartialEq for MilkPreset {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            version,
+            ps_version_warp,
+            ps_version_comp,
+            parameters,
+            per_frame_equations,
+            per_pixel_equations,
+            per_frame_init_equations,
+            waves,
+            shapes,
+            warp_shader,
+            comp_shader,
+            source_lines: _,
+        } = self;
+        *version == other.version
+            && *ps_version_warp == other.ps_version_warp
+            && *ps_version_comp == other.ps_version_comp
+            && *parameters == other.parameters
+            && *per_frame_equations == other.per_frame_equations
+            && *per_pixel_equations == other.per_pixel_equations
+            && *per_frame_init_equations == other.per_frame_init_equations
+            && *waves == other.waves
+            && *shapes == other.shapes
+            && *warp_shader == other.warp_shader
+            && *comp_shader == other.comp_shader
+    }
+}
+
+/// 1-based line numbers of parsed equations, parallel to the preset's
+/// equation vectors. Empty for presets built in code.
+///
+/// Not part of a preset's identity: [`MilkPreset`]'s `PartialEq` ignores it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceLines {
+    /// Lines of `per_frame_init_equations`
+    pub per_frame_init: Vec<usize>,
+    /// Lines of `per_frame_equations`
+    pub per_frame: Vec<usize>,
+    /// Lines of `per_pixel_equations`
+    pub per_pixel: Vec<usize>,
+    /// Lines of each custom wave's equations, indexed like `waves`
+    pub waves: Vec<CodeSourceLines>,
+    /// Lines of each custom shape's equations, indexed like `shapes`
+    pub shapes: Vec<CodeSourceLines>,
+}
+
+/// 1-based line numbers of one custom wave's or shape's equations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeSourceLines {
+    /// Lines of `per_frame_init_equations`
+    pub per_frame_init: Vec<usize>,
+    /// Lines of `per_frame_equations`
+    pub per_frame: Vec<usize>,
+    /// Lines of `per_point_equations` (always empty for shapes)
+    pub per_point: Vec<usize>,
 }
 
 /// Base parameters for a preset (static values).
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PresetParameters {
     // Rating and visual adjustments
     pub f_rating: f32,
@@ -136,7 +205,133 @@ pub struct PresetParameters {
     pub extra: HashMap<String, String>,
 }
 
+/// All parameters unset (zero), except the rating, which is
+/// [`DEFAULT_RATING`] like in presets that don't set one.
+impl Default for PresetParameters {
+    fn default() -> Self {
+        Self {
+            // Rating and visual adjustments
+            f_rating: DEFAULT_RATING,
+            f_gamma_adj: 0.0,
+            f_decay: 0.0,
+            f_video_echo_zoom: 0.0,
+            f_video_echo_alpha: 0.0,
+            n_video_echo_orientation: 0,
+
+            // Wave settings
+            n_wave_mode: 0,
+            b_additive_waves: false,
+            b_wave_dots: false,
+            b_wave_thick: false,
+            b_mod_wave_alpha_by_volume: false,
+            b_maximize_wave_color: false,
+            f_wave_alpha: 0.0,
+            f_wave_scale: 0.0,
+            f_wave_smoothing: 0.0,
+            f_wave_param: 0.0,
+            f_mod_wave_alpha_start: 0.0,
+            f_mod_wave_alpha_end: 0.0,
+
+            // Rendering options
+            b_tex_wrap: false,
+            b_darken_center: false,
+            b_red_blue_stereo: false,
+            b_brighten: false,
+            b_darken: false,
+            b_solarize: false,
+            b_invert: false,
+
+            // Warp settings
+            f_warp_anim_speed: 0.0,
+            f_warp_scale: 0.0,
+            f_zoom_exponent: 0.0,
+            f_shader: 0.0,
+
+            // Motion parameters (can be modified by per-frame equations)
+            zoom: 0.0,
+            rot: 0.0,
+            cx: 0.0,
+            cy: 0.0,
+            dx: 0.0,
+            dy: 0.0,
+            warp: 0.0,
+            sx: 0.0,
+            sy: 0.0,
+
+            // Wave color
+            wave_r: 0.0,
+            wave_g: 0.0,
+            wave_b: 0.0,
+            wave_x: 0.0,
+            wave_y: 0.0,
+
+            // Outer border
+            ob_size: 0.0,
+            ob_r: 0.0,
+            ob_g: 0.0,
+            ob_b: 0.0,
+            ob_a: 0.0,
+
+            // Inner border
+            ib_size: 0.0,
+            ib_r: 0.0,
+            ib_g: 0.0,
+            ib_b: 0.0,
+            ib_a: 0.0,
+
+            // Motion vectors
+            n_motion_vectors_x: 0.0,
+            n_motion_vectors_y: 0.0,
+            mv_dx: 0.0,
+            mv_dy: 0.0,
+            mv_l: 0.0,
+            mv_r: 0.0,
+            mv_g: 0.0,
+            mv_b: 0.0,
+            mv_a: 0.0,
+
+            // Beat detection parameters
+            b1n: 0.0,
+            b2n: 0.0,
+            b3n: 0.0,
+            b1x: 0.0,
+            b2x: 0.0,
+            b3x: 0.0,
+            b1ed: 0.0,
+
+            // Additional parameters stored as key-value pairs
+            extra: HashMap::new(),
+        }
+    }
+}
+
 impl PresetParameters {
+    /// Check whether the base motion parameters are at MilkDrop's defaults.
+    ///
+    /// Unset values (`0.0`) are treated as default.
+    pub fn has_default_motion(&self) -> bool {
+        let default_or_unset = |v: f32, default: f32| v == 0.0 || (v - default).abs() < 1e-4;
+        let zero = |v: f32| v.abs() < 1e-4;
+
+        default_or_unset(self.zoom, 1.0)
+            && default_or_unset(self.sx, 1.0)
+            && default_or_unset(self.sy, 1.0)
+            && default_or_unset(self.cx, 0.5)
+            && default_or_unset(self.cy, 0.5)
+            && zero(self.rot)
+            && zero(self.dx)
+            && zero(self.dy)
+            && zero(self.warp)
+    }
+
+    /// Preset-requested auto-advance interval in seconds (`fTimeBetweenPresets`).
+    pub fn time_between_presets(&self) -> Option<f32> {
+        self.extra
+            .get("fTimeBetweenPresets")
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .filter(|secs| *secs > 0.0)
+    }
+
     // Compatibility getters for engine (without prefixes)
     pub fn zoomexp(&self) -> f32 {
         self.f_zoom_exponent
@@ -243,6 +438,65 @@ pub struct ShapeCode {
     pub per_frame_init_equations: Vec<String>,
 }
 
+impl MilkPreset {
+    /// Check whether this preset does nothing beyond MilkDrop's defaults.
+    ///
+    /// A blank preset has no equations of any kind, no shaders, no enabled
+    /// custom waves/shapes, and motion parameters left at their defaults.
+    pub fn is_blank(&self) -> bool {
+        self.per_frame_equations.is_empty()
+            && self.per_frame_init_equations.is_empty()
+            && self.is_default()
+    }
+
+    /// Check whether this preset is a default/warm-up preset.
+    ///
+    /// Like [`is_blank`](Self::is_blank), but per-frame equations are allowed,
+    /// as long as there is no per-pixel code, no shaders, no enabled custom
+    /// waves/shapes, and the base motion parameters are at their defaults.
+    /// The engine's fallback preset falls in this category.
+    pub fn is_default(&self) -> bool {
+        self.per_pixel_equations.is_empty()
+            && self.warp_shader.is_none()
+            && self.comp_shader.is_none()
+            && !self.waves.iter().any(|w| w.enabled)
+            && !self.shapes.iter().any(|s| s.enabled)
+            && self.parameters.has_default_motion()
+    }
+
+    /// Rewrite every equation with canonical spacing.
+    ///
+    /// See [`crate::normalize::normalize_equation`]. Presets differing only
+    /// in equation whitespace are equal afterwards.
+    pub fn normalize_equations(&mut self) {
+        let normalize = |equations: &mut Vec<String>| {
+            for equation in equations.iter_mut() {
+                *equation = crate::normalize::normalize_equation(equation);
+            }
+        };
+
+        normalize(&mut self.per_frame_init_equations);
+        normalize(&mut self.per_frame_equations);
+        normalize(&mut self.per_pixel_equations);
+        for wave in &mut self.waves {
+            normalize(&mut wave.per_frame_init_equations);
+            normalize(&mut wave.per_frame_equations);
+            normalize(&mut wave.per_point_equations);
+        }
+        for shape in &mut self.shapes {
+            normalize(&mut shape.per_frame_init_equations);
+            normalize(&mut shape.per_frame_equations);
+        }
+    }
+
+    /// Serialize this preset back to `.milk` text.
+    ///
+    /// See [`crate::parser::serialize_milk_preset`] for the output format.
+    pub fn to_milk_string(&self) -> String {
+        crate::parser::serialize_milk_preset(self)
+    }
+}
+
 impl Default for MilkPreset {
     fn default() -> Self {
         Self {
@@ -257,6 +511,7 @@ impl Default for MilkPreset {
             shapes: Vec::new(),
             warp_shader: None,
             comp_shader: None,
+            source_lines: SourceLines::default(),
         }
     }
 }
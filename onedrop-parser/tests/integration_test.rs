@@ -1,4 +1,4 @@
-use onedrop_parser::parse_preset;
+use onedrop_parser::{MilkPreset, parse_preset};
 use std::fs;
 use std::path::Path;
 
@@ -202,3 +202,70 @@ fn test_preset_structure_statistics() {
 
     assert!(total > 0, "Should analyze at least one preset");
 }
+
+#[test]
+fn test_round_trip_test_presets() {
+    let test_presets_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .join("test-presets");
+
+    if !test_presets_dir.exists() {
+        println!("Skipping test: test-presets directory not found");
+        return;
+    }
+
+    let mut checked = 0;
+    for entry in fs::read_dir(&test_presets_dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("milk") {
+            continue;
+        }
+
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(preset) = parse_preset(&content) else {
+            continue;
+        };
+
+        let serialized = preset.to_milk_string();
+        let reparsed = parse_preset(&serialized).unwrap_or_else(|e| {
+            panic!(
+                "Failed to re-parse {}: {}",
+                path.file_name().unwrap().to_string_lossy(),
+                e
+            )
+        });
+
+        assert_eq!(
+            preset,
+            reparsed,
+            "Round-trip mismatch for {}",
+            path.file_name().unwrap().to_string_lossy()
+        );
+        checked += 1;
+    }
+
+    assert!(checked > 0, "No presets were round-tripped");
+}
+
+#[test]
+fn test_blank_and_default_classification() {
+    assert!(MilkPreset::default().is_blank());
+    assert!(MilkPreset::default().is_default());
+
+    let preset_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .join("test-presets")
+        .join("144.milk");
+    if !preset_path.exists() {
+        println!("Skipping test: 144.milk not found");
+        return;
+    }
+
+    let rich = parse_preset(&fs::read_to_string(&preset_path).unwrap()).unwrap();
+    assert!(!rich.is_blank());
+    assert!(!rich.is_default());
+}
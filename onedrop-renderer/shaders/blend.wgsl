@@ -5,7 +5,8 @@ struct BlendUniforms {
     blend_pattern: u32,
     blend_amount: f32,
     time: f32,
-    _padding: f32,
+    // Nonzero: blend in linear light, decoding and re-encoding sRGB
+    gamma_correct: u32,
 }
 
 @group(0) @binding(0) var texture_a: texture_2d<f32>;
@@ -32,6 +33,22 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return output;
 }
 
+// sRGB transfer functions (alpha is left as is)
+
+fn srgb_to_linear(c: vec4<f32>) -> vec4<f32> {
+    let rgb = max(c.rgb, vec3<f32>(0.0));
+    let low = rgb / 12.92;
+    let high = pow((rgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return vec4<f32>(select(high, low, rgb <= vec3<f32>(0.04045)), c.a);
+}
+
+fn linear_to_srgb(c: vec4<f32>) -> vec4<f32> {
+    let rgb = max(c.rgb, vec3<f32>(0.0));
+    let low = rgb * 12.92;
+    let high = 1.055 * pow(rgb, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return vec4<f32>(select(high, low, rgb <= vec3<f32>(0.0031308)), c.a);
+}
+
 // Helper functions for blending
 
 fn blend_alpha(a: vec4<f32>, b: vec4<f32>, t: f32) -> vec4<f32> {
@@ -155,7 +172,7 @@ fn angular_pattern(uv: vec2<f32>, time: f32) -> f32 {
     let center = vec2<f32>(0.5, 0.5);
     let delta = uv - center;
     let angle = atan2(delta.y, delta.x);
-    return (angle + 3.14159 + time * 0.5) / (2.0 * 3.14159);
+    return fract((angle + 3.14159 + time * 0.5) / (2.0 * 3.14159));
 }
 
 fn perlin_noise_pattern(uv: vec2<f32>, time: f32) -> f32 {
@@ -200,10 +217,24 @@ fn random_pixel_pattern(uv: vec2<f32>, time: f32) -> f32 {
     return fract(sin(dot(p, vec2<f32>(12.9898, 78.233)) + time) * 43758.5453);
 }
 
+// Edge softness of pattern wipes
+const WIPE_SOFTNESS: f32 = 0.2;
+
+// Turn a pattern value into a mix factor. Pixels whose pattern value is low
+// switch to B first; blend amount 0.0 is all A and 1.0 is all B.
+fn pattern_wipe(pattern: f32, amount: f32) -> f32 {
+    let p = clamp(pattern, 0.0, 1.0);
+    return clamp((amount * (1.0 + WIPE_SOFTNESS) - p) / WIPE_SOFTNESS, 0.0, 1.0);
+}
+
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    let color_a = textureSample(texture_a, texture_sampler, input.uv);
-    let color_b = textureSample(texture_b, texture_sampler, input.uv);
+    var color_a = textureSample(texture_a, texture_sampler, input.uv);
+    var color_b = textureSample(texture_b, texture_sampler, input.uv);
+    if (uniforms.gamma_correct != 0u) {
+        color_a = srgb_to_linear(color_a);
+        color_b = srgb_to_linear(color_b);
+    }
     
     var result: vec4<f32>;
     
@@ -224,62 +255,65 @@ fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
         case 12u: { result = blend_exclusion(color_a, color_b, uniforms.blend_amount); }
         case 13u: { 
             let t = plasma_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 14u: { 
             let t = snail_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 15u: { 
             let t = triangle_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 16u: { 
             let t = donuts_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 17u: { 
             let t = checkerboard_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 18u: { 
             let t = horizontal_stripes_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 19u: { 
             let t = vertical_stripes_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 20u: { 
             let t = diagonal_stripes_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 21u: { 
             let t = radial_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 22u: { 
             let t = angular_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 23u: { 
             let t = perlin_noise_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 24u: { 
             let t = voronoi_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 25u: { 
             let t = wave_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         case 26u: { 
             let t = random_pixel_pattern(input.uv, uniforms.time);
-            result = mix(color_a, color_b, t * uniforms.blend_amount);
+            result = mix(color_a, color_b, pattern_wipe(t, uniforms.blend_amount));
         }
         default: { result = blend_alpha(color_a, color_b, uniforms.blend_amount); }
     }
-    
+
+    if (uniforms.gamma_correct != 0u) {
+        result = linear_to_srgb(result);
+    }
     return result;
 }
@@ -0,0 +1,45 @@
+// Border shader - outer and inner frame around the image
+
+struct BorderUniforms {
+    outer_color: vec4<f32>,
+    inner_color: vec4<f32>,
+    outer_size: f32,
+    inner_size: f32,
+    resolution: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: BorderUniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Full-screen quad
+    let x = f32((vertex_index & 1u) << 2u);
+    let y = f32((vertex_index & 2u) << 1u);
+
+    output.position = vec4<f32>(x - 1.0, 1.0 - y, 0.0, 1.0);
+
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = input.position.xy / uniforms.resolution;
+
+    // Distance to the nearest screen edge, as a fraction of the screen
+    let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+
+    if (edge < uniforms.outer_size) {
+        return uniforms.outer_color;
+    }
+    if (edge < uniforms.outer_size + uniforms.inner_size) {
+        return uniforms.inner_color;
+    }
+    discard;
+}
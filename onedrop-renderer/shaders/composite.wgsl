@@ -13,18 +13,30 @@ struct Uniforms {
     sx: f32,
     sy: f32,
     warp: f32,
-    _padding: f32,
+    zoomexp: f32,
+    warp_scale: f32,
+    warp_time: f32,
 }
 
+// Standard composite layout (see src/bindings.rs)
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
 @group(0) @binding(1)
-var prev_texture: texture_2d<f32>;
+var main_texture: texture_2d<f32>;
 
 @group(0) @binding(2)
 var texture_sampler: sampler;
 
+@group(0) @binding(3)
+var blur1_texture: texture_2d<f32>;
+
+@group(0) @binding(4)
+var blur2_texture: texture_2d<f32>;
+
+@group(0) @binding(5)
+var blur3_texture: texture_2d<f32>;
+
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec2<f32>,
@@ -44,10 +56,67 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return output;
 }
 
-@fragment
-fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+struct MeshInput {
+    @location(0) position: vec2<f32>,
+    @location(1) warped_uv: vec2<f32>,
+}
+
+struct MeshOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) warped_uv: vec2<f32>,
+}
+
+// Warp mesh: sample positions come from per-vertex (per-pixel equation) motion
+@vertex
+fn vs_mesh(input: MeshInput) -> MeshOutput {
+    var output: MeshOutput;
+    output.position = vec4<f32>(input.position, 0.0, 1.0);
+    output.uv = vec2<f32>(input.position.x * 0.5 + 0.5, 0.5 - input.position.y * 0.5);
+    output.warped_uv = input.warped_uv;
+    return output;
+}
+
+// Zoom at `screen_uv`: zoom^(zoomexp^(2 * rad - 1)), with rad the distance
+// from the screen center, 1 at the corners. Matches `MotionParams::zoom_at`.
+fn zoom_at(screen_uv: vec2<f32>) -> f32 {
+    if (uniforms.zoomexp == 1.0) {
+        return uniforms.zoom;
+    }
+    let rad = length(screen_uv - vec2<f32>(0.5, 0.5)) / sqrt(0.5);
+    return pow(uniforms.zoom, pow(uniforms.zoomexp, rad * 2.0 - 1.0));
+}
+
+// Milkdrop's animated warp: four sine terms over the screen whose phases
+// drift with warp_time. warp_scale widens the ripples. Matches
+// `MotionParams::warp_offset`, which the warp mesh applies per vertex.
+fn warp_offset(screen_uv: vec2<f32>) -> vec2<f32> {
+    if (uniforms.warp == 0.0) {
+        return vec2<f32>(0.0, 0.0);
+    }
+    let t = uniforms.warp_time;
+    let inv_scale = 1.0 / uniforms.warp_scale;
+    let f0 = 11.68 + 4.0 * cos(t * 1.413 + 10.0);
+    let f1 = 8.77 + 3.0 * cos(t * 1.113 + 7.0);
+    let f2 = 10.54 + 3.0 * cos(t * 1.233 + 3.0);
+    let f3 = 11.49 + 4.0 * cos(t * 0.933 + 5.0);
+    let p = screen_uv * 2.0 - 1.0;
+
+    var offset = vec2<f32>(
+        sin(t * 0.333 + inv_scale * (p.x * f0 - p.y * f3)),
+        cos(t * 0.375 - inv_scale * (p.x * f2 + p.y * f1))
+    );
+    offset += vec2<f32>(
+        cos(t * 0.753 - inv_scale * (p.x * f1 - p.y * f2)),
+        sin(t * 0.825 + inv_scale * (p.x * f0 + p.y * f3))
+    );
+    return offset * uniforms.warp * 0.0035;
+}
+
+// Where the previous frame is sampled for `uv`, from the uniform motion
+fn warp_uv(screen_uv: vec2<f32>) -> vec2<f32> {
     // Center coordinates
-    var uv = input.uv - vec2<f32>(uniforms.cx, uniforms.cy);
+    var uv = screen_uv - vec2<f32>(uniforms.cx, uniforms.cy);
     
     // Apply rotation
     let cos_r = cos(uniforms.rot);
@@ -57,28 +126,45 @@ fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
         uv.x * sin_r + uv.y * cos_r
     );
     
-    // Apply zoom
-    uv = uv / uniforms.zoom;
+    // Apply zoom, varying with the radius unless zoomexp is 1
+    uv = uv / zoom_at(screen_uv);
     
     // Apply stretch
     uv = vec2<f32>(uv.x / uniforms.sx, uv.y / uniforms.sy);
     
     // Apply translation
     uv = uv + vec2<f32>(uniforms.dx, uniforms.dy);
+
+    // Apply the animated warp
+    uv = uv + warp_offset(screen_uv);
     
     // Back to texture coordinates
-    uv = uv + vec2<f32>(uniforms.cx, uniforms.cy);
-    
+    return uv + vec2<f32>(uniforms.cx, uniforms.cy);
+}
+
+// Sample the previous frame at `uv`, then decay
+fn shade(uv: vec2<f32>) -> vec4<f32> {
     // Sample previous frame
     var color: vec4<f32>;
     if (uv.x >= 0.0 && uv.x <= 1.0 && uv.y >= 0.0 && uv.y <= 1.0) {
-        color = textureSample(prev_texture, texture_sampler, uv);
+        color = textureSample(main_texture, texture_sampler, uv);
     } else {
         color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
     }
-    
+
     // Apply decay
     color = color * uniforms.decay;
     
     return color;
 }
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(warp_uv(input.uv));
+}
+
+@fragment
+fn fs_mesh(input: MeshOutput) -> @location(0) vec4<f32> {
+    // Each vertex already carries its own warp
+    return shade(input.warped_uv);
+}
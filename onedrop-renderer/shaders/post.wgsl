@@ -0,0 +1,100 @@
+// Post-processing shader - display-only effects applied after the feedback loop
+
+struct PostUniforms {
+    brighten: f32,
+    darken: f32,
+    solarize: f32,
+    invert: f32,
+    darken_center: f32,
+    gamma: f32,
+    echo_zoom: f32,
+    echo_alpha: f32,
+    echo_orientation: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: PostUniforms;
+
+@group(0) @binding(1)
+var main_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var texture_sampler: sampler;
+
+// Same curve as onedrop_renderer::tonemap: colors in range are unchanged,
+// brighter ones have their peak scaled to 1.0 and fade towards white
+fn tonemap(c: vec3<f32>) -> vec3<f32> {
+    let v = max(c, vec3<f32>(0.0));
+    let peak = max(v.r, max(v.g, v.b));
+    if (peak <= 1.0) {
+        return v;
+    }
+    return v / (peak * peak) + (1.0 - 1.0 / peak);
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    // Full-screen quad
+    let x = f32((vertex_index & 1u) << 2u);
+    let y = f32((vertex_index & 2u) << 1u);
+
+    output.position = vec4<f32>(x - 1.0, 1.0 - y, 0.0, 1.0);
+
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // 1:1 copy of the warped frame, no filtering
+    var color = textureLoad(main_texture, vec2<i32>(input.position.xy), 0).rgb;
+    let size = vec2<f32>(textureDimensions(main_texture));
+    let uv = input.position.xy / size;
+
+    // Video echo: blend a second, zoomed and optionally flipped copy. Only
+    // the displayed image shows it; the feedback buffer stays unechoed.
+    var echo_uv = (uv - vec2<f32>(0.5, 0.5)) / uniforms.echo_zoom;
+    let orientation = u32(uniforms.echo_orientation);
+    if ((orientation & 1u) != 0u) {
+        echo_uv.x = -echo_uv.x;
+    }
+    if ((orientation & 2u) != 0u) {
+        echo_uv.y = -echo_uv.y;
+    }
+    let echo = textureSample(main_texture, texture_sampler, echo_uv + vec2<f32>(0.5, 0.5)).rgb;
+    color = mix(color, echo, uniforms.echo_alpha);
+
+    if (uniforms.darken_center > 0.5) {
+        // Radial falloff around the center, corrected for aspect ratio
+        let offset = (uv - 0.5) * vec2<f32>(size.x / size.y, 1.0);
+        let amount = 1.0 - smoothstep(0.0, 0.35, length(offset));
+        color = color * (1.0 - 0.5 * amount);
+    }
+    if (uniforms.gamma > 0.0 && uniforms.gamma != 1.0) {
+        color = pow(max(color, vec3<f32>(0.0)), vec3<f32>(1.0 / uniforms.gamma));
+    }
+
+    if (uniforms.brighten > 0.5) {
+        color = 1.0 - (1.0 - color) * (1.0 - color);
+    }
+    if (uniforms.darken > 0.5) {
+        color = color * color;
+    }
+    if (uniforms.solarize > 0.5) {
+        color = color * (1.0 - color) * 4.0;
+    }
+    if (uniforms.invert > 0.5) {
+        color = 1.0 - color;
+    }
+
+    // Float targets can hold values above 1.0; bring them into display range
+    return vec4<f32>(tonemap(color), 1.0);
+}
@@ -6,10 +6,12 @@ struct Uniforms {
     wave_mode: u32,
     wave_scale: f32,
     wave_alpha: f32,
-    wave_smoothing: f32,
+    wave_mystery: f32,
     wave_additive: u32,
     wave_dots: u32,
     wave_thick: u32,
+    wave_x: f32,
+    wave_y: f32,
     wave_color: vec4<f32>,
 }
 
@@ -22,6 +24,19 @@ struct WavePoint {
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
 @group(0) @binding(1) var<storage, read> wave_data: array<WavePoint>;
 
+const WAVE_MODE_SPECTRUM: u32 = 3u;
+const WAVE_MODE_LINE: u32 = 5u;
+
+// Samples are placed per mode on the CPU; see `wave_points` in waveform.rs.
+fn wave_position(idx: u32) -> vec2<f32> {
+    return wave_data[idx].position;
+}
+
+// At least one pixel, so thin waves don't fall between pixel centers.
+fn min_thickness() -> f32 {
+    return 1.0 / max(min(uniforms.resolution.x, uniforms.resolution.y), 1.0);
+}
+
 struct VertexInput {
     @builtin(vertex_index) vertex_index: u32,
 }
@@ -40,6 +55,8 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     let point_idx = input.vertex_index / 6u; // 6 vertices per quad
     let vertex_in_quad = input.vertex_index % 6u;
     
+    // One quad per segment between neighbouring samples, or per bar in the
+    // spectrum mode
     if (point_idx >= num_points - 1u) {
         // Out of bounds
         output.position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
@@ -48,37 +65,53 @@ fn vs_main(input: VertexInput) -> VertexOutput {
         return output;
     }
     
-    let point = wave_data[point_idx];
-    let next_point = wave_data[point_idx + 1u];
-    
-    // Calculate positions based on wave mode
-    var pos: vec2<f32>;
-    var thickness = 0.002;
-    
-    if (uniforms.wave_thick != 0u) {
-        thickness = 0.004;
-    }
-    
-    // Mode 0: Centered waveform
-    // Mode 1: Left channel
-    // Mode 2: Right channel
-    // Mode 3: Spectrum
-    
-    let x = point.position.x;
-    let y = point.value * uniforms.wave_scale;
-    
-    // Create quad vertices
-    var offset: vec2<f32>;
+    // Corner of the quad: 0 or 1 on each axis
+    var corner: vec2<f32>;
     switch (vertex_in_quad) {
-        case 0u: { offset = vec2<f32>(-thickness, -thickness); }
-        case 1u: { offset = vec2<f32>(thickness, -thickness); }
-        case 2u: { offset = vec2<f32>(thickness, thickness); }
-        case 3u: { offset = vec2<f32>(-thickness, -thickness); }
-        case 4u: { offset = vec2<f32>(thickness, thickness); }
-        default: { offset = vec2<f32>(-thickness, thickness); }
+        case 0u: { corner = vec2<f32>(0.0, 0.0); }
+        case 1u: { corner = vec2<f32>(1.0, 0.0); }
+        case 2u: { corner = vec2<f32>(1.0, 1.0); }
+        case 3u: { corner = vec2<f32>(0.0, 0.0); }
+        case 4u: { corner = vec2<f32>(1.0, 1.0); }
+        default: { corner = vec2<f32>(0.0, 1.0); }
+    }
+
+    var pos: vec2<f32>;
+    if (uniforms.wave_mode == WAVE_MODE_SPECTRUM) {
+        // Bar from the bottom edge up to the sample, leaving a gap between bars
+        let center = wave_position(point_idx);
+        let half_width = max(0.4 / f32(num_points), min_thickness() * 0.5);
+        pos = vec2<f32>(
+            center.x + mix(-half_width, half_width, corner.x),
+            center.y * corner.y
+        );
+    } else {
+        // Segment from this sample to the next, widened across its direction
+        // in pixels so steep segments are as thick as flat ones. The line
+        // mode stays one pixel wide.
+        let resolution = max(uniforms.resolution, vec2<f32>(1.0));
+        var half_width = max(0.002 / min_thickness(), 1.0);
+        if (uniforms.wave_mode == WAVE_MODE_LINE) {
+            half_width = 0.5;
+        } else if (uniforms.wave_thick != 0u) {
+            half_width *= 2.0;
+        }
+
+        let start = wave_position(point_idx);
+        let end = wave_position(point_idx + 1u);
+        let delta = (end - start) * resolution;
+        let length_px = length(delta);
+        var direction = vec2<f32>(1.0, 0.0);
+        if (length_px > 1e-6) {
+            direction = delta / length_px;
+        }
+        let normal = vec2<f32>(-direction.y, direction.x);
+
+        // Overshoot the ends by the half width, closing the gaps at joints
+        let along = mix(-half_width, length_px + half_width, corner.x);
+        let across = mix(-half_width, half_width, corner.y);
+        pos = start + (direction * along + normal * across) / resolution;
     }
-    
-    pos = vec2<f32>(x, y) + offset;
     
     // Convert to clip space (-1 to 1)
     let clip_pos = vec2<f32>(
@@ -101,17 +134,8 @@ fn vs_main(input: VertexInput) -> VertexOutput {
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    var color = input.color;
-    
-    // Apply smoothing/glow effect
-    if (uniforms.wave_smoothing > 0.0) {
-        let dist = length(input.uv - vec2<f32>(0.5));
-        let glow = exp(-dist * uniforms.wave_smoothing * 10.0);
-        color.a *= glow;
-    }
-    
-    // Additive blending handled by pipeline
-    return color;
+    // Smoothing is applied to the samples, additive blending by the pipeline
+    return input.color;
 }
 
 // Dot rendering mode
@@ -130,11 +154,14 @@ fn vs_dots(input: VertexInput) -> VertexOutput {
         return output;
     }
     
-    let point = wave_data[point_idx];
-    let dot_size = 0.005;
+    var dot_size = max(0.005, min_thickness());
+    if (uniforms.wave_thick != 0u) {
+        dot_size *= 2.0;
+    }
     
-    let x = point.position.x;
-    let y = point.value * uniforms.wave_scale;
+    let center = wave_position(point_idx);
+    let x = center.x;
+    let y = center.y;
     
     // Create quad for dot
     var offset: vec2<f32>;
@@ -151,7 +178,10 @@ fn vs_dots(input: VertexInput) -> VertexOutput {
     let clip_pos = vec2<f32>(pos.x * 2.0 - 1.0, pos.y * 2.0 - 1.0);
     
     output.position = vec4<f32>(clip_pos, 0.0, 1.0);
-    output.color = vec4<f32>(uniforms.wave_color.rgb, uniforms.wave_alpha);
+    output.color = vec4<f32>(
+        uniforms.wave_color.rgb,
+        uniforms.wave_color.a * uniforms.wave_alpha
+    );
     output.uv = offset / dot_size; // -1 to 1 within dot
     
     return output;
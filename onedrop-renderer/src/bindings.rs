@@ -0,0 +1,17 @@
+//! Standard bind group layout for composite shaders.
+//!
+//! Both the built-in `composite.wgsl` and shaders produced by
+//! `onedrop-codegen` use this layout in group 0, so a generated composite
+//! shader can be dropped into the composite pipeline unchanged.
+
+/// Composite uniforms (`var<uniform> uniforms: Uniforms`)
+pub const UNIFORMS: u32 = 0;
+
+/// Main (previous frame) texture, `sampler_main` / `GetMain` in presets
+pub const MAIN_TEXTURE: u32 = 1;
+
+/// Sampler shared by the main and blur textures
+pub const SAMPLER: u32 = 2;
+
+/// Blur textures, `GetBlur1`..`GetBlur3` in presets
+pub const BLUR_TEXTURES: [u32; 3] = [3, 4, 5];
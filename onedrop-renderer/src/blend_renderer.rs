@@ -3,6 +3,7 @@
 //! This module implements rendering of two presets simultaneously with
 //! 27 different blending patterns.
 
+use crate::config::FilterMode;
 use crate::error::Result;
 use std::sync::Arc;
 use wgpu::{Device, Queue, TextureView};
@@ -17,6 +18,10 @@ pub struct BlendRenderer {
     sampler: wgpu::Sampler,
     /// Cached bind group key and bind group for texture pair
     cached_bind_group: Option<CachedBindGroup>,
+    /// Blend in linear light instead of on sRGB-encoded values
+    gamma_correct: bool,
+    /// Textures are sRGB formats, so sampling already decodes to linear
+    srgb_textures: bool,
 }
 
 /// Holds cached bind group data
@@ -32,15 +37,19 @@ struct BlendUniforms {
     blend_pattern: u32,
     blend_amount: f32,
     time: f32,
-    _padding: f32,
+    gamma_correct: u32,
 }
 
 impl BlendRenderer {
-    /// Create a new blend renderer.
+    /// Create a blend renderer drawing into `texture_format` targets with
+    /// `sample_count` samples per pixel, sampling the presets' outputs with
+    /// `filter_mode`.
     pub fn new(
         device: Arc<Device>,
         queue: Arc<Queue>,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
+        filter_mode: FilterMode,
     ) -> Result<Self> {
         // Create uniform buffer
         let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -51,16 +60,7 @@ impl BlendRenderer {
         });
 
         // Create sampler
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("Blend Sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+        let sampler = Self::create_sampler(&device, filter_mode);
 
         // Create bind group layout
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -143,7 +143,7 @@ impl BlendRenderer {
                 compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
                 cull_mode: None,
@@ -153,7 +153,7 @@ impl BlendRenderer {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -169,6 +169,8 @@ impl BlendRenderer {
             uniform_buffer,
             sampler,
             cached_bind_group: None,
+            gamma_correct: false,
+            srgb_textures: texture_format.is_srgb(),
         })
     }
 
@@ -224,12 +226,56 @@ impl BlendRenderer {
         });
     }
 
-    /// Render blended output.
+    /// Drop the cached bind group.
+    ///
+    /// Views are cached by address, so call this after replacing textures
+    /// whose new views may live where the old ones did.
+    pub fn clear_cache(&mut self) {
+        self.cached_bind_group = None;
+    }
+
+    /// Change texture filtering used when sampling the two preset outputs.
+    pub fn set_filter_mode(&mut self, filter_mode: FilterMode) {
+        self.sampler = Self::create_sampler(&self.device, filter_mode);
+        // Cached bind group references the old sampler
+        self.cached_bind_group = None;
+    }
+
+    /// Blend in linear light, so modes like Multiply and Screen darken and
+    /// brighten the way light mixes instead of acting on gamma-encoded values.
+    ///
+    /// sRGB texture formats are already sampled as linear, so this only
+    /// changes the output for non-sRGB formats.
+    pub fn set_gamma_correct(&mut self, gamma_correct: bool) {
+        self.gamma_correct = gamma_correct;
+    }
+
+    /// Whether blending happens in linear light.
+    pub fn gamma_correct(&self) -> bool {
+        self.gamma_correct
+    }
+
+    /// Create the blend sampler.
+    fn create_sampler(device: &Device, filter_mode: FilterMode) -> wgpu::Sampler {
+        let filter = filter_mode.to_wgpu();
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blend Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            ..Default::default()
+        })
+    }
+
+    /// Render blended output into `target` (view and optional resolve target).
     pub fn render(
         &mut self,
         texture_a: &TextureView,
         texture_b: &TextureView,
-        output: &TextureView,
+        target: (&TextureView, Option<&TextureView>),
         blend_pattern: u32,
         blend_amount: f32,
         time: f32,
@@ -239,7 +285,7 @@ impl BlendRenderer {
             blend_pattern,
             blend_amount,
             time,
-            _padding: 0.0,
+            gamma_correct: (self.gamma_correct && !self.srgb_textures) as u32,
         };
         self.queue
             .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
@@ -256,6 +302,8 @@ impl BlendRenderer {
             .get_cached_bind_group()
             .expect("bind group should be cached");
 
+        let (output, resolve_target) = target;
+
         // Create command encoder
         let mut encoder = self
             .device
@@ -269,7 +317,7 @@ impl BlendRenderer {
                 label: Some("Blend Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: output,
-                    resolve_target: None,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                         store: wgpu::StoreOp::Store,
@@ -282,7 +330,8 @@ impl BlendRenderer {
 
             render_pass.set_pipeline(&self.pipeline);
             render_pass.set_bind_group(0, bind_group, &[]);
-            render_pass.draw(0..3, 0..1);
+            // Full-screen quad as a 4-vertex strip
+            render_pass.draw(0..4, 0..1);
         }
 
         // Submit commands
@@ -295,9 +344,184 @@ impl BlendRenderer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{RenderConfig, TextureFormat};
+    use crate::gpu_context::GpuContext;
 
     #[test]
     fn test_blend_uniforms_size() {
         assert_eq!(std::mem::size_of::<BlendUniforms>(), 16);
     }
+
+    /// 8x8 render target filled with a solid RGBA8 color.
+    fn solid_texture(gpu: &GpuContext, color: [u8; 4]) -> wgpu::Texture {
+        let texture = gpu.create_render_target("Solid Texture");
+        gpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &color.repeat(8 * 8),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(8 * 4),
+                rows_per_image: Some(8),
+            },
+            wgpu::Extent3d {
+                width: 8,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+        );
+        texture
+    }
+
+    /// Blend two solid colors and read back every output pixel.
+    fn blend_solid(pattern: u32, amount: f32, a: [u8; 4], b: [u8; 4]) -> Vec<[u8; 4]> {
+        blend_solid_with(pattern, amount, a, b, false)
+    }
+
+    /// [`blend_solid`], optionally blending in linear light.
+    fn blend_solid_with(
+        pattern: u32,
+        amount: f32,
+        a: [u8; 4],
+        b: [u8; 4],
+        gamma_correct: bool,
+    ) -> Vec<[u8; 4]> {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let gpu = pollster::block_on(GpuContext::new(config)).unwrap();
+        let view = |t: &wgpu::Texture| t.create_view(&wgpu::TextureViewDescriptor::default());
+        let (texture_a, texture_b) = (solid_texture(&gpu, a), solid_texture(&gpu, b));
+        let output = gpu.create_render_target("Blend Output");
+
+        let mut renderer = BlendRenderer::new(
+            gpu.device.clone(),
+            gpu.queue.clone(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            1,
+            FilterMode::Linear,
+        )
+        .unwrap();
+        renderer.set_gamma_correct(gamma_correct);
+        renderer
+            .render(
+                &view(&texture_a),
+                &view(&texture_b),
+                (&view(&output), None),
+                pattern,
+                amount,
+                0.0,
+            )
+            .unwrap();
+
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Blend Readback"),
+            size: 256 * 8,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = gpu.device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            output.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(256),
+                    rows_per_image: Some(8),
+                },
+            },
+            wgpu::Extent3d {
+                width: 8,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+        );
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        gpu.device.poll(wgpu::Maintain::Wait);
+        let data = slice.get_mapped_range();
+        (0..8)
+            .flat_map(|row| data[row * 256..row * 256 + 8 * 4].chunks(4))
+            .map(|px| [px[0], px[1], px[2], px[3]])
+            .collect()
+    }
+
+    #[test]
+    fn test_gamma_correct_multiply() {
+        let gray = [128, 128, 128, 255];
+        let plain = blend_solid_with(2, 1.0, gray, gray, false)[0];
+        let linear = blend_solid_with(2, 1.0, gray, gray, true)[0];
+
+        // Multiplying encoded values: 0.502 * 0.502
+        assert!((plain[0] as i32 - 64).abs() <= 1, "plain {:?}", plain);
+
+        // Multiplying light: decode, multiply, re-encode
+        let decode = |c: f32| ((c + 0.055) / 1.055).powf(2.4);
+        let encode = |c: f32| 1.055 * c.powf(1.0 / 2.4) - 0.055;
+        let gray = 128.0 / 255.0;
+        let expected = (encode(decode(gray) * decode(gray)) * 255.0).round() as i32;
+        assert!(
+            (linear[0] as i32 - expected).abs() <= 1,
+            "linear {:?}",
+            linear
+        );
+        assert!(linear[0] < plain[0]);
+        assert_eq!(linear[3], 255);
+    }
+
+    #[test]
+    fn test_multiply_vs_screen() {
+        let a = [128, 64, 255, 255];
+        let b = [128, 128, 128, 255];
+        let close = |got: [u8; 4], want: [u8; 3]| {
+            (0..3).all(|c| (got[c] as i32 - want[c] as i32).abs() <= 2)
+        };
+
+        // a * b
+        let multiply = blend_solid(2, 1.0, a, b);
+        assert!(
+            multiply.iter().all(|px| close(*px, [64, 32, 128])),
+            "{:?}",
+            multiply[0]
+        );
+
+        // 1 - (1 - a) * (1 - b)
+        let screen = blend_solid(3, 1.0, a, b);
+        assert!(
+            screen.iter().all(|px| close(*px, [192, 160, 255])),
+            "{:?}",
+            screen[0]
+        );
+    }
+
+    #[test]
+    fn test_pattern_wipes_reach_both_presets() {
+        let a = [255, 0, 0, 255];
+        let b = [0, 0, 255, 255];
+        // Spatial patterns (Plasma through RandomPixel)
+        for pattern in 13..=26 {
+            let start = blend_solid(pattern, 0.0, a, b);
+            assert!(
+                start.iter().all(|px| px[..3] == a[..3]),
+                "pattern {}",
+                pattern
+            );
+            let end = blend_solid(pattern, 1.0, a, b);
+            assert!(
+                end.iter().all(|px| px[..3] == b[..3]),
+                "pattern {}",
+                pattern
+            );
+        }
+    }
 }
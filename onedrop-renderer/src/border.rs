@@ -0,0 +1,176 @@
+//! Outer and inner border rendering.
+
+use crate::config::BorderParams;
+use bytemuck::{Pod, Zeroable};
+
+/// Uniforms for the border shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct BorderUniforms {
+    outer_color: [f32; 4],
+    inner_color: [f32; 4],
+    outer_size: f32,
+    inner_size: f32,
+    resolution: [f32; 2],
+}
+
+/// Draws the `ob_*`/`ib_*` borders over a frame with alpha blending.
+pub struct BorderRenderer {
+    /// Render pipeline
+    pipeline: wgpu::RenderPipeline,
+
+    /// Uniform buffer
+    uniform_buffer: wgpu::Buffer,
+
+    /// Bind group
+    bind_group: wgpu::BindGroup,
+}
+
+impl BorderRenderer {
+    /// Create a border renderer drawing onto `format` targets with
+    /// `sample_count` samples per pixel.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        // Load shader
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Border Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/border.wgsl").into()),
+        });
+
+        // Create uniform buffer
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Border Uniform Buffer"),
+            size: std::mem::size_of::<BorderUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Create bind group layout
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Border Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        // Create bind group
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Border Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        // Create pipeline layout
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Border Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Create render pipeline
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Border Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    /// Draw the borders onto `target`, which is `width` x `height` pixels: a
+    /// view and, when it is multisampled, the view it resolves into.
+    ///
+    /// Does nothing when neither border is visible.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: (&wgpu::TextureView, Option<&wgpu::TextureView>),
+        size: (u32, u32),
+        params: &BorderParams,
+    ) {
+        let visible = |size: f32, color: [f32; 4]| size > 0.0 && color[3] > 0.0;
+        let outer = visible(params.outer_size, params.outer_color);
+        let inner = visible(params.inner_size, params.inner_color);
+        if !outer && !inner {
+            return;
+        }
+
+        // The inner border keeps its place even when the outer one is hidden
+        let transparent = [0.0; 4];
+        let uniforms = BorderUniforms {
+            outer_color: if outer {
+                params.outer_color
+            } else {
+                transparent
+            },
+            inner_color: if inner {
+                params.inner_color
+            } else {
+                transparent
+            },
+            outer_size: params.outer_size.clamp(0.0, 0.5),
+            inner_size: params.inner_size.clamp(0.0, 0.5),
+            resolution: [size.0 as f32, size.1 as f32],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Border Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target.0,
+                resolve_target: target.1,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
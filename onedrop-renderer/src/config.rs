@@ -5,23 +5,37 @@ use serde::{Deserialize, Serialize};
 /// Renderer configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenderConfig {
-    /// Output resolution width
+    /// Output width in logical pixels
     pub width: u32,
 
-    /// Output resolution height
+    /// Output height in logical pixels
     pub height: u32,
 
+    /// Physical pixels per logical pixel (the window's HiDPI scale)
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f32,
+
     /// Texture format
     pub texture_format: TextureFormat,
 
-    /// Enable multisampling
-    pub msaa_samples: u32,
+    /// Samples per pixel for anti-aliased shapes and borders: 1 (off) or 4.
+    /// Falls back to 1 when the device cannot multisample the texture format.
+    #[serde(default = "default_sample_count", alias = "msaa_samples")]
+    pub sample_count: u32,
 
     /// Enable VSync
     pub vsync: bool,
 
     /// Target FPS (0 = unlimited)
     pub target_fps: u32,
+
+    /// Texture filtering for feedback and composite sampling
+    #[serde(default)]
+    pub filter_mode: FilterMode,
+
+    /// Enable GPU validation and log backend validation errors
+    #[serde(default)]
+    pub debug: bool,
 }
 
 impl Default for RenderConfig {
@@ -29,14 +43,33 @@ impl Default for RenderConfig {
         Self {
             width: 1280,
             height: 720,
+            scale_factor: default_scale_factor(),
             texture_format: TextureFormat::Bgra8UnormSrgb,
-            msaa_samples: 1,
+            sample_count: default_sample_count(),
             vsync: true,
             target_fps: 60,
+            filter_mode: FilterMode::Linear,
+            debug: false,
         }
     }
 }
 
+fn default_scale_factor() -> f32 {
+    1.0
+}
+
+fn default_sample_count() -> u32 {
+    1
+}
+
+impl RenderConfig {
+    /// Render target size in physical pixels.
+    pub fn physical_size(&self) -> (u32, u32) {
+        let scale = |logical: u32| ((logical as f32 * self.scale_factor).round() as u32).max(1);
+        (scale(self.width), scale(self.height))
+    }
+}
+
 /// Texture format options.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TextureFormat {
@@ -44,6 +77,9 @@ pub enum TextureFormat {
     Rgba8UnormSrgb,
     Bgra8Unorm,
     Rgba8Unorm,
+    /// Half-float HDR target: feedback and additive drawing keep values
+    /// above 1.0, tonemapped on output
+    Rgba16Float,
 }
 
 impl TextureFormat {
@@ -54,10 +90,45 @@ impl TextureFormat {
             TextureFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
             TextureFormat::Bgra8Unorm => wgpu::TextureFormat::Bgra8Unorm,
             TextureFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            TextureFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+
+    /// Whether texels are floats, unclamped and tonemapped on output.
+    pub fn is_float(&self) -> bool {
+        matches!(self, TextureFormat::Rgba16Float)
+    }
+}
+
+/// Texture filtering options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FilterMode {
+    /// Hard texel edges (pixel-art / retro look)
+    Nearest,
+    /// Bilinear interpolation between texels
+    #[default]
+    Linear,
+}
+
+impl FilterMode {
+    /// Convert to wgpu filter mode.
+    pub fn to_wgpu(&self) -> wgpu::FilterMode {
+        match self {
+            FilterMode::Nearest => wgpu::FilterMode::Nearest,
+            FilterMode::Linear => wgpu::FilterMode::Linear,
         }
     }
 }
 
+/// Number of magnitude bins in [`RenderState::spectrum`].
+pub const SPECTRUM_BINS: usize = 512;
+
+/// Number of audio samples in [`RenderState::waveform`].
+pub const WAVEFORM_SAMPLES: usize = 512;
+
+/// Number of custom shapes in [`RenderState::shapes`], as in Milkdrop.
+pub const MAX_SHAPES: usize = 4;
+
 /// Render state containing dynamic parameters.
 #[derive(Debug, Clone, Copy)]
 pub struct RenderState {
@@ -70,11 +141,30 @@ pub struct RenderState {
     /// Audio levels (bass, mid, treble)
     pub audio: AudioLevels,
 
+    /// Normalized (0-1) FFT magnitude per frequency bin, lowest first
+    pub spectrum: [f32; SPECTRUM_BINS],
+
+    /// Mono audio samples (-1 to 1) of the current frame, drawn as the
+    /// waveform
+    pub waveform: [f32; WAVEFORM_SAMPLES],
+
     /// Motion parameters
     pub motion: MotionParams,
 
     /// Wave parameters
     pub wave: WaveParams,
+
+    /// Video echo parameters
+    pub echo: EchoParams,
+
+    /// Post-processing flags
+    pub post: PostParams,
+
+    /// Outer and inner borders
+    pub border: BorderParams,
+
+    /// Custom shapes, drawn in order
+    pub shapes: [ShapeParams; MAX_SHAPES],
 }
 
 impl Default for RenderState {
@@ -83,8 +173,14 @@ impl Default for RenderState {
             time: 0.0,
             frame: 0,
             audio: AudioLevels::default(),
+            spectrum: [0.0; SPECTRUM_BINS],
+            waveform: [0.0; WAVEFORM_SAMPLES],
             motion: MotionParams::default(),
             wave: WaveParams::default(),
+            echo: EchoParams::default(),
+            post: PostParams::default(),
+            border: BorderParams::default(),
+            shapes: [ShapeParams::default(); MAX_SHAPES],
         }
     }
 }
@@ -117,26 +213,85 @@ impl Default for AudioLevels {
 #[derive(Debug, Clone, Copy)]
 pub struct MotionParams {
     pub zoom: f32,
+    /// Bends the zoom with the distance from the center (`fZoomExponent`);
+    /// 1.0 zooms uniformly
+    pub zoomexp: f32,
     pub rot: f32,
     pub cx: f32,
     pub cy: f32,
     pub dx: f32,
     pub dy: f32,
+    /// Strength of the animated sinusoidal warp
     pub warp: f32,
+    /// Size of the warp's ripples (`fWarpScale`); larger is smoother
+    pub warp_scale: f32,
+    /// How fast the warp animates (`fWarpAnimSpeed`)
+    pub warp_anim_speed: f32,
+    /// Phase of the animated warp: frame times accumulated at
+    /// `warp_anim_speed`, so speed changes don't make the pattern jump
+    pub warp_time: f32,
     pub sx: f32,
     pub sy: f32,
 }
 
+impl MotionParams {
+    /// Zoom at screen point (`x`, `y`): `zoom^(zoomexp^(2 * rad - 1))`, with
+    /// `rad` the distance from the screen center scaled to 1 at the corners,
+    /// as in Milkdrop. Above 1, `zoomexp` zooms the edges more than the
+    /// center (a tunnel); below 1, the center more than the edges (a
+    /// fisheye). Unset (non-positive) exponents zoom uniformly.
+    pub fn zoom_at(&self, x: f32, y: f32) -> f32 {
+        if self.zoomexp <= 0.0 || self.zoomexp == 1.0 {
+            return self.zoom;
+        }
+        let rad = (x - 0.5).hypot(y - 0.5) / 0.5f32.sqrt();
+        self.zoom.powf(self.zoomexp.powf(rad * 2.0 - 1.0))
+    }
+
+    /// Offset Milkdrop's animated warp adds to the sample position at screen
+    /// point (`x`, `y`): four sine terms whose phases drift with
+    /// [`warp_time`](Self::warp_time), scaled by `warp`. Matches
+    /// `warp_offset` in `composite.wgsl`.
+    pub fn warp_offset(&self, x: f32, y: f32) -> [f32; 2] {
+        if self.warp == 0.0 {
+            return [0.0, 0.0];
+        }
+        let t = self.warp_time;
+        // Unset (non-positive) scales fall back to Milkdrop's default
+        let inv_scale = if self.warp_scale > 0.0 {
+            1.0 / self.warp_scale
+        } else {
+            1.0
+        };
+        let f0 = 11.68 + 4.0 * (t * 1.413 + 10.0).cos();
+        let f1 = 8.77 + 3.0 * (t * 1.113 + 7.0).cos();
+        let f2 = 10.54 + 3.0 * (t * 1.233 + 3.0).cos();
+        let f3 = 11.49 + 4.0 * (t * 0.933 + 5.0).cos();
+        let (px, py) = (x * 2.0 - 1.0, y * 2.0 - 1.0);
+
+        let u = (t * 0.333 + inv_scale * (px * f0 - py * f3)).sin()
+            + (t * 0.753 - inv_scale * (px * f1 - py * f2)).cos();
+        let v = (t * 0.375 - inv_scale * (px * f2 + py * f1)).cos()
+            + (t * 0.825 + inv_scale * (px * f0 + py * f3)).sin();
+        let amount = self.warp * 0.0035;
+        [u * amount, v * amount]
+    }
+}
+
 impl Default for MotionParams {
     fn default() -> Self {
         Self {
             zoom: 1.0,
+            zoomexp: 1.0,
             rot: 0.0,
             cx: 0.5,
             cy: 0.5,
             dx: 0.0,
             dy: 0.0,
             warp: 0.0,
+            warp_scale: 1.0,
+            warp_anim_speed: 1.0,
+            warp_time: 0.0,
             sx: 1.0,
             sy: 1.0,
         }
@@ -153,6 +308,59 @@ pub struct WaveParams {
     pub x: f32,
     pub y: f32,
     pub mode: i32,
+    /// Scale the color so its brightest component is 1.0
+    /// (`bMaximizeWaveColor` / `wave_brighten`)
+    pub maximize_color: bool,
+    /// Amplitude multiplier (`fWaveScale`)
+    pub scale: f32,
+    /// Moving-average strength over the samples, 0-1 (`fWaveSmoothing`)
+    pub smoothing: f32,
+    /// Mode-specific shape parameter (`fWaveParam` / `wave_mystery`)
+    pub mystery: f32,
+    /// Draw twice as wide (`bWaveThick` / `wave_thick`)
+    pub thick: bool,
+    /// Add to the image instead of alpha blending
+    /// (`bAdditiveWaves` / `wave_additive`)
+    pub additive: bool,
+    /// Draw each sample as a dot (`bWaveDots` / `wave_usedots`)
+    pub dots: bool,
+    /// Fade with the volume between
+    /// [`mod_alpha_start`](Self::mod_alpha_start) and
+    /// [`mod_alpha_end`](Self::mod_alpha_end) (`bModWaveAlphaByVolume`)
+    pub mod_alpha_by_volume: bool,
+    pub mod_alpha_start: f32,
+    pub mod_alpha_end: f32,
+}
+
+impl WaveParams {
+    /// Waveform RGBA, maximized if [`maximize_color`](Self::maximize_color)
+    /// is set.
+    pub fn color(&self) -> [f32; 4] {
+        let max = self.r.max(self.g).max(self.b);
+        if self.maximize_color && max > 0.0 {
+            [self.r / max, self.g / max, self.b / max, self.a]
+        } else {
+            [self.r, self.g, self.b, self.a]
+        }
+    }
+
+    /// Alpha multiplier for the average of bass, mid and treble: 1.0 unless
+    /// [`mod_alpha_by_volume`](Self::mod_alpha_by_volume) is set, then
+    /// ramping from 0 at `mod_alpha_start` to 1 at `mod_alpha_end`.
+    pub fn volume_alpha(&self, volume: f32) -> f32 {
+        if !self.mod_alpha_by_volume {
+            return 1.0;
+        }
+        let range = self.mod_alpha_end - self.mod_alpha_start;
+        if range.abs() < f32::EPSILON {
+            return if volume >= self.mod_alpha_end {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        ((volume - self.mod_alpha_start) / range).clamp(0.0, 1.0)
+    }
 }
 
 impl Default for WaveParams {
@@ -165,6 +373,149 @@ impl Default for WaveParams {
             x: 0.5,
             y: 0.5,
             mode: 0,
+            maximize_color: false,
+            scale: 1.0,
+            smoothing: 0.0,
+            mystery: 0.0,
+            thick: false,
+            additive: false,
+            dots: false,
+            mod_alpha_by_volume: false,
+            mod_alpha_start: 0.75,
+            mod_alpha_end: 0.95,
+        }
+    }
+}
+
+/// Video echo parameters (`fVideoEchoZoom`, `fVideoEchoAlpha`,
+/// `nVideoEchoOrientation`).
+#[derive(Debug, Clone, Copy)]
+pub struct EchoParams {
+    /// Zoom of the second sample around the screen center
+    pub zoom: f32,
+    /// Blend weight of the echo (0 disables it)
+    pub alpha: f32,
+    /// 0 = normal, 1 = flip x, 2 = flip y, 3 = flip both
+    pub orientation: i32,
+}
+
+impl Default for EchoParams {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            alpha: 0.0,
+            orientation: 0,
+        }
+    }
+}
+
+/// Post-processing flags (`bBrighten`, `bDarken`, `bSolarize`, `bInvert`,
+/// `bDarkenCenter`) and gamma (`fGammaAdj`).
+///
+/// Applied to the displayed image only; the feedback buffer is unaffected.
+#[derive(Debug, Clone, Copy)]
+pub struct PostParams {
+    pub brighten: bool,
+    pub darken: bool,
+    pub solarize: bool,
+    pub invert: bool,
+    pub darken_center: bool,
+    /// Gamma correction, applied as `pow(color, 1 / gamma)`
+    pub gamma: f32,
+}
+
+impl Default for PostParams {
+    fn default() -> Self {
+        Self {
+            brighten: false,
+            darken: false,
+            solarize: false,
+            invert: false,
+            darken_center: false,
+            gamma: 1.0,
         }
     }
 }
+
+/// Outer (`ob_*`) and inner (`ib_*`) border parameters.
+///
+/// Sizes are fractions of the screen; the inner border sits just inside the
+/// outer one. A border is skipped when its size or alpha is zero.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BorderParams {
+    pub outer_size: f32,
+    /// Outer border RGBA
+    pub outer_color: [f32; 4],
+    pub inner_size: f32,
+    /// Inner border RGBA
+    pub inner_color: [f32; 4],
+}
+
+/// A custom shape (`shapecode_N`): a regular polygon filled with a gradient
+/// from its center to its edge, with an optional border.
+///
+/// Positions and radius are in Milkdrop's 0-1 screen units with y pointing
+/// up. Disabled shapes, and shapes with zero radius, are skipped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShapeParams {
+    pub enabled: bool,
+    /// Number of sides (clamped to 3-100)
+    pub sides: u32,
+    /// Number of copies drawn (at least 1)
+    pub num_inst: u32,
+    pub x: f32,
+    pub y: f32,
+    pub rad: f32,
+    /// Rotation in radians
+    pub ang: f32,
+    /// RGBA at the center
+    pub color: [f32; 4],
+    /// RGBA at the edge
+    pub edge_color: [f32; 4],
+    /// Border RGBA; the border is skipped when its alpha is zero
+    pub border_color: [f32; 4],
+    /// Draw the border several pixels wide
+    pub thick_outline: bool,
+    /// Add to the frame instead of alpha blending
+    pub additive: bool,
+}
+
+/// Intermediate render stage exposed by [`MilkRenderer::debug_capture`].
+///
+/// [`MilkRenderer::debug_capture`]: crate::MilkRenderer::debug_capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapturePass {
+    /// Feedback buffer right after the warp pass
+    Warp,
+    /// After custom shapes, right before the waveform is drawn
+    PreWaveform,
+    /// Before post-processing (brighten/darken/solarize/invert)
+    PrePost,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_old_config() {
+        // Saved before scale_factor, filter_mode and debug existed
+        let json = r#"{
+            "width": 800,
+            "height": 600,
+            "texture_format": "Rgba8Unorm",
+            "msaa_samples": 4,
+            "vsync": false,
+            "target_fps": 30
+        }"#;
+        let config: RenderConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!((config.width, config.height), (800, 600));
+        assert_eq!(config.texture_format, TextureFormat::Rgba8Unorm);
+        assert_eq!(config.sample_count, 4);
+        assert_eq!(config.scale_factor, 1.0);
+        assert_eq!(config.filter_mode, FilterMode::Linear);
+        assert!(!config.debug);
+        assert_eq!(config.physical_size(), (800, 600));
+    }
+}
@@ -26,6 +26,9 @@ pub enum RenderError {
     /// Invalid configuration
     InvalidConfiguration(String),
 
+    /// GPU validation layer rejected an operation
+    ValidationFailed { context: String, reason: String },
+
     /// Generic error
     Other(String),
 }
@@ -54,6 +57,9 @@ impl fmt::Display for RenderError {
             RenderError::InvalidConfiguration(msg) => {
                 write!(f, "Invalid configuration: {}", msg)
             }
+            RenderError::ValidationFailed { context, reason } => {
+                write!(f, "GPU validation failed in {}: {}", context, reason)
+            }
             RenderError::Other(msg) => {
                 write!(f, "Error: {}", msg)
             }
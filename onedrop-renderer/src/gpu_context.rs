@@ -1,6 +1,6 @@
 //! GPU context management.
 
-use crate::config::RenderConfig;
+use crate::config::{RenderConfig, TextureFormat};
 use crate::error::{RenderError, Result};
 use std::sync::Arc;
 
@@ -26,14 +26,36 @@ pub struct GpuContext {
 
     /// Previous frame texture view
     pub prev_texture_view: wgpu::TextureView,
+
+    /// Displayed frame after post-processing
+    pub output_texture: wgpu::Texture,
+
+    /// Output texture view
+    pub output_texture_view: wgpu::TextureView,
+
+    /// Multisampled render target resolved into the render texture, when
+    /// [`RenderConfig::sample_count`] is above 1
+    pub msaa_texture_view: Option<wgpu::TextureView>,
 }
 
 impl GpuContext {
+    /// Usages of the render, previous and output textures.
+    const TEXTURE_USAGES: wgpu::TextureUsages = wgpu::TextureUsages::RENDER_ATTACHMENT
+        .union(wgpu::TextureUsages::TEXTURE_BINDING)
+        .union(wgpu::TextureUsages::COPY_SRC)
+        .union(wgpu::TextureUsages::COPY_DST);
+
     /// Create a new GPU context.
     pub async fn new(config: RenderConfig) -> Result<Self> {
-        // Create instance
+        // Create instance, with backend validation when debugging
+        let flags = if config.debug {
+            wgpu::InstanceFlags::debugging()
+        } else {
+            wgpu::InstanceFlags::from_build_config()
+        };
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
+            flags,
             ..Default::default()
         });
 
@@ -62,26 +84,7 @@ impl GpuContext {
             )
             .await?;
 
-        let device = Arc::new(device);
-        let queue = Arc::new(queue);
-
-        // Create render textures
-        let render_texture = Self::create_texture(&device, &config, "Render Texture");
-        let render_texture_view =
-            render_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let prev_texture = Self::create_texture(&device, &config, "Previous Frame Texture");
-        let prev_texture_view = prev_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-        Ok(Self {
-            device,
-            queue,
-            config,
-            render_texture,
-            render_texture_view,
-            prev_texture,
-            prev_texture_view,
-        })
+        Ok(Self::from_device(Arc::new(device), Arc::new(queue), config))
     }
 
     /// Create a GPU context from an existing device and queue.
@@ -89,8 +92,18 @@ impl GpuContext {
     pub fn from_device(
         device: Arc<wgpu::Device>,
         queue: Arc<wgpu::Queue>,
-        config: RenderConfig,
+        mut config: RenderConfig,
     ) -> Self {
+        config.texture_format = Self::supported_texture_format(&device, &config);
+        config.sample_count = Self::supported_sample_count(&device, &config);
+
+        if config.debug {
+            // Log instead of panicking so one bad call doesn't end a debug session
+            device.on_uncaptured_error(Box::new(|error| {
+                log::error!("GPU validation error: {}", error);
+            }));
+        }
+
         // Create render textures
         let render_texture = Self::create_texture(&device, &config, "Render Texture");
         let render_texture_view =
@@ -99,6 +112,12 @@ impl GpuContext {
         let prev_texture = Self::create_texture(&device, &config, "Previous Frame Texture");
         let prev_texture_view = prev_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
+        let output_texture = Self::create_texture(&device, &config, "Output Texture");
+        let output_texture_view =
+            output_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let msaa_texture_view = Self::create_msaa_view(&device, &config);
+
         Self {
             device,
             queue,
@@ -107,60 +126,184 @@ impl GpuContext {
             render_texture_view,
             prev_texture,
             prev_texture_view,
+            output_texture,
+            output_texture_view,
+            msaa_texture_view,
+        }
+    }
+
+    /// The texture format from `config`, or `Rgba8Unorm` if `device` cannot
+    /// render to, blend, filter and copy it.
+    fn supported_texture_format(device: &wgpu::Device, config: &RenderConfig) -> TextureFormat {
+        let features = config
+            .texture_format
+            .to_wgpu()
+            .guaranteed_format_features(device.features());
+        let supported = features.allowed_usages.contains(Self::TEXTURE_USAGES)
+            && features.flags.contains(
+                wgpu::TextureFormatFeatureFlags::FILTERABLE
+                    | wgpu::TextureFormatFeatureFlags::BLENDABLE,
+            );
+        if supported {
+            config.texture_format
+        } else {
+            log::warn!(
+                "{:?} render targets are not supported, falling back to Rgba8Unorm",
+                config.texture_format
+            );
+            TextureFormat::Rgba8Unorm
+        }
+    }
+
+    /// The sample count from `config` that `device` can render with.
+    ///
+    /// Devices are created without adapter-specific format features, so
+    /// only the multisample counts guaranteed for the format are usable.
+    fn supported_sample_count(device: &wgpu::Device, config: &RenderConfig) -> u32 {
+        let requested = config.sample_count;
+        let features = config
+            .texture_format
+            .to_wgpu()
+            .guaranteed_format_features(device.features());
+        match requested {
+            0 | 1 => 1,
+            4 if features.flags.sample_count_supported(4) => 4,
+            _ => {
+                log::warn!(
+                    "{}x multisampling is not supported for {:?}, rendering without it",
+                    requested,
+                    config.texture_format
+                );
+                1
+            }
         }
     }
 
+    /// Create the multisampled render target, if multisampling is enabled.
+    fn create_msaa_view(device: &wgpu::Device, config: &RenderConfig) -> Option<wgpu::TextureView> {
+        if config.sample_count <= 1 {
+            return None;
+        }
+        let (width, height) = config.physical_size();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisampled Render Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: config.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.texture_format.to_wgpu(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Attachment views for drawing into the render texture: the view to
+    /// draw into and, when multisampling, the render texture view it
+    /// resolves to.
+    pub fn render_target(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        match &self.msaa_texture_view {
+            Some(msaa_view) => (msaa_view, Some(&self.render_texture_view)),
+            None => (&self.render_texture_view, None),
+        }
+    }
+
+    /// Samples per pixel of pipelines drawing into [`render_target`](Self::render_target).
+    pub fn sample_count(&self) -> u32 {
+        self.config.sample_count
+    }
+
     /// Create a texture with the given configuration.
     fn create_texture(device: &wgpu::Device, config: &RenderConfig, label: &str) -> wgpu::Texture {
+        let (width, height) = config.physical_size();
         device.create_texture(&wgpu::TextureDescriptor {
             label: Some(label),
             size: wgpu::Extent3d {
-                width: config.width,
-                height: config.height,
+                width,
+                height,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: config.texture_format.to_wgpu(),
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                | wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::COPY_SRC
-                | wgpu::TextureUsages::COPY_DST,
+            usage: Self::TEXTURE_USAGES,
             view_formats: &[],
         })
     }
 
+    /// Run `operation` inside a validation error scope when
+    /// [`RenderConfig::debug`] is set, turning any validation error it
+    /// raises into [`RenderError::ValidationFailed`] tagged with `context`.
+    /// Without `debug` the operation simply runs.
+    pub async fn validate<T>(
+        &self,
+        context: &str,
+        operation: impl FnOnce(&wgpu::Device) -> T,
+    ) -> Result<T> {
+        if !self.config.debug {
+            return Ok(operation(&self.device));
+        }
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let value = operation(&self.device);
+        match self.device.pop_error_scope().await {
+            Some(error) => {
+                log::error!("GPU validation error in {}: {}", context, error);
+                Err(RenderError::ValidationFailed {
+                    context: context.to_string(),
+                    reason: error.to_string(),
+                })
+            }
+            None => Ok(value),
+        }
+    }
+
     /// Swap render and previous textures (for feedback effects).
     pub fn swap_textures(&mut self) {
         std::mem::swap(&mut self.render_texture, &mut self.prev_texture);
         std::mem::swap(&mut self.render_texture_view, &mut self.prev_texture_view);
     }
 
-    /// Copy current render texture to previous texture.
-    pub fn copy_to_prev(&self, encoder: &mut wgpu::CommandEncoder) {
+    /// Copy a full-size texture into another of the same size and format.
+    pub fn copy_texture(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::Texture,
+        destination: &wgpu::Texture,
+    ) {
+        let (width, height) = self.config.physical_size();
         encoder.copy_texture_to_texture(
             wgpu::ImageCopyTexture {
-                texture: &self.render_texture,
+                texture: source,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
             wgpu::ImageCopyTexture {
-                texture: &self.prev_texture,
+                texture: destination,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
             wgpu::Extent3d {
-                width: self.config.width,
-                height: self.config.height,
+                width,
+                height,
                 depth_or_array_layers: 1,
             },
         );
     }
 
-    /// Resize textures.
+    /// Create an extra texture matching the render texture's size and format.
+    pub fn create_render_target(&self, label: &str) -> wgpu::Texture {
+        Self::create_texture(&self.device, &self.config, label)
+    }
+
+    /// Resize textures to a new logical size.
     pub fn resize(&mut self, width: u32, height: u32) {
         self.config.width = width;
         self.config.height = height;
@@ -175,11 +318,30 @@ impl GpuContext {
         self.prev_texture_view = self
             .prev_texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.output_texture = Self::create_texture(&self.device, &self.config, "Output Texture");
+        self.output_texture_view = self
+            .output_texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.msaa_texture_view = Self::create_msaa_view(&self.device, &self.config);
+    }
+
+    /// Set the HiDPI scale factor and resize textures to match.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.config.scale_factor = scale_factor;
+        self.resize(self.config.width, self.config.height);
+    }
+
+    /// Render target size in physical pixels.
+    pub fn physical_size(&self) -> (u32, u32) {
+        self.config.physical_size()
     }
 
     /// Get aspect ratio.
     pub fn aspect_ratio(&self) -> f32 {
-        self.config.width as f32 / self.config.height as f32
+        let (width, height) = self.physical_size();
+        width as f32 / height as f32
     }
 }
 
@@ -204,4 +366,67 @@ mod tests {
         let context = pollster::block_on(GpuContext::new(config)).unwrap();
         assert!((context.aspect_ratio() - 16.0 / 9.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_debug_context_creation() {
+        let config = RenderConfig {
+            debug: true,
+            ..Default::default()
+        };
+        let context = pollster::block_on(GpuContext::new(config)).unwrap();
+        let view = pollster::block_on(context.validate("create view", |_| {
+            context
+                .render_texture
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        }));
+        assert!(view.is_ok());
+    }
+
+    #[test]
+    fn test_debug_surfaces_validation_error() {
+        let config = RenderConfig {
+            debug: true,
+            ..Default::default()
+        };
+        let context = pollster::block_on(GpuContext::new(config)).unwrap();
+        // MAP_READ and MAP_WRITE are mutually exclusive
+        let result = pollster::block_on(context.validate("invalid buffer", |device| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Invalid Buffer"),
+                size: 16,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::MAP_WRITE,
+                mapped_at_creation: false,
+            })
+        }));
+        match result {
+            Err(RenderError::ValidationFailed { context, reason }) => {
+                assert_eq!(context, "invalid buffer");
+                assert!(!reason.is_empty());
+            }
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_scale_factor_render_target() {
+        let config = RenderConfig {
+            width: 640,
+            height: 480,
+            scale_factor: 2.0,
+            ..Default::default()
+        };
+        let mut context = pollster::block_on(GpuContext::new(config)).unwrap();
+        assert_eq!(context.physical_size(), (1280, 960));
+        for texture in [
+            &context.render_texture,
+            &context.prev_texture,
+            &context.output_texture,
+        ] {
+            assert_eq!((texture.width(), texture.height()), (1280, 960));
+        }
+
+        context.set_scale_factor(1.5);
+        assert_eq!(context.render_texture.width(), 960);
+        assert_eq!(context.render_texture.height(), 720);
+    }
 }
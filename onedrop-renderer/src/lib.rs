@@ -5,23 +5,37 @@
 //! This crate provides a complete rendering engine for Milkdrop presets,
 //! including waveform rendering, motion effects, and shader-based transformations.
 
+pub mod bindings;
+pub mod bitmap_font;
 pub mod blend_renderer;
+pub mod border;
 pub mod config;
 pub mod error;
 pub mod gpu_context;
 pub mod per_pixel_pipeline;
 pub mod per_vertex_pipeline;
 pub mod renderer;
+pub mod shape;
+pub mod tonemap;
+pub mod warp_mesh;
 pub mod waveform;
 
 pub use blend_renderer::BlendRenderer;
-pub use config::{AudioLevels, MotionParams, RenderConfig, RenderState, WaveParams};
+pub use border::BorderRenderer;
+pub use config::{
+    AudioLevels, BorderParams, CapturePass, EchoParams, FilterMode, MAX_SHAPES, MotionParams,
+    PostParams, RenderConfig, RenderState, SPECTRUM_BINS, ShapeParams, TextureFormat,
+    WAVEFORM_SAMPLES, WaveParams,
+};
 pub use error::{RenderError, Result};
 pub use gpu_context::GpuContext;
 pub use per_pixel_pipeline::{PerPixelPipeline, PixelVarsUniform};
 pub use per_vertex_pipeline::{PerVertexPipeline, VertexVarsUniform};
 pub use renderer::MilkRenderer;
-pub use waveform::{WavePoint, WaveformMode, WaveformRenderer};
+pub use shape::{ShapeGeometry, ShapeRenderer, ShapeVertex};
+pub use tonemap::{rgba16f_to_rgba8, tonemap};
+pub use warp_mesh::{WarpMesh, WarpMeshVertex, warp_uv};
+pub use waveform::{WavePoint, WaveformMode, WaveformRenderer, WaveformUniforms};
 
 #[cfg(test)]
 mod tests {
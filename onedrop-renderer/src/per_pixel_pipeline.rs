@@ -2,6 +2,7 @@
 //!
 //! Executes per-pixel equations on the GPU using dynamically compiled shaders.
 
+use crate::config::FilterMode;
 use crate::error::{RenderError, Result};
 use wgpu::util::DeviceExt;
 
@@ -28,8 +29,18 @@ pub struct PixelVarsUniform {
     pub frame: f32,
     pub fps: f32,
 
-    // Padding for alignment
-    pub _padding: f32,
+    // Motion (per-frame values, before per-pixel equations)
+    pub decay: f32,
+    pub zoom: f32,
+    pub rot: f32,
+    pub cx: f32,
+    pub cy: f32,
+    pub dx: f32,
+    pub dy: f32,
+    pub sx: f32,
+    pub sy: f32,
+    pub warp: f32,
+    pub zoomexp: f32,
 
     // Custom variables (64 floats as 16 vec4s)
     pub q: [[f32; 4]; 16],
@@ -51,7 +62,17 @@ impl Default for PixelVarsUniform {
             time: 0.0,
             frame: 0.0,
             fps: 60.0,
-            _padding: 0.0,
+            decay: 0.98,
+            zoom: 1.0,
+            rot: 0.0,
+            cx: 0.5,
+            cy: 0.5,
+            dx: 0.0,
+            dy: 0.0,
+            sx: 1.0,
+            sy: 1.0,
+            warp: 1.0,
+            zoomexp: 1.0,
             q: [[0.0; 4]; 16],
         }
     }
@@ -80,10 +101,20 @@ pub struct PerPixelPipeline {
     // Resolution
     width: u32,
     height: u32,
+
+    // Samples per pixel of the render target
+    sample_count: u32,
 }
 
 impl PerPixelPipeline {
-    pub fn new(device: wgpu::Device, queue: wgpu::Queue, width: u32, height: u32) -> Result<Self> {
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        filter_mode: FilterMode,
+    ) -> Result<Self> {
         // Create uniform buffer
         let vars = PixelVarsUniform::default();
         let vars_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -129,16 +160,7 @@ impl PerPixelPipeline {
         });
 
         // Create sampler
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("Per-Pixel Sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = Self::create_sampler(&device, filter_mode);
 
         Ok(Self {
             device,
@@ -153,6 +175,7 @@ impl PerPixelPipeline {
             sampler,
             width,
             height,
+            sample_count,
         })
     }
 
@@ -197,7 +220,7 @@ impl PerPixelPipeline {
                 },
                 depth_stencil: None,
                 multisample: wgpu::MultisampleState {
-                    count: 1,
+                    count: self.sample_count,
                     mask: !0,
                     alpha_to_coverage_enabled: false,
                 },
@@ -222,6 +245,27 @@ impl PerPixelPipeline {
         self.update_bind_group();
     }
 
+    /// Change texture filtering used when sampling the input texture.
+    pub fn set_filter_mode(&mut self, filter_mode: FilterMode) {
+        self.sampler = Self::create_sampler(&self.device, filter_mode);
+        self.update_bind_group();
+    }
+
+    /// Create the input sampler.
+    fn create_sampler(device: &wgpu::Device, filter_mode: FilterMode) -> wgpu::Sampler {
+        let filter = filter_mode.to_wgpu();
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Per-Pixel Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
+
     /// Update bind group with current textures
     fn update_bind_group(&mut self) {
         if let Some(ref input_texture) = self.input_texture {
@@ -249,7 +293,11 @@ impl PerPixelPipeline {
     }
 
     /// Render per-pixel effects
-    pub fn render(&mut self, output_view: &wgpu::TextureView) -> Result<()> {
+    pub fn render(
+        &mut self,
+        target: (&wgpu::TextureView, Option<&wgpu::TextureView>),
+    ) -> Result<()> {
+        let (output_view, resolve_target) = target;
         let pipeline = self
             .render_pipeline
             .as_ref()
@@ -271,7 +319,7 @@ impl PerPixelPipeline {
                 label: Some("Per-Pixel Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: output_view,
-                    resolve_target: None,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
                         store: wgpu::StoreOp::Store,
@@ -299,8 +347,9 @@ mod tests {
 
     #[test]
     fn test_pixel_vars_size() {
-        // Verify struct size is correct (312 bytes)
-        assert_eq!(std::mem::size_of::<PixelVarsUniform>(), 312);
+        // Verify struct size is correct (352 bytes)
+        assert_eq!(std::mem::size_of::<PixelVarsUniform>(), 352);
+        assert_eq!(std::mem::offset_of!(PixelVarsUniform, q), 96);
     }
 
     #[test]
@@ -83,11 +83,20 @@ pub struct PerVertexPipeline {
 
     /// Number of indices
     num_indices: u32,
+
+    /// Samples per pixel of the render target
+    sample_count: u32,
 }
 
 impl PerVertexPipeline {
-    /// Create a new per-vertex pipeline
-    pub fn new(device: wgpu::Device, queue: wgpu::Queue, vertex_count: u32) -> Result<Self> {
+    /// Create a per-vertex pipeline drawing into targets with `sample_count`
+    /// samples per pixel
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        vertex_count: u32,
+        sample_count: u32,
+    ) -> Result<Self> {
         // Create uniform buffer
         let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Vertex Vars Uniform Buffer"),
@@ -140,6 +149,7 @@ impl PerVertexPipeline {
             vertex_buffer,
             index_buffer,
             num_indices: indices.len() as u32,
+            sample_count,
         })
     }
 
@@ -232,7 +242,7 @@ impl PerVertexPipeline {
                 },
                 depth_stencil: None,
                 multisample: wgpu::MultisampleState {
-                    count: 1,
+                    count: self.sample_count,
                     mask: !0,
                     alpha_to_coverage_enabled: false,
                 },
@@ -253,7 +263,11 @@ impl PerVertexPipeline {
     }
 
     /// Render per-vertex effects
-    pub fn render(&mut self, output_view: &wgpu::TextureView) -> Result<()> {
+    pub fn render(
+        &mut self,
+        target: (&wgpu::TextureView, Option<&wgpu::TextureView>),
+    ) -> Result<()> {
+        let (output_view, resolve_target) = target;
         let pipeline = self
             .render_pipeline
             .as_ref()
@@ -275,7 +289,7 @@ impl PerVertexPipeline {
                 label: Some("Per-Vertex Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: output_view,
-                    resolve_target: None,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
                         store: wgpu::StoreOp::Store,
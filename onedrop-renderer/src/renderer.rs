@@ -1,8 +1,13 @@
 //! Main renderer implementation.
 
-use crate::config::{RenderConfig, RenderState};
+use crate::bindings;
+use crate::border::BorderRenderer;
+use crate::config::{CapturePass, FilterMode, RenderConfig, RenderState, WAVEFORM_SAMPLES};
 use crate::error::Result;
 use crate::gpu_context::GpuContext;
+use crate::shape::ShapeRenderer;
+use crate::warp_mesh::{WarpMesh, WarpMeshBuffers, WarpMeshVertex};
+use crate::waveform::{WaveformMode, WaveformRenderer, WaveformUniforms};
 use bytemuck::{Pod, Zeroable};
 
 /// Main Milkdrop renderer.
@@ -13,8 +18,14 @@ pub struct MilkRenderer {
     /// Composite pipeline
     composite_pipeline: wgpu::RenderPipeline,
 
-    /// Composite bind group
-    composite_bind_group: wgpu::BindGroup,
+    /// Composite pipeline drawing the warp mesh
+    mesh_pipeline: wgpu::RenderPipeline,
+
+    /// Warp mesh replacing the uniform motion, with its GPU buffers
+    warp_mesh: Option<(WarpMesh, WarpMeshBuffers)>,
+
+    /// Composite bind groups, sampling each feedback texture in turn
+    composite_bind_groups: [wgpu::BindGroup; 2],
 
     /// Bind group layout (stored for resize)
     composite_bind_group_layout: wgpu::BindGroupLayout,
@@ -25,6 +36,34 @@ pub struct MilkRenderer {
     /// Sampler for textures
     sampler: wgpu::Sampler,
 
+    /// Post-processing pipeline (feedback buffer -> output texture)
+    post_pipeline: wgpu::RenderPipeline,
+
+    /// Post-processing bind groups, reading each feedback texture in turn
+    post_bind_groups: [wgpu::BindGroup; 2],
+
+    /// Which bind group of each pair matches the current feedback texture
+    /// roles; flips whenever the render and previous textures swap
+    feedback_index: usize,
+
+    /// Post-processing bind group layout (stored for resize)
+    post_bind_group_layout: wgpu::BindGroupLayout,
+
+    /// Uniform buffer for post-processing flags
+    post_uniforms_buffer: wgpu::Buffer,
+
+    /// Preset waveform renderer
+    waveform_renderer: WaveformRenderer,
+
+    /// Outer/inner border renderer
+    border_renderer: BorderRenderer,
+
+    /// Custom shape renderer
+    shape_renderer: ShapeRenderer,
+
+    /// Intermediate stage copied out for debugging, with its texture
+    debug_capture: Option<(CapturePass, wgpu::Texture)>,
+
     /// Current render state
     state: RenderState,
 }
@@ -40,16 +79,7 @@ impl MilkRenderer {
     /// This is useful when sharing a GPU context between multiple components.
     pub fn from_gpu_context(gpu: GpuContext) -> Result<Self> {
         // Create sampler
-        let sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("Texture Sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+        let sampler = Self::create_sampler(&gpu);
 
         // Create composite shader
         let composite_shader = gpu
@@ -75,7 +105,7 @@ impl MilkRenderer {
                     entries: &[
                         // Uniforms
                         wgpu::BindGroupLayoutEntry {
-                            binding: 0,
+                            binding: bindings::UNIFORMS,
                             visibility: wgpu::ShaderStages::FRAGMENT,
                             ty: wgpu::BindingType::Buffer {
                                 ty: wgpu::BufferBindingType::Uniform,
@@ -84,46 +114,29 @@ impl MilkRenderer {
                             },
                             count: None,
                         },
-                        // Previous frame texture
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                multisampled: false,
-                            },
-                            count: None,
-                        },
+                        // Main (previous frame) texture
+                        Self::texture_layout_entry(bindings::MAIN_TEXTURE),
                         // Sampler
                         wgpu::BindGroupLayoutEntry {
-                            binding: 2,
+                            binding: bindings::SAMPLER,
                             visibility: wgpu::ShaderStages::FRAGMENT,
                             ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                             count: None,
                         },
+                        // Blur textures
+                        Self::texture_layout_entry(bindings::BLUR_TEXTURES[0]),
+                        Self::texture_layout_entry(bindings::BLUR_TEXTURES[1]),
+                        Self::texture_layout_entry(bindings::BLUR_TEXTURES[2]),
                     ],
                 });
 
-        // Create bind group
-        let composite_bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Composite Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: composite_uniforms_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&gpu.prev_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
+        // Create bind groups
+        let composite_bind_groups = Self::create_composite_bind_groups(
+            &gpu,
+            &bind_group_layout,
+            &composite_uniforms_buffer,
+            &sampler,
+        );
 
         // Create pipeline layout
         let pipeline_layout = gpu
@@ -134,54 +147,258 @@ impl MilkRenderer {
                 push_constant_ranges: &[],
             });
 
-        // Create render pipeline
+        // Create render pipelines: a fullscreen quad warped by the uniform
+        // motion, and a mesh carrying per-vertex motion
         let composite_pipeline =
-            gpu.device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("Composite Pipeline"),
-                    layout: Some(&pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &composite_shader,
-                        entry_point: Some("vs_main"),
-                        buffers: &[],
-                        compilation_options: Default::default(),
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &composite_shader,
-                        entry_point: Some("fs_main"),
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: gpu.config.texture_format.to_wgpu(),
-                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: Default::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleStrip,
-                        ..Default::default()
-                    },
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState::default(),
-                    multiview: None,
-                    cache: None,
-                });
+            Self::create_composite_pipeline(&gpu, &composite_shader, &pipeline_layout, false);
+        let mesh_pipeline =
+            Self::create_composite_pipeline(&gpu, &composite_shader, &pipeline_layout, true);
+
+        let (post_pipeline, post_bind_group_layout, post_uniforms_buffer) =
+            Self::create_post_pipeline(&gpu);
+        let post_bind_groups = Self::create_post_bind_groups(
+            &gpu,
+            &post_bind_group_layout,
+            &post_uniforms_buffer,
+            &sampler,
+        );
+
+        let format = gpu.config.texture_format.to_wgpu();
+        let waveform_renderer =
+            WaveformRenderer::new(&gpu.device, format, WAVEFORM_SAMPLES, gpu.sample_count());
+        let border_renderer = BorderRenderer::new(&gpu.device, format, gpu.sample_count());
+        let shape_renderer = ShapeRenderer::new(&gpu.device, format, gpu.sample_count());
 
         Ok(Self {
             gpu,
             composite_pipeline,
-            composite_bind_group,
+            mesh_pipeline,
+            warp_mesh: None,
+            composite_bind_groups,
             composite_bind_group_layout: bind_group_layout,
             composite_uniforms_buffer,
             sampler,
+            post_pipeline,
+            post_bind_groups,
+            post_bind_group_layout,
+            feedback_index: 0,
+            post_uniforms_buffer,
+            waveform_renderer,
+            border_renderer,
+            shape_renderer,
+            debug_capture: None,
             state: RenderState::default(),
         })
     }
 
+    /// Create the composite pipeline, drawing either a fullscreen quad or,
+    /// with `mesh`, a [`WarpMesh`].
+    fn create_composite_pipeline(
+        gpu: &GpuContext,
+        shader: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        mesh: bool,
+    ) -> wgpu::RenderPipeline {
+        let (label, vs_entry, fs_entry, buffers, topology): (_, _, _, &[_], _) = if mesh {
+            (
+                "Warp Mesh Pipeline",
+                "vs_mesh",
+                "fs_mesh",
+                &[WarpMeshVertex::LAYOUT],
+                wgpu::PrimitiveTopology::TriangleList,
+            )
+        } else {
+            (
+                "Composite Pipeline",
+                "vs_main",
+                "fs_main",
+                &[],
+                wgpu::PrimitiveTopology::TriangleStrip,
+            )
+        };
+
+        gpu.device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: Some(vs_entry),
+                    buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: Some(fs_entry),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.config.texture_format.to_wgpu(),
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: gpu.sample_count(),
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+            })
+    }
+
+    /// Create the post-processing pipeline, its layout and uniform buffer.
+    fn create_post_pipeline(
+        gpu: &GpuContext,
+    ) -> (wgpu::RenderPipeline, wgpu::BindGroupLayout, wgpu::Buffer) {
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Post Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/post.wgsl").into()),
+            });
+
+        let uniforms_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Uniforms"),
+            size: std::mem::size_of::<PostUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = gpu
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Post Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: bindings::UNIFORMS,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    Self::texture_layout_entry(bindings::MAIN_TEXTURE),
+                    // Sampler for the video echo
+                    wgpu::BindGroupLayoutEntry {
+                        binding: bindings::SAMPLER,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let pipeline_layout = gpu
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Post Pipeline Layout"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Post Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: gpu.config.texture_format.to_wgpu(),
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        (pipeline, layout, uniforms_buffer)
+    }
+
+    /// Create the post-processing bind groups reading the render texture,
+    /// before and after the feedback textures swap.
+    fn create_post_bind_groups(
+        gpu: &GpuContext,
+        layout: &wgpu::BindGroupLayout,
+        uniforms_buffer: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+    ) -> [wgpu::BindGroup; 2] {
+        [&gpu.render_texture_view, &gpu.prev_texture_view].map(|source| {
+            gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: bindings::UNIFORMS,
+                        resource: uniforms_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: bindings::MAIN_TEXTURE,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: bindings::SAMPLER,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            })
+        })
+    }
+
     /// Update render state.
     pub fn update_state(&mut self, state: RenderState) {
         self.state = state;
     }
 
+    /// Warp with per-vertex motion from `mesh` instead of the uniform
+    /// motion in the render state, or go back to the uniform warp with `None`.
+    pub fn set_warp_mesh(&mut self, mesh: Option<WarpMesh>) {
+        self.warp_mesh = mesh.map(|mesh| match self.warp_mesh.take() {
+            // Same grid: only the vertices change
+            Some((old, buffers))
+                if (old.columns(), old.rows()) == (mesh.columns(), mesh.rows()) =>
+            {
+                self.gpu.queue.write_buffer(
+                    &buffers.vertices,
+                    0,
+                    bytemuck::cast_slice(mesh.vertices()),
+                );
+                (mesh, buffers)
+            }
+            _ => {
+                let buffers = WarpMeshBuffers::new(&self.gpu.device, &mesh);
+                (mesh, buffers)
+            }
+        });
+    }
+
+    /// Get the warp mesh in use, if any.
+    pub fn warp_mesh(&self) -> Option<&WarpMesh> {
+        self.warp_mesh.as_ref().map(|(mesh, _)| mesh)
+    }
+
     /// Render a frame.
     pub fn render(&mut self) -> Result<()> {
         // Create command encoder
@@ -193,11 +410,18 @@ impl MilkRenderer {
             });
 
         // Update uniforms
+        let (width, height) = self.gpu.physical_size();
         let uniforms = CompositeUniforms {
-            resolution: [self.gpu.config.width as f32, self.gpu.config.height as f32],
+            resolution: [width as f32, height as f32],
             time: self.state.time,
             decay: 0.98,
             zoom: self.state.motion.zoom,
+            // Unset (non-positive) exponents zoom uniformly
+            zoomexp: if self.state.motion.zoomexp > 0.0 {
+                self.state.motion.zoomexp
+            } else {
+                1.0
+            },
             rot: self.state.motion.rot,
             cx: self.state.motion.cx,
             cy: self.state.motion.cy,
@@ -206,7 +430,13 @@ impl MilkRenderer {
             sx: self.state.motion.sx,
             sy: self.state.motion.sy,
             warp: self.state.motion.warp,
-            _padding: 0.0,
+            // Unset (non-positive) scales fall back to Milkdrop's default
+            warp_scale: if self.state.motion.warp_scale > 0.0 {
+                self.state.motion.warp_scale
+            } else {
+                1.0
+            },
+            warp_time: self.state.motion.warp_time,
         };
 
         self.gpu.queue.write_buffer(
@@ -215,12 +445,124 @@ impl MilkRenderer {
             bytemuck::bytes_of(&uniforms),
         );
 
+        // Draw into the multisampled target when enabled; every pass
+        // resolves it into the render texture, so captures and the post
+        // pass read anti-aliased pixels
+        let (target_view, resolve_target) = self.gpu.render_target();
+
         // Render composite pass
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Composite Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.gpu.render_texture_view,
+                    view: target_view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_bind_group(0, &self.composite_bind_groups[self.feedback_index], &[]);
+            match &self.warp_mesh {
+                Some((_, buffers)) => {
+                    render_pass.set_pipeline(&self.mesh_pipeline);
+                    render_pass.set_vertex_buffer(0, buffers.vertices.slice(..));
+                    render_pass
+                        .set_index_buffer(buffers.indices.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..buffers.index_count, 0, 0..1);
+                }
+                None => {
+                    render_pass.set_pipeline(&self.composite_pipeline);
+                    render_pass.draw(0..4, 0..1);
+                }
+            }
+        }
+        self.capture(CapturePass::Warp, &mut encoder);
+
+        // Custom shapes over the warped frame, as in Milkdrop
+        self.shape_renderer.render(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &mut encoder,
+            (target_view, resolve_target),
+            self.gpu.physical_size(),
+            &self.state.shapes,
+        );
+
+        self.capture(CapturePass::PreWaveform, &mut encoder);
+
+        // Waveform over the shapes
+        let wave = &self.state.wave;
+        let audio = &self.state.audio;
+        let volume = (audio.bass + audio.mid + audio.treb) / 3.0;
+        let wave_uniforms = WaveformUniforms {
+            time: self.state.time,
+            ..WaveformUniforms::from_wave(wave, volume, [width as f32, height as f32])
+        };
+        let samples: &[f32] = match WaveformMode::from_index(wave_uniforms.wave_mode) {
+            WaveformMode::Spectrum => &self.state.spectrum,
+            _ => &self.state.waveform,
+        };
+        self.waveform_renderer
+            .update_uniforms(&self.gpu.queue, &wave_uniforms);
+        self.waveform_renderer.update_wave_data(
+            &self.gpu.queue,
+            samples,
+            &wave_uniforms,
+            wave.smoothing,
+        );
+        self.waveform_renderer.render(
+            &mut encoder,
+            (target_view, resolve_target),
+            wave.dots,
+            wave.additive,
+        );
+
+        // Borders are drawn into the feedback buffer, as in Milkdrop
+        self.border_renderer.render(
+            &self.gpu.queue,
+            &mut encoder,
+            (target_view, resolve_target),
+            self.gpu.physical_size(),
+            &self.state.border,
+        );
+        self.capture(CapturePass::PrePost, &mut encoder);
+
+        // Post-process into the displayed output
+        let post = self.state.post;
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        let post_uniforms = PostUniforms {
+            brighten: flag(post.brighten),
+            darken: flag(post.darken),
+            solarize: flag(post.solarize),
+            invert: flag(post.invert),
+            darken_center: flag(post.darken_center),
+            gamma: post.gamma,
+            // Guard against unset (zero) zoom; the echo is invisible at alpha 0 anyway
+            echo_zoom: if self.state.echo.zoom > 0.0 {
+                self.state.echo.zoom
+            } else {
+                1.0
+            },
+            echo_alpha: self.state.echo.alpha.clamp(0.0, 1.0),
+            echo_orientation: self.state.echo.orientation.rem_euclid(4) as f32,
+            _padding: [0.0; 3],
+        };
+        self.gpu.queue.write_buffer(
+            &self.post_uniforms_buffer,
+            0,
+            bytemuck::bytes_of(&post_uniforms),
+        );
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.gpu.output_texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -232,26 +574,71 @@ impl MilkRenderer {
                 occlusion_query_set: None,
             });
 
-            render_pass.set_pipeline(&self.composite_pipeline);
-            render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+            render_pass.set_pipeline(&self.post_pipeline);
+            render_pass.set_bind_group(0, &self.post_bind_groups[self.feedback_index], &[]);
             render_pass.draw(0..4, 0..1);
         }
 
-        // Copy current frame to previous frame for next render
-        self.gpu.copy_to_prev(&mut encoder);
-
         // Submit commands
         self.gpu.queue.submit(std::iter::once(encoder.finish()));
 
+        // This frame becomes the previous frame of the next one: swap the
+        // textures rather than copying, and switch to the bind groups
+        // sampling them in their new roles
+        self.gpu.swap_textures();
+        self.feedback_index ^= 1;
+
         // Increment frame
         self.state.frame += 1;
 
         Ok(())
     }
 
-    /// Get the current render texture.
+    /// Get the GPU context.
+    pub fn gpu(&self) -> &GpuContext {
+        &self.gpu
+    }
+
+    /// Get the current render texture (the displayed, post-processed frame).
     pub fn render_texture(&self) -> &wgpu::Texture {
-        &self.gpu.render_texture
+        self.output_texture()
+    }
+
+    /// Get the post-processed output texture.
+    pub fn output_texture(&self) -> &wgpu::Texture {
+        &self.gpu.output_texture
+    }
+
+    /// Get the feedback buffer (warp output, fed back as the next frame's input).
+    pub fn feedback_texture(&self) -> &wgpu::Texture {
+        // Swapped into the previous frame slot at the end of `render`
+        &self.gpu.prev_texture
+    }
+
+    /// Expose an intermediate render stage for readback, or `None` to stop.
+    ///
+    /// The selected stage is copied into [`captured_texture`](Self::captured_texture)
+    /// on every subsequent [`render`](Self::render).
+    pub fn debug_capture(&mut self, pass: Option<CapturePass>) {
+        self.debug_capture = pass.map(|pass| {
+            let texture = self.gpu.create_render_target("Debug Capture Texture");
+            (pass, texture)
+        });
+    }
+
+    /// Get the texture holding the captured intermediate stage, if enabled.
+    pub fn captured_texture(&self) -> Option<&wgpu::Texture> {
+        self.debug_capture.as_ref().map(|(_, texture)| texture)
+    }
+
+    /// Copy the feedback buffer into the capture texture if `pass` is selected.
+    fn capture(&self, pass: CapturePass, encoder: &mut wgpu::CommandEncoder) {
+        if let Some((selected, texture)) = &self.debug_capture {
+            if *selected == pass {
+                self.gpu
+                    .copy_texture(encoder, &self.gpu.render_texture, texture);
+            }
+        }
     }
 
     /// Get render state.
@@ -259,32 +646,124 @@ impl MilkRenderer {
         &self.state
     }
 
-    /// Resize the renderer.
+    /// Resize the renderer to a new logical size.
     pub fn resize(&mut self, width: u32, height: u32) {
         self.gpu.resize(width, height);
 
-        // Recreate bind group with new texture views
-        self.composite_bind_group = self
-            .gpu
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
+        // Recreate bind groups with new texture views
+        self.rebuild_bind_groups();
+
+        // Capture texture must match the new size
+        let pass = self.debug_capture.as_ref().map(|(pass, _)| *pass);
+        self.debug_capture(pass);
+    }
+
+    /// Set the HiDPI scale factor, resizing render targets to match.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.gpu.config.scale_factor = scale_factor;
+        self.resize(self.gpu.config.width, self.gpu.config.height);
+    }
+
+    /// Change texture filtering at runtime.
+    pub fn set_filter_mode(&mut self, filter_mode: FilterMode) {
+        self.gpu.config.filter_mode = filter_mode;
+        self.sampler = Self::create_sampler(&self.gpu);
+        self.rebuild_bind_groups();
+    }
+
+    /// Recreate the composite and post bind groups for the current texture
+    /// views and feedback texture roles.
+    fn rebuild_bind_groups(&mut self) {
+        self.composite_bind_groups = Self::create_composite_bind_groups(
+            &self.gpu,
+            &self.composite_bind_group_layout,
+            &self.composite_uniforms_buffer,
+            &self.sampler,
+        );
+        self.post_bind_groups = Self::create_post_bind_groups(
+            &self.gpu,
+            &self.post_bind_group_layout,
+            &self.post_uniforms_buffer,
+            &self.sampler,
+        );
+        self.feedback_index = 0;
+    }
+
+    /// Get the current texture filtering mode.
+    pub fn filter_mode(&self) -> FilterMode {
+        self.gpu.config.filter_mode
+    }
+
+    /// Create the feedback/composite sampler using the configured filter mode.
+    fn create_sampler(gpu: &GpuContext) -> wgpu::Sampler {
+        let filter = gpu.config.filter_mode.to_wgpu();
+        gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            ..Default::default()
+        })
+    }
+
+    /// Create the composite bind groups sampling the previous frame, before
+    /// and after the feedback textures swap.
+    fn create_composite_bind_groups(
+        gpu: &GpuContext,
+        layout: &wgpu::BindGroupLayout,
+        uniforms_buffer: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+    ) -> [wgpu::BindGroup; 2] {
+        [&gpu.prev_texture_view, &gpu.render_texture_view].map(|source| {
+            gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("Composite Bind Group"),
-                layout: &self.composite_bind_group_layout,
+                layout,
                 entries: &[
                     wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: self.composite_uniforms_buffer.as_entire_binding(),
+                        binding: bindings::UNIFORMS,
+                        resource: uniforms_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: bindings::MAIN_TEXTURE,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: bindings::SAMPLER,
+                        resource: wgpu::BindingResource::Sampler(sampler),
                     },
+                    // No blur passes yet: blur slots sample the unblurred main texture
                     wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&self.gpu.prev_texture_view),
+                        binding: bindings::BLUR_TEXTURES[0],
+                        resource: wgpu::BindingResource::TextureView(source),
                     },
                     wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        binding: bindings::BLUR_TEXTURES[1],
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: bindings::BLUR_TEXTURES[2],
+                        resource: wgpu::BindingResource::TextureView(source),
                     },
                 ],
-            });
+            })
+        })
+    }
+
+    /// Layout entry for a filterable 2D texture in the composite bind group.
+    fn texture_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }
     }
 }
 
@@ -304,12 +783,31 @@ struct CompositeUniforms {
     sx: f32,
     sy: f32,
     warp: f32,
-    _padding: f32,
+    zoomexp: f32,
+    warp_scale: f32,
+    warp_time: f32,
+}
+
+/// Uniforms for post-processing shader (flags as 0.0/1.0).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PostUniforms {
+    brighten: f32,
+    darken: f32,
+    solarize: f32,
+    invert: f32,
+    darken_center: f32,
+    gamma: f32,
+    echo_zoom: f32,
+    echo_alpha: f32,
+    echo_orientation: f32,
+    _padding: [f32; 3],
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{BorderParams, EchoParams, MotionParams, PostParams, ShapeParams};
 
     #[test]
     fn test_renderer_creation() {
@@ -353,4 +851,676 @@ mod tests {
         // Verify state progressed
         assert_eq!(renderer.state().frame, 10);
     }
+
+    #[test]
+    fn test_render_after_resize() {
+        let config = RenderConfig::default();
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+
+        renderer.render().unwrap();
+        renderer.resize(640, 360);
+
+        let texture = renderer.render_texture();
+        assert_eq!(texture.width(), 640);
+        assert_eq!(texture.height(), 360);
+
+        // Composite bind group must reference the new previous-frame texture
+        for _ in 0..3 {
+            assert!(renderer.render().is_ok());
+        }
+    }
+
+    /// Render one 8x8 frame zoomed 4x onto a checkerboard and read it back.
+    fn render_zoomed_checkerboard(filter_mode: FilterMode) -> Vec<u8> {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            filter_mode,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+
+        // 2x2 checkerboard in the center of the previous frame (texels 3..=4)
+        let mut pixels = vec![0u8; 8 * 8 * 4];
+        for (x, y) in [(3, 3), (4, 4)] {
+            let i = (y * 8 + x) * 4;
+            pixels[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+        }
+        write_prev_8x8(&renderer, &pixels);
+
+        // Zoom in 4x so the 2x2 pattern fills the 8x8 output
+        let mut state = RenderState::default();
+        state.motion.zoom = 4.0;
+        renderer.update_state(state);
+        renderer.render().unwrap();
+
+        read_back_8x8(&renderer, renderer.render_texture())
+            .chunks(4)
+            .map(|px| px[0])
+            .collect()
+    }
+
+    /// Wave parameters that draw nothing, for tests of other passes.
+    fn hidden_wave() -> crate::config::WaveParams {
+        crate::config::WaveParams {
+            a: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// Upload 8x8 RGBA8 pixels as the previous frame.
+    fn write_prev_8x8(renderer: &MilkRenderer, pixels: &[u8]) {
+        renderer.gpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &renderer.gpu.prev_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(8 * 4),
+                rows_per_image: Some(8),
+            },
+            wgpu::Extent3d {
+                width: 8,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Read back an 8x8 texture's texels (rows padded to 256 bytes).
+    fn read_back_8x8(renderer: &MilkRenderer, texture: &wgpu::Texture) -> Vec<u8> {
+        let row_bytes = 8 * texture.format().block_copy_size(None).unwrap() as usize;
+        let device = &renderer.gpu.device;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: 256 * 8,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(256),
+                    rows_per_image: Some(8),
+                },
+            },
+            wgpu::Extent3d {
+                width: 8,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+        );
+        renderer.gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+        let data = slice.get_mapped_range();
+        (0..8)
+            .flat_map(|row| data[row * 256..row * 256 + row_bytes].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_mode_nearest_vs_linear() {
+        // Red channel of every output pixel; white texels come out at ~96% after decay
+        let is_intermediate = |v: &u8| (16..224).contains(v);
+
+        let nearest = render_zoomed_checkerboard(FilterMode::Nearest);
+        assert!(
+            !nearest.iter().any(is_intermediate),
+            "nearest filtering should produce hard edges: {:?}",
+            nearest
+        );
+        assert!(nearest.iter().any(|v| *v >= 224));
+
+        let linear = render_zoomed_checkerboard(FilterMode::Linear);
+        assert!(
+            linear.iter().any(is_intermediate),
+            "linear filtering should interpolate: {:?}",
+            linear
+        );
+    }
+
+    #[test]
+    fn test_warp_mesh_matches_uniform_motion() {
+        let render = |mesh: bool| {
+            let config = RenderConfig {
+                width: 8,
+                height: 8,
+                texture_format: crate::config::TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            };
+            let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+            let pixels: Vec<u8> = (0..64u8)
+                .flat_map(|i| [i * 4, 255 - i * 4, 0, 255])
+                .collect();
+            write_prev_8x8(&renderer, &pixels);
+
+            let motion = MotionParams {
+                zoom: 1.5,
+                rot: 0.3,
+                dx: 0.05,
+                ..Default::default()
+            };
+            if mesh {
+                renderer.set_warp_mesh(Some(WarpMesh::new(4, 3, &[motion; 12])));
+            } else {
+                renderer.update_state(RenderState {
+                    motion,
+                    ..Default::default()
+                });
+            }
+            renderer.render().unwrap();
+            read_back_8x8(&renderer, renderer.render_texture())
+        };
+
+        // Affine motion interpolates exactly across the mesh
+        let (uniform, mesh) = (render(false), render(true));
+        for (u, m) in uniform.iter().zip(&mesh) {
+            assert!(
+                (*u as i32 - *m as i32).abs() <= 2,
+                "{:?} vs {:?}",
+                uniform,
+                mesh
+            );
+        }
+    }
+
+    #[test]
+    fn test_zoomexp_varies_zoom_with_radius() {
+        let render = |zoom: f32, zoomexp: f32| {
+            let config = RenderConfig {
+                width: 8,
+                height: 8,
+                texture_format: crate::config::TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            };
+            let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+            let pixels: Vec<u8> = (0..64u8)
+                .flat_map(|i| [i * 4, 255 - i * 4, (i % 8) * 32, 255])
+                .collect();
+            write_prev_8x8(&renderer, &pixels);
+            renderer.update_state(RenderState {
+                motion: MotionParams {
+                    zoom,
+                    zoomexp,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            renderer.render().unwrap();
+            read_back_8x8(&renderer, renderer.render_texture())
+        };
+        let pixel = |frame: &[u8], x: usize, y: usize| frame[(y * 8 + x) * 4..][..3].to_vec();
+        let close = |a: Vec<u8>, b: Vec<u8>| a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= 3);
+
+        // zoomexp = 1 zooms uniformly; 2 changes the picture
+        let uniform = render(1.5, 1.0);
+        let tunnel = render(1.5, 2.0);
+        assert_eq!(uniform, render(1.5, 0.0));
+        assert_ne!(uniform, tunnel);
+
+        // Each pixel is zoomed by zoom^(zoomexp^(2 * rad - 1)) for its own
+        // radius, with rad 1 at the corners: 1.5^(2^(2 * 0.125 - 1)) next to
+        // the center, 1.5^(2^(2 * 0.875 - 1)) in the corner pixel
+        let motion = MotionParams {
+            zoom: 1.5,
+            zoomexp: 2.0,
+            ..Default::default()
+        };
+        for (x, y, zoom) in [(3, 3, 1.2726), (0, 0, 1.9776), (7, 2, 1.6744)] {
+            let (u, v) = ((x as f32 + 0.5) / 8.0, (y as f32 + 0.5) / 8.0);
+            assert!((motion.zoom_at(u, v) - zoom).abs() < 1e-3, "({}, {})", x, y);
+            let expected = render(zoom, 1.0);
+            assert!(
+                close(pixel(&tunnel, x, y), pixel(&expected, x, y)),
+                "({}, {}) at zoom {}: {:?} vs {:?}",
+                x,
+                y,
+                zoom,
+                pixel(&tunnel, x, y),
+                pixel(&expected, x, y)
+            );
+        }
+        // zoom^(zoomexp^-1) in the center, zoom^zoomexp at the corners
+        assert!((motion.zoom_at(0.5, 0.5) - 1.5f32.sqrt()).abs() < 1e-5);
+        assert!((motion.zoom_at(0.0, 0.0) - 2.25).abs() < 1e-5);
+        assert!((motion.zoom_at(1.0, 1.0) - 2.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_warp_animates() {
+        let render = |warp: f32, warp_time: f32| {
+            let config = RenderConfig {
+                width: 8,
+                height: 8,
+                texture_format: crate::config::TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            };
+            let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+            let pixels: Vec<u8> = (0..64u8)
+                .flat_map(|i| [i * 4, 255 - i * 4, (i % 8) * 32, 255])
+                .collect();
+            write_prev_8x8(&renderer, &pixels);
+            renderer.update_state(RenderState {
+                motion: MotionParams {
+                    warp,
+                    warp_scale: 1.0,
+                    warp_time,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            renderer.render().unwrap();
+            read_back_8x8(&renderer, renderer.render_texture())
+        };
+
+        // Without warp the phase changes nothing; with it the picture moves
+        // as the phase advances
+        let still = render(0.0, 0.0);
+        assert_eq!(still, render(0.0, 2.0));
+        let warped = render(10.0, 0.0);
+        assert_ne!(warped, still);
+        assert_ne!(render(10.0, 2.0), warped);
+    }
+
+    #[test]
+    fn test_feedback_decays_across_frames() {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        write_prev_8x8(&renderer, &[255; 8 * 8 * 4]);
+
+        // Each frame samples the one before it, so brightness keeps decaying
+        // through both the feedback buffer and the displayed output
+        let mut last = 255;
+        for frame in 0..5 {
+            renderer.render().unwrap();
+            let feedback = read_back_8x8(&renderer, renderer.feedback_texture())[0];
+            let output = read_back_8x8(&renderer, renderer.render_texture())[0];
+            assert!(
+                feedback < last && feedback > 0,
+                "frame {}: {} after {}",
+                frame,
+                feedback,
+                last
+            );
+            assert_eq!(output, feedback);
+            last = feedback;
+        }
+    }
+
+    #[test]
+    fn test_debug_capture_warp_vs_final() {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        assert!(renderer.captured_texture().is_none());
+
+        renderer.debug_capture(Some(CapturePass::Warp));
+        let mut state = *renderer.state();
+        state.wave = hidden_wave();
+        state.post.invert = true;
+        renderer.update_state(state);
+        renderer.render().unwrap();
+
+        let warp = read_back_8x8(&renderer, renderer.captured_texture().unwrap());
+        let output = read_back_8x8(&renderer, renderer.render_texture());
+        assert_ne!(warp, output);
+
+        // The output is the inverted warp buffer
+        for (w, o) in warp.chunks(4).zip(output.chunks(4)) {
+            for c in 0..3 {
+                assert!((255 - w[c] as i32 - o[c] as i32).abs() <= 1);
+            }
+        }
+
+        // Without post-processing the captured warp buffer matches the output
+        state.post.invert = false;
+        renderer.update_state(state);
+        renderer.render().unwrap();
+        let warp = read_back_8x8(&renderer, renderer.captured_texture().unwrap());
+        let output = read_back_8x8(&renderer, renderer.render_texture());
+        assert_eq!(warp, output);
+
+        renderer.debug_capture(None);
+        assert!(renderer.captured_texture().is_none());
+    }
+
+    /// Render one 8x8 frame over an opaque black previous frame with a white left column,
+    /// returning the output and feedback pixels.
+    fn render_with_echo(echo: Option<EchoParams>) -> (Vec<u8>, Vec<u8>) {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            filter_mode: FilterMode::Nearest,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+
+        let mut pixels = [0, 0, 0, 255].repeat(8 * 8);
+        for y in 0..8 {
+            let i = y * 8 * 4;
+            pixels[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+        }
+        write_prev_8x8(&renderer, &pixels);
+
+        let mut state = RenderState::default();
+        if let Some(echo) = echo {
+            state.echo = echo;
+        }
+        renderer.update_state(state);
+        renderer.render().unwrap();
+        (
+            read_back_8x8(&renderer, renderer.render_texture()),
+            read_back_8x8(&renderer, renderer.feedback_texture()),
+        )
+    }
+
+    #[test]
+    fn test_video_echo() {
+        let (plain, plain_feedback) = render_with_echo(None);
+
+        let (invisible, _) = render_with_echo(Some(EchoParams {
+            zoom: 2.0,
+            alpha: 0.0,
+            orientation: 1,
+        }));
+        assert_eq!(plain, invisible);
+
+        // A horizontally flipped echo mirrors the white column to the right edge
+        let (echoed, echoed_feedback) = render_with_echo(Some(EchoParams {
+            zoom: 1.0,
+            alpha: 0.5,
+            orientation: 1,
+        }));
+        assert_ne!(plain, echoed);
+        let right_red = echoed[7 * 4];
+        assert!(plain[7 * 4] < 16 && right_red > 64, "{:?}", echoed);
+
+        // The echo is display-only: it doesn't feed back into the next frame
+        assert_eq!(plain_feedback, echoed_feedback);
+    }
+
+    /// Render one 8x8 frame over a uniform gray previous frame.
+    fn render_with_post(post: PostParams) -> Vec<u8> {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        write_prev_8x8(&renderer, &[64, 128, 192, 255].repeat(8 * 8));
+
+        renderer.update_state(RenderState {
+            post,
+            wave: hidden_wave(),
+            ..Default::default()
+        });
+        renderer.render().unwrap();
+        read_back_8x8(&renderer, renderer.render_texture())
+    }
+
+    #[test]
+    fn test_post_invert_center_pixel() {
+        let center = (4 * 8 + 4) * 4;
+        let plain = render_with_post(PostParams::default());
+        let inverted = render_with_post(PostParams {
+            invert: true,
+            ..Default::default()
+        });
+
+        for c in 0..3 {
+            let sum = plain[center + c] as i32 + inverted[center + c] as i32;
+            assert!((sum - 255).abs() <= 1, "{:?} vs {:?}", plain, inverted);
+        }
+    }
+
+    #[test]
+    fn test_post_darken_center_and_gamma() {
+        let center = (4 * 8 + 4) * 4;
+        let plain = render_with_post(PostParams::default());
+
+        let darkened = render_with_post(PostParams {
+            darken_center: true,
+            ..Default::default()
+        });
+        assert!(darkened[center] < plain[center]);
+        assert_eq!(darkened[0], plain[0], "corners stay untouched");
+
+        // gamma > 1 brightens midtones
+        let brighter = render_with_post(PostParams {
+            gamma: 2.0,
+            ..Default::default()
+        });
+        assert!(brighter[center + 1] > plain[center + 1]);
+    }
+
+    #[test]
+    fn test_debug_capture_pre_waveform_includes_shapes() {
+        let capture = |pass: CapturePass| {
+            let config = RenderConfig {
+                width: 8,
+                height: 8,
+                texture_format: crate::config::TextureFormat::Rgba8Unorm,
+                ..Default::default()
+            };
+            let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+            let mut shapes = [ShapeParams::default(); crate::config::MAX_SHAPES];
+            shapes[0] = ShapeParams {
+                enabled: true,
+                sides: 4,
+                num_inst: 1,
+                x: 0.5,
+                y: 0.5,
+                rad: 0.5,
+                color: [0.0, 1.0, 0.0, 1.0],
+                edge_color: [0.0, 1.0, 0.0, 1.0],
+                ..Default::default()
+            };
+            renderer.debug_capture(Some(pass));
+            renderer.update_state(RenderState {
+                shapes,
+                wave: hidden_wave(),
+                ..Default::default()
+            });
+            renderer.render().unwrap();
+            read_back_8x8(&renderer, renderer.captured_texture().unwrap())
+        };
+
+        let center = |pixels: &[u8]| pixels[(4 * 8 + 4) * 4..(4 * 8 + 4) * 4 + 3].to_vec();
+        assert_ne!(center(&capture(CapturePass::Warp)), [0, 255, 0]);
+        assert_eq!(center(&capture(CapturePass::PreWaveform)), [0, 255, 0]);
+    }
+
+    #[test]
+    fn test_shape_fill_gradient() {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        let mut shapes = [ShapeParams::default(); crate::config::MAX_SHAPES];
+        shapes[0] = ShapeParams {
+            enabled: true,
+            sides: 4,
+            num_inst: 1,
+            x: 0.5,
+            y: 0.5,
+            rad: 0.5,
+            color: [0.0, 1.0, 0.0, 1.0],
+            edge_color: [0.0, 1.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        // The waveform is drawn over shapes, so keep it out of the way
+        renderer.update_state(RenderState {
+            shapes,
+            wave: hidden_wave(),
+            ..Default::default()
+        });
+        renderer.render().unwrap();
+        let pixels = read_back_8x8(&renderer, renderer.render_texture());
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 3];
+        assert_eq!(pixel(4, 4), [0, 255, 0]);
+        assert_ne!(pixel(0, 0), [0, 255, 0], "corners are outside the diamond");
+    }
+
+    #[test]
+    fn test_outer_border() {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        renderer.update_state(RenderState {
+            border: BorderParams {
+                outer_size: 0.1,
+                outer_color: [1.0, 0.0, 0.0, 1.0],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        renderer.render().unwrap();
+        let pixels = read_back_8x8(&renderer, renderer.render_texture());
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 3];
+        for i in 0..8 {
+            for (x, y) in [(i, 0), (i, 7), (0, i), (7, i)] {
+                assert_eq!(pixel(x, y), [255, 0, 0], "edge pixel ({}, {})", x, y);
+            }
+        }
+        assert_ne!(pixel(4, 4), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_msaa_render() {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba8Unorm,
+            sample_count: 4,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        let mut shapes = [ShapeParams::default(); crate::config::MAX_SHAPES];
+        shapes[0] = ShapeParams {
+            enabled: true,
+            sides: 4,
+            num_inst: 1,
+            x: 0.5,
+            y: 0.5,
+            rad: 0.5,
+            color: [0.0, 1.0, 0.0, 1.0],
+            edge_color: [0.0, 1.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        // The waveform is drawn over shapes, so keep it out of the way
+        renderer.update_state(RenderState {
+            shapes,
+            wave: hidden_wave(),
+            ..Default::default()
+        });
+        renderer.render().unwrap();
+        let pixels = read_back_8x8(&renderer, renderer.render_texture());
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 3];
+        assert_eq!(pixel(4, 4), [0, 255, 0]);
+
+        // Diagonal edges cut through pixels, which only come out partially
+        // covered when multisampled
+        let partial = (0..64).any(|i| (1..255).contains(&pixels[i * 4 + 1]));
+        match renderer.gpu().sample_count() {
+            4 => assert!(partial, "no anti-aliased edge pixels"),
+            count => assert_eq!(count, 1, "unsupported counts fall back to 1"),
+        }
+
+        // Multisampled target follows resizes
+        renderer.resize(16, 8);
+        renderer.render().unwrap();
+    }
+
+    #[test]
+    fn test_float_target_keeps_values_above_one() {
+        let config = RenderConfig {
+            width: 8,
+            height: 8,
+            texture_format: crate::config::TextureFormat::Rgba16Float,
+            ..Default::default()
+        };
+        let mut renderer = pollster::block_on(MilkRenderer::new(config)).unwrap();
+        assert_eq!(
+            renderer.feedback_texture().format(),
+            wgpu::TextureFormat::Rgba16Float
+        );
+
+        // Two additive full-intensity squares stack to 2.0 in the middle,
+        // which an 8-bit target would clip to 1.0
+        let square = ShapeParams {
+            enabled: true,
+            sides: 4,
+            num_inst: 1,
+            x: 0.5,
+            y: 0.5,
+            rad: 0.5,
+            additive: true,
+            color: [0.0, 1.0, 0.0, 1.0],
+            edge_color: [0.0, 1.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        let mut shapes = [ShapeParams::default(); crate::config::MAX_SHAPES];
+        shapes[0] = square;
+        shapes[1] = square;
+        renderer.update_state(RenderState {
+            shapes,
+            wave: hidden_wave(),
+            ..Default::default()
+        });
+        renderer.render().unwrap();
+
+        let channel = |texels: &[u8], x: usize, y: usize, c: usize| {
+            let i = (y * 8 + x) * 8 + c * 2;
+            half::f16::from_le_bytes([texels[i], texels[i + 1]]).to_f32()
+        };
+        let feedback = read_back_8x8(&renderer, renderer.feedback_texture());
+        let green = channel(&feedback, 4, 4, 1);
+        assert!((green - 2.0).abs() < 0.01, "got {}", green);
+
+        // The displayed output is tonemapped: the peak lands on 1.0 and the
+        // excess spills into red and blue
+        let output = read_back_8x8(&renderer, renderer.output_texture());
+        let rgb = [0, 1, 2].map(|c| channel(&output, 4, 4, c));
+        assert!((rgb[1] - 1.0).abs() < 0.01, "got {:?}", rgb);
+        assert!((rgb[0] - 0.5).abs() < 0.01, "got {:?}", rgb);
+        assert!((rgb[2] - 0.5).abs() < 0.01, "got {:?}", rgb);
+    }
 }
@@ -0,0 +1,349 @@
+//! Custom shape rendering.
+
+use crate::config::ShapeParams;
+use bytemuck::{Pod, Zeroable};
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+/// Most instances drawn for one shape.
+const MAX_INSTANCES: u32 = 1024;
+
+/// Initial vertex buffer size, in vertices.
+const INITIAL_VERTEX_CAPACITY: u64 = 4096;
+
+/// Shape vertex in clip space.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct ShapeVertex {
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl ShapeVertex {
+    /// Vertex buffer layout matching `VertexInput` in `shape.wgsl`.
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<ShapeVertex>() as u64,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+    };
+}
+
+/// Triangles and border lines of one shape, all instances included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShapeGeometry {
+    /// Triangle list: one fan triangle per side, center first
+    pub fill: Vec<ShapeVertex>,
+
+    /// Line list: one segment per side, four offset copies when thick
+    pub border: Vec<ShapeVertex>,
+}
+
+impl ShapeGeometry {
+    /// Build the geometry of `shape` for a `size` pixel render target.
+    ///
+    /// As in Milkdrop, the first vertex sits 45° past `ang` and the radius
+    /// is corrected for aspect ratio so shapes stay regular on wide screens.
+    pub fn new(shape: &ShapeParams, size: (u32, u32)) -> Self {
+        let mut geometry = Self::default();
+        if !shape.enabled || shape.rad <= 0.0 {
+            return geometry;
+        }
+
+        let sides = shape.sides.clamp(3, 100);
+        let instances = shape.num_inst.clamp(1, MAX_INSTANCES);
+        let (width, height) = (size.0.max(1) as f32, size.1.max(1) as f32);
+        let aspect_x = if width > height { height / width } else { 1.0 };
+        let aspect_y = if height > width { width / height } else { 1.0 };
+
+        let center = [shape.x * 2.0 - 1.0, shape.y * 2.0 - 1.0];
+        let corners: Vec<[f32; 2]> = (0..=sides)
+            .map(|i| {
+                let angle = i as f32 / sides as f32 * TAU + shape.ang + FRAC_PI_4;
+                [
+                    center[0] + shape.rad * angle.cos() * aspect_x,
+                    center[1] + shape.rad * angle.sin() * aspect_y,
+                ]
+            })
+            .collect();
+
+        let vertex = |position: [f32; 2], color: [f32; 4]| ShapeVertex { position, color };
+        let border_offsets: &[[f32; 2]] = if shape.thick_outline {
+            let (px, py) = (2.0 / width, 2.0 / height);
+            &[[0.0, 0.0], [px, 0.0], [0.0, py], [px, py]]
+        } else {
+            &[[0.0, 0.0]]
+        };
+
+        for _ in 0..instances {
+            for edge in corners.windows(2) {
+                geometry.fill.push(vertex(center, shape.color));
+                geometry.fill.push(vertex(edge[0], shape.edge_color));
+                geometry.fill.push(vertex(edge[1], shape.edge_color));
+            }
+
+            if shape.border_color[3] > 0.0 {
+                for offset in border_offsets {
+                    for edge in corners.windows(2) {
+                        for corner in edge {
+                            let position = [corner[0] + offset[0], corner[1] + offset[1]];
+                            geometry.border.push(vertex(position, shape.border_color));
+                        }
+                    }
+                }
+            }
+        }
+
+        geometry
+    }
+}
+
+/// Draws custom shapes over a frame.
+pub struct ShapeRenderer {
+    /// Fill pipelines, alpha-blended then additive
+    fill_pipelines: [wgpu::RenderPipeline; 2],
+
+    /// Border pipelines, alpha-blended then additive
+    border_pipelines: [wgpu::RenderPipeline; 2],
+
+    /// Vertices of all shapes drawn this frame
+    vertex_buffer: wgpu::Buffer,
+
+    /// Capacity of `vertex_buffer`, in vertices
+    vertex_capacity: u64,
+}
+
+impl ShapeRenderer {
+    /// Create a shape renderer drawing onto `format` targets with
+    /// `sample_count` samples per pixel.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        // Load shader
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shape Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shape.wgsl").into()),
+        });
+
+        // Create pipeline layout
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shape Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |topology, additive| {
+            Self::create_pipeline(
+                device,
+                &shader,
+                &pipeline_layout,
+                format,
+                sample_count,
+                topology,
+                additive,
+            )
+        };
+        let fill_pipelines = [
+            pipeline(wgpu::PrimitiveTopology::TriangleList, false),
+            pipeline(wgpu::PrimitiveTopology::TriangleList, true),
+        ];
+        let border_pipelines = [
+            pipeline(wgpu::PrimitiveTopology::LineList, false),
+            pipeline(wgpu::PrimitiveTopology::LineList, true),
+        ];
+
+        Self {
+            fill_pipelines,
+            border_pipelines,
+            vertex_buffer: Self::create_vertex_buffer(device, INITIAL_VERTEX_CAPACITY),
+            vertex_capacity: INITIAL_VERTEX_CAPACITY,
+        }
+    }
+
+    /// Create a shape pipeline for one topology and blend mode.
+    fn create_pipeline(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        topology: wgpu::PrimitiveTopology,
+        additive: bool,
+    ) -> wgpu::RenderPipeline {
+        let blend = if additive {
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            }
+        } else {
+            wgpu::BlendState::ALPHA_BLENDING
+        };
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shape Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                buffers: &[ShapeVertex::LAYOUT],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Create a vertex buffer holding `capacity` vertices.
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shape Vertex Buffer"),
+            size: capacity * std::mem::size_of::<ShapeVertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Draw `shapes` in order onto `target`, which is `size` pixels: a view
+    /// and, when it is multisampled, the view it resolves into.
+    ///
+    /// Does nothing when no shape is visible.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: (&wgpu::TextureView, Option<&wgpu::TextureView>),
+        size: (u32, u32),
+        shapes: &[ShapeParams],
+    ) {
+        // Gather all vertices, remembering which pipeline draws each range
+        let mut vertices = Vec::new();
+        let mut draws = Vec::new();
+        for shape in shapes {
+            let geometry = ShapeGeometry::new(shape, size);
+            let blend = shape.additive as usize;
+            for (pipeline, part) in [
+                (&self.fill_pipelines[blend], geometry.fill),
+                (&self.border_pipelines[blend], geometry.border),
+            ] {
+                if !part.is_empty() {
+                    let start = vertices.len() as u32;
+                    vertices.extend(part);
+                    draws.push((pipeline, start..vertices.len() as u32));
+                }
+            }
+        }
+        if vertices.is_empty() {
+            return;
+        }
+
+        if vertices.len() as u64 > self.vertex_capacity {
+            self.vertex_capacity = (vertices.len() as u64).next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.vertex_capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shape Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target.0,
+                resolve_target: target.1,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for (pipeline, range) in draws {
+            render_pass.set_pipeline(pipeline);
+            render_pass.draw(range, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pentagon() -> ShapeParams {
+        ShapeParams {
+            enabled: true,
+            sides: 5,
+            num_inst: 3,
+            x: 0.5,
+            y: 0.5,
+            rad: 0.2,
+            color: [1.0, 0.0, 0.0, 1.0],
+            edge_color: [0.0, 0.0, 1.0, 0.5],
+            border_color: [1.0, 1.0, 1.0, 1.0],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_vertex_counts() {
+        let geometry = ShapeGeometry::new(&pentagon(), (640, 480));
+        // 5 fan triangles and 5 border segments per instance
+        assert_eq!(geometry.fill.len(), 3 * 5 * 3);
+        assert_eq!(geometry.border.len(), 3 * 5 * 2);
+
+        let thick = ShapeGeometry::new(
+            &ShapeParams {
+                thick_outline: true,
+                ..pentagon()
+            },
+            (640, 480),
+        );
+        assert_eq!(thick.fill.len(), 3 * 5 * 3);
+        assert_eq!(thick.border.len(), 3 * 5 * 2 * 4);
+    }
+
+    #[test]
+    fn test_gradient_and_hidden_parts() {
+        let geometry = ShapeGeometry::new(&pentagon(), (480, 480));
+        assert_eq!(geometry.fill[0].position, [0.0, 0.0]);
+        assert_eq!(geometry.fill[0].color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(geometry.fill[1].color, [0.0, 0.0, 1.0, 0.5]);
+        let corner = geometry.fill[1].position;
+        assert!(((corner[0].powi(2) + corner[1].powi(2)).sqrt() - 0.2).abs() < 1e-5);
+
+        // No border without border alpha, nothing when disabled
+        let borderless = ShapeParams {
+            border_color: [1.0, 1.0, 1.0, 0.0],
+            ..pentagon()
+        };
+        assert!(ShapeGeometry::new(&borderless, (64, 64)).border.is_empty());
+        let disabled = ShapeParams {
+            enabled: false,
+            ..pentagon()
+        };
+        assert_eq!(
+            ShapeGeometry::new(&disabled, (64, 64)),
+            ShapeGeometry::default()
+        );
+    }
+}
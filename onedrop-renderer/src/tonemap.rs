@@ -0,0 +1,68 @@
+//! Tonemapping of float render targets down to 8-bit output.
+//!
+//! With [`TextureFormat::Rgba16Float`](crate::config::TextureFormat) the
+//! feedback loop keeps values above 1.0 instead of clipping them. They are
+//! only compressed when leaving the renderer: colors whose channels are all
+//! at most 1.0 pass through unchanged, so presets that stay in range look as
+//! they do in 8-bit, and brighter colors fade towards white instead of
+//! clipping channel by channel. `tonemap` in the post pass (`post.wgsl`) and
+//! the GUI's `blit.wgsl` apply the same curve.
+
+/// Map a linear color with channels in `[0, ∞)` to `[0, 1]`.
+///
+/// The brightest channel is scaled down to 1.0 and the excess spills into
+/// the others, so the color approaches white as it gets brighter.
+pub fn tonemap(color: [f32; 3]) -> [f32; 3] {
+    let color = color.map(|channel| channel.max(0.0));
+    let peak = color[0].max(color[1]).max(color[2]);
+    if peak <= 1.0 {
+        return color;
+    }
+    color.map(|channel| channel / (peak * peak) + (1.0 - 1.0 / peak))
+}
+
+/// Convert tightly packed `Rgba16Float` texels (little-endian halves) to
+/// RGBA8, tonemapping color and clamping alpha.
+pub fn rgba16f_to_rgba8(texels: &[u8]) -> Vec<u8> {
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    texels
+        .chunks_exact(8)
+        .flat_map(|texel| {
+            let channel =
+                |i: usize| half::f16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]]).to_f32();
+            let [r, g, b] = tonemap([channel(0), channel(1), channel(2)]);
+            [to_u8(r), to_u8(g), to_u8(b), to_u8(channel(3))]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tonemap_keeps_range_and_rolls_off() {
+        assert_eq!(tonemap([-1.0, 0.5, 1.0]), [0.0, 0.5, 1.0]);
+        assert_eq!(tonemap([0.25, 0.5, 0.75]), [0.25, 0.5, 0.75]);
+
+        // The peak channel lands on 1.0 and the others rise towards it
+        let [r, g, b] = tonemap([2.0, 1.0, 0.0]);
+        assert_eq!((r, g, b), (1.0, 0.75, 0.5));
+        let [_, g_bright, _] = tonemap([4.0, 1.0, 0.0]);
+        assert!(g_bright > g && g_bright <= 1.0);
+        assert!(tonemap([100.0, 0.0, 0.0]).iter().all(|&c| c > 0.98));
+    }
+
+    #[test]
+    fn test_rgba16f_to_rgba8() {
+        let texels: Vec<u8> = [0.5f32, 1.0, 0.25, 2.0, 2.0, 1.0, 0.0, 1.0]
+            .iter()
+            .flat_map(|&v| half::f16::from_f32(v).to_le_bytes())
+            .collect();
+        // In-range colors are kept, 1.0 reaches white; alpha is only clamped
+        assert_eq!(
+            rgba16f_to_rgba8(&texels),
+            [128, 255, 64, 255, 255, 191, 128, 255]
+        );
+    }
+}
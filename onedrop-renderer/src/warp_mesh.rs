@@ -0,0 +1,209 @@
+//! Per-vertex warp mesh.
+//!
+//! As in Milkdrop, per-pixel equations run once per vertex of a coarse grid
+//! rather than once per pixel. Each vertex records where the previous frame
+//! is sampled, and the GPU interpolates between vertices.
+
+use crate::config::MotionParams;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+/// Warp mesh vertex: clip-space position and the texture coordinate the
+/// previous frame is sampled at.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct WarpMeshVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+}
+
+impl WarpMeshVertex {
+    /// Vertex buffer layout matching `MeshInput` in `composite.wgsl`.
+    pub(crate) const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<WarpMeshVertex>() as u64,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+    };
+}
+
+/// Grid of warped sample positions covering the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarpMesh {
+    columns: usize,
+    rows: usize,
+    vertices: Vec<WarpMeshVertex>,
+}
+
+impl WarpMesh {
+    /// Build a `columns`×`rows` mesh from per-vertex motion, given row by
+    /// row. Vertex (i, j) sits at x = i / (columns - 1), y = j / (rows - 1),
+    /// with y = 0 at the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid is smaller than 2×2 or `motion` does not hold
+    /// exactly `columns * rows` entries.
+    pub fn new(columns: usize, rows: usize, motion: &[MotionParams]) -> Self {
+        assert!(
+            columns >= 2 && rows >= 2,
+            "warp mesh needs at least 2x2 vertices"
+        );
+        assert_eq!(motion.len(), columns * rows, "one motion entry per vertex");
+
+        let vertices = motion
+            .iter()
+            .enumerate()
+            .map(|(index, motion)| {
+                let x = (index % columns) as f32 / (columns - 1) as f32;
+                let y = (index / columns) as f32 / (rows - 1) as f32;
+                WarpMeshVertex {
+                    position: [x * 2.0 - 1.0, 1.0 - y * 2.0],
+                    uv: warp_uv(x, y, motion),
+                }
+            })
+            .collect();
+
+        Self {
+            columns,
+            rows,
+            vertices,
+        }
+    }
+
+    /// Vertices per row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Vertices, row by row.
+    pub fn vertices(&self) -> &[WarpMeshVertex] {
+        &self.vertices
+    }
+
+    /// Triangle list indices: two triangles per grid cell.
+    pub fn indices(&self) -> Vec<u32> {
+        let columns = self.columns as u32;
+        let mut indices = Vec::with_capacity((self.columns - 1) * (self.rows - 1) * 6);
+        for j in 0..self.rows as u32 - 1 {
+            for i in 0..columns - 1 {
+                let top_left = j * columns + i;
+                let bottom_left = top_left + columns;
+                indices.extend([
+                    top_left,
+                    bottom_left,
+                    top_left + 1,
+                    top_left + 1,
+                    bottom_left,
+                    bottom_left + 1,
+                ]);
+            }
+        }
+        indices
+    }
+}
+
+/// Texture coordinate the previous frame is sampled at for screen point
+/// (`x`, `y`), the same transform `fs_main` in `composite.wgsl` applies,
+/// including the vertex's own animated warp.
+pub fn warp_uv(x: f32, y: f32, motion: &MotionParams) -> [f32; 2] {
+    let (u, v) = (x - motion.cx, y - motion.cy);
+    let (sin_r, cos_r) = motion.rot.sin_cos();
+    let (u, v) = (u * cos_r - v * sin_r, u * sin_r + v * cos_r);
+    let zoom = motion.zoom_at(x, y);
+    let (u, v) = (u / zoom / motion.sx, v / zoom / motion.sy);
+    let [warp_u, warp_v] = motion.warp_offset(x, y);
+    [
+        u + motion.dx + warp_u + motion.cx,
+        v + motion.dy + warp_v + motion.cy,
+    ]
+}
+
+/// GPU buffers of an uploaded [`WarpMesh`].
+pub(crate) struct WarpMeshBuffers {
+    pub vertices: wgpu::Buffer,
+    pub indices: wgpu::Buffer,
+    pub index_count: u32,
+}
+
+impl WarpMeshBuffers {
+    /// Upload `mesh`.
+    pub fn new(device: &wgpu::Device, mesh: &WarpMesh) -> Self {
+        let indices = mesh.indices();
+        Self {
+            vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Warp Mesh Vertex Buffer"),
+                contents: bytemuck::cast_slice(mesh.vertices()),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            }),
+            indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Warp Mesh Index Buffer"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            index_count: indices.len() as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_mesh_samples_in_place() {
+        let mesh = WarpMesh::new(3, 2, &[MotionParams::default(); 6]);
+        assert_eq!(mesh.indices().len(), 2 * 6);
+
+        let corner = mesh.vertices()[0];
+        assert_eq!(corner.position, [-1.0, 1.0]);
+        assert_eq!(corner.uv, [0.0, 0.0]);
+        let last = mesh.vertices()[5];
+        assert_eq!(last.position, [1.0, -1.0]);
+        assert_eq!(last.uv, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_zoom_pulls_samples_toward_center() {
+        let zoomed = MotionParams {
+            zoom: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(warp_uv(0.0, 1.0, &zoomed), [0.25, 0.75]);
+
+        let shifted = MotionParams {
+            dx: 0.1,
+            ..Default::default()
+        };
+        let [u, v] = warp_uv(0.5, 0.5, &shifted);
+        assert!((u - 0.6).abs() < 1e-6 && (v - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_vertex_warp_offsets_samples() {
+        let still = MotionParams::default();
+        let warped = MotionParams {
+            warp: 2.0,
+            warp_time: 1.5,
+            ..Default::default()
+        };
+        let offset = warped.warp_offset(0.25, 0.75);
+        assert_ne!(offset, [0.0, 0.0]);
+        assert!(offset[0].abs() <= 2.0 * 0.007 && offset[1].abs() <= 2.0 * 0.007);
+
+        let [u, v] = warp_uv(0.25, 0.75, &warped);
+        let [u0, v0] = warp_uv(0.25, 0.75, &still);
+        assert!((u - u0 - offset[0]).abs() < 1e-6 && (v - v0 - offset[1]).abs() < 1e-6);
+
+        // The pattern moves with the accumulated warp time
+        let later = MotionParams {
+            warp_time: 2.0,
+            ..warped
+        };
+        assert_ne!(later.warp_offset(0.25, 0.75), offset);
+    }
+}
@@ -1,23 +1,54 @@
 //! Waveform rendering module.
 
+use crate::config::WaveParams;
+
+/// Samples averaged on each side of a point at full smoothing.
+const MAX_SMOOTHING_RADIUS: usize = 8;
+
+/// Height, in UV units, of a full-scale (±1) sample in the strip modes.
+const STRIP_AMPLITUDE: f32 = 0.25;
+
+/// Distance of the left and right channel strips from `wave_y`.
+const CHANNEL_OFFSET: f32 = 0.25;
+
+/// Radians per second the circular ring turns, as in Milkdrop.
+const RING_SPIN: f32 = 0.2;
+
 /// Waveform rendering modes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WaveformMode {
     /// Centered waveform (default)
     Centered = 0,
-    /// Left channel only
+    /// Left channel only, above the center
     LeftChannel = 1,
-    /// Right channel only
+    /// Right channel only, below the center
     RightChannel = 2,
-    /// Spectrum analyzer
+    /// Spectrum analyzer: one vertical bar per FFT magnitude
     Spectrum = 3,
     /// Circular waveform
     Circular = 4,
-    /// Line waveform
+    /// Thin line waveform
     Line = 5,
 }
 
+impl WaveformMode {
+    /// Mode for a `wave_mode` value; unknown modes draw centered.
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => WaveformMode::LeftChannel,
+            2 => WaveformMode::RightChannel,
+            3 => WaveformMode::Spectrum,
+            4 => WaveformMode::Circular,
+            5 => WaveformMode::Line,
+            _ => WaveformMode::Centered,
+        }
+    }
+}
+
 /// Waveform point data.
+///
+/// `position` is where the sample is drawn, in UV space (0-1, y up); in
+/// spectrum mode it's the top of the sample's bar.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct WavePoint {
@@ -26,8 +57,72 @@ pub struct WavePoint {
     pub _padding: f32,
 }
 
+/// Waveform uniforms, matching `Uniforms` in `waveform_advanced.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WaveformUniforms {
+    pub resolution: [f32; 2],
+    pub time: f32,
+    /// [`WaveformMode`] as an integer
+    pub wave_mode: u32,
+    pub wave_scale: f32,
+    /// Multiplier on `wave_color`'s alpha
+    pub wave_alpha: f32,
+    /// Mode-specific shape parameter; grows or shrinks the circular ring
+    pub wave_mystery: f32,
+    pub wave_additive: u32,
+    pub wave_dots: u32,
+    pub wave_thick: u32,
+    /// Ring center in circular mode (0-1)
+    pub wave_x: f32,
+    pub wave_y: f32,
+    pub wave_color: [f32; 4],
+}
+
+impl Default for WaveformUniforms {
+    fn default() -> Self {
+        Self {
+            resolution: [1280.0, 720.0],
+            time: 0.0,
+            wave_mode: WaveformMode::Centered as u32,
+            wave_scale: 1.0,
+            wave_alpha: 1.0,
+            wave_mystery: 0.0,
+            wave_additive: 0,
+            wave_dots: 0,
+            wave_thick: 0,
+            wave_x: 0.5,
+            wave_y: 0.5,
+            wave_color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl WaveformUniforms {
+    /// Uniforms for a preset's waveform at the given resolution, with
+    /// `volume` the average of bass, mid and treble.
+    ///
+    /// Smoothing isn't a uniform: pass [`WaveParams::smoothing`] to
+    /// [`WaveformRenderer::update_wave_data`].
+    pub fn from_wave(wave: &WaveParams, volume: f32, resolution: [f32; 2]) -> Self {
+        Self {
+            resolution,
+            wave_mode: wave.mode.max(0) as u32,
+            wave_scale: wave.scale,
+            wave_alpha: wave.volume_alpha(volume),
+            wave_mystery: wave.mystery,
+            wave_additive: wave.additive as u32,
+            wave_dots: wave.dots as u32,
+            wave_thick: wave.thick as u32,
+            wave_x: wave.x,
+            wave_y: wave.y,
+            wave_color: wave.color(),
+            ..Default::default()
+        }
+    }
+}
+
 /// Waveform renderer.
-#[allow(dead_code)]
 pub struct WaveformRenderer {
     /// Render pipeline
     pipeline: wgpu::RenderPipeline,
@@ -35,6 +130,10 @@ pub struct WaveformRenderer {
     /// Dots pipeline
     dots_pipeline: wgpu::RenderPipeline,
 
+    /// Line and dots pipelines adding to the target (`wave_additive`)
+    additive_pipeline: wgpu::RenderPipeline,
+    additive_dots_pipeline: wgpu::RenderPipeline,
+
     /// Uniform buffer
     uniform_buffer: wgpu::Buffer,
 
@@ -49,8 +148,14 @@ pub struct WaveformRenderer {
 }
 
 impl WaveformRenderer {
-    /// Create a new waveform renderer.
-    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, num_samples: usize) -> Self {
+    /// Create a renderer drawing `num_samples` points into targets with
+    /// `sample_count` samples per pixel.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        num_samples: usize,
+        sample_count: u32,
+    ) -> Self {
         // Load shader
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Waveform Shader"),
@@ -62,7 +167,7 @@ impl WaveformRenderer {
         // Create uniform buffer
         let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Waveform Uniform Buffer"),
-            size: 64, // Enough for uniforms
+            size: std::mem::size_of::<WaveformUniforms>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -125,69 +230,76 @@ impl WaveformRenderer {
             push_constant_ranges: &[],
         });
 
-        // Create render pipeline
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Waveform Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let create_pipeline = |label: &str, vs: &str, fs: &str, blend: wgpu::BlendState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(vs),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(fs),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+            })
+        };
 
-        // Create dots pipeline
-        let dots_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Waveform Dots Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_dots"),
-                buffers: &[],
-                compilation_options: Default::default(),
+        // Additive waves scale by their alpha, then add to what's there
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_dots"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+            alpha: wgpu::BlendComponent::OVER,
+        };
+
+        let pipeline = create_pipeline(
+            "Waveform Pipeline",
+            "vs_main",
+            "fs_main",
+            wgpu::BlendState::ALPHA_BLENDING,
+        );
+        let dots_pipeline = create_pipeline(
+            "Waveform Dots Pipeline",
+            "vs_dots",
+            "fs_dots",
+            wgpu::BlendState::ALPHA_BLENDING,
+        );
+        let additive_pipeline =
+            create_pipeline("Waveform Additive Pipeline", "vs_main", "fs_main", additive);
+        let additive_dots_pipeline = create_pipeline(
+            "Waveform Additive Dots Pipeline",
+            "vs_dots",
+            "fs_dots",
+            additive,
+        );
 
         Self {
             pipeline,
             dots_pipeline,
+            additive_pipeline,
+            additive_dots_pipeline,
             uniform_buffer,
             wave_buffer,
             bind_group,
@@ -195,44 +307,45 @@ impl WaveformRenderer {
         }
     }
 
-    /// Update waveform data.
-    pub fn update_wave_data(&self, queue: &wgpu::Queue, samples: &[f32]) {
-        // Convert samples to wave points
-        let mut points = Vec::with_capacity(samples.len().min(self.num_samples));
-
-        for (i, &sample) in samples.iter().take(self.num_samples).enumerate() {
-            let x = i as f32 / self.num_samples as f32;
-            points.push(WavePoint {
-                position: [x, 0.5],
-                value: sample,
-                _padding: 0.0,
-            });
-        }
-
-        // Pad if necessary
-        while points.len() < self.num_samples {
-            points.push(WavePoint {
-                position: [0.0, 0.5],
-                value: 0.0,
-                _padding: 0.0,
-            });
-        }
+    /// Update mode, color and placement.
+    pub fn update_uniforms(&self, queue: &wgpu::Queue, uniforms: &WaveformUniforms) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(uniforms));
+    }
 
+    /// Update waveform data.
+    ///
+    /// Samples are resampled to the renderer's point count, so the wave keeps
+    /// its shape whatever the audio frame length, then averaged over
+    /// neighbouring points by `smoothing` (0-1, `fWaveSmoothing`) and placed
+    /// for the mode, center, scale and aspect ratio in `uniforms`. In
+    /// spectrum mode `samples` are FFT magnitudes rather than audio.
+    pub fn update_wave_data(
+        &self,
+        queue: &wgpu::Queue,
+        samples: &[f32],
+        uniforms: &WaveformUniforms,
+        smoothing: f32,
+    ) {
+        let values = smooth(&resample_linear(samples, self.num_samples), smoothing);
+        let points = wave_points(&values, uniforms);
         queue.write_buffer(&self.wave_buffer, 0, bytemuck::cast_slice(&points));
     }
 
-    /// Render waveform.
+    /// Render waveform into `target` (view and optional resolve target),
+    /// as dots with `use_dots` and adding to the target with `additive`.
     pub fn render(
         &self,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
+        target: (&wgpu::TextureView, Option<&wgpu::TextureView>),
         use_dots: bool,
+        additive: bool,
     ) {
+        let (view, resolve_target) = target;
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Waveform Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                 view,
-                resolve_target: None,
+                resolve_target,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Load,
                     store: wgpu::StoreOp::Store,
@@ -245,14 +358,508 @@ impl WaveformRenderer {
 
         render_pass.set_bind_group(0, &self.bind_group, &[]);
 
-        if use_dots {
-            render_pass.set_pipeline(&self.dots_pipeline);
-        } else {
-            render_pass.set_pipeline(&self.pipeline);
-        }
+        render_pass.set_pipeline(match (use_dots, additive) {
+            (false, false) => &self.pipeline,
+            (true, false) => &self.dots_pipeline,
+            (false, true) => &self.additive_pipeline,
+            (true, true) => &self.additive_dots_pipeline,
+        });
 
-        // Draw 6 vertices per point (2 triangles = 1 quad)
+        // Draw 6 vertices per segment or dot (2 triangles = 1 quad)
         let vertex_count = (self.num_samples * 6) as u32;
         render_pass.draw(0..vertex_count, 0..1);
     }
 }
+
+/// Place `values` on screen for the mode in `uniforms`.
+///
+/// Strip modes spread the values evenly along x around a baseline: `wave_y`
+/// for centered and line, offset up or down for the left and right channel.
+/// Spectrum bars rise from the bottom edge. Circular mode maps the index to
+/// an angle `ang` around (`wave_x`, `wave_y`), turning slowly with `time`,
+/// and the value to the radius; the base radius is half the distance from
+/// the center to the nearest edge, so the ring stays on screen, and
+/// `wave_mystery` grows or shrinks it.
+fn wave_points(values: &[f32], uniforms: &WaveformUniforms) -> Vec<WavePoint> {
+    let mode = WaveformMode::from_index(uniforms.wave_mode);
+    let scale = uniforms.wave_scale;
+    let last = values.len().saturating_sub(1).max(1) as f32;
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            let t = i as f32 / last;
+            let strip = |baseline: f32| [t, baseline + value * scale * STRIP_AMPLITUDE];
+            let position = match mode {
+                WaveformMode::Centered | WaveformMode::Line => strip(uniforms.wave_y),
+                WaveformMode::LeftChannel => strip(uniforms.wave_y + CHANNEL_OFFSET),
+                WaveformMode::RightChannel => strip(uniforms.wave_y - CHANNEL_OFFSET),
+                WaveformMode::Spectrum => [t, (value.abs() * scale).min(1.0)],
+                WaveformMode::Circular => {
+                    let (x, y) = (uniforms.wave_x, uniforms.wave_y);
+                    let edge = x.min(1.0 - x).min(y).min(1.0 - y).max(0.0);
+                    let radius =
+                        0.5 * edge * (1.0 + uniforms.wave_mystery + value * scale * 0.5).max(0.0);
+                    // Last sample closes the ring
+                    let ang = std::f32::consts::TAU * t + uniforms.time * RING_SPIN;
+                    let [width, height] = uniforms.resolution;
+                    let aspect = height / width.max(1.0);
+                    [x + ang.cos() * aspect * radius, y + ang.sin() * radius]
+                }
+            };
+            WavePoint {
+                position,
+                value,
+                _padding: 0.0,
+            }
+        })
+        .collect()
+}
+
+/// Resample to exactly `count` values by linear interpolation, keeping the
+/// first and last sample in place. Empty input gives silence.
+fn resample_linear(samples: &[f32], count: usize) -> Vec<f32> {
+    match samples.len() {
+        0 => return vec![0.0; count],
+        n if n == count => return samples.to_vec(),
+        _ => {}
+    }
+
+    let last = samples.len() - 1;
+    let step = last as f32 / count.saturating_sub(1).max(1) as f32;
+    (0..count)
+        .map(|i| {
+            let pos = i as f32 * step;
+            let index = (pos as usize).min(last);
+            let next = (index + 1).min(last);
+            let t = pos - index as f32;
+            samples[index] + (samples[next] - samples[index]) * t
+        })
+        .collect()
+}
+
+/// Centered moving average over up to [`MAX_SMOOTHING_RADIUS`] values on
+/// each side, scaled by `smoothing` (0-1). The window shrinks at the ends
+/// rather than padding with silence.
+fn smooth(values: &[f32], smoothing: f32) -> Vec<f32> {
+    let radius = (smoothing.clamp(0.0, 1.0) * MAX_SMOOTHING_RADIUS as f32).round() as usize;
+    if radius == 0 {
+        return values.to_vec();
+    }
+    (0..values.len())
+        .map(|i| {
+            let window = &values[i.saturating_sub(radius)..(i + radius + 1).min(values.len())];
+            window.iter().sum::<f32>() / window.len() as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RenderConfig, TextureFormat};
+    use crate::gpu_context::GpuContext;
+
+    const SIZE: u32 = 64;
+
+    #[test]
+    fn test_waveform_uniforms_size() {
+        assert_eq!(std::mem::size_of::<WaveformUniforms>(), 64);
+    }
+
+    /// Draw a flat wave in `mode` onto black and return which pixels lit up.
+    fn render_flat_wave(mode: WaveformMode) -> Vec<Vec<bool>> {
+        let uniforms = WaveformUniforms {
+            resolution: [SIZE as f32, SIZE as f32],
+            wave_mode: mode as u32,
+            ..Default::default()
+        };
+        render_wave_pixels(&uniforms, false)
+            .iter()
+            .map(|row| row.iter().map(|px| px[0] > 128).collect())
+            .collect()
+    }
+
+    /// Draw a flat wave onto black, blended or added, and return the RGBA
+    /// pixels.
+    fn render_wave_pixels(uniforms: &WaveformUniforms, additive: bool) -> Vec<Vec<[u8; 4]>> {
+        render_samples(uniforms, &[0.5; 256], additive)
+    }
+
+    /// Draw `samples` onto black and return the RGBA pixels.
+    fn render_samples(
+        uniforms: &WaveformUniforms,
+        samples: &[f32],
+        additive: bool,
+    ) -> Vec<Vec<[u8; 4]>> {
+        let config = RenderConfig {
+            width: SIZE,
+            height: SIZE,
+            texture_format: TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let gpu = pollster::block_on(GpuContext::new(config)).unwrap();
+        let target = gpu.create_render_target("Waveform Target");
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let renderer = WaveformRenderer::new(&gpu.device, wgpu::TextureFormat::Rgba8Unorm, 256, 1);
+        renderer.update_uniforms(&gpu.queue, uniforms);
+        renderer.update_wave_data(&gpu.queue, samples, uniforms, 0.0);
+
+        let mut encoder = gpu.device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        renderer.render(&mut encoder, (&view, None), false, additive);
+
+        let bytes_per_row = SIZE * 4;
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Waveform Readback"),
+            size: (bytes_per_row * SIZE) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(SIZE),
+                },
+            },
+            wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        gpu.device.poll(wgpu::Maintain::Wait);
+        let data = slice.get_mapped_range();
+        data.chunks(bytes_per_row as usize)
+            .map(|row| {
+                row.chunks(4)
+                    .map(|px| [px[0], px[1], px[2], px[3]])
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn lit_rows(pixels: &[Vec<bool>]) -> usize {
+        pixels
+            .iter()
+            .filter(|row| row.iter().any(|lit| *lit))
+            .count()
+    }
+
+    #[test]
+    fn test_resample_to_point_count() {
+        // Two periods of a sine in a 735-sample frame, drawn with 512 points
+        let sine = |t: f32| (t * 4.0 * std::f32::consts::PI).sin();
+        let samples: Vec<f32> = (0..735).map(|i| sine(i as f32 / 734.0)).collect();
+
+        let points = resample_linear(&samples, 512);
+        assert_eq!(points.len(), 512);
+        for (i, value) in points.iter().enumerate() {
+            let expected = sine(i as f32 / 511.0);
+            assert!(
+                (value - expected).abs() < 1e-3,
+                "point {}: {} vs {}",
+                i,
+                value,
+                expected
+            );
+        }
+        // No truncation: the wave runs to the last point instead of trailing zeros
+        assert!((points[511] - samples[734]).abs() < 1e-6);
+        assert!(points[480].abs() > 0.3);
+
+        // Short frames are stretched, empty ones are silent
+        assert_eq!(
+            resample_linear(&[0.0, 1.0], 5),
+            vec![0.0, 0.25, 0.5, 0.75, 1.0]
+        );
+        assert_eq!(resample_linear(&[], 3), vec![0.0; 3]);
+        assert_eq!(resample_linear(&[0.7], 2), vec![0.7, 0.7]);
+    }
+
+    #[test]
+    fn test_circular_waveform_draws_ring() {
+        let ring = render_flat_wave(WaveformMode::Circular);
+        let center = (SIZE / 2) as usize;
+
+        // Hollow center, bright pixels spread over many rows
+        assert!(!ring[center][center]);
+        assert!(lit_rows(&ring) > 10, "ring spans {} rows", lit_rows(&ring));
+
+        // Base radius is a quarter of the screen; the flat 0.5 sample widens it a bit
+        let lit_in_center_row: Vec<usize> = (0..SIZE as usize)
+            .filter(|x| ring[center][*x] || ring[center - 1][*x])
+            .collect();
+        assert!(lit_in_center_row.iter().any(|x| *x < center - 8));
+        assert!(lit_in_center_row.iter().any(|x| *x > center + 8));
+
+        // The strip modes draw a horizontal line instead
+        let line = render_flat_wave(WaveformMode::Centered);
+        assert!(lit_rows(&line) <= 3, "line spans {} rows", lit_rows(&line));
+    }
+
+    #[test]
+    fn test_line_is_connected() {
+        // Sixteen triangle periods: each sample climbs about four pixels
+        let samples: Vec<f32> = (0..256)
+            .map(|i| 1.0 - 4.0 * ((i % 16) as f32 / 16.0 - 0.5).abs())
+            .collect();
+        let uniforms = WaveformUniforms {
+            resolution: [SIZE as f32, SIZE as f32],
+            wave_mode: WaveformMode::Line as u32,
+            ..Default::default()
+        };
+        let pixels = render_samples(&uniforms, &samples, false);
+
+        // Every column is lit from the wave's bottom to its top, without the
+        // holes separate dots per sample would leave
+        for x in 2..SIZE as usize - 2 {
+            let lit: Vec<usize> = (0..SIZE as usize)
+                .filter(|&y| pixels[y][x][0] > 0)
+                .collect();
+            let (first, last) = (lit[0], lit[lit.len() - 1]);
+            assert_eq!(lit.len(), last - first + 1, "column {} has holes", x);
+            assert!(last - first >= 12, "column {} spans {}", x, last - first);
+        }
+    }
+
+    #[test]
+    fn test_maximize_wave_color() {
+        let wave = WaveParams {
+            r: 0.2,
+            g: 0.1,
+            b: 0.05,
+            maximize_color: true,
+            ..Default::default()
+        };
+        assert_eq!(wave.color(), [1.0, 0.5, 0.25, 1.0]);
+
+        let uniforms = WaveformUniforms::from_wave(&wave, 0.0, [SIZE as f32, SIZE as f32]);
+        let pixels = render_wave_pixels(&uniforms, false);
+        let brightest = pixels
+            .iter()
+            .flatten()
+            .max_by_key(|px| px[0])
+            .copied()
+            .unwrap();
+        assert_eq!(brightest[0], 255);
+        assert!((brightest[1] as i32 - 128).abs() <= 2, "{:?}", brightest);
+        assert!((brightest[2] as i32 - 64).abs() <= 2, "{:?}", brightest);
+
+        // Without the flag the dim color is kept
+        let dim = WaveParams {
+            maximize_color: false,
+            ..wave
+        };
+        assert_eq!(dim.color(), [0.2, 0.1, 0.05, 1.0]);
+    }
+
+    #[test]
+    fn test_smoothing_reduces_variance() {
+        // Alternating samples: the noisiest a wave can be
+        let samples: Vec<f32> = (0..256)
+            .map(|i| if i % 2 == 0 { 0.8 } else { 0.2 })
+            .collect();
+        let step_variance = |smoothing: f32| {
+            let values = smooth(&samples, smoothing);
+            let steps: Vec<f32> = values.windows(2).map(|w| w[1] - w[0]).collect();
+            steps.iter().map(|d| d * d).sum::<f32>() / steps.len() as f32
+        };
+
+        let raw = step_variance(0.0);
+        let light = step_variance(0.25);
+        let heavy = step_variance(1.0);
+        assert!((raw - 0.36).abs() < 1e-5, "raw variance {}", raw);
+        assert!(light < raw * 0.1, "{} vs {}", light, raw);
+        assert!(heavy <= light, "{} vs {}", heavy, light);
+
+        // The average level is kept
+        let mean = smooth(&samples, 1.0).iter().sum::<f32>() / 256.0;
+        assert!((mean - 0.5).abs() < 0.01, "mean {}", mean);
+        assert_eq!(smooth(&[0.1, 0.9], 0.0), vec![0.1, 0.9]);
+    }
+
+    #[test]
+    fn test_wave_uniforms_from_params() {
+        let wave = WaveParams {
+            a: 0.8,
+            scale: 2.0,
+            mystery: -0.5,
+            thick: true,
+            additive: true,
+            mod_alpha_by_volume: true,
+            mod_alpha_start: 0.5,
+            mod_alpha_end: 1.5,
+            ..Default::default()
+        };
+        let uniforms = WaveformUniforms::from_wave(&wave, 1.0, [SIZE as f32, SIZE as f32]);
+        assert_eq!(uniforms.wave_scale, 2.0);
+        assert_eq!(uniforms.wave_mystery, -0.5);
+        assert_eq!((uniforms.wave_thick, uniforms.wave_additive), (1, 1));
+        assert_eq!(uniforms.wave_color[3], 0.8);
+        // Halfway between start and end
+        assert_eq!(uniforms.wave_alpha, 0.5);
+
+        assert_eq!(wave.volume_alpha(0.2), 0.0);
+        assert_eq!(wave.volume_alpha(3.0), 1.0);
+        let unmodulated = WaveParams {
+            mod_alpha_by_volume: false,
+            ..wave
+        };
+        assert_eq!(unmodulated.volume_alpha(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_additive_and_thick_waves() {
+        let brightest =
+            |pixels: &[Vec<[u8; 4]>]| pixels.iter().flatten().map(|px| px[0]).max().unwrap();
+        let lit = |pixels: &[Vec<[u8; 4]>]| pixels.iter().flatten().filter(|px| px[0] > 0).count();
+        let uniforms = WaveformUniforms {
+            resolution: [SIZE as f32, SIZE as f32],
+            wave_color: [0.5, 0.5, 0.5, 1.0],
+            ..Default::default()
+        };
+
+        // Overlapping quads stack up when added instead of blended
+        let blended = render_wave_pixels(&uniforms, false);
+        let added = render_wave_pixels(&uniforms, true);
+        assert!(brightest(&blended) <= 128, "{}", brightest(&blended));
+        assert!(brightest(&added) > 200, "{}", brightest(&added));
+
+        let thick = render_wave_pixels(
+            &WaveformUniforms {
+                wave_thick: 1,
+                ..uniforms
+            },
+            false,
+        );
+        assert!(
+            lit(&thick) > lit(&blended),
+            "{} vs {}",
+            lit(&thick),
+            lit(&blended)
+        );
+    }
+
+    const ALL_MODES: [WaveformMode; 6] = [
+        WaveformMode::Centered,
+        WaveformMode::LeftChannel,
+        WaveformMode::RightChannel,
+        WaveformMode::Spectrum,
+        WaveformMode::Circular,
+        WaveformMode::Line,
+    ];
+
+    #[test]
+    fn test_mode_placement() {
+        let sine: Vec<f32> = (0..64).map(|i| (i as f32 * 0.3).sin()).collect();
+        let positions = |mode: WaveformMode| -> Vec<[f32; 2]> {
+            let uniforms = WaveformUniforms {
+                wave_mode: mode as u32,
+                resolution: [SIZE as f32, SIZE as f32],
+                ..Default::default()
+            };
+            wave_points(&sine, &uniforms)
+                .iter()
+                .map(|point| point.position)
+                .collect()
+        };
+        let xs_increase = |points: &[[f32; 2]]| points.windows(2).all(|w| w[1][0] > w[0][0]);
+        let mean_y = |points: &[[f32; 2]]| points.iter().map(|p| p[1]).sum::<f32>() / 64.0;
+
+        // Strips run left to right; channels sit above and below the center
+        let centered = positions(WaveformMode::Centered);
+        let left = positions(WaveformMode::LeftChannel);
+        let right = positions(WaveformMode::RightChannel);
+        for strip in [&centered, &left, &right] {
+            assert!(xs_increase(strip));
+            assert_eq!((strip[0][0], strip[63][0]), (0.0, 1.0));
+        }
+        assert!(mean_y(&left) > mean_y(&centered) + 0.2);
+        assert!(mean_y(&right) < mean_y(&centered) - 0.2);
+        assert_eq!(positions(WaveformMode::Line), centered);
+
+        // Spectrum bars are as tall as the magnitude, whatever its sign
+        let spectrum = positions(WaveformMode::Spectrum);
+        assert!(xs_increase(&spectrum));
+        for (point, value) in spectrum.iter().zip(&sine) {
+            assert!((point[1] - value.abs()).abs() < 1e-6);
+        }
+
+        // The ring goes out and back: x isn't monotonic and the ends meet
+        let ring = positions(WaveformMode::Circular);
+        assert!(!xs_increase(&ring));
+        assert!(ring.iter().any(|p| p[0] < 0.4) && ring.iter().any(|p| p[0] > 0.6));
+        let gap = (ring[0][0] - ring[63][0]).hypot(ring[0][1] - ring[63][1]);
+        assert!(gap < 0.01, "ring ends {} apart", gap);
+
+        // Over time the ring turns around its center, keeping each radius
+        let later = wave_points(
+            &sine,
+            &WaveformUniforms {
+                wave_mode: WaveformMode::Circular as u32,
+                resolution: [SIZE as f32, SIZE as f32],
+                time: 2.0,
+                ..Default::default()
+            },
+        );
+        let radius = |p: [f32; 2]| (p[0] - 0.5).hypot(p[1] - 0.5);
+        for (before, after) in ring.iter().zip(&later) {
+            assert!((radius(*before) - radius(after.position)).abs() < 1e-5);
+        }
+        assert!((later[0].position[1] - 0.5).abs() > 0.05, "{:?}", later[0]);
+
+        // Every mode places the wave differently
+        let all: Vec<_> = ALL_MODES.iter().map(|&mode| positions(mode)).collect();
+        for (i, a) in all.iter().enumerate() {
+            for (j, b) in all.iter().enumerate().skip(i + 1) {
+                if (ALL_MODES[i], ALL_MODES[j]) != (WaveformMode::Centered, WaveformMode::Line) {
+                    assert_ne!(a, b, "{:?} and {:?}", ALL_MODES[i], ALL_MODES[j]);
+                }
+            }
+        }
+        assert_eq!(WaveformMode::from_index(9), WaveformMode::Centered);
+    }
+
+    #[test]
+    fn test_every_mode_draws() {
+        let lit = |mode: WaveformMode| {
+            let pixels = render_flat_wave(mode);
+            pixels.iter().flatten().filter(|lit| **lit).count()
+        };
+        let counts: Vec<usize> = ALL_MODES.iter().map(|&mode| lit(mode)).collect();
+        for (mode, count) in ALL_MODES.iter().zip(&counts) {
+            assert!(*count > 0, "{:?} drew nothing", mode);
+        }
+
+        // Spectrum bars fill the area under the wave, the line is thinner
+        let [centered, _, _, spectrum, _, line] = counts[..] else {
+            unreachable!()
+        };
+        assert!(spectrum > centered * 10, "{} vs {}", spectrum, centered);
+        assert!(line < centered, "{} vs {}", line, centered);
+    }
+}
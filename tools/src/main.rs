@@ -6,7 +6,7 @@
 //!
 //! Usage: cargo run --bin test_preset_compatibility
 
-use onedrop_eval::MilkEvaluator;
+use onedrop_eval::equation_errors;
 use onedrop_parser::parse_preset;
 use std::fs;
 use std::path::Path;
@@ -74,25 +74,11 @@ fn test_preset(path: &Path, report: &mut CompatibilityReport) {
         }
     };
 
-    // Try to evaluate per-frame equations
-    let mut evaluator = MilkEvaluator::new();
-
-    // Test per-frame equations
-    let mut eval_errors = Vec::new();
-
-    for (i, eq) in preset.per_frame_equations.iter().enumerate() {
-        if let Err(e) = evaluator.eval(eq) {
-            eval_errors.push(format!("Frame eq {}: {:?}", i, e));
-        }
-    }
-
-    // Test per-pixel equations
-    for (i, eq) in preset.per_pixel_equations.iter().enumerate() {
-        if let Err(e) = evaluator.eval(eq) {
-            eval_errors.push(format!("Pixel eq {}: {:?}", i, e));
-        }
-    }
-
+    // Try to evaluate every equation
+    let eval_errors: Vec<String> = equation_errors(&preset)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
     if eval_errors.is_empty() {
         report.eval_success += 1;
     } else {